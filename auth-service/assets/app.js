@@ -104,7 +104,7 @@ signupButton.addEventListener("click", (e) => {
       signupForm.password.value = "";
       signupForm.twoFA.checked = false;
       signupErrAlter.style.display = "none";
-      alert("You have successfully created a user.");
+      alert("You have successfully created a user. Check your email to verify your address before logging in.");
       loginSection.style.display = "block";
       twoFASection.style.display = "none";
       signupSection.style.display = "none";
@@ -0,0 +1,2 @@
+ALTER TABLE users DROP INDEX users_id_unique;
+ALTER TABLE users DROP COLUMN id;
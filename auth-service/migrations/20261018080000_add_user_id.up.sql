@@ -0,0 +1,5 @@
+-- Stable user id, used as the JWT subject instead of the email
+ALTER TABLE users ADD COLUMN id CHAR(36) NULL;
+UPDATE users SET id = UUID() WHERE id IS NULL;
+ALTER TABLE users MODIFY COLUMN id CHAR(36) NOT NULL;
+ALTER TABLE users ADD CONSTRAINT users_id_unique UNIQUE (id);
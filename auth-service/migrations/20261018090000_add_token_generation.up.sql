@@ -0,0 +1,2 @@
+-- Embedded in every JWT. Incrementing it invalidates all of a user's tokens.
+ALTER TABLE users ADD COLUMN token_generation INT UNSIGNED NOT NULL DEFAULT 0;
@@ -0,0 +1,6 @@
+-- Replaces the requires_2fa flag so users can pick their second factor
+ALTER TABLE users ADD COLUMN two_fa_method VARCHAR(16) NOT NULL DEFAULT 'none';
+UPDATE users SET two_fa_method = 'email' WHERE requires_2fa;
+ALTER TABLE users DROP COLUMN requires_2fa;
+-- Base32 TOTP secret, empty until the user enrolls an authenticator app
+ALTER TABLE users ADD COLUMN totp_secret VARCHAR(64) NOT NULL DEFAULT '';
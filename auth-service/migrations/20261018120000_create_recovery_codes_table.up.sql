@@ -0,0 +1,7 @@
+-- Argon2 hashes of each user's unused 2FA recovery codes
+CREATE TABLE IF NOT EXISTS recovery_codes (
+    id CHAR(36) NOT NULL PRIMARY KEY,
+    user_id CHAR(36) NOT NULL,
+    code_hash TEXT NOT NULL,
+    INDEX recovery_codes_user_id (user_id)
+);
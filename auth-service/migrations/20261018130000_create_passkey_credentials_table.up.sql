@@ -0,0 +1,9 @@
+-- WebAuthn credentials registered by users for passwordless login
+CREATE TABLE IF NOT EXISTS passkey_credentials (
+    id VARCHAR(512) NOT NULL PRIMARY KEY,
+    user_id CHAR(36) NOT NULL,
+    public_key VARCHAR(255) NOT NULL,
+    sign_count INT UNSIGNED NOT NULL,
+    created_at BIGINT NOT NULL,
+    INDEX passkey_credentials_user_id (user_id)
+);
@@ -0,0 +1,3 @@
+-- Login is refused until the address is confirmed. Existing accounts are grandfathered in.
+ALTER TABLE users ADD COLUMN email_verified BOOLEAN NOT NULL DEFAULT FALSE;
+UPDATE users SET email_verified = TRUE;
@@ -0,0 +1,4 @@
+-- Failed logins in a row, reset by a successful one. Enough of them lock the account.
+ALTER TABLE users ADD COLUMN failed_login_count INT UNSIGNED NOT NULL DEFAULT 0;
+-- Unix seconds the current lock ends at, NULL if the account was never locked
+ALTER TABLE users ADD COLUMN locked_until BIGINT NULL;
@@ -0,0 +1,2 @@
+-- Time step of the last TOTP code accepted, so the same code cannot be used twice
+ALTER TABLE users ADD COLUMN totp_last_step BIGINT UNSIGNED NULL;
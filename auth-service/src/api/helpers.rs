@@ -1,10 +1,16 @@
 use crate::{
     app_state::app_state::AppState,
-    configure_mysql,
+    configure_breached_password_checker, configure_keyring, configure_mysql,
     services::{
-        data_stores::{HashmapTwoFACodeStore, HashsetBannedTokenStore, MySqlUserStore},
+        data_stores::{
+            HashmapEmailVerificationTokenStore, HashmapPasskeyChallengeStore, HashmapPasskeyStore,
+            HashmapPasswordResetTokenStore, HashmapRateLimitStore, HashmapRecoveryCodeStore,
+            HashmapRefreshTokenStore, HashmapSessionStore, HashmapSigningKeyStore,
+            HashmapTwoFACodeStore, HashsetBannedTokenStore, MySqlUserStore,
+        },
         MockEmailClient,
     },
+    utils::constants::TWO_FA_CODE_HASH_KEY,
     Application,
 };
 
@@ -24,10 +30,42 @@ impl TestApp {
 
         let user_store = Arc::new(RwLock::new(MySqlUserStore::new(mysql_pool)));
         let banned_token_store = Arc::new(RwLock::new(HashsetBannedTokenStore::default()));
-        let two_fa_store = Arc::new(RwLock::new(HashmapTwoFACodeStore::default()));
+        let two_fa_store = Arc::new(RwLock::new(HashmapTwoFACodeStore::new(
+            TWO_FA_CODE_HASH_KEY.clone(),
+        )));
         let email_client = Arc::new(RwLock::new(MockEmailClient));
-
-        let app_state = AppState::new(user_store, banned_token_store, two_fa_store, email_client);
+        let refresh_token_store = Arc::new(RwLock::new(HashmapRefreshTokenStore::default()));
+        let keyring = Arc::new(RwLock::new(configure_keyring()));
+        let session_store = Arc::new(RwLock::new(HashmapSessionStore::default()));
+        let recovery_code_store = Arc::new(RwLock::new(HashmapRecoveryCodeStore::default()));
+        let passkey_store = Arc::new(RwLock::new(HashmapPasskeyStore::default()));
+        let passkey_challenge_store =
+            Arc::new(RwLock::new(HashmapPasskeyChallengeStore::default()));
+        let password_reset_token_store =
+            Arc::new(RwLock::new(HashmapPasswordResetTokenStore::default()));
+        let email_verification_token_store =
+            Arc::new(RwLock::new(HashmapEmailVerificationTokenStore::default()));
+        let breached_password_checker = configure_breached_password_checker();
+        let rate_limit_store = Arc::new(RwLock::new(HashmapRateLimitStore::default()));
+        let signing_key_store = Arc::new(RwLock::new(HashmapSigningKeyStore::default()));
+
+        let app_state = AppState::new(
+            user_store,
+            banned_token_store,
+            two_fa_store,
+            email_client,
+            refresh_token_store,
+            keyring,
+            session_store,
+            recovery_code_store,
+            passkey_store,
+            passkey_challenge_store,
+            password_reset_token_store,
+            email_verification_token_store,
+            breached_password_checker,
+            rate_limit_store,
+            signing_key_store,
+        );
 
         let address = "127.0.0.1:0";
 
@@ -52,7 +90,7 @@ impl TestApp {
 
     pub async fn get_root(&self) -> reqwest::Response {
         self.http_client
-            .get(&format!("{}/", &self.address))
+            .get(format!("{}/", &self.address))
             .send()
             .await
             .expect("Failed to execute request.")
@@ -63,7 +101,7 @@ impl TestApp {
         Body: serde::Serialize,
     {
         self.http_client
-            .post(&format!("{}/signup", &self.address))
+            .post(format!("{}/signup", &self.address))
             .json(body)
             .send()
             .await
@@ -72,7 +110,7 @@ impl TestApp {
 
     pub async fn login_root(&self) -> reqwest::Response {
         self.http_client
-            .post(&format!("{}/login", &self.address))
+            .post(format!("{}/login", &self.address))
             .send()
             .await
             .expect("Login failed.")
@@ -80,7 +118,7 @@ impl TestApp {
 
     pub async fn logout_root(&self) -> reqwest::Response {
         self.http_client
-            .post(&format!("{}/logout", &self.address))
+            .post(format!("{}/logout", &self.address))
             .send()
             .await
             .expect("Logout failed.")
@@ -88,7 +126,7 @@ impl TestApp {
 
     pub async fn verify_2fa_root(&self) -> reqwest::Response {
         self.http_client
-            .post(&format!("{}/verify-2fa", &self.address))
+            .post(format!("{}/verify-2fa", &self.address))
             .send()
             .await
             .expect("2fa failed.")
@@ -96,7 +134,7 @@ impl TestApp {
 
     pub async fn verify_token_root(&self) -> reqwest::Response {
         self.http_client
-            .post(&format!("{}/verify-token", &self.address))
+            .post(format!("{}/verify-token", &self.address))
             .send()
             .await
             .expect("Verify token failed.")
@@ -1,5 +1,5 @@
 use axum::{http::StatusCode, response::IntoResponse};
 
-async fn logout_handler() -> impl IntoResponse {
+pub async fn logout_handler() -> impl IntoResponse {
     StatusCode::OK.into_response()
 }
@@ -1,6 +1,13 @@
-use crate::domain::{
-    data_stores::{BannedTokenStore, TwoFACodeStore, UserStore},
-    EmailClient,
+use crate::{
+    domain::{
+        data_stores::{
+            BannedTokenStore, EmailVerificationTokenStore, PasskeyChallengeStore, PasskeyStore,
+            PasswordResetTokenStore, RateLimitStore, RecoveryCodeStore, RefreshTokenStore,
+            SessionStore, SigningKeyStore, TwoFACodeStore, UserStore,
+        },
+        BreachedPasswordChecker, EmailClient,
+    },
+    utils::keyring::Keyring,
 };
 
 use std::sync::Arc;
@@ -10,6 +17,18 @@ pub type UserStoreType = Arc<RwLock<dyn UserStore + Send + Sync>>;
 pub type BannedTokenStoreType = Arc<RwLock<dyn BannedTokenStore + Send + Sync>>;
 pub type TwoFACodeStoreType = Arc<RwLock<dyn TwoFACodeStore + Send + Sync>>;
 pub type EmailClientType = Arc<RwLock<dyn EmailClient + Send + Sync>>;
+pub type RefreshTokenStoreType = Arc<RwLock<dyn RefreshTokenStore + Send + Sync>>;
+pub type SessionStoreType = Arc<RwLock<dyn SessionStore + Send + Sync>>;
+pub type RecoveryCodeStoreType = Arc<RwLock<dyn RecoveryCodeStore + Send + Sync>>;
+pub type PasskeyStoreType = Arc<RwLock<dyn PasskeyStore + Send + Sync>>;
+pub type PasskeyChallengeStoreType = Arc<RwLock<dyn PasskeyChallengeStore + Send + Sync>>;
+pub type PasswordResetTokenStoreType = Arc<RwLock<dyn PasswordResetTokenStore + Send + Sync>>;
+pub type EmailVerificationTokenStoreType =
+    Arc<RwLock<dyn EmailVerificationTokenStore + Send + Sync>>;
+pub type RateLimitStoreType = Arc<RwLock<dyn RateLimitStore + Send + Sync>>;
+pub type BreachedPasswordCheckerType = Arc<RwLock<dyn BreachedPasswordChecker + Send + Sync>>;
+pub type KeyringType = Arc<RwLock<Keyring>>;
+pub type SigningKeyStoreType = Arc<RwLock<dyn SigningKeyStore + Send + Sync>>;
 
 #[derive(Clone)]
 pub struct AppState {
@@ -17,20 +36,54 @@ pub struct AppState {
     pub banned_token_store: BannedTokenStoreType,
     pub two_fa_code_store: TwoFACodeStoreType,
     pub email_client: EmailClientType,
+    pub refresh_token_store: RefreshTokenStoreType,
+    pub keyring: KeyringType,
+    pub session_store: SessionStoreType,
+    pub recovery_code_store: RecoveryCodeStoreType,
+    pub passkey_store: PasskeyStoreType,
+    pub passkey_challenge_store: PasskeyChallengeStoreType,
+    pub password_reset_token_store: PasswordResetTokenStoreType,
+    pub email_verification_token_store: EmailVerificationTokenStoreType,
+    pub breached_password_checker: BreachedPasswordCheckerType,
+    pub rate_limit_store: RateLimitStoreType,
+    pub signing_key_store: SigningKeyStoreType,
 }
 
 impl AppState {
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         user_store: UserStoreType,
         banned_token_store: BannedTokenStoreType,
         two_fa_code_store: TwoFACodeStoreType,
         email_client: EmailClientType,
+        refresh_token_store: RefreshTokenStoreType,
+        keyring: KeyringType,
+        session_store: SessionStoreType,
+        recovery_code_store: RecoveryCodeStoreType,
+        passkey_store: PasskeyStoreType,
+        passkey_challenge_store: PasskeyChallengeStoreType,
+        password_reset_token_store: PasswordResetTokenStoreType,
+        email_verification_token_store: EmailVerificationTokenStoreType,
+        breached_password_checker: BreachedPasswordCheckerType,
+        rate_limit_store: RateLimitStoreType,
+        signing_key_store: SigningKeyStoreType,
     ) -> Self {
         Self {
             user_store,
             banned_token_store,
             two_fa_code_store,
             email_client,
+            refresh_token_store,
+            keyring,
+            session_store,
+            recovery_code_store,
+            passkey_store,
+            passkey_challenge_store,
+            password_reset_token_store,
+            email_verification_token_store,
+            breached_password_checker,
+            rate_limit_store,
+            signing_key_store,
         }
     }
 }
@@ -0,0 +1,85 @@
+// Builds the bloom filter `BREACHED_PASSWORDS_PATH` can point at from a HIBP
+// SHA-1 dump, either a single `HASH:COUNT` file or a directory of range files.
+//
+//     cargo run --release --bin build_breached_password_filter -- \
+//         pwned-passwords-sha1.txt breached-passwords.bloom \
+//         --false-positive-rate 0.001 --min-count 2
+
+use auth_service::utils::breached_passwords::{read_hashes_from_path, BloomFilter};
+use color_eyre::eyre::{self, eyre, WrapErr};
+use std::{env, path::PathBuf};
+
+const DEFAULT_FALSE_POSITIVE_RATE: f64 = 0.001;
+const DEFAULT_MIN_COUNT: u64 = 1;
+
+const USAGE: &str = "Usage: build_breached_password_filter <INPUT> <OUTPUT> \
+                     [--false-positive-rate <RATE>] [--min-count <COUNT>]";
+
+struct Args {
+    input: PathBuf,
+    output: PathBuf,
+    false_positive_rate: f64,
+    // Passwords seen fewer times than this are left out, to shrink the filter
+    min_count: u64,
+}
+
+fn parse_args() -> eyre::Result<Args> {
+    let mut paths = Vec::new();
+    let mut false_positive_rate = DEFAULT_FALSE_POSITIVE_RATE;
+    let mut min_count = DEFAULT_MIN_COUNT;
+    let mut args = env::args().skip(1);
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--false-positive-rate" => {
+                false_positive_rate = args
+                    .next()
+                    .ok_or_else(|| eyre!("--false-positive-rate needs a value"))?
+                    .parse()
+                    .wrap_err("--false-positive-rate must be a number")?;
+            }
+            "--min-count" => {
+                min_count = args
+                    .next()
+                    .ok_or_else(|| eyre!("--min-count needs a value"))?
+                    .parse()
+                    .wrap_err("--min-count must be a number")?;
+            }
+            "-h" | "--help" => return Err(eyre!(USAGE)),
+            _ if arg.starts_with("--") => return Err(eyre!("Unknown option {}\n{}", arg, USAGE)),
+            _ => paths.push(PathBuf::from(arg)),
+        }
+    }
+
+    let [input, output]: [PathBuf; 2] = paths.try_into().map_err(|_| eyre!(USAGE))?;
+
+    Ok(Args {
+        input,
+        output,
+        false_positive_rate,
+        min_count,
+    })
+}
+
+fn main() -> eyre::Result<()> {
+    color_eyre::install()?;
+
+    let args = parse_args()?;
+
+    // Dumps run to billions of lines, so they are streamed twice rather than
+    // held in memory: once to size the filter, once to fill it
+    let mut count = 0u64;
+    read_hashes_from_path(&args.input, args.min_count, |_| count += 1)?;
+
+    let mut filter = BloomFilter::new(count, args.false_positive_rate)?;
+    read_hashes_from_path(&args.input, args.min_count, |hash| filter.insert(&hash))?;
+
+    filter.save(&args.output)?;
+
+    println!(
+        "Wrote {} breached password hashes to {:?}",
+        count, args.output
+    );
+
+    Ok(())
+}
@@ -0,0 +1,7 @@
+use secrecy::Secret;
+
+// Looks new passwords up in a local copy of known breach corpora. Lookups never
+// leave the process, so candidate passwords are not shared with anyone.
+pub trait BreachedPasswordChecker {
+    fn is_breached(&self, password: &Secret<String>) -> bool;
+}
@@ -1,8 +1,19 @@
-use crate::domain::{email::Email, password::Password, user::User};
+use crate::domain::{
+    email::Email, password::Password, two_fa_method::TwoFAMethod, user::User, user_id::UserId,
+};
 
+use crate::utils::constants::TWO_FA_CODE_GENERATOR;
+
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use chrono::{DateTime, Utc};
 use color_eyre::eyre::{self, eyre, Context, Ok};
+use hmac::{Hmac, Mac};
+use jsonwebtoken::Algorithm;
 use secrecy::{ExposeSecret, Secret};
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::hash::Hash;
+use subtle::ConstantTimeEq;
 use uuid::Uuid;
 
 // ~~~ User Store
@@ -36,10 +47,41 @@ impl PartialEq for UserStoreError {
 
 #[async_trait::async_trait]
 pub trait UserStore: Send + Sync {
+    // The user's password must already be hashed with `compute_password_hash`,
+    // so the hash is never computed while the store is locked
     async fn add_user(&mut self, user: User) -> Result<(), UserStoreError>;
     async fn get_user(&self, email: &Email) -> Result<User, UserStoreError>;
+    async fn get_user_by_id(&self, id: &UserId) -> Result<User, UserStoreError>;
     async fn validate_user(&self, email: &Email, password: &Password)
         -> Result<(), UserStoreError>;
+    async fn update_password(
+        &mut self,
+        id: &UserId,
+        password_hash: Secret<String>,
+    ) -> Result<(), UserStoreError>;
+    async fn mark_email_verified(&mut self, id: &UserId) -> Result<(), UserStoreError>;
+    // Invalidates every token issued to the user so far
+    async fn increment_token_generation(&mut self, id: &UserId) -> Result<(), UserStoreError>;
+    // Replaces the user's TOTP secret. Does not change which 2FA method is in use.
+    async fn set_totp_secret(
+        &mut self,
+        id: &UserId,
+        secret: Secret<String>,
+    ) -> Result<(), UserStoreError>;
+    async fn clear_totp_secret(&mut self, id: &UserId) -> Result<(), UserStoreError>;
+    async fn set_two_fa_method(
+        &mut self,
+        id: &UserId,
+        method: TwoFAMethod,
+    ) -> Result<(), UserStoreError>;
+    // Records the TOTP time step of an accepted code. False if it is not newer than
+    // the last one recorded, so a code that was already used is refused.
+    async fn accept_totp_step(&mut self, id: &UserId, step: u64) -> Result<bool, UserStoreError>;
+    // Counts a failed login, returning how many there have been in a row
+    async fn record_failed_login(&mut self, id: &UserId) -> Result<u32, UserStoreError>;
+    async fn lock_user(&mut self, id: &UserId, until: DateTime<Utc>) -> Result<(), UserStoreError>;
+    // Lifts any lock and forgets the failed logins
+    async fn unlock_user(&mut self, id: &UserId) -> Result<(), UserStoreError>;
 }
 
 // ~~~ Banned token store
@@ -49,10 +91,175 @@ pub enum BannedTokenStoreError {
     UnexpectedError(#[source] eyre::Report),
 }
 
+// Tokens are banned by their `jti` claim, never by the raw token.
+// A ban only needs to last until the token's own `exp`.
 #[async_trait::async_trait]
 pub trait BannedTokenStore: Send + Sync {
-    async fn add_token(&mut self, token: Secret<String>) -> Result<(), BannedTokenStoreError>;
-    async fn contains_token(&self, token: &Secret<String>) -> Result<bool, BannedTokenStoreError>;
+    async fn add_token(&mut self, jti: &str, exp: usize) -> Result<(), BannedTokenStoreError>;
+    async fn contains_token(&self, jti: &str) -> Result<bool, BannedTokenStoreError>;
+}
+
+// ~~~ Refresh token store
+#[derive(Debug, Clone, Deserialize)]
+pub struct RefreshToken(Secret<String>);
+
+impl AsRef<Secret<String>> for RefreshToken {
+    fn as_ref(&self) -> &Secret<String> {
+        &self.0
+    }
+}
+
+impl From<Secret<String>> for RefreshToken {
+    fn from(value: Secret<String>) -> Self {
+        Self(value)
+    }
+}
+
+impl RefreshToken {
+    // What stores key tokens by, so a leaked store does not hand out usable tokens
+    pub fn fingerprint(&self) -> String {
+        URL_SAFE_NO_PAD.encode(Sha256::digest(self.0.expose_secret().as_bytes()))
+    }
+}
+
+impl PartialEq for RefreshToken {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.expose_secret() == other.0.expose_secret()
+    }
+}
+
+impl Eq for RefreshToken {}
+
+impl Default for RefreshToken {
+    // Opaque, high entropy value. Carries no claims, so it is only meaningful to the store.
+    fn default() -> Self {
+        use rand::{distr::Alphanumeric, rng, Rng};
+
+        let token: String = rng()
+            .sample_iter(&Alphanumeric)
+            .take(64)
+            .map(char::from)
+            .collect();
+
+        Self(Secret::new(token))
+    }
+}
+
+// Every refresh token issued from the same login shares a family id,
+// so a replayed token can take down the whole chain of rotations.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RefreshTokenRecord {
+    pub user_id: UserId,
+    pub family_id: String,
+    // User's token generation at login. Refreshing fails once it has been bumped.
+    pub token_generation: u32,
+    // Session started by the login. Refreshing fails once it has been revoked.
+    pub session_id: String,
+}
+
+impl RefreshTokenRecord {
+    pub fn new(user_id: UserId, token_generation: u32, session_id: String) -> Self {
+        Self {
+            user_id,
+            family_id: Uuid::new_v4().to_string(),
+            token_generation,
+            session_id,
+        }
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum RefreshTokenStoreError {
+    #[error("Refresh token not found")]
+    TokenNotFound,
+    #[error("Refresh token already used")]
+    TokenReused(RefreshTokenRecord),
+    #[error("Unexpected error")]
+    UnexpectedError(#[source] eyre::Report),
+}
+
+impl PartialEq for RefreshTokenStoreError {
+    fn eq(&self, other: &Self) -> bool {
+        core::mem::discriminant(self) == core::mem::discriminant(other)
+    }
+}
+
+#[async_trait::async_trait]
+pub trait RefreshTokenStore: Send + Sync {
+    async fn add_token(
+        &mut self,
+        token: RefreshToken,
+        record: RefreshTokenRecord,
+    ) -> Result<(), RefreshTokenStoreError>;
+    // Marks the token as used and returns its record. Presenting the same token
+    // a second time yields `TokenReused`.
+    async fn consume_token(
+        &mut self,
+        token: &RefreshToken,
+    ) -> Result<RefreshTokenRecord, RefreshTokenStoreError>;
+    async fn revoke_family(&mut self, family_id: &str) -> Result<(), RefreshTokenStoreError>;
+    async fn is_family_revoked(&self, family_id: &str) -> Result<bool, RefreshTokenStoreError>;
+}
+
+// ~~~ Session store
+// One per login. JWTs and refresh tokens carry the session id, so revoking
+// the session ends them on that device only.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Session {
+    pub id: String,
+    pub user_id: UserId,
+    pub device: String,
+    pub ip_address: String,
+    pub user_agent: String,
+    pub created_at: DateTime<Utc>,
+    pub last_seen_at: DateTime<Utc>,
+}
+
+impl Session {
+    pub fn new(user_id: UserId, device: String, ip_address: String, user_agent: String) -> Self {
+        let now = Utc::now();
+
+        Self {
+            id: Uuid::new_v4().to_string(),
+            user_id,
+            device,
+            ip_address,
+            user_agent,
+            created_at: now,
+            last_seen_at: now,
+        }
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum SessionStoreError {
+    #[error("Session not found")]
+    SessionNotFound,
+    #[error("Unexpected error")]
+    UnexpectedError(#[source] eyre::Report),
+}
+
+impl PartialEq for SessionStoreError {
+    fn eq(&self, other: &Self) -> bool {
+        core::mem::discriminant(self) == core::mem::discriminant(other)
+    }
+}
+
+// Sessions expire once they have not been seen for SESSION_TTL_SECONDS.
+// Revoked and expired sessions are reported as `SessionNotFound`.
+#[async_trait::async_trait]
+pub trait SessionStore: Send + Sync {
+    async fn add_session(&mut self, session: Session) -> Result<(), SessionStoreError>;
+    async fn get_session(&self, id: &str) -> Result<Session, SessionStoreError>;
+    // Active sessions of the user, most recently seen first
+    async fn list_sessions(&self, user_id: &UserId) -> Result<Vec<Session>, SessionStoreError>;
+    async fn touch_session(
+        &mut self,
+        id: &str,
+        last_seen_at: DateTime<Utc>,
+    ) -> Result<(), SessionStoreError>;
+    async fn revoke_session(&mut self, id: &str) -> Result<(), SessionStoreError>;
+    async fn revoke_user_sessions(&mut self, user_id: &UserId) -> Result<(), SessionStoreError>;
 }
 
 // ~~~ 2FA Store
@@ -62,7 +269,7 @@ pub struct LoginAttemptId(Secret<String>);
 impl LoginAttemptId {
     pub fn parse(id: Secret<String>) -> eyre::Result<Self> {
         let id = id.expose_secret();
-        let parse_id = uuid::Uuid::parse_str(&id).wrap_err("Invalid login attempt id")?;
+        let parse_id = uuid::Uuid::parse_str(id).wrap_err("Invalid login attempt id")?;
 
         Ok(Self(Secret::new(parse_id.to_string())))
     }
@@ -88,6 +295,12 @@ impl PartialEq for LoginAttemptId {
 
 impl Eq for LoginAttemptId {}
 
+impl Hash for LoginAttemptId {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.0.expose_secret().hash(state);
+    }
+}
+
 impl Default for LoginAttemptId {
     fn default() -> Self {
         Self(Secret::new(Uuid::new_v4().to_string()))
@@ -98,6 +311,12 @@ impl Default for LoginAttemptId {
 pub enum TwoFACodeStoreError {
     #[error("Login Attempt ID not found")]
     LoginAttemptIdNotFound,
+    #[error("Resend requested too soon")]
+    ResendTooSoon(u64), // Seconds left in the cooldown
+    #[error("Too many resends")]
+    TooManyResends,
+    #[error("Too many attempts")]
+    TooManyAttempts,
     #[error("Unexpected error")]
     UnexpectedError(#[source] eyre::Report),
 }
@@ -107,19 +326,53 @@ impl PartialEq for TwoFACodeStoreError {
     }
 }
 
+// Pending challenges are keyed by login attempt, so a user can have several
+// logins in flight (one per device) without them overwriting each other
 #[async_trait::async_trait]
 pub trait TwoFACodeStore {
+    // Hashes with the key the store was created with. Codes are passed to the
+    // store, and compared, only in this form.
+    fn hash_code(&self, code: &TwoFACode) -> TwoFACodeHash;
     async fn add_code(
         &mut self,
-        email: Email,
         login_attempt_id: LoginAttemptId,
-        code: TwoFACode,
+        email: Email,
+        code_hash: TwoFACodeHash,
     ) -> Result<(), TwoFACodeStoreError>;
-    async fn remove_code(&mut self, email: &Email) -> std::result::Result<(), TwoFACodeStoreError>;
+    async fn remove_code(
+        &mut self,
+        login_attempt_id: &LoginAttemptId,
+    ) -> std::result::Result<(), TwoFACodeStoreError>;
     async fn get_code(
         &self,
-        email: &Email,
-    ) -> Result<(LoginAttemptId, TwoFACode), TwoFACodeStoreError>;
+        login_attempt_id: &LoginAttemptId,
+    ) -> Result<(Email, TwoFACodeHash), TwoFACodeStoreError>;
+    // Takes the pending code out of reach in one step, so of several requests
+    // checking it only one can use it. Put back with `restore_code` when the
+    // guess is wrong; `remove_code` drops it for good.
+    async fn take_code(
+        &mut self,
+        login_attempt_id: &LoginAttemptId,
+    ) -> Result<(Email, TwoFACodeHash), TwoFACodeStoreError>;
+    async fn restore_code(
+        &mut self,
+        login_attempt_id: &LoginAttemptId,
+    ) -> Result<(), TwoFACodeStoreError>;
+    // Counts a guess against the pending code before it is checked, and returns its
+    // number, so a burst of concurrent guesses cannot all get in under the limit.
+    // Past MAX_2FA_ATTEMPTS the code is removed and TooManyAttempts returned.
+    async fn reserve_attempt(
+        &mut self,
+        login_attempt_id: &LoginAttemptId,
+    ) -> Result<u32, TwoFACodeStoreError>;
+    // Swaps the pending code for a freshly sent one. Refused within
+    // TWO_FA_RESEND_COOLDOWN_SECONDS of the last code and after MAX_2FA_RESENDS.
+    // Failed guesses carry over, so resending does not buy more attempts.
+    async fn replace_code(
+        &mut self,
+        login_attempt_id: &LoginAttemptId,
+        code_hash: TwoFACodeHash,
+    ) -> Result<(), TwoFACodeStoreError>;
 }
 
 #[derive(Debug, Clone, Deserialize)]
@@ -127,14 +380,23 @@ pub struct TwoFACode(Secret<String>);
 
 impl TwoFACode {
     pub fn parse(code: String) -> eyre::Result<Self> {
-        let code_as_u32 = code.parse::<u32>().wrap_err("Invalid 2FA code")?;
-
-        if (100_000..=999_999).contains(&code_as_u32) {
+        if TWO_FA_CODE_GENERATOR.is_well_formed(&code) {
             Ok(Self(Secret::new(code)))
         } else {
-            Err(eyre!("Invalid 2Fa code"))
+            Err(eyre!("Invalid 2FA code"))
         }
     }
+
+    // Only this keyed hash is kept while the login is pending
+    pub fn hash(&self, key: &Secret<String>) -> TwoFACodeHash {
+        let mut mac = Hmac::<Sha256>::new_from_slice(key.expose_secret().as_bytes())
+            .expect("HMAC accepts keys of any length");
+        mac.update(self.0.expose_secret().as_bytes());
+
+        TwoFACodeHash(Secret::new(
+            URL_SAFE_NO_PAD.encode(mac.finalize().into_bytes()),
+        ))
+    }
 }
 
 impl AsRef<Secret<String>> for TwoFACode {
@@ -157,8 +419,516 @@ impl PartialEq for TwoFACode {
 
 impl Eq for TwoFACode {}
 
+// Random code in the shape accepted by `parse`
 impl Default for TwoFACode {
     fn default() -> Self {
-        Self(Secret::new(Uuid::new_v4().to_string()))
+        Self(TWO_FA_CODE_GENERATOR.generate())
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct TwoFACodeHash(Secret<String>);
+
+impl AsRef<Secret<String>> for TwoFACodeHash {
+    fn as_ref(&self) -> &Secret<String> {
+        &self.0
+    }
+}
+
+impl From<Secret<String>> for TwoFACodeHash {
+    fn from(value: Secret<String>) -> Self {
+        Self(value)
+    }
+}
+
+// Constant time, so response timing says nothing about how close a guess was
+impl PartialEq for TwoFACodeHash {
+    fn eq(&self, other: &Self) -> bool {
+        self.0
+            .expose_secret()
+            .as_bytes()
+            .ct_eq(other.0.expose_secret().as_bytes())
+            .into()
+    }
+}
+
+impl Eq for TwoFACodeHash {}
+
+// ~~~ Recovery code store
+// One-time codes that stand in for the second factor when the user has lost it
+#[derive(Debug, Clone)]
+pub struct RecoveryCode(Secret<String>);
+
+// Lowercase letters and digits, minus the easily confused 0/o, 1/l/i
+const RECOVERY_CODE_ALPHABET: &[u8] = b"abcdefghjkmnpqrstuvwxyz23456789";
+const RECOVERY_CODE_LENGTH: usize = 10;
+
+impl RecoveryCode {
+    // Accepts codes as shown to the user, ignoring case, dashes and spaces
+    pub fn parse(code: Secret<String>) -> eyre::Result<Self> {
+        let code: String = code
+            .expose_secret()
+            .chars()
+            .filter(|c| *c != '-' && !c.is_whitespace())
+            .map(|c| c.to_ascii_lowercase())
+            .collect();
+
+        if code.len() != RECOVERY_CODE_LENGTH
+            || !code.bytes().all(|b| RECOVERY_CODE_ALPHABET.contains(&b))
+        {
+            return Err(eyre!("Invalid recovery code"));
+        }
+
+        Ok(Self(Secret::new(code)))
+    }
+
+    // `xxxxx-xxxxx`, as shown to the user
+    pub fn formatted(&self) -> String {
+        let (first, second) = self.0.expose_secret().split_at(RECOVERY_CODE_LENGTH / 2);
+
+        format!("{}-{}", first, second)
+    }
+}
+
+impl AsRef<Secret<String>> for RecoveryCode {
+    fn as_ref(&self) -> &Secret<String> {
+        &self.0
+    }
+}
+
+impl PartialEq for RecoveryCode {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.expose_secret() == other.0.expose_secret()
+    }
+}
+
+impl Eq for RecoveryCode {}
+
+impl Default for RecoveryCode {
+    fn default() -> Self {
+        use rand::{rng, Rng};
+
+        let mut rng = rng();
+        let code = (0..RECOVERY_CODE_LENGTH)
+            .map(|_| {
+                RECOVERY_CODE_ALPHABET[rng.random_range(0..RECOVERY_CODE_ALPHABET.len())] as char
+            })
+            .collect();
+
+        Self(Secret::new(code))
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum RecoveryCodeStoreError {
+    #[error("Invalid recovery code")]
+    InvalidCode,
+    #[error("Unexpected error")]
+    UnexpectedError(#[source] eyre::Report),
+}
+
+impl PartialEq for RecoveryCodeStoreError {
+    fn eq(&self, other: &Self) -> bool {
+        core::mem::discriminant(self) == core::mem::discriminant(other)
+    }
+}
+
+#[async_trait::async_trait]
+pub trait RecoveryCodeStore: Send + Sync {
+    // Replaces every code of the user with the given ones
+    async fn replace_codes(
+        &mut self,
+        user_id: &UserId,
+        codes: Vec<RecoveryCode>,
+    ) -> Result<(), RecoveryCodeStoreError>;
+    // Removes the code if the user has it, so it cannot be used twice
+    async fn use_code(
+        &mut self,
+        user_id: &UserId,
+        code: &RecoveryCode,
+    ) -> Result<(), RecoveryCodeStoreError>;
+    async fn count_codes(&self, user_id: &UserId) -> Result<usize, RecoveryCodeStoreError>;
+}
+
+// ~~~ Passkey store
+// A WebAuthn credential registered by one of the user's authenticators
+#[derive(Debug, Clone, PartialEq)]
+pub struct PasskeyCredential {
+    // Base64url credential id, as chosen by the authenticator
+    pub id: String,
+    pub user_id: UserId,
+    // Uncompressed SEC1 encoding of the P-256 public key
+    pub public_key: Vec<u8>,
+    // Last signature counter reported by the authenticator, 0 if it keeps none
+    pub sign_count: u32,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum PasskeyStoreError {
+    #[error("Credential already exists")]
+    CredentialAlreadyExists,
+    #[error("Credential not found")]
+    CredentialNotFound,
+    #[error("Unexpected error")]
+    UnexpectedError(#[source] eyre::Report),
+}
+
+impl PartialEq for PasskeyStoreError {
+    fn eq(&self, other: &Self) -> bool {
+        core::mem::discriminant(self) == core::mem::discriminant(other)
+    }
+}
+
+#[async_trait::async_trait]
+pub trait PasskeyStore: Send + Sync {
+    async fn add_credential(
+        &mut self,
+        credential: PasskeyCredential,
+    ) -> Result<(), PasskeyStoreError>;
+    async fn get_credential(&self, id: &str) -> Result<PasskeyCredential, PasskeyStoreError>;
+    // Oldest first
+    async fn list_credentials(
+        &self,
+        user_id: &UserId,
+    ) -> Result<Vec<PasskeyCredential>, PasskeyStoreError>;
+    async fn update_sign_count(
+        &mut self,
+        id: &str,
+        sign_count: u32,
+    ) -> Result<(), PasskeyStoreError>;
+}
+
+// ~~~ Passkey challenge store
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PasskeyCeremony {
+    Registration,
+    Authentication,
+}
+
+// Issued when a ceremony starts and consumed when it finishes
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PasskeyChallenge {
+    pub ceremony: PasskeyCeremony,
+    // Base64url random bytes the authenticator signs over
+    pub challenge: String,
+    // The registering user, or the user named when starting a login
+    pub user_id: Option<UserId>,
+}
+
+impl PasskeyChallenge {
+    pub fn new(ceremony: PasskeyCeremony, user_id: Option<UserId>) -> Self {
+        use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+        use rand::{rng, Rng};
+
+        let mut challenge = [0u8; 32];
+        rng().fill(&mut challenge);
+
+        Self {
+            ceremony,
+            challenge: URL_SAFE_NO_PAD.encode(challenge),
+            user_id,
+        }
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum PasskeyChallengeStoreError {
+    #[error("Challenge not found")]
+    ChallengeNotFound,
+    #[error("Unexpected error")]
+    UnexpectedError(#[source] eyre::Report),
+}
+
+impl PartialEq for PasskeyChallengeStoreError {
+    fn eq(&self, other: &Self) -> bool {
+        core::mem::discriminant(self) == core::mem::discriminant(other)
+    }
+}
+
+// Challenges expire after PASSKEY_CHALLENGE_TTL_SECONDS
+#[async_trait::async_trait]
+pub trait PasskeyChallengeStore: Send + Sync {
+    async fn add_challenge(
+        &mut self,
+        id: String,
+        challenge: PasskeyChallenge,
+    ) -> Result<(), PasskeyChallengeStoreError>;
+    // Removes the challenge, so each one can only be answered once
+    async fn take_challenge(
+        &mut self,
+        id: &str,
+    ) -> Result<PasskeyChallenge, PasskeyChallengeStoreError>;
+}
+
+// ~~~ Password reset token store
+// Emailed in a reset link. Stores only see its SHA-256 fingerprint, so a leaked
+// store does not hand out working links.
+#[derive(Debug, Clone)]
+pub struct PasswordResetToken(Secret<String>);
+
+impl PasswordResetToken {
+    pub fn parse(token: Secret<String>) -> eyre::Result<Self> {
+        let bytes = URL_SAFE_NO_PAD
+            .decode(token.expose_secret())
+            .wrap_err("Invalid password reset token")?;
+
+        if bytes.len() != 32 {
+            return Err(eyre!("Invalid password reset token"));
+        }
+
+        Ok(Self(token))
+    }
+
+    pub fn fingerprint(&self) -> String {
+        URL_SAFE_NO_PAD.encode(Sha256::digest(self.0.expose_secret().as_bytes()))
+    }
+}
+
+impl AsRef<Secret<String>> for PasswordResetToken {
+    fn as_ref(&self) -> &Secret<String> {
+        &self.0
+    }
+}
+
+impl Default for PasswordResetToken {
+    fn default() -> Self {
+        use rand::{rng, Rng};
+
+        let mut token = [0u8; 32];
+        rng().fill(&mut token);
+
+        Self(Secret::new(URL_SAFE_NO_PAD.encode(token)))
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum PasswordResetTokenStoreError {
+    #[error("Token not found")]
+    TokenNotFound,
+    #[error("Unexpected error")]
+    UnexpectedError(#[source] eyre::Report),
+}
+
+impl PartialEq for PasswordResetTokenStoreError {
+    fn eq(&self, other: &Self) -> bool {
+        core::mem::discriminant(self) == core::mem::discriminant(other)
+    }
+}
+
+// Tokens expire after PASSWORD_RESET_TOKEN_TTL_SECONDS
+#[async_trait::async_trait]
+pub trait PasswordResetTokenStore: Send + Sync {
+    async fn add_token(
+        &mut self,
+        token: &PasswordResetToken,
+        user_id: UserId,
+    ) -> Result<(), PasswordResetTokenStoreError>;
+    // Looks the token up without using it
+    async fn peek_token(
+        &self,
+        token: &PasswordResetToken,
+    ) -> Result<UserId, PasswordResetTokenStoreError>;
+    // Removes the token, so each link can only be used once
+    async fn take_token(
+        &mut self,
+        token: &PasswordResetToken,
+    ) -> Result<UserId, PasswordResetTokenStoreError>;
+}
+
+// ~~~ Email verification token store
+// Emailed in a verification link on signup. Like reset tokens, only the
+// fingerprint is stored.
+#[derive(Debug, Clone)]
+pub struct EmailVerificationToken(Secret<String>);
+
+impl EmailVerificationToken {
+    pub fn parse(token: Secret<String>) -> eyre::Result<Self> {
+        let bytes = URL_SAFE_NO_PAD
+            .decode(token.expose_secret())
+            .wrap_err("Invalid email verification token")?;
+
+        if bytes.len() != 32 {
+            return Err(eyre!("Invalid email verification token"));
+        }
+
+        Ok(Self(token))
+    }
+
+    pub fn fingerprint(&self) -> String {
+        URL_SAFE_NO_PAD.encode(Sha256::digest(self.0.expose_secret().as_bytes()))
+    }
+}
+
+impl AsRef<Secret<String>> for EmailVerificationToken {
+    fn as_ref(&self) -> &Secret<String> {
+        &self.0
+    }
+}
+
+impl Default for EmailVerificationToken {
+    fn default() -> Self {
+        use rand::{rng, Rng};
+
+        let mut token = [0u8; 32];
+        rng().fill(&mut token);
+
+        Self(Secret::new(URL_SAFE_NO_PAD.encode(token)))
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum EmailVerificationTokenStoreError {
+    #[error("Token not found")]
+    TokenNotFound,
+    #[error("Unexpected error")]
+    UnexpectedError(#[source] eyre::Report),
+}
+
+impl PartialEq for EmailVerificationTokenStoreError {
+    fn eq(&self, other: &Self) -> bool {
+        core::mem::discriminant(self) == core::mem::discriminant(other)
+    }
+}
+
+// Tokens expire after EMAIL_VERIFICATION_TOKEN_TTL_SECONDS. Resending adds a new
+// token without revoking earlier ones.
+#[async_trait::async_trait]
+pub trait EmailVerificationTokenStore: Send + Sync {
+    async fn add_token(
+        &mut self,
+        token: &EmailVerificationToken,
+        user_id: UserId,
+    ) -> Result<(), EmailVerificationTokenStoreError>;
+    async fn take_token(
+        &mut self,
+        token: &EmailVerificationToken,
+    ) -> Result<UserId, EmailVerificationTokenStoreError>;
+}
+
+// ~~~ Rate limit store
+#[derive(Debug, thiserror::Error)]
+pub enum RateLimitStoreError {
+    #[error("Rate limit exceeded")]
+    LimitExceeded(u64), // Seconds until the key may be hit again
+    #[error("Unexpected error")]
+    UnexpectedError(#[source] eyre::Report),
+}
+
+impl PartialEq for RateLimitStoreError {
+    fn eq(&self, other: &Self) -> bool {
+        core::mem::discriminant(self) == core::mem::discriminant(other)
+    }
+}
+
+// Sliding window: a hit is allowed while fewer than `limit` hits were let
+// through for the key in the last `window_seconds`. Refused hits are not
+// counted, so a client is let back in as soon as its oldest hit leaves the window.
+#[async_trait::async_trait]
+pub trait RateLimitStore: Send + Sync {
+    async fn hit(
+        &mut self,
+        key: &str,
+        limit: u32,
+        window_seconds: i64,
+    ) -> Result<(), RateLimitStoreError>;
+}
+
+// ~~~ Signing key store
+// A key of the keyring as shared between replicas. The configured key is stored
+// without material, since every replica loads it from its own configuration.
+#[derive(Debug, Clone)]
+pub struct StoredSigningKey {
+    pub kid: String,
+    pub algorithm: Algorithm,
+    // Encrypted by the keyring with a key derived from the configured one
+    pub material: Option<Secret<String>>,
+    // Unset for the active key
+    pub retired_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum SigningKeyStoreError {
+    #[error("Signing keys were rotated concurrently")]
+    Conflict,
+    #[error("Unexpected error")]
+    UnexpectedError(#[source] eyre::Report),
+}
+
+impl PartialEq for SigningKeyStoreError {
+    fn eq(&self, other: &Self) -> bool {
+        core::mem::discriminant(self) == core::mem::discriminant(other)
+    }
+}
+
+// Keys are kept per configured key (`base_kid`), so configuring a new key starts over
+#[async_trait::async_trait]
+pub trait SigningKeyStore: Send + Sync {
+    // Empty until the configured key is first rotated
+    async fn load_keys(
+        &self,
+        base_kid: &str,
+    ) -> Result<Vec<StoredSigningKey>, SigningKeyStoreError>;
+    // Only succeeds while the stored active key is still `expected_active_kid`,
+    // `None` meaning nothing was stored yet, so two rotations cannot both win
+    async fn replace_keys(
+        &mut self,
+        base_kid: &str,
+        expected_active_kid: Option<&str>,
+        keys: Vec<StoredSigningKey>,
+    ) -> Result<(), SigningKeyStoreError>;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_two_fa_code_round_trips_parse() {
+        let code = TwoFACode::default();
+        let parsed = TwoFACode::parse(code.as_ref().expose_secret().to_owned()).unwrap();
+
+        assert_eq!(parsed, code);
+        assert!(TwoFACode::parse("12345".to_owned()).is_err());
+        assert!(TwoFACode::parse("12345a".to_owned()).is_err());
+    }
+
+    #[test]
+    fn test_two_fa_code_hash_matches_only_its_code_and_key() {
+        let key = Secret::new("two-fa-code-hash-key".to_owned());
+        let code = TwoFACode::parse("012345".to_owned()).unwrap();
+        let hash = code.hash(&key);
+
+        assert_eq!(hash, code.hash(&key));
+        assert_ne!(
+            hash,
+            TwoFACode::parse("012346".to_owned()).unwrap().hash(&key)
+        );
+        assert_ne!(hash, code.hash(&Secret::new("other-key".to_owned())));
+        assert_ne!(hash.as_ref().expose_secret(), code.as_ref().expose_secret());
+    }
+
+    #[test]
+    fn test_recovery_code_round_trips_formatted() {
+        let code = RecoveryCode::default();
+        let parsed = RecoveryCode::parse(Secret::new(code.formatted())).unwrap();
+
+        assert_eq!(parsed, code);
+        assert_eq!(code.formatted().len(), RECOVERY_CODE_LENGTH + 1);
+    }
+
+    #[test]
+    fn test_recovery_code_parse_normalizes_input() {
+        let code = RecoveryCode::parse(Secret::new(" ABCDE-fghjk ".to_owned())).unwrap();
+
+        assert_eq!(code.as_ref().expose_secret(), "abcdefghjk");
+    }
+
+    #[test]
+    fn test_recovery_code_parse_rejects_invalid_codes() {
+        for code in ["", "abcde", "abcde-fghjk-m", "abcde-fghj0", "abcde-fghj!"] {
+            assert!(RecoveryCode::parse(Secret::new(code.to_owned())).is_err());
+        }
     }
 }
@@ -1,4 +1,10 @@
+use crate::{
+    domain::PasswordPolicyViolation,
+    utils::{constants::HASHING_RETRY_AFTER_SECONDS, hashing_pool::HashingPoolError},
+};
+
 use axum::{
+    http::header::RETRY_AFTER,
     response::{IntoResponse, Response},
     Json,
 };
@@ -9,6 +15,15 @@ use serde::{Deserialize, Serialize};
 #[derive(Serialize, Deserialize, Clone)]
 pub struct ErrorResponse {
     pub error: String,
+    // Only set when a new password breaks the password policy
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub violations: Vec<PolicyViolationResponse>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct PolicyViolationResponse {
+    pub rule: String,
+    pub message: String,
 }
 
 #[derive(Debug, thiserror::Error)]
@@ -23,15 +38,89 @@ pub enum AuthAPIError {
     MissingToken,
     #[error("Invalid token")]
     InvalidToken,
+    #[error("Invalid client")]
+    InvalidClient,
+    #[error("TOTP already enabled")]
+    TotpAlreadyEnabled,
+    #[error("TOTP not enabled")]
+    TotpNotEnabled,
+    #[error("Session not found")]
+    SessionNotFound,
+    #[error("User not found")]
+    UserNotFound,
+    #[error("2FA not enabled")]
+    TwoFANotEnabled,
+    #[error("Passkey already registered")]
+    PasskeyAlreadyRegistered,
+    #[error("Too many 2FA attempts")]
+    TooManyTwoFAAttempts,
+    #[error("2FA code not sent by email")]
+    TwoFACodeNotEmailed,
+    #[error("2FA code resent too soon")]
+    ResendTooSoon(u64), // Seconds until the next resend is allowed
+    #[error("Too many 2FA code resends")]
+    TooManyResends,
+    #[error("Invalid password reset token")]
+    InvalidPasswordResetToken,
+    #[error("Email not verified")]
+    EmailNotVerified,
+    #[error("Invalid email verification token")]
+    InvalidEmailVerificationToken,
+    #[error("Password does not meet the password policy")]
+    WeakPassword(Vec<PasswordPolicyViolation>),
+    #[error("Account locked")]
+    AccountLocked(u64), // Seconds until the lock expires
+    #[error("Too many requests")]
+    TooManyRequests(u64), // Seconds until the client may try again
+    #[error("Service busy")]
+    ServiceBusy(u64), // Seconds to wait before retrying
     #[error("Unexpected error")]
     UnexpectedError(#[source] eyre::Report),
 }
 
+impl AuthAPIError {
+    // The stores report a full hashing queue like any other failure; it is
+    // only told apart here, so clients can back off instead of seeing a 500
+    fn with_overload_detected(self) -> Self {
+        match self {
+            AuthAPIError::UnexpectedError(e)
+                if e.chain().any(|cause| {
+                    cause.downcast_ref::<HashingPoolError>() == Some(&HashingPoolError::Saturated)
+                }) =>
+            {
+                AuthAPIError::ServiceBusy(HASHING_RETRY_AFTER_SECONDS)
+            }
+            e => e,
+        }
+    }
+}
+
 impl IntoResponse for AuthAPIError {
     fn into_response(self) -> Response {
-        log_error_chain(&self);
+        let error = self.with_overload_detected();
 
-        let (status, error_message) = match self {
+        log_error_chain(&error);
+
+        let retry_after = match error {
+            AuthAPIError::ResendTooSoon(seconds)
+            | AuthAPIError::AccountLocked(seconds)
+            | AuthAPIError::TooManyRequests(seconds)
+            | AuthAPIError::ServiceBusy(seconds) => Some(seconds),
+            _ => None,
+        };
+
+        let violations = match &error {
+            AuthAPIError::WeakPassword(violations) => violations
+                .iter()
+                .map(|violation| PolicyViolationResponse {
+                    rule: violation.rule().to_owned(),
+                    message: violation.to_string(),
+                })
+                .collect(),
+            _ => Vec::new(),
+        };
+
+        let (status, error_message) = match error {
             AuthAPIError::UserAlreadyExists => (StatusCode::CONFLICT, "User already exists"),
             AuthAPIError::InvalidCredentials => (StatusCode::BAD_REQUEST, "Invalid credentials"),
             AuthAPIError::IncorrectCredentials => {
@@ -39,6 +128,58 @@ impl IntoResponse for AuthAPIError {
             }
             AuthAPIError::MissingToken => (StatusCode::BAD_REQUEST, "Missing auth token"),
             AuthAPIError::InvalidToken => (StatusCode::UNAUTHORIZED, "Invalid auth token"),
+            AuthAPIError::InvalidClient => (StatusCode::UNAUTHORIZED, "Invalid client credentials"),
+            AuthAPIError::TotpAlreadyEnabled => (StatusCode::CONFLICT, "TOTP is already enabled"),
+            AuthAPIError::TotpNotEnabled => (StatusCode::CONFLICT, "TOTP is not enabled"),
+            AuthAPIError::SessionNotFound => (StatusCode::NOT_FOUND, "Session not found"),
+            AuthAPIError::UserNotFound => (StatusCode::NOT_FOUND, "User not found"),
+            AuthAPIError::TwoFANotEnabled => (StatusCode::BAD_REQUEST, "2FA is not enabled"),
+            AuthAPIError::PasskeyAlreadyRegistered => {
+                (StatusCode::CONFLICT, "Passkey is already registered")
+            }
+            AuthAPIError::TooManyTwoFAAttempts => (
+                StatusCode::UNAUTHORIZED,
+                "Too many failed attempts, please log in again",
+            ),
+            AuthAPIError::TwoFACodeNotEmailed => (
+                StatusCode::BAD_REQUEST,
+                "2FA codes for this login come from an authenticator app",
+            ),
+            AuthAPIError::ResendTooSoon(_) => (
+                StatusCode::TOO_MANY_REQUESTS,
+                "Please wait before requesting another code",
+            ),
+            AuthAPIError::TooManyResends => (
+                StatusCode::TOO_MANY_REQUESTS,
+                "Too many codes requested, please log in again",
+            ),
+            AuthAPIError::InvalidPasswordResetToken => (
+                StatusCode::BAD_REQUEST,
+                "Invalid or expired password reset link",
+            ),
+            AuthAPIError::EmailNotVerified => {
+                (StatusCode::FORBIDDEN, "Email address has not been verified")
+            }
+            AuthAPIError::InvalidEmailVerificationToken => (
+                StatusCode::BAD_REQUEST,
+                "Invalid or expired verification link",
+            ),
+            AuthAPIError::WeakPassword(_) => (
+                StatusCode::BAD_REQUEST,
+                "Password does not meet the requirements",
+            ),
+            AuthAPIError::AccountLocked(_) => (
+                StatusCode::LOCKED,
+                "Account is locked after too many failed logins, please try again later",
+            ),
+            AuthAPIError::TooManyRequests(_) => (
+                StatusCode::TOO_MANY_REQUESTS,
+                "Too many requests, please try again later",
+            ),
+            AuthAPIError::ServiceBusy(_) => (
+                StatusCode::SERVICE_UNAVAILABLE,
+                "Server is busy, please try again later",
+            ),
             AuthAPIError::UnexpectedError(_) => {
                 (StatusCode::INTERNAL_SERVER_ERROR, "Unexpected error")
             }
@@ -46,9 +187,13 @@ impl IntoResponse for AuthAPIError {
 
         let body = Json(ErrorResponse {
             error: error_message.to_string(),
+            violations,
         });
 
-        (status, body).into_response()
+        match retry_after {
+            Some(seconds) => (status, [(RETRY_AFTER, seconds.to_string())], body).into_response(),
+            None => (status, body).into_response(),
+        }
     }
 }
 
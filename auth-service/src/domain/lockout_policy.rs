@@ -0,0 +1,63 @@
+use chrono::Duration;
+
+// Locks an account after `threshold` failed logins in a row. Each further run of
+// `threshold` failures locks it again for twice as long, up to the maximum.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LockoutPolicy {
+    threshold: u32,
+    duration: Duration,
+    max_duration: Duration,
+}
+
+impl LockoutPolicy {
+    pub fn new(threshold: u32, seconds: i64, max_seconds: i64) -> Self {
+        Self {
+            threshold,
+            duration: Duration::seconds(seconds),
+            max_duration: Duration::seconds(max_seconds),
+        }
+    }
+
+    // How long to lock the account for once it has failed `failures` logins in a
+    // row, if this failure locks it at all
+    pub fn lock_duration(&self, failures: u32) -> Option<Duration> {
+        if self.threshold == 0 || failures == 0 || !failures.is_multiple_of(self.threshold) {
+            return None;
+        }
+
+        // Capped well before the shift could overflow
+        let doublings = (failures / self.threshold - 1).min(32);
+        let seconds = self
+            .duration
+            .num_seconds()
+            .saturating_mul(1 << doublings)
+            .min(self.max_duration.num_seconds());
+
+        Some(Duration::seconds(seconds))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_lock_every_threshold_failures_for_longer() {
+        let policy = LockoutPolicy::new(5, 900, 3600);
+
+        assert_eq!(policy.lock_duration(0), None);
+        assert_eq!(policy.lock_duration(4), None);
+        assert_eq!(policy.lock_duration(5), Some(Duration::minutes(15)));
+        assert_eq!(policy.lock_duration(6), None);
+        assert_eq!(policy.lock_duration(10), Some(Duration::minutes(30)));
+        assert_eq!(policy.lock_duration(15), Some(Duration::hours(1)));
+        assert_eq!(policy.lock_duration(5000), Some(Duration::hours(1)));
+    }
+
+    #[test]
+    fn should_never_lock_with_zero_threshold() {
+        let policy = LockoutPolicy::new(0, 900, 3600);
+
+        assert_eq!(policy.lock_duration(5), None);
+    }
+}
@@ -1,11 +1,23 @@
+pub mod breached_password_checker;
 pub mod data_stores;
 pub mod email;
 pub mod email_client;
 pub mod error;
+pub mod lockout_policy;
 pub mod password;
+pub mod password_hasher;
+pub mod password_policy;
+pub mod two_fa_method;
 pub mod user;
+pub mod user_id;
 
+pub use breached_password_checker::*;
 pub use email::*;
 pub use email_client::*;
+pub use lockout_policy::*;
 pub use password::*;
+pub use password_hasher::*;
+pub use password_policy::*;
+pub use two_fa_method::*;
 pub use user::*;
+pub use user_id::*;
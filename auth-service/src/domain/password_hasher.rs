@@ -0,0 +1,28 @@
+use color_eyre::eyre;
+use secrecy::Secret;
+
+// Hashes are PHC strings (`$<algorithm>$...`), or bcrypt's `$2b$...` which has
+// the same shape. Hashing is CPU bound, so callers run it off the async runtime.
+pub trait PasswordHasher {
+    // Whether the hash is in a format this hasher verifies
+    fn can_verify(&self, password_hash: &str) -> bool;
+    fn hash(&self, password: &Secret<String>) -> eyre::Result<Secret<String>>;
+    // `Ok(false)` for a wrong password. Errors mean the hash could not be checked,
+    // like a malformed or unsupported one.
+    fn verify(
+        &self,
+        password_hash: &Secret<String>,
+        password: &Secret<String>,
+    ) -> eyre::Result<bool>;
+    // Whether the hash is weaker than one this hasher would make now
+    fn needs_rehash(&self, password_hash: &str) -> bool;
+}
+
+// The `argon2id` of `$argon2id$v=19$...`
+pub fn phc_algorithm(password_hash: &str) -> Option<&str> {
+    password_hash
+        .strip_prefix('$')?
+        .split('$')
+        .next()
+        .filter(|algorithm| !algorithm.is_empty())
+}
@@ -0,0 +1,252 @@
+use crate::domain::{Email, Password};
+
+use color_eyre::eyre::{self, eyre};
+use secrecy::{ExposeSecret, Secret};
+use std::fmt;
+
+// The floor `Password::parse` enforces on every password, old or new
+const MIN_PASSWORD_LENGTH: usize = 8;
+
+// Local parts shorter than this would reject too many unrelated passwords
+const MIN_EMAIL_LOCAL_PART_LENGTH: usize = 3;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CharacterClass {
+    Lowercase,
+    Uppercase,
+    Digit,
+    Symbol,
+}
+
+impl CharacterClass {
+    pub fn parse(class: &str) -> eyre::Result<Self> {
+        match class.trim().to_lowercase().as_str() {
+            "lowercase" => Ok(Self::Lowercase),
+            "uppercase" => Ok(Self::Uppercase),
+            "digit" => Ok(Self::Digit),
+            "symbol" => Ok(Self::Symbol),
+            _ => Err(eyre!("Unknown character class: {}", class)),
+        }
+    }
+
+    fn matches(&self, c: char) -> bool {
+        match self {
+            Self::Lowercase => c.is_lowercase(),
+            Self::Uppercase => c.is_uppercase(),
+            Self::Digit => c.is_numeric(),
+            Self::Symbol => !c.is_alphanumeric() && !c.is_whitespace(),
+        }
+    }
+}
+
+impl fmt::Display for CharacterClass {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Lowercase => write!(f, "lowercase letter"),
+            Self::Uppercase => write!(f, "uppercase letter"),
+            Self::Digit => write!(f, "digit"),
+            Self::Symbol => write!(f, "symbol"),
+        }
+    }
+}
+
+// One failed rule. The message is meant for the user, `rule` for clients.
+#[derive(Debug, Clone, PartialEq, thiserror::Error)]
+pub enum PasswordPolicyViolation {
+    #[error("Password must be at least {0} characters long")]
+    TooShort(usize),
+    #[error("Password must be at most {0} characters long")]
+    TooLong(usize),
+    #[error("Password must contain a {0}")]
+    MissingCharacterClass(CharacterClass),
+    #[error("Password is too easy to guess. {0}")]
+    TooWeak(String), // Suggestions from the strength estimate
+    #[error("Password must not contain the email address")]
+    ContainsEmail,
+    // Reported by a `BreachedPasswordChecker` rather than the policy itself
+    #[error("Password has appeared in a data breach")]
+    Breached,
+}
+
+impl PasswordPolicyViolation {
+    pub fn rule(&self) -> &'static str {
+        match self {
+            Self::TooShort(_) => "min_length",
+            Self::TooLong(_) => "max_length",
+            Self::MissingCharacterClass(_) => "character_class",
+            Self::TooWeak(_) => "strength",
+            Self::ContainsEmail => "contains_email",
+            Self::Breached => "breached",
+        }
+    }
+}
+
+// Rules new passwords are checked against at signup, password change and reset.
+// Existing passwords are never re-checked, so tightening the policy does not
+// lock anyone out.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PasswordPolicy {
+    min_length: usize,
+    max_length: usize,
+    required_classes: Vec<CharacterClass>,
+    // zxcvbn score from 0 (guessable in 10^3 tries) to 4 (over 10^10 tries)
+    min_strength: u8,
+}
+
+impl PasswordPolicy {
+    pub fn new(
+        min_length: usize,
+        max_length: usize,
+        required_classes: Vec<CharacterClass>,
+        min_strength: u8,
+    ) -> eyre::Result<Self> {
+        if min_length < MIN_PASSWORD_LENGTH {
+            return Err(eyre!(
+                "Minimum password length must be at least {}",
+                MIN_PASSWORD_LENGTH
+            ));
+        }
+
+        if max_length < min_length {
+            return Err(eyre!("Maximum password length is below the minimum"));
+        }
+
+        if min_strength > 4 {
+            return Err(eyre!("Minimum password strength must be between 0 and 4"));
+        }
+
+        Ok(Self {
+            min_length,
+            max_length,
+            required_classes,
+            min_strength,
+        })
+    }
+
+    // Reports every rule the password breaks, not just the first
+    pub fn check(
+        &self,
+        password: Secret<String>,
+        email: &Email,
+    ) -> Result<Password, Vec<PasswordPolicyViolation>> {
+        let candidate = password.expose_secret();
+        let length = candidate.chars().count();
+        let mut violations = Vec::new();
+
+        if length < self.min_length {
+            violations.push(PasswordPolicyViolation::TooShort(self.min_length));
+        }
+
+        if length > self.max_length {
+            violations.push(PasswordPolicyViolation::TooLong(self.max_length));
+        }
+
+        for class in &self.required_classes {
+            if !candidate.chars().any(|c| class.matches(c)) {
+                violations.push(PasswordPolicyViolation::MissingCharacterClass(*class));
+            }
+        }
+
+        let email = email.as_ref().expose_secret().to_lowercase();
+        let local_part = email.split('@').next().unwrap_or_default();
+
+        if local_part.chars().count() >= MIN_EMAIL_LOCAL_PART_LENGTH
+            && candidate.to_lowercase().contains(local_part)
+        {
+            violations.push(PasswordPolicyViolation::ContainsEmail);
+        }
+
+        // Overly long input is already rejected, and would only slow the estimate down
+        if length <= self.max_length {
+            let entropy = zxcvbn::zxcvbn(candidate, &[local_part, &email]);
+
+            if u8::from(entropy.score()) < self.min_strength {
+                let suggestions = entropy
+                    .feedback()
+                    .map(|feedback| {
+                        feedback
+                            .suggestions()
+                            .iter()
+                            .map(ToString::to_string)
+                            .collect::<Vec<_>>()
+                            .join(" ")
+                    })
+                    .filter(|suggestions| !suggestions.is_empty())
+                    .unwrap_or("Add another word or two.".to_owned());
+
+                violations.push(PasswordPolicyViolation::TooWeak(suggestions));
+            }
+        }
+
+        if !violations.is_empty() {
+            return Err(violations);
+        }
+
+        Ok(Password::from(password))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn email() -> Email {
+        Email::parse(Secret::new("jane.doe@example.com".to_owned())).unwrap()
+    }
+
+    fn check(policy: &PasswordPolicy, password: &str) -> Vec<PasswordPolicyViolation> {
+        policy
+            .check(Secret::new(password.to_owned()), &email())
+            .err()
+            .unwrap_or_default()
+    }
+
+    #[test]
+    fn test_accepts_strong_password() {
+        let policy = PasswordPolicy::new(8, 64, vec![], 3).unwrap();
+
+        assert!(check(&policy, "Tr0ub4dor&3-horse").is_empty());
+    }
+
+    #[test]
+    fn test_reports_every_violation() {
+        let policy = PasswordPolicy::new(12, 64, vec![CharacterClass::Uppercase], 3).unwrap();
+
+        let rules: Vec<_> = check(&policy, "jane.doe1")
+            .iter()
+            .map(PasswordPolicyViolation::rule)
+            .collect();
+
+        assert_eq!(
+            rules,
+            [
+                "min_length",
+                "character_class",
+                "contains_email",
+                "strength"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_rejects_common_and_overly_long_passwords() {
+        let policy = PasswordPolicy::new(8, 20, vec![], 3).unwrap();
+
+        assert!(matches!(
+            check(&policy, "password123")[..],
+            [PasswordPolicyViolation::TooWeak(_)]
+        ));
+        assert_eq!(
+            check(&policy, &"Tr0ub4dor&3-horse".repeat(2)),
+            [PasswordPolicyViolation::TooLong(20)]
+        );
+    }
+
+    #[test]
+    fn test_rejects_invalid_configuration() {
+        assert!(PasswordPolicy::new(7, 64, vec![], 3).is_err());
+        assert!(PasswordPolicy::new(12, 10, vec![], 3).is_err());
+        assert!(PasswordPolicy::new(8, 64, vec![], 5).is_err());
+        assert!(CharacterClass::parse("emoji").is_err());
+    }
+}
@@ -0,0 +1,57 @@
+use color_eyre::eyre;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+// Second factor a user must present after their password
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TwoFAMethod {
+    #[default]
+    None,
+    // Code emailed at login
+    Email,
+    // Code from an authenticator app (RFC 6238)
+    Totp,
+}
+
+impl TwoFAMethod {
+    pub fn parse(method: &str) -> eyre::Result<Self> {
+        match method {
+            "none" => Ok(Self::None),
+            "email" => Ok(Self::Email),
+            "totp" => Ok(Self::Totp),
+            _ => Err(eyre::eyre!("{} is not a valid 2FA method.", method)),
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::None => "none",
+            Self::Email => "email",
+            Self::Totp => "totp",
+        }
+    }
+}
+
+impl fmt::Display for TwoFAMethod {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_round_trips_as_str() {
+        for method in [TwoFAMethod::None, TwoFAMethod::Email, TwoFAMethod::Totp] {
+            assert_eq!(TwoFAMethod::parse(method.as_str()).unwrap(), method);
+        }
+    }
+
+    #[test]
+    fn test_parse_rejects_unknown_method() {
+        assert!(TwoFAMethod::parse("sms").is_err());
+    }
+}
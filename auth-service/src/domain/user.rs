@@ -1,29 +1,101 @@
-use crate::domain::{email::Email, password::Password};
+use crate::domain::{
+    email::Email, password::Password, two_fa_method::TwoFAMethod, user_id::UserId,
+};
 
+use chrono::{DateTime, Utc};
+use secrecy::Secret;
 use serde::Deserialize;
 
-#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[derive(Debug, Clone, Deserialize)]
 pub struct User {
+    #[serde(default)]
+    pub id: UserId,
     pub email: Email,
     pub password: Password,
-    #[serde(rename = "requires2FA")]
-    pub requires_2fa: bool,
+    #[serde(default, rename = "twoFAMethod")]
+    pub two_fa_method: TwoFAMethod,
+    // Base32 TOTP secret. Set at enrollment, only used once `two_fa_method` is TOTP.
+    #[serde(skip)]
+    pub totp_secret: Option<Secret<String>>,
+    // Embedded in every token. Bumping it logs the user out everywhere.
+    #[serde(default)]
+    pub token_generation: u32,
+    // Set once the user follows the emailed verification link. Required to log in.
+    #[serde(default)]
+    pub email_verified: bool,
+    // Failed logins since the last successful one
+    #[serde(skip)]
+    pub failed_login_count: u32,
+    #[serde(skip)]
+    pub locked_until: Option<DateTime<Utc>>,
 }
 
 impl User {
-    pub fn new(email: Email, password: Password, requires_2fa: bool) -> Self {
+    pub fn new(email: Email, password: Password, two_fa_method: TwoFAMethod) -> Self {
         Self {
+            id: UserId::default(),
             email,
             password,
-            requires_2fa,
+            two_fa_method,
+            totp_secret: None,
+            token_generation: 0,
+            email_verified: false,
+            failed_login_count: 0,
+            locked_until: None,
         }
     }
 
+    pub fn id(&self) -> &UserId {
+        &self.id
+    }
+
     pub fn email(&self) -> &Email {
         &self.email
     }
 
+    pub fn token_generation(&self) -> u32 {
+        self.token_generation
+    }
+
+    pub fn email_verified(&self) -> bool {
+        self.email_verified
+    }
+
+    pub fn failed_login_count(&self) -> u32 {
+        self.failed_login_count
+    }
+
+    // When the current lock ends, if the account is locked now
+    pub fn locked_until(&self) -> Option<DateTime<Utc>> {
+        self.locked_until.filter(|until| *until > Utc::now())
+    }
+
+    pub fn two_fa_method(&self) -> TwoFAMethod {
+        self.two_fa_method
+    }
+
+    pub fn totp_secret(&self) -> Option<&Secret<String>> {
+        self.totp_secret.as_ref()
+    }
+
     pub fn has_2fa(&self) -> bool {
-        self.requires_2fa
+        self.two_fa_method != TwoFAMethod::None
+    }
+}
+
+impl PartialEq for User {
+    fn eq(&self, other: &Self) -> bool {
+        use secrecy::ExposeSecret;
+
+        self.id == other.id
+            && self.email == other.email
+            && self.password == other.password
+            && self.two_fa_method == other.two_fa_method
+            && self.totp_secret.as_ref().map(ExposeSecret::expose_secret)
+                == other.totp_secret.as_ref().map(ExposeSecret::expose_secret)
+            && self.token_generation == other.token_generation
+            && self.email_verified == other.email_verified
+            && self.failed_login_count == other.failed_login_count
+            && self.locked_until == other.locked_until
     }
 }
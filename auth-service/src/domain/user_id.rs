@@ -0,0 +1,52 @@
+use color_eyre::eyre::{self, Context};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use uuid::Uuid;
+
+// Stable identifier for a user. Unlike the email it never changes, so it is used as the JWT `sub`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct UserId(Uuid);
+
+impl UserId {
+    pub fn parse(id: &str) -> eyre::Result<Self> {
+        let id = Uuid::parse_str(id).wrap_err(format!("{} is not a valid user id.", id))?;
+
+        Ok(Self(id))
+    }
+}
+
+impl Default for UserId {
+    fn default() -> Self {
+        Self(Uuid::new_v4())
+    }
+}
+
+impl AsRef<Uuid> for UserId {
+    fn as_ref(&self) -> &Uuid {
+        &self.0
+    }
+}
+
+impl fmt::Display for UserId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_round_trips_display() {
+        let id = UserId::default();
+
+        assert_eq!(UserId::parse(&id.to_string()).unwrap(), id);
+    }
+
+    #[test]
+    fn test_parse_rejects_invalid_id() {
+        assert!(UserId::parse("test@example.com").is_err());
+        assert!(UserId::parse("").is_err());
+    }
+}
@@ -1,21 +1,52 @@
 use crate::{
-    app_state::app_state::AppState,
+    app_state::app_state::{AppState, BreachedPasswordCheckerType},
     routes::{
-        login::login_handler, logout::logout_handler, signup::signup_handler,
-        verify_2fa::verify_2fa_handler, verify_token::verify_token_handler,
+        admin::{hashing_metrics_handler, rotate_signing_key_handler, unlock_account_handler},
+        change_password::change_password_handler,
+        introspect::introspect_handler,
+        jwks::jwks_handler,
+        login::login_handler,
+        logout::logout_handler,
+        logout_all::logout_all_handler,
+        passkey::{
+            finish_passkey_login_handler, finish_passkey_registration_handler,
+            start_passkey_login_handler, start_passkey_registration_handler,
+        },
+        password_reset::{confirm_password_reset_handler, request_password_reset_handler},
+        recovery_codes::{count_recovery_codes_handler, regenerate_recovery_codes_handler},
+        refresh::refresh_handler,
+        resend_2fa::resend_2fa_handler,
+        sessions::{list_sessions_handler, revoke_session_handler},
+        signup::signup_handler,
+        totp::{confirm_totp_handler, disable_totp_handler, enroll_totp_handler},
+        verify_2fa::verify_2fa_handler,
+        verify_email::{resend_verification_email_handler, verify_email_handler},
+        verify_token::verify_token_handler,
     },
+    services::{BloomFilterBreachedPasswordChecker, HibpBreachedPasswordChecker},
     utils::{
-        constants::{DATABASE_URL, REDIS_HOST_NAME},
+        auth::load_signing_key,
+        breached_passwords::BloomFilter,
+        constants::{BREACHED_PASSWORDS_PATH, DATABASE_URL, REDIS_HOST_NAME},
+        keyring::{refresh_keyring_periodically, reload_keyring, Keyring},
+        rate_limit::rate_limit,
         tracing::{make_span_with_request_id, on_request, on_response},
     },
 };
 
-use axum::{routing::post, serve::Serve, Router};
+use axum::{
+    extract::{connect_info::IntoMakeServiceWithConnectInfo, ConnectInfo},
+    middleware::{self, AddExtension},
+    routing::{delete, get, post},
+    serve::Serve,
+    Router,
+};
 use redis::{Client, RedisResult};
 use reqwest::Method;
 use secrecy::{ExposeSecret, Secret};
 use sqlx::{mysql::MySqlPoolOptions, MySqlPool};
-use std::{error::Error, io};
+use std::{error::Error, io, net::SocketAddr, path::Path, sync::Arc};
+use tokio::sync::RwLock;
 use tower_http::{cors::CorsLayer, services::ServeDir, trace::TraceLayer};
 
 pub mod api;
@@ -26,29 +57,117 @@ pub mod services;
 pub mod utils;
 
 pub struct Application {
-    server: Serve<Router, Router>,
+    server: Serve<
+        IntoMakeServiceWithConnectInfo<Router, SocketAddr>,
+        AddExtension<Router, ConnectInfo<SocketAddr>>,
+    >,
     pub address: String,
 }
 
 impl Application {
     pub async fn build(app_state: AppState, address: &str) -> Result<Self, Box<dyn Error>> {
+        // Starts from the keys rotated in by earlier runs or other replicas
+        reload_keyring(&app_state.keyring, &app_state.signing_key_store).await?;
+        tokio::spawn(refresh_keyring_periodically(
+            app_state.keyring.clone(),
+            app_state.signing_key_store.clone(),
+        ));
+
         let allowed_origins = [
             "http://localhost:8000".parse()?,
             "http://[droplet_IP]:8000".parse()?,
         ];
 
         let cors = CorsLayer::new()
-            .allow_methods([Method::GET, Method::POST])
+            .allow_methods([Method::GET, Method::POST, Method::DELETE])
             .allow_credentials(true)
             .allow_origin(allowed_origins);
 
+        // Routes that take credentials or send email
+        let rate_limited = middleware::from_fn_with_state(app_state.clone(), rate_limit);
+
         let router = Router::new()
             .nest_service("/", ServeDir::new("assets"))
-            .route("/signup", post(signup_handler))
-            .route("/login", post(login_handler))
+            .route(
+                "/signup",
+                post(signup_handler).route_layer(rate_limited.clone()),
+            )
+            .route(
+                "/login",
+                post(login_handler).route_layer(rate_limited.clone()),
+            )
+            .route(
+                "/login/passkey/start",
+                post(start_passkey_login_handler).route_layer(rate_limited.clone()),
+            )
+            .route(
+                "/login/passkey/finish",
+                post(finish_passkey_login_handler).route_layer(rate_limited.clone()),
+            )
             .route("/logout", post(logout_handler))
-            .route("/verify-2fa", post(verify_2fa_handler))
+            .route("/logout-all", post(logout_all_handler))
+            .route(
+                "/verify-2fa",
+                post(verify_2fa_handler).route_layer(rate_limited.clone()),
+            )
+            .route("/verify-email", post(verify_email_handler))
+            .route(
+                "/verify-email/resend",
+                post(resend_verification_email_handler).route_layer(rate_limited.clone()),
+            )
+            .route(
+                "/password-reset/request",
+                post(request_password_reset_handler).route_layer(rate_limited.clone()),
+            )
+            .route(
+                "/password-reset/confirm",
+                post(confirm_password_reset_handler).route_layer(rate_limited.clone()),
+            )
+            .route(
+                "/change-password",
+                post(change_password_handler).route_layer(rate_limited.clone()),
+            )
+            .route(
+                "/resend-2fa",
+                post(resend_2fa_handler).route_layer(rate_limited.clone()),
+            )
+            .route(
+                "/2fa/totp/enroll",
+                post(enroll_totp_handler).route_layer(rate_limited.clone()),
+            )
+            .route(
+                "/2fa/totp/confirm",
+                post(confirm_totp_handler).route_layer(rate_limited.clone()),
+            )
+            .route(
+                "/2fa/totp/disable",
+                post(disable_totp_handler).route_layer(rate_limited.clone()),
+            )
+            .route(
+                "/passkeys/register/start",
+                post(start_passkey_registration_handler).route_layer(rate_limited.clone()),
+            )
+            .route(
+                "/passkeys/register/finish",
+                post(finish_passkey_registration_handler).route_layer(rate_limited.clone()),
+            )
+            .route(
+                "/2fa/recovery-codes",
+                get(count_recovery_codes_handler)
+                    .merge(post(regenerate_recovery_codes_handler).route_layer(rate_limited)),
+            )
             .route("/verify-token", post(verify_token_handler))
+            .route("/introspect", post(introspect_handler))
+            .route("/refresh", post(refresh_handler))
+            .route("/sessions", get(list_sessions_handler))
+            .route("/sessions/:id", delete(revoke_session_handler))
+            .route("/.well-known/jwks.json", get(jwks_handler))
+            .route(
+                "/admin/rotate-signing-key",
+                post(rotate_signing_key_handler),
+            )
+            .route("/admin/metrics/hashing", get(hashing_metrics_handler))
+            .route("/admin/unlock-account", post(unlock_account_handler))
             .with_state(app_state)
             .layer(cors)
             .layer(
@@ -61,7 +180,11 @@ impl Application {
         let listener = tokio::net::TcpListener::bind(address).await?;
 
         let address = listener.local_addr()?.to_string();
-        let server = axum::serve(listener, router);
+        // Peer addresses are recorded on sessions
+        let server = axum::serve(
+            listener,
+            router.into_make_service_with_connect_info::<SocketAddr>(),
+        );
 
         Ok(Application { server, address })
     }
@@ -77,7 +200,6 @@ pub async fn configure_mysql() -> MySqlPool {
         .await
         .expect("Failed to create MySql connection pool!");
 
- 
     sqlx::migrate!()
         .run(&mysql_pool)
         .await
@@ -100,6 +222,30 @@ pub fn configure_redis() -> redis::Connection {
         .expect("Failed to get Redis connection")
 }
 
+pub fn configure_keyring() -> Keyring {
+    Keyring::new(load_signing_key())
+}
+
+pub fn configure_breached_password_checker() -> BreachedPasswordCheckerType {
+    let Some(path) = BREACHED_PASSWORDS_PATH.as_ref().map(Path::new) else {
+        tracing::warn!("BREACHED_PASSWORDS_PATH is not set, breached passwords are accepted");
+        return Arc::new(RwLock::new(HibpBreachedPasswordChecker::default()));
+    };
+
+    if BloomFilter::is_filter_file(path) {
+        let checker = BloomFilterBreachedPasswordChecker::load(path)
+            .expect("Failed to load breached password filter.");
+
+        return Arc::new(RwLock::new(checker));
+    }
+
+    let checker =
+        HibpBreachedPasswordChecker::load(path).expect("Failed to load breached passwords.");
+    tracing::info!("Loaded {} breached password hashes", checker.len());
+
+    Arc::new(RwLock::new(checker))
+}
+
 pub fn get_redis_client(redis_hostname: String) -> RedisResult<Client> {
     let redis_url = format!("redis://{}/", redis_hostname);
     redis::Client::open(redis_url)
@@ -1,13 +1,18 @@
 use auth_service::{
     app_state::app_state::AppState,
-    configure_mysql, configure_redis,
+    configure_breached_password_checker, configure_keyring, configure_mysql, configure_redis,
     domain::Email,
     services::{
-        data_stores::{MySqlUserStore, RedisBannedTokenStore, RedisTwoFACodeStore},
-        MockEmailClient, PostmarkEmailClient,
+        data_stores::{
+            MySqlPasskeyStore, MySqlRecoveryCodeStore, MySqlSessionStore, MySqlUserStore,
+            RedisBannedTokenStore, RedisEmailVerificationTokenStore, RedisPasskeyChallengeStore,
+            RedisPasswordResetTokenStore, RedisRateLimitStore, RedisRefreshTokenStore,
+            RedisSigningKeyStore, RedisTwoFACodeStore,
+        },
+        PostmarkEmailClient,
     },
     utils::{
-        constants::{prod, POSTMARK_AUTH_TOKEN},
+        constants::{prod, POSTMARK_AUTH_TOKEN, TWO_FA_CODE_HASH_KEY},
         tracing::init_tracing,
     },
     Application,
@@ -25,14 +30,55 @@ async fn main() {
     let mysql_pool = configure_mysql().await;
     let redis_connection = Arc::new(RwLock::new(configure_redis()));
 
-    let user_store = Arc::new(RwLock::new(MySqlUserStore::new(mysql_pool)));
+    let user_store = Arc::new(RwLock::new(MySqlUserStore::new(mysql_pool.clone())));
+    let session_store = Arc::new(RwLock::new(MySqlSessionStore::new(mysql_pool.clone())));
+    let recovery_code_store =
+        Arc::new(RwLock::new(MySqlRecoveryCodeStore::new(mysql_pool.clone())));
+    let passkey_store = Arc::new(RwLock::new(MySqlPasskeyStore::new(mysql_pool)));
     let banned_token_store = Arc::new(RwLock::new(RedisBannedTokenStore::new(
         redis_connection.clone(),
     )));
-    let two_fa_store = Arc::new(RwLock::new(RedisTwoFACodeStore::new(redis_connection)));
+    let two_fa_store = Arc::new(RwLock::new(RedisTwoFACodeStore::new(
+        redis_connection.clone(),
+        TWO_FA_CODE_HASH_KEY.clone(),
+    )));
+    let passkey_challenge_store = Arc::new(RwLock::new(RedisPasskeyChallengeStore::new(
+        redis_connection.clone(),
+    )));
+    let password_reset_token_store = Arc::new(RwLock::new(RedisPasswordResetTokenStore::new(
+        redis_connection.clone(),
+    )));
+    let email_verification_token_store = Arc::new(RwLock::new(
+        RedisEmailVerificationTokenStore::new(redis_connection.clone()),
+    ));
+    let rate_limit_store = Arc::new(RwLock::new(RedisRateLimitStore::new(
+        redis_connection.clone(),
+    )));
+    let signing_key_store = Arc::new(RwLock::new(RedisSigningKeyStore::new(
+        redis_connection.clone(),
+    )));
+    let refresh_token_store = Arc::new(RwLock::new(RedisRefreshTokenStore::new(redis_connection)));
     let email_client = Arc::new(RwLock::new(configure_postmark_email_client()));
+    let keyring = Arc::new(RwLock::new(configure_keyring()));
+    let breached_password_checker = configure_breached_password_checker();
 
-    let app_state = AppState::new(user_store, banned_token_store, two_fa_store, email_client);
+    let app_state = AppState::new(
+        user_store,
+        banned_token_store,
+        two_fa_store,
+        email_client,
+        refresh_token_store,
+        keyring,
+        session_store,
+        recovery_code_store,
+        passkey_store,
+        passkey_challenge_store,
+        password_reset_token_store,
+        email_verification_token_store,
+        breached_password_checker,
+        rate_limit_store,
+        signing_key_store,
+    );
 
     let app = Application::build(app_state, prod::APP_ADDRESS)
         .await
@@ -0,0 +1,104 @@
+use crate::{
+    app_state::app_state::AppState,
+    domain::{data_stores::UserStoreError, email::Email, error::AuthAPIError},
+    utils::{
+        auth::credentials_match,
+        constants::ADMIN_API_TOKEN,
+        hashing_pool::{HashingPoolStats, HASHING_POOL},
+        keyring::rotate_keyring,
+    },
+};
+
+use axum::{extract::State, response::IntoResponse, Json};
+use axum_extra::{
+    headers::{authorization::Bearer, Authorization},
+    TypedHeader,
+};
+use secrecy::Secret;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct RotateSigningKeyResponse {
+    pub kid: String,
+}
+
+// Makes a new signing key active on every replica. Tokens signed by the previous
+// key keep validating until they expire, after which the old key is dropped.
+#[tracing::instrument(name = "Rotate_Signing_Key", skip_all)]
+pub async fn rotate_signing_key_handler(
+    State(state): State<AppState>,
+    authorization: Option<TypedHeader<Authorization<Bearer>>>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    authorize_admin(authorization)?;
+
+    let kid = rotate_keyring(&state.keyring, &state.signing_key_store)
+        .await
+        .map_err(AuthAPIError::UnexpectedError)?;
+
+    tracing::info!(kid = %kid, "Rotated JWT signing key");
+
+    Ok(Json(RotateSigningKeyResponse { kid }))
+}
+
+// Load on the password hashing pool, including how long jobs wait for a thread
+#[tracing::instrument(name = "Hashing_Metrics", skip_all)]
+pub async fn hashing_metrics_handler(
+    authorization: Option<TypedHeader<Authorization<Bearer>>>,
+) -> Result<Json<HashingPoolStats>, AuthAPIError> {
+    authorize_admin(authorization)?;
+
+    Ok(Json(HASHING_POOL.stats()))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UnlockAccountRequest {
+    pub email: Secret<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct UnlockAccountResponse {
+    pub message: String,
+}
+
+// Lifts a lockout from failed logins before it runs out, and forgets the failures
+#[tracing::instrument(name = "Unlock_Account", skip_all)]
+pub async fn unlock_account_handler(
+    State(state): State<AppState>,
+    authorization: Option<TypedHeader<Authorization<Bearer>>>,
+    Json(request): Json<UnlockAccountRequest>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    authorize_admin(authorization)?;
+
+    let email = Email::parse(request.email).map_err(|_| AuthAPIError::InvalidCredentials)?;
+    let mut user_store = state.user_store.write().await;
+
+    let user = user_store.get_user(&email).await.map_err(|e| match e {
+        UserStoreError::UnexpectedError(e) => AuthAPIError::UnexpectedError(e),
+        _ => AuthAPIError::UserNotFound,
+    })?;
+
+    user_store
+        .unlock_user(user.id())
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    tracing::info!(user_id = %user.id(), "Unlocked account");
+
+    Ok(Json(UnlockAccountResponse {
+        message: "Account unlocked".into(),
+    }))
+}
+
+fn authorize_admin(
+    authorization: Option<TypedHeader<Authorization<Bearer>>>,
+) -> Result<(), AuthAPIError> {
+    let TypedHeader(Authorization(bearer)) = authorization.ok_or(AuthAPIError::MissingToken)?;
+
+    let expected = ADMIN_API_TOKEN.as_ref().ok_or(AuthAPIError::InvalidToken)?;
+
+    if !credentials_match(bearer.token(), expected) {
+        return Err(AuthAPIError::InvalidToken);
+    }
+
+    Ok(())
+}
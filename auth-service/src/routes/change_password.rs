@@ -0,0 +1,84 @@
+use crate::{
+    app_state::app_state::AppState,
+    domain::error::AuthAPIError,
+    routes::{login::reauthenticate, signup::check_new_password},
+    utils::{auth::authenticate, password_hashing::compute_password_hash},
+};
+
+use axum::{extract::State, http::StatusCode, response::IntoResponse, Json};
+use axum_extra::extract::CookieJar;
+use secrecy::Secret;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Deserialize)]
+pub struct ChangePasswordRequest {
+    #[serde(rename = "currentPassword")]
+    pub current_password: Secret<String>,
+    #[serde(rename = "newPassword")]
+    pub new_password: Secret<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct ChangePasswordResponse {
+    pub message: String,
+}
+
+#[tracing::instrument(name = "Change_Password", skip_all)]
+pub async fn change_password_handler(
+    State(state): State<AppState>,
+    cookie_jar: CookieJar,
+    Json(request): Json<ChangePasswordRequest>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let (_, user_id) = authenticate(&cookie_jar, &state).await?;
+
+    let user = state
+        .user_store
+        .read()
+        .await
+        .get_user_by_id(&user_id)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    // A stolen session alone is not enough to take over the account
+    reauthenticate(&user, request.current_password, &state).await?;
+
+    let new_password = check_new_password(request.new_password, user.email(), &state).await?;
+
+    let password_hash = compute_password_hash(new_password.as_ref().clone())
+        .await
+        .map_err(AuthAPIError::UnexpectedError)?;
+
+    state
+        .user_store
+        .write()
+        .await
+        .update_password(&user_id, password_hash)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    tracing::info!(user_id = %user_id, "Security event: password changed");
+
+    // Lets the owner notice if someone else changed it. The password is already
+    // changed, so a failed email does not fail the request.
+    let sent = state
+        .email_client
+        .write()
+        .await
+        .send_email(
+            user.email(),
+            "Your password was changed",
+            "The password for your account was just changed. If this wasn't you, reset your password right away.",
+        )
+        .await;
+
+    if let Err(e) = sent {
+        tracing::error!("Failed to send password change email: {:?}", e);
+    }
+
+    Ok((
+        StatusCode::OK,
+        Json(ChangePasswordResponse {
+            message: "Password has been changed".into(),
+        }),
+    ))
+}
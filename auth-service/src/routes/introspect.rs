@@ -0,0 +1,65 @@
+use crate::{
+    app_state::app_state::AppState,
+    domain::error::AuthAPIError,
+    utils::{
+        auth::{credentials_match, validate_token, Claims},
+        constants::INTROSPECTION_CLIENTS,
+    },
+};
+
+use axum::{extract::State, response::IntoResponse, Form, Json};
+use axum_extra::{
+    headers::{authorization::Basic, Authorization},
+    TypedHeader,
+};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct IntrospectionRequest {
+    pub token: String,
+    // Accepted for RFC 7662 compatibility. Only access tokens can be introspected.
+    pub token_type_hint: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct IntrospectionResponse {
+    pub active: bool,
+    #[serde(flatten)]
+    pub claims: Option<Claims>,
+}
+
+// RFC 7662 token introspection. Resource servers authenticate with HTTP Basic
+// client credentials. Expired, banned or unknown tokens are reported as inactive
+// without any further detail.
+#[tracing::instrument(name = "Introspect", skip_all)]
+pub async fn introspect_handler(
+    State(state): State<AppState>,
+    authorization: Option<TypedHeader<Authorization<Basic>>>,
+    Form(request): Form<IntrospectionRequest>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    authorize_client(authorization)?;
+
+    let claims = validate_token(&request.token, &state).await.ok();
+
+    Ok(Json(IntrospectionResponse {
+        active: claims.is_some(),
+        claims,
+    }))
+}
+
+fn authorize_client(
+    authorization: Option<TypedHeader<Authorization<Basic>>>,
+) -> Result<(), AuthAPIError> {
+    let TypedHeader(Authorization(credentials)) =
+        authorization.ok_or(AuthAPIError::InvalidClient)?;
+
+    let expected = INTROSPECTION_CLIENTS
+        .get(credentials.username())
+        .ok_or(AuthAPIError::InvalidClient)?;
+
+    if !credentials_match(credentials.password(), expected) {
+        return Err(AuthAPIError::InvalidClient);
+    }
+
+    Ok(())
+}
@@ -0,0 +1,11 @@
+use crate::app_state::app_state::AppState;
+
+use axum::{extract::State, response::IntoResponse, Json};
+
+// Publishes the public signing keys so other services can verify tokens locally.
+// Retired keys stay listed until the tokens they signed have expired.
+// The set is empty when tokens are signed with HS256 secrets.
+#[tracing::instrument(name = "JWKS", skip_all)]
+pub async fn jwks_handler(State(state): State<AppState>) -> impl IntoResponse {
+    Json(state.keyring.read().await.jwks())
+}
@@ -1,16 +1,20 @@
 use crate::{
     app_state::app_state::AppState,
     domain::{
-        data_stores::{LoginAttemptId, TwoFACode},
+        data_stores::{LoginAttemptId, TwoFACode, UserStoreError},
         email::Email,
         error::AuthAPIError,
         password::Password,
+        two_fa_method::TwoFAMethod,
+        user::User,
     },
-    utils::auth::generate_auth_cookie,
+    utils::{auth::start_session, client_info::ClientInfo, constants::LOCKOUT_POLICY},
 };
 
 use axum::{extract::State, http::StatusCode, response::IntoResponse, Json};
 use axum_extra::extract::CookieJar;
+use chrono::{DateTime, Utc};
+use color_eyre::eyre;
 use secrecy::{ExposeSecret, Secret};
 use serde::{self, Deserialize, Serialize};
 use validator::Validate;
@@ -26,6 +30,9 @@ pub struct TwoFactorAuthResponse {
     pub message: String,
     #[serde(rename = "loginAttemptId")]
     pub login_attempt_id: String,
+    // Where the user gets the code to send to /verify-2fa
+    #[serde(rename = "twoFAMethod")]
+    pub two_fa_method: TwoFAMethod,
 }
 
 #[derive(Debug, Serialize, Deserialize, PartialEq)]
@@ -39,6 +46,7 @@ pub enum LoginResponse {
 pub async fn login_handler(
     State(state): State<AppState>,
     cookie_jar: CookieJar,
+    client: ClientInfo,
     Json(request): Json<LoginRequest>,
 ) -> (CookieJar, Result<impl IntoResponse, AuthAPIError>) {
     let (valid_email, valid_password) = match parse_credentials(request.email, request.password) {
@@ -46,33 +54,182 @@ pub async fn login_handler(
         _ => return (cookie_jar, Err(AuthAPIError::InvalidCredentials)),
     };
 
-    let user_store = &state.user_store.read().await;
+    let user = match state.user_store.read().await.get_user(&valid_email).await {
+        Ok(user) => Some(user),
+        Err(UserStoreError::UnexpectedError(e)) => {
+            return (cookie_jar, Err(AuthAPIError::UnexpectedError(e)))
+        }
+        Err(_) => None,
+    };
 
-    if user_store
-        .validate_user(&valid_email, &valid_password)
+    // Refused before the password is checked, so guessing cannot go on while locked.
+    // Answered like an unknown account, so the lock does not reveal that the address
+    // is registered, nor whether a guess was right. The owner learns of it by email.
+    if user.as_ref().and_then(User::locked_until).is_some() {
+        return (cookie_jar, Err(AuthAPIError::IncorrectCredentials));
+    }
+
+    let validation = state
+        .user_store
+        .read()
         .await
-        .is_err()
-    {
+        .validate_user(&valid_email, &valid_password)
+        .await;
+
+    match validation {
+        Ok(()) => {}
+        // The password could not be checked, which does not make it wrong
+        Err(UserStoreError::UnexpectedError(e)) => {
+            return (cookie_jar, Err(AuthAPIError::UnexpectedError(e)))
+        }
+        Err(_) => {
+            let error = match &user {
+                Some(user) => match record_failed_login(user, &state).await {
+                    // Same as above, locking is not told apart from a wrong password
+                    AuthAPIError::AccountLocked(_) => AuthAPIError::IncorrectCredentials,
+                    error => error,
+                },
+                // No account to lock
+                None => AuthAPIError::IncorrectCredentials,
+            };
+
+            return (cookie_jar, Err(error));
+        }
+    }
+
+    let Some(user) = user else {
         return (cookie_jar, Err(AuthAPIError::IncorrectCredentials));
+    };
+
+    if user.failed_login_count() > 0 {
+        if let Err(e) = state.user_store.write().await.unlock_user(user.id()).await {
+            return (cookie_jar, Err(AuthAPIError::UnexpectedError(e.into())));
+        }
+    }
+
+    // Checked after the password, so it does not reveal which addresses have accounts
+    if !user.email_verified() {
+        return (cookie_jar, Err(AuthAPIError::EmailNotVerified));
+    }
+
+    if !user.has_2fa() {
+        return handle_no_2fa(&user, &client, &state, cookie_jar).await;
+    }
+
+    handle_2fa(&user, &state, cookie_jar).await
+}
+
+// Asks a logged in user for their password again before a sensitive change, so a
+// stolen session alone is not enough. Wrong passwords count towards the lockout
+// like failed logins, and nothing is accepted while the account is locked.
+pub(crate) async fn reauthenticate(
+    user: &User,
+    password: Secret<String>,
+    state: &AppState,
+) -> Result<(), AuthAPIError> {
+    let password = Password::parse(password).map_err(|_| AuthAPIError::InvalidCredentials)?;
+
+    if let Some(locked_until) = user.locked_until() {
+        let seconds = (locked_until - Utc::now()).num_seconds().max(1) as u64;
+
+        return Err(AuthAPIError::AccountLocked(seconds));
     }
 
-    let user = match user_store.get_user(&valid_email).await {
-        Ok(user) => user,
-        _ => return (cookie_jar, Err(AuthAPIError::IncorrectCredentials)),
+    let validation = state
+        .user_store
+        .read()
+        .await
+        .validate_user(user.email(), &password)
+        .await;
+
+    match validation {
+        Ok(()) => {}
+        Err(UserStoreError::UnexpectedError(e)) => return Err(AuthAPIError::UnexpectedError(e)),
+        Err(_) => return Err(record_failed_login(user, state).await),
+    }
+
+    if user.failed_login_count() > 0 {
+        state
+            .user_store
+            .write()
+            .await
+            .unlock_user(user.id())
+            .await
+            .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+    }
+
+    Ok(())
+}
+
+// Counts a wrong password against the account, locking it and emailing its owner
+// once there have been too many in a row. Returns the error to answer with.
+pub(crate) async fn record_failed_login(user: &User, state: &AppState) -> AuthAPIError {
+    let failures = match state
+        .user_store
+        .write()
+        .await
+        .record_failed_login(user.id())
+        .await
+    {
+        Ok(failures) => failures,
+        Err(e) => return AuthAPIError::UnexpectedError(e.into()),
     };
 
-    let auth_cookie = match generate_auth_cookie(&valid_email) {
-        Ok(cookie) => cookie,
-        Err(e) => return (cookie_jar, Err(AuthAPIError::UnexpectedError(e))),
+    tracing::warn!(
+        user_id = %user.id(),
+        failures,
+        "Security event: failed login"
+    );
+
+    let Some(duration) = LOCKOUT_POLICY.lock_duration(failures) else {
+        return AuthAPIError::IncorrectCredentials;
     };
 
-    let updated_jar = cookie_jar.add(auth_cookie);
+    let locked_until = Utc::now() + duration;
 
-    if !user.has_2fa() {
-        return handle_no_2fa(&user.email(), updated_jar.clone()).await;
+    if let Err(e) = state
+        .user_store
+        .write()
+        .await
+        .lock_user(user.id(), locked_until)
+        .await
+    {
+        return AuthAPIError::UnexpectedError(e.into());
     }
 
-    handle_2fa(&valid_email, &state, updated_jar.clone()).await
+    tracing::warn!(
+        user_id = %user.id(),
+        failures,
+        locked_until = %locked_until,
+        "Security event: account locked after too many failed logins"
+    );
+
+    // The lock is in place either way, so a failed email does not fail the request
+    if let Err(e) = send_lockout_email(user.email(), locked_until, state).await {
+        tracing::error!("Failed to send account lockout email: {:?}", e);
+    }
+
+    AuthAPIError::AccountLocked(duration.num_seconds() as u64)
+}
+
+async fn send_lockout_email(
+    email: &Email,
+    locked_until: DateTime<Utc>,
+    state: &AppState,
+) -> eyre::Result<()> {
+    let content = format!(
+        "Your account was locked after too many failed login attempts. You can log in again after {}, or right away by resetting your password. If this wasn't you, consider changing your password.",
+        locked_until.format("%Y-%m-%d %H:%M UTC")
+    );
+
+    state
+        .email_client
+        .write()
+        .await
+        .send_email(email, "Your account has been locked", &content)
+        .await?;
+
+    Ok(())
 }
 
 fn parse_credentials(
@@ -87,58 +244,74 @@ fn parse_credentials(
 
 #[tracing::instrument(name = "Handle_2FA", skip_all)]
 async fn handle_2fa(
-    email: &Email,
+    user: &User,
     state: &AppState,
     jar: CookieJar,
 ) -> (
     CookieJar,
     Result<(StatusCode, Json<LoginResponse>), AuthAPIError>,
 ) {
+    let email = user.email();
     let login_attempt_id = LoginAttemptId::default();
+    // Only emailed to users of email 2FA. TOTP users get their code from their
+    // authenticator app, and the entry just tracks the login attempt.
     let two_fa_code = TwoFACode::default();
 
-    if let Err(e) = state
-        .two_fa_code_store
-        .write()
-        .await
-        .add_code(email.clone(), login_attempt_id.clone(), two_fa_code.clone())
-        .await
-    {
+    let mut two_fa_store = state.two_fa_code_store.write().await;
+    let code_hash = two_fa_store.hash_code(&two_fa_code);
+    let added = two_fa_store
+        .add_code(login_attempt_id.clone(), email.clone(), code_hash)
+        .await;
+
+    drop(two_fa_store);
+
+    if let Err(e) = added {
         return (jar, Err(AuthAPIError::UnexpectedError(e.into())));
     }
 
-    if let Err(e) = state
-        .email_client
-        .write()
-        .await
-        .send_email(&email, "2FA Code", two_fa_code.as_ref().expose_secret())
-        .await
-    {
-        return (jar, Err(AuthAPIError::UnexpectedError(e.into())));
-    };
+    if user.two_fa_method() == TwoFAMethod::Email {
+        if let Err(e) = send_2fa_code(email, &two_fa_code, state).await {
+            return (jar, Err(AuthAPIError::UnexpectedError(e)));
+        };
+    }
 
     let response = Json(LoginResponse::TwoFactorAuth(TwoFactorAuthResponse {
         login_attempt_id: login_attempt_id.as_ref().expose_secret().to_owned(),
         message: "2FA required".into(),
+        two_fa_method: user.two_fa_method(),
     }));
 
     (jar, Ok((StatusCode::PARTIAL_CONTENT, response)))
 }
 
+// Emails a code for a pending login attempt, on login and on resend
+pub async fn send_2fa_code(email: &Email, code: &TwoFACode, state: &AppState) -> eyre::Result<()> {
+    state
+        .email_client
+        .write()
+        .await
+        .send_email(email, "2FA Code", code.as_ref().expose_secret())
+        .await?;
+
+    Ok(())
+}
+
 #[tracing::instrument(name = "Handle_No_2FA", skip_all)]
 async fn handle_no_2fa(
-    email: &Email,
+    user: &User,
+    client: &ClientInfo,
+    state: &AppState,
     jar: CookieJar,
 ) -> (
     CookieJar,
     Result<(StatusCode, Json<LoginResponse>), AuthAPIError>,
 ) {
-    let auth_cookie = match generate_auth_cookie(email) {
-        Ok(cookie) => cookie,
+    let (auth_cookie, refresh_cookie) = match start_session(user, client, state).await {
+        Ok(cookies) => cookies,
         Err(e) => return (jar, Err(AuthAPIError::UnexpectedError(e))),
     };
 
-    let updated_jar = jar.add(auth_cookie);
+    let updated_jar = jar.add(auth_cookie).add(refresh_cookie);
 
     (
         updated_jar,
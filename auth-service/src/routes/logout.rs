@@ -1,7 +1,13 @@
 use crate::{
     app_state::app_state::AppState,
-    domain::error::AuthAPIError,
-    utils::{auth::validate_token, constants::JWT_COOKIE_NAME},
+    domain::{
+        data_stores::{RefreshToken, RefreshTokenStoreError, SessionStoreError},
+        error::AuthAPIError,
+    },
+    utils::{
+        auth::validate_token,
+        constants::{JWT_COOKIE_NAME, REFRESH_COOKIE_NAME},
+    },
 };
 
 use axum::{extract::State, http::StatusCode, response::IntoResponse};
@@ -20,7 +26,7 @@ pub async fn logout_handler(
 
     let token = cookie.value().to_owned();
 
-    let _ = match validate_token(&token, state.banned_token_store.clone()).await {
+    let claims = match validate_token(&token, &state).await {
         Ok(claims) => claims,
         Err(_) => return (cookie_jar, Err(AuthAPIError::InvalidToken)),
     };
@@ -29,13 +35,44 @@ pub async fn logout_handler(
         .banned_token_store
         .write()
         .await
-        .add_token(Secret::new(token))
+        .add_token(&claims.jti, claims.exp)
         .await
     {
         return (cookie_jar, Err(AuthAPIError::UnexpectedError(e.into())));
     }
 
-    let cookie_jar = cookie_jar.remove(JWT_COOKIE_NAME);
+    match state
+        .session_store
+        .write()
+        .await
+        .revoke_session(&claims.sid)
+        .await
+    {
+        Ok(()) | Err(SessionStoreError::SessionNotFound) => {}
+        Err(e) => return (cookie_jar, Err(AuthAPIError::UnexpectedError(e.into()))),
+    }
+
+    // Logging out ends the whole refresh token family, not just the current token
+    if let Some(cookie) = cookie_jar.get(REFRESH_COOKIE_NAME) {
+        let refresh_token = RefreshToken::from(Secret::new(cookie.value().to_owned()));
+        let mut refresh_token_store = state.refresh_token_store.write().await;
+
+        let family_id = match refresh_token_store.consume_token(&refresh_token).await {
+            Ok(record) | Err(RefreshTokenStoreError::TokenReused(record)) => Some(record.family_id),
+            Err(RefreshTokenStoreError::TokenNotFound) => None,
+            Err(e) => return (cookie_jar, Err(AuthAPIError::UnexpectedError(e.into()))),
+        };
+
+        if let Some(family_id) = family_id {
+            if let Err(e) = refresh_token_store.revoke_family(&family_id).await {
+                return (cookie_jar, Err(AuthAPIError::UnexpectedError(e.into())));
+            }
+        }
+    }
+
+    let cookie_jar = cookie_jar
+        .remove(JWT_COOKIE_NAME)
+        .remove(REFRESH_COOKIE_NAME);
 
     (cookie_jar, Ok(StatusCode::OK))
 }
@@ -0,0 +1,60 @@
+use crate::{
+    app_state::app_state::AppState,
+    domain::{error::AuthAPIError, UserId},
+    utils::{
+        auth::validate_token,
+        constants::{JWT_COOKIE_NAME, REFRESH_COOKIE_NAME},
+    },
+};
+
+use axum::{extract::State, http::StatusCode, response::IntoResponse};
+use axum_extra::extract::CookieJar;
+
+// Bumps the user's token generation, which invalidates every JWT and refresh
+// token issued to them so far, on every device, and ends all of their sessions.
+#[tracing::instrument(name = "Logout_All", skip_all)]
+pub async fn logout_all_handler(
+    State(state): State<AppState>,
+    cookie_jar: CookieJar,
+) -> (CookieJar, Result<impl IntoResponse, AuthAPIError>) {
+    let cookie = match cookie_jar.get(JWT_COOKIE_NAME) {
+        Some(cookie) => cookie,
+        None => return (cookie_jar, Err(AuthAPIError::MissingToken)),
+    };
+
+    let claims = match validate_token(cookie.value(), &state).await {
+        Ok(claims) => claims,
+        Err(_) => return (cookie_jar, Err(AuthAPIError::InvalidToken)),
+    };
+
+    let user_id = match UserId::parse(&claims.sub) {
+        Ok(user_id) => user_id,
+        Err(e) => return (cookie_jar, Err(AuthAPIError::UnexpectedError(e))),
+    };
+
+    if let Err(e) = state
+        .user_store
+        .write()
+        .await
+        .increment_token_generation(&user_id)
+        .await
+    {
+        return (cookie_jar, Err(AuthAPIError::UnexpectedError(e.into())));
+    }
+
+    if let Err(e) = state
+        .session_store
+        .write()
+        .await
+        .revoke_user_sessions(&user_id)
+        .await
+    {
+        return (cookie_jar, Err(AuthAPIError::UnexpectedError(e.into())));
+    }
+
+    let cookie_jar = cookie_jar
+        .remove(JWT_COOKIE_NAME)
+        .remove(REFRESH_COOKIE_NAME);
+
+    (cookie_jar, Ok(StatusCode::OK))
+}
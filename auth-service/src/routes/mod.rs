@@ -1,5 +1,18 @@
+pub mod admin;
+pub mod change_password;
+pub mod introspect;
+pub mod jwks;
 pub mod login;
 pub mod logout;
+pub mod logout_all;
+pub mod passkey;
+pub mod password_reset;
+pub mod recovery_codes;
+pub mod refresh;
+pub mod resend_2fa;
+pub mod sessions;
 pub mod signup;
+pub mod totp;
 pub mod verify_2fa;
+pub mod verify_email;
 pub mod verify_token;
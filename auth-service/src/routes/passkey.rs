@@ -0,0 +1,444 @@
+use crate::{
+    app_state::app_state::AppState,
+    domain::{
+        data_stores::{
+            PasskeyCeremony, PasskeyChallenge, PasskeyChallengeStoreError, PasskeyCredential,
+            PasskeyStoreError,
+        },
+        error::AuthAPIError,
+        Email, User, UserId,
+    },
+    utils::{
+        auth::{authenticate, start_session},
+        client_info::ClientInfo,
+        constants::{PASSKEY_CHALLENGE_TTL_SECONDS, WEBAUTHN_RP_ID, WEBAUTHN_RP_NAME},
+        webauthn::{verify_assertion, verify_registration, ES256},
+    },
+};
+
+use axum::{extract::State, http::StatusCode, response::IntoResponse, Json};
+use axum_extra::extract::CookieJar;
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use chrono::Utc;
+use secrecy::{ExposeSecret, Secret};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+const PUBLIC_KEY_CREDENTIAL_TYPE: &str = "public-key";
+
+// The options below mirror the WebAuthn dictionaries passed to
+// `navigator.credentials.create()` and `navigator.credentials.get()`,
+// with binary values base64url encoded
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct CredentialDescriptor {
+    #[serde(rename = "type")]
+    pub credential_type: String,
+    pub id: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct RelyingParty {
+    pub id: String,
+    pub name: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct PasskeyUser {
+    pub id: String,
+    pub name: String,
+    pub display_name: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct CredentialParameters {
+    #[serde(rename = "type")]
+    pub credential_type: String,
+    pub alg: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct AuthenticatorSelection {
+    pub resident_key: String,
+    pub user_verification: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct CreationOptions {
+    pub challenge: String,
+    pub rp: RelyingParty,
+    pub user: PasskeyUser,
+    pub pub_key_cred_params: Vec<CredentialParameters>,
+    // Milliseconds
+    pub timeout: i64,
+    pub attestation: String,
+    pub authenticator_selection: AuthenticatorSelection,
+    pub exclude_credentials: Vec<CredentialDescriptor>,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct StartRegistrationResponse {
+    // Sent back when finishing the ceremony
+    pub challenge_id: String,
+    pub public_key: CreationOptions,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AttestationResponse {
+    #[serde(rename = "clientDataJSON")]
+    pub client_data_json: String,
+    pub attestation_object: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RegistrationCredential {
+    pub id: String,
+    pub response: AttestationResponse,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FinishRegistrationRequest {
+    pub challenge_id: String,
+    pub credential: RegistrationCredential,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct FinishRegistrationResponse {
+    pub id: String,
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct StartLoginRequest {
+    // Limits the login to this user's passkeys. Without it the browser
+    // offers every passkey it has for the site.
+    #[serde(default)]
+    pub email: Option<Secret<String>>,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct RequestOptions {
+    pub challenge: String,
+    pub rp_id: String,
+    // Milliseconds
+    pub timeout: i64,
+    pub user_verification: String,
+    pub allow_credentials: Vec<CredentialDescriptor>,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct StartLoginResponse {
+    pub challenge_id: String,
+    pub public_key: RequestOptions,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AssertionResponse {
+    #[serde(rename = "clientDataJSON")]
+    pub client_data_json: String,
+    pub authenticator_data: String,
+    pub signature: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct LoginCredential {
+    pub id: String,
+    pub response: AssertionResponse,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FinishLoginRequest {
+    pub challenge_id: String,
+    pub credential: LoginCredential,
+}
+
+#[tracing::instrument(name = "Start_Passkey_Registration", skip_all)]
+pub async fn start_passkey_registration_handler(
+    State(state): State<AppState>,
+    cookie_jar: CookieJar,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let (_, user_id) = authenticate(&cookie_jar, &state).await?;
+
+    let user = state
+        .user_store
+        .read()
+        .await
+        .get_user_by_id(&user_id)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    // Stops the authenticator from registering a second passkey for the same account
+    let exclude_credentials = credential_descriptors(&state, &user_id).await?;
+
+    let challenge_id = Uuid::new_v4().to_string();
+    let challenge = PasskeyChallenge::new(PasskeyCeremony::Registration, Some(user_id));
+    let options = CreationOptions {
+        challenge: challenge.challenge.clone(),
+        rp: RelyingParty {
+            id: WEBAUTHN_RP_ID.to_owned(),
+            name: WEBAUTHN_RP_NAME.to_owned(),
+        },
+        user: PasskeyUser {
+            id: URL_SAFE_NO_PAD.encode(user_id.to_string()),
+            name: user.email().as_ref().expose_secret().to_owned(),
+            display_name: user.email().as_ref().expose_secret().to_owned(),
+        },
+        pub_key_cred_params: vec![CredentialParameters {
+            credential_type: PUBLIC_KEY_CREDENTIAL_TYPE.to_owned(),
+            alg: ES256,
+        }],
+        timeout: PASSKEY_CHALLENGE_TTL_SECONDS * 1000,
+        attestation: "none".to_owned(),
+        authenticator_selection: AuthenticatorSelection {
+            resident_key: "required".to_owned(),
+            user_verification: "required".to_owned(),
+        },
+        exclude_credentials,
+    };
+
+    state
+        .passkey_challenge_store
+        .write()
+        .await
+        .add_challenge(challenge_id.clone(), challenge)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    Ok(Json(StartRegistrationResponse {
+        challenge_id,
+        public_key: options,
+    }))
+}
+
+#[tracing::instrument(name = "Finish_Passkey_Registration", skip_all)]
+pub async fn finish_passkey_registration_handler(
+    State(state): State<AppState>,
+    cookie_jar: CookieJar,
+    Json(request): Json<FinishRegistrationRequest>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let (_, user_id) = authenticate(&cookie_jar, &state).await?;
+
+    let challenge = take_challenge(&state, &request.challenge_id).await?;
+
+    if challenge.ceremony != PasskeyCeremony::Registration || challenge.user_id != Some(user_id) {
+        return Err(AuthAPIError::IncorrectCredentials);
+    }
+
+    let client_data_json = decode(&request.credential.response.client_data_json)?;
+    let attestation_object = decode(&request.credential.response.attestation_object)?;
+
+    let new_credential =
+        verify_registration(&client_data_json, &attestation_object, &challenge.challenge).map_err(
+            |e| {
+                tracing::warn!("Rejected passkey registration: {:?}", e);
+                AuthAPIError::IncorrectCredentials
+            },
+        )?;
+
+    if new_credential.id != request.credential.id {
+        return Err(AuthAPIError::InvalidCredentials);
+    }
+
+    let credential = PasskeyCredential {
+        id: new_credential.id,
+        user_id,
+        public_key: new_credential.public_key,
+        sign_count: new_credential.sign_count,
+        created_at: Utc::now(),
+    };
+    let id = credential.id.clone();
+
+    match state
+        .passkey_store
+        .write()
+        .await
+        .add_credential(credential)
+        .await
+    {
+        Ok(()) => {}
+        Err(PasskeyStoreError::CredentialAlreadyExists) => {
+            return Err(AuthAPIError::PasskeyAlreadyRegistered)
+        }
+        Err(e) => return Err(AuthAPIError::UnexpectedError(e.into())),
+    }
+
+    Ok((StatusCode::CREATED, Json(FinishRegistrationResponse { id })))
+}
+
+#[tracing::instrument(name = "Start_Passkey_Login", skip_all)]
+pub async fn start_passkey_login_handler(
+    State(state): State<AppState>,
+    Json(request): Json<StartLoginRequest>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let user_id = match request.email {
+        Some(email) => {
+            let email = Email::parse(email).map_err(|_| AuthAPIError::InvalidCredentials)?;
+
+            // Unknown emails get options like any other, so they cannot be told apart
+            state
+                .user_store
+                .read()
+                .await
+                .get_user(&email)
+                .await
+                .ok()
+                .map(|user| *user.id())
+        }
+        None => None,
+    };
+
+    let allow_credentials = match user_id {
+        Some(user_id) => credential_descriptors(&state, &user_id).await?,
+        None => Vec::new(),
+    };
+
+    let challenge_id = Uuid::new_v4().to_string();
+    let challenge = PasskeyChallenge::new(PasskeyCeremony::Authentication, user_id);
+    let options = RequestOptions {
+        challenge: challenge.challenge.clone(),
+        rp_id: WEBAUTHN_RP_ID.to_owned(),
+        timeout: PASSKEY_CHALLENGE_TTL_SECONDS * 1000,
+        user_verification: "required".to_owned(),
+        allow_credentials,
+    };
+
+    state
+        .passkey_challenge_store
+        .write()
+        .await
+        .add_challenge(challenge_id.clone(), challenge)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    Ok(Json(StartLoginResponse {
+        challenge_id,
+        public_key: options,
+    }))
+}
+
+// A verified passkey proves possession of the authenticator and, through its
+// PIN or biometrics, the user. It replaces both the password and the 2FA step.
+#[tracing::instrument(name = "Finish_Passkey_Login", skip_all)]
+pub async fn finish_passkey_login_handler(
+    State(state): State<AppState>,
+    cookie_jar: CookieJar,
+    client: ClientInfo,
+    Json(request): Json<FinishLoginRequest>,
+) -> (CookieJar, Result<impl IntoResponse, AuthAPIError>) {
+    let user = match verify_login(&state, request).await {
+        Ok(user) => user,
+        Err(e) => return (cookie_jar, Err(e)),
+    };
+
+    let (auth_cookie, refresh_cookie) = match start_session(&user, &client, &state).await {
+        Ok(cookies) => cookies,
+        Err(e) => return (cookie_jar, Err(AuthAPIError::UnexpectedError(e))),
+    };
+
+    let updated_jar = cookie_jar.add(auth_cookie).add(refresh_cookie);
+
+    (updated_jar, Ok(StatusCode::OK))
+}
+
+async fn verify_login(state: &AppState, request: FinishLoginRequest) -> Result<User, AuthAPIError> {
+    let challenge = take_challenge(state, &request.challenge_id).await?;
+
+    if challenge.ceremony != PasskeyCeremony::Authentication {
+        return Err(AuthAPIError::IncorrectCredentials);
+    }
+
+    let mut passkey_store = state.passkey_store.write().await;
+
+    let credential = match passkey_store.get_credential(&request.credential.id).await {
+        Ok(credential) => credential,
+        Err(PasskeyStoreError::CredentialNotFound) => {
+            return Err(AuthAPIError::IncorrectCredentials)
+        }
+        Err(e) => return Err(AuthAPIError::UnexpectedError(e.into())),
+    };
+
+    if challenge
+        .user_id
+        .is_some_and(|user_id| user_id != credential.user_id)
+    {
+        return Err(AuthAPIError::IncorrectCredentials);
+    }
+
+    let response = &request.credential.response;
+    let sign_count = verify_assertion(
+        &decode(&response.client_data_json)?,
+        &decode(&response.authenticator_data)?,
+        &decode(&response.signature)?,
+        &challenge.challenge,
+        &credential.public_key,
+        credential.sign_count,
+    )
+    .map_err(|e| {
+        tracing::warn!("Rejected passkey login: {:?}", e);
+        AuthAPIError::IncorrectCredentials
+    })?;
+
+    passkey_store
+        .update_sign_count(&credential.id, sign_count)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    state
+        .user_store
+        .read()
+        .await
+        .get_user_by_id(&credential.user_id)
+        .await
+        .map_err(|_| AuthAPIError::IncorrectCredentials)
+}
+
+async fn take_challenge(
+    state: &AppState,
+    challenge_id: &str,
+) -> Result<PasskeyChallenge, AuthAPIError> {
+    state
+        .passkey_challenge_store
+        .write()
+        .await
+        .take_challenge(challenge_id)
+        .await
+        .map_err(|e| match e {
+            PasskeyChallengeStoreError::ChallengeNotFound => AuthAPIError::IncorrectCredentials,
+            e => AuthAPIError::UnexpectedError(e.into()),
+        })
+}
+
+async fn credential_descriptors(
+    state: &AppState,
+    user_id: &UserId,
+) -> Result<Vec<CredentialDescriptor>, AuthAPIError> {
+    let credentials = state
+        .passkey_store
+        .read()
+        .await
+        .list_credentials(user_id)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    Ok(credentials
+        .into_iter()
+        .map(|credential| CredentialDescriptor {
+            credential_type: PUBLIC_KEY_CREDENTIAL_TYPE.to_owned(),
+            id: credential.id,
+        })
+        .collect())
+}
+
+fn decode(value: &str) -> Result<Vec<u8>, AuthAPIError> {
+    URL_SAFE_NO_PAD
+        .decode(value)
+        .map_err(|_| AuthAPIError::InvalidCredentials)
+}
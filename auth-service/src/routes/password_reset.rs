@@ -0,0 +1,188 @@
+use crate::{
+    app_state::app_state::AppState,
+    domain::{
+        data_stores::{PasswordResetToken, PasswordResetTokenStoreError, UserStoreError},
+        error::AuthAPIError,
+        Email,
+    },
+    routes::signup::check_new_password,
+    utils::{
+        constants::{PASSWORD_RESET_TOKEN_TTL_SECONDS, PASSWORD_RESET_URL},
+        password_hashing::compute_password_hash,
+    },
+};
+
+use axum::{extract::State, http::StatusCode, response::IntoResponse, Json};
+use color_eyre::eyre;
+use secrecy::{ExposeSecret, Secret};
+use serde::{Deserialize, Serialize};
+use tracing::Instrument;
+
+#[derive(Debug, Deserialize)]
+pub struct PasswordResetRequest {
+    pub email: Secret<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ConfirmPasswordResetRequest {
+    pub token: Secret<String>,
+    #[serde(rename = "newPassword")]
+    pub new_password: Secret<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct PasswordResetResponse {
+    pub message: String,
+}
+
+// Answers the same whether or not the account exists, so the endpoint cannot be
+// used to find out who has signed up. The link is stored and sent in the
+// background, so neither the response time nor a failed email gives it away.
+#[tracing::instrument(name = "Request_Password_Reset", skip_all)]
+pub async fn request_password_reset_handler(
+    State(state): State<AppState>,
+    Json(request): Json<PasswordResetRequest>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let email = Email::parse(request.email).map_err(|_| AuthAPIError::InvalidCredentials)?;
+
+    tokio::spawn(send_password_reset_email(email, state).in_current_span());
+
+    Ok((
+        StatusCode::ACCEPTED,
+        Json(PasswordResetResponse {
+            message: "If the account exists, a reset link has been sent".into(),
+        }),
+    ))
+}
+
+#[tracing::instrument(name = "Sending password reset email", skip_all)]
+async fn send_password_reset_email(email: Email, state: AppState) {
+    let result = async {
+        let user = match state.user_store.read().await.get_user(&email).await {
+            Ok(user) => user,
+            Err(UserStoreError::UserNotFound) => return eyre::Ok(()),
+            Err(e) => return Err(e.into()),
+        };
+
+        let token = PasswordResetToken::default();
+
+        state
+            .password_reset_token_store
+            .write()
+            .await
+            .add_token(&token, *user.id())
+            .await?;
+
+        let content = format!(
+            "Reset your password here: {}?token={}\nThe link works once and expires in {} minutes.",
+            *PASSWORD_RESET_URL,
+            token.as_ref().expose_secret(),
+            PASSWORD_RESET_TOKEN_TTL_SECONDS / 60
+        );
+
+        state
+            .email_client
+            .write()
+            .await
+            .send_email(&email, "Reset your password", &content)
+            .await?;
+
+        eyre::Ok(())
+    }
+    .await;
+
+    if let Err(e) = result {
+        tracing::error!("Failed to send password reset email: {:?}", e);
+    }
+}
+
+// Sets the new password and signs the user out everywhere, in case the reset
+// was prompted by someone else having the old one
+#[tracing::instrument(name = "Confirm_Password_Reset", skip_all)]
+pub async fn confirm_password_reset_handler(
+    State(state): State<AppState>,
+    Json(request): Json<ConfirmPasswordResetRequest>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let token = PasswordResetToken::parse(request.token)
+        .map_err(|_| AuthAPIError::InvalidPasswordResetToken)?;
+
+    let user_id = state
+        .password_reset_token_store
+        .read()
+        .await
+        .peek_token(&token)
+        .await
+        .map_err(token_error)?;
+
+    let user = state
+        .user_store
+        .read()
+        .await
+        .get_user_by_id(&user_id)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    let password = check_new_password(request.new_password, user.email(), &state).await?;
+
+    let password_hash = compute_password_hash(password.as_ref().clone())
+        .await
+        .map_err(AuthAPIError::UnexpectedError)?;
+
+    // Only used up once the new password is accepted, so a rejected one does
+    // not cost the user their link
+    state
+        .password_reset_token_store
+        .write()
+        .await
+        .take_token(&token)
+        .await
+        .map_err(token_error)?;
+
+    {
+        let mut user_store = state.user_store.write().await;
+
+        user_store
+            .update_password(&user_id, password_hash)
+            .await
+            .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+        // Following the emailed link proves the address too
+        user_store
+            .mark_email_verified(&user_id)
+            .await
+            .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+        // The new password is not the one that was being guessed at
+        user_store
+            .unlock_user(&user_id)
+            .await
+            .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+        user_store
+            .increment_token_generation(&user_id)
+            .await
+            .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+    }
+
+    state
+        .session_store
+        .write()
+        .await
+        .revoke_user_sessions(&user_id)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    Ok((
+        StatusCode::OK,
+        Json(PasswordResetResponse {
+            message: "Password has been reset".into(),
+        }),
+    ))
+}
+
+fn token_error(e: PasswordResetTokenStoreError) -> AuthAPIError {
+    match e {
+        PasswordResetTokenStoreError::TokenNotFound => AuthAPIError::InvalidPasswordResetToken,
+        e => AuthAPIError::UnexpectedError(e.into()),
+    }
+}
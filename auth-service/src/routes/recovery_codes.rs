@@ -0,0 +1,96 @@
+use crate::{
+    app_state::app_state::AppState,
+    domain::{data_stores::RecoveryCode, error::AuthAPIError, UserId},
+    routes::login::reauthenticate,
+    utils::{auth::authenticate, constants::RECOVERY_CODE_COUNT},
+};
+
+use axum::{extract::State, response::IntoResponse, Json};
+use axum_extra::extract::CookieJar;
+use color_eyre::eyre;
+use secrecy::Secret;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Deserialize)]
+pub struct RegenerateRecoveryCodesRequest {
+    pub password: Secret<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct RecoveryCodesResponse {
+    // Shown to the user once; only hashes are kept
+    pub recovery_codes: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct RemainingRecoveryCodesResponse {
+    pub remaining: usize,
+}
+
+// Replaces any codes the user has with a fresh set, returned formatted for display
+pub async fn issue_recovery_codes(user_id: &UserId, state: &AppState) -> eyre::Result<Vec<String>> {
+    let codes: Vec<RecoveryCode> = (0..RECOVERY_CODE_COUNT)
+        .map(|_| RecoveryCode::default())
+        .collect();
+    let formatted = codes.iter().map(RecoveryCode::formatted).collect();
+
+    state
+        .recovery_code_store
+        .write()
+        .await
+        .replace_codes(user_id, codes)
+        .await?;
+
+    Ok(formatted)
+}
+
+// Recovery codes get past the second factor, so a stolen session alone must not
+// be enough to mint them
+#[tracing::instrument(name = "Regenerate_Recovery_Codes", skip_all)]
+pub async fn regenerate_recovery_codes_handler(
+    State(state): State<AppState>,
+    cookie_jar: CookieJar,
+    Json(request): Json<RegenerateRecoveryCodesRequest>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let (_, user_id) = authenticate(&cookie_jar, &state).await?;
+
+    let user = state
+        .user_store
+        .read()
+        .await
+        .get_user_by_id(&user_id)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    // Without a second factor there is nothing to recover
+    if !user.has_2fa() {
+        return Err(AuthAPIError::TwoFANotEnabled);
+    }
+
+    reauthenticate(&user, request.password, &state).await?;
+
+    let recovery_codes = issue_recovery_codes(&user_id, &state)
+        .await
+        .map_err(AuthAPIError::UnexpectedError)?;
+
+    Ok(Json(RecoveryCodesResponse { recovery_codes }))
+}
+
+#[tracing::instrument(name = "Count_Recovery_Codes", skip_all)]
+pub async fn count_recovery_codes_handler(
+    State(state): State<AppState>,
+    cookie_jar: CookieJar,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let (_, user_id) = authenticate(&cookie_jar, &state).await?;
+
+    let remaining = state
+        .recovery_code_store
+        .read()
+        .await
+        .count_codes(&user_id)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    Ok(Json(RemainingRecoveryCodesResponse { remaining }))
+}
@@ -0,0 +1,140 @@
+use crate::{
+    app_state::app_state::AppState,
+    domain::{
+        data_stores::{RefreshToken, RefreshTokenStoreError, SessionStoreError, UserStoreError},
+        error::AuthAPIError,
+    },
+    utils::{
+        auth::{generate_auth_cookie, generate_refresh_cookie},
+        constants::{JWT_COOKIE_NAME, REFRESH_COOKIE_NAME},
+    },
+};
+
+use axum::{extract::State, http::StatusCode, response::IntoResponse};
+use axum_extra::extract::CookieJar;
+use chrono::Utc;
+use secrecy::Secret;
+
+#[tracing::instrument(name = "Refresh", skip_all)]
+pub async fn refresh_handler(
+    State(state): State<AppState>,
+    cookie_jar: CookieJar,
+) -> (CookieJar, Result<impl IntoResponse, AuthAPIError>) {
+    let cookie = match cookie_jar.get(REFRESH_COOKIE_NAME) {
+        Some(cookie) => cookie,
+        None => return (cookie_jar, Err(AuthAPIError::MissingToken)),
+    };
+
+    let token = RefreshToken::from(Secret::new(cookie.value().to_owned()));
+
+    let record = {
+        let mut refresh_token_store = state.refresh_token_store.write().await;
+
+        let record = match refresh_token_store.consume_token(&token).await {
+            Ok(record) => record,
+            Err(RefreshTokenStoreError::TokenReused(record)) => {
+                // A rotated token came back, so it was most likely stolen.
+                // Kill every token descended from the same login.
+                tracing::warn!("Refresh token reuse detected, revoking token family");
+
+                if let Err(e) = refresh_token_store.revoke_family(&record.family_id).await {
+                    return (cookie_jar, Err(AuthAPIError::UnexpectedError(e.into())));
+                }
+
+                return (
+                    clear_auth_cookies(cookie_jar),
+                    Err(AuthAPIError::InvalidToken),
+                );
+            }
+            Err(RefreshTokenStoreError::TokenNotFound) => {
+                return (cookie_jar, Err(AuthAPIError::InvalidToken))
+            }
+            Err(e) => return (cookie_jar, Err(AuthAPIError::UnexpectedError(e.into()))),
+        };
+
+        match refresh_token_store
+            .is_family_revoked(&record.family_id)
+            .await
+        {
+            Ok(false) => record,
+            Ok(true) => {
+                return (
+                    clear_auth_cookies(cookie_jar),
+                    Err(AuthAPIError::InvalidToken),
+                )
+            }
+            Err(e) => return (cookie_jar, Err(AuthAPIError::UnexpectedError(e.into()))),
+        }
+    };
+
+    let user = match state
+        .user_store
+        .read()
+        .await
+        .get_user_by_id(&record.user_id)
+        .await
+    {
+        Ok(user) => user,
+        Err(UserStoreError::UserNotFound) => {
+            return (
+                clear_auth_cookies(cookie_jar),
+                Err(AuthAPIError::InvalidToken),
+            )
+        }
+        Err(e) => return (cookie_jar, Err(AuthAPIError::UnexpectedError(e.into()))),
+    };
+
+    // Refreshing counts as activity on the session, and fails once it has been revoked
+    let session_active = match state
+        .session_store
+        .write()
+        .await
+        .touch_session(&record.session_id, Utc::now())
+        .await
+    {
+        Ok(()) => true,
+        Err(SessionStoreError::SessionNotFound) => false,
+        Err(e) => return (cookie_jar, Err(AuthAPIError::UnexpectedError(e.into()))),
+    };
+
+    // The user logged out everywhere since this token family was issued,
+    // or ended this session from another device
+    if record.token_generation != user.token_generation() || !session_active {
+        if let Err(e) = state
+            .refresh_token_store
+            .write()
+            .await
+            .revoke_family(&record.family_id)
+            .await
+        {
+            return (cookie_jar, Err(AuthAPIError::UnexpectedError(e.into())));
+        }
+
+        return (
+            clear_auth_cookies(cookie_jar),
+            Err(AuthAPIError::InvalidToken),
+        );
+    }
+
+    let auth_cookie =
+        match generate_auth_cookie(&user, &record.session_id, state.keyring.clone()).await {
+            Ok(cookie) => cookie,
+            Err(e) => return (cookie_jar, Err(AuthAPIError::UnexpectedError(e))),
+        };
+
+    let refresh_cookie =
+        match generate_refresh_cookie(record, state.refresh_token_store.clone()).await {
+            Ok(cookie) => cookie,
+            Err(e) => return (cookie_jar, Err(AuthAPIError::UnexpectedError(e))),
+        };
+
+    let updated_jar = cookie_jar.add(auth_cookie).add(refresh_cookie);
+
+    (updated_jar, Ok(StatusCode::OK))
+}
+
+fn clear_auth_cookies(cookie_jar: CookieJar) -> CookieJar {
+    cookie_jar
+        .remove(JWT_COOKIE_NAME)
+        .remove(REFRESH_COOKIE_NAME)
+}
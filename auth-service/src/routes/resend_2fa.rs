@@ -0,0 +1,74 @@
+use crate::{
+    app_state::app_state::AppState,
+    domain::{
+        data_stores::{LoginAttemptId, TwoFACode, TwoFACodeStoreError},
+        error::AuthAPIError,
+        TwoFAMethod,
+    },
+    routes::login::{send_2fa_code, TwoFactorAuthResponse},
+};
+
+use axum::{extract::State, http::StatusCode, response::IntoResponse, Json};
+use secrecy::{ExposeSecret, Secret};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct Resend2FARequest {
+    #[serde(rename = "loginAttemptId")]
+    pub login_attempt_id: String,
+}
+
+#[tracing::instrument(name = "Resend_2FA", skip_all)]
+pub async fn resend_2fa_handler(
+    State(state): State<AppState>,
+    Json(request): Json<Resend2FARequest>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let login_attempt_id = LoginAttemptId::parse(Secret::new(request.login_attempt_id))
+        .map_err(|_| AuthAPIError::InvalidCredentials)?;
+
+    let mut two_fa_store = state.two_fa_code_store.write().await;
+
+    let (email, _) = two_fa_store
+        .get_code(&login_attempt_id)
+        .await
+        .map_err(|_| AuthAPIError::IncorrectCredentials)?;
+
+    let user = state
+        .user_store
+        .read()
+        .await
+        .get_user(&email)
+        .await
+        .map_err(|_| AuthAPIError::IncorrectCredentials)?;
+
+    if user.two_fa_method() != TwoFAMethod::Email {
+        return Err(AuthAPIError::TwoFACodeNotEmailed);
+    }
+
+    let two_fa_code = TwoFACode::default();
+    let code_hash = two_fa_store.hash_code(&two_fa_code);
+
+    two_fa_store
+        .replace_code(&login_attempt_id, code_hash)
+        .await
+        .map_err(|e| match e {
+            TwoFACodeStoreError::LoginAttemptIdNotFound => AuthAPIError::IncorrectCredentials,
+            TwoFACodeStoreError::ResendTooSoon(seconds) => AuthAPIError::ResendTooSoon(seconds),
+            TwoFACodeStoreError::TooManyResends => AuthAPIError::TooManyResends,
+            e => AuthAPIError::UnexpectedError(e.into()),
+        })?;
+
+    drop(two_fa_store);
+
+    send_2fa_code(&email, &two_fa_code, &state)
+        .await
+        .map_err(AuthAPIError::UnexpectedError)?;
+
+    let response = Json(TwoFactorAuthResponse {
+        login_attempt_id: login_attempt_id.as_ref().expose_secret().to_owned(),
+        message: "2FA code resent".into(),
+        two_fa_method: TwoFAMethod::Email,
+    });
+
+    Ok((StatusCode::OK, response))
+}
@@ -0,0 +1,123 @@
+use crate::{
+    app_state::app_state::AppState,
+    domain::{
+        data_stores::{Session, SessionStoreError},
+        error::AuthAPIError,
+    },
+    utils::{
+        auth::authenticate,
+        constants::{JWT_COOKIE_NAME, REFRESH_COOKIE_NAME},
+    },
+};
+
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::IntoResponse,
+    Json,
+};
+use axum_extra::extract::CookieJar;
+use chrono::SecondsFormat;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct SessionResponse {
+    pub id: String,
+    pub device: String,
+    pub ip_address: String,
+    pub user_agent: String,
+    // RFC 3339 timestamps
+    pub created_at: String,
+    pub last_seen_at: String,
+    // Whether this is the session making the request
+    pub current: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct ListSessionsResponse {
+    pub sessions: Vec<SessionResponse>,
+}
+
+impl SessionResponse {
+    fn new(session: Session, current_session_id: &str) -> Self {
+        Self {
+            current: session.id == current_session_id,
+            id: session.id,
+            device: session.device,
+            ip_address: session.ip_address,
+            user_agent: session.user_agent,
+            created_at: session
+                .created_at
+                .to_rfc3339_opts(SecondsFormat::Secs, true),
+            last_seen_at: session
+                .last_seen_at
+                .to_rfc3339_opts(SecondsFormat::Secs, true),
+        }
+    }
+}
+
+#[tracing::instrument(name = "List_Sessions", skip_all)]
+pub async fn list_sessions_handler(
+    State(state): State<AppState>,
+    cookie_jar: CookieJar,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let (claims, user_id) = authenticate(&cookie_jar, &state).await?;
+
+    let sessions = state
+        .session_store
+        .read()
+        .await
+        .list_sessions(&user_id)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    let sessions = sessions
+        .into_iter()
+        .map(|session| SessionResponse::new(session, &claims.sid))
+        .collect();
+
+    Ok(Json(ListSessionsResponse { sessions }))
+}
+
+// Ends one of the user's sessions. Its JWTs stop validating and its refresh tokens stop refreshing.
+#[tracing::instrument(name = "Revoke_Session", skip_all)]
+pub async fn revoke_session_handler(
+    State(state): State<AppState>,
+    cookie_jar: CookieJar,
+    Path(session_id): Path<String>,
+) -> (CookieJar, Result<impl IntoResponse, AuthAPIError>) {
+    let (claims, user_id) = match authenticate(&cookie_jar, &state).await {
+        Ok(authenticated) => authenticated,
+        Err(e) => return (cookie_jar, Err(e)),
+    };
+
+    let mut session_store = state.session_store.write().await;
+
+    // Other users' sessions are reported as missing so their ids cannot be probed
+    match session_store.get_session(&session_id).await {
+        Ok(session) if session.user_id == user_id => {}
+        Ok(_) | Err(SessionStoreError::SessionNotFound) => {
+            return (cookie_jar, Err(AuthAPIError::SessionNotFound))
+        }
+        Err(e) => return (cookie_jar, Err(AuthAPIError::UnexpectedError(e.into()))),
+    }
+
+    match session_store.revoke_session(&session_id).await {
+        Ok(()) => {}
+        Err(SessionStoreError::SessionNotFound) => {
+            return (cookie_jar, Err(AuthAPIError::SessionNotFound))
+        }
+        Err(e) => return (cookie_jar, Err(AuthAPIError::UnexpectedError(e.into()))),
+    }
+
+    let cookie_jar = if session_id == claims.sid {
+        cookie_jar
+            .remove(JWT_COOKIE_NAME)
+            .remove(REFRESH_COOKIE_NAME)
+    } else {
+        cookie_jar
+    };
+
+    (cookie_jar, Ok(StatusCode::NO_CONTENT))
+}
@@ -1,6 +1,11 @@
 use crate::{
     app_state::app_state::AppState,
-    domain::{email::Email, error::AuthAPIError, password::Password, user::User},
+    domain::{
+        email::Email, error::AuthAPIError, two_fa_method::TwoFAMethod, user::User, Password,
+        PasswordPolicyViolation,
+    },
+    routes::{recovery_codes::issue_recovery_codes, verify_email::send_verification_email},
+    utils::{constants::PASSWORD_POLICY, password_hashing::compute_password_hash},
 };
 
 use axum::{extract::State, http::StatusCode, response::IntoResponse, Json};
@@ -11,6 +16,7 @@ use serde::{Deserialize, Serialize};
 pub struct SignupRequest {
     pub email: Secret<String>,
     pub password: Secret<String>,
+    // Signs the user up for emailed 2FA codes. An authenticator app can be enrolled later.
     #[serde(rename = "requires2FA")]
     pub requires_2fa: bool,
 }
@@ -18,6 +24,39 @@ pub struct SignupRequest {
 #[derive(Debug, Serialize, Deserialize, PartialEq)]
 pub struct SignupResponse {
     pub message: String,
+    // Only issued when signing up with 2FA
+    #[serde(
+        default,
+        rename = "recoveryCodes",
+        skip_serializing_if = "Vec::is_empty"
+    )]
+    pub recovery_codes: Vec<String>,
+}
+
+// Checks a password about to be set against the policy and the breached password
+// list, on signup, password change and reset
+pub async fn check_new_password(
+    password: Secret<String>,
+    email: &Email,
+    state: &AppState,
+) -> Result<Password, AuthAPIError> {
+    let breached = state
+        .breached_password_checker
+        .read()
+        .await
+        .is_breached(&password);
+
+    let mut violations = match PASSWORD_POLICY.check(password, email) {
+        Ok(password) if !breached => return Ok(password),
+        Ok(_) => Vec::new(),
+        Err(violations) => violations,
+    };
+
+    if breached {
+        violations.push(PasswordPolicyViolation::Breached);
+    }
+
+    Err(AuthAPIError::WeakPassword(violations))
 }
 
 #[tracing::instrument(name = "Signup", skip_all)]
@@ -26,10 +65,20 @@ pub async fn signup_handler(
     Json(request): Json<SignupRequest>,
 ) -> Result<impl IntoResponse, AuthAPIError> {
     let email = Email::parse(request.email).map_err(|_| AuthAPIError::InvalidCredentials)?;
-    let password =
-        Password::parse(request.password.clone()).map_err(|_| AuthAPIError::InvalidCredentials)?;
+    let password = check_new_password(request.password, &email, &state).await?;
 
-    let user = User::new(email, password, request.requires_2fa);
+    let two_fa_method = if request.requires_2fa {
+        TwoFAMethod::Email
+    } else {
+        TwoFAMethod::None
+    };
+
+    // Hashed before the store is locked, so signups do not queue behind it
+    let password_hash = compute_password_hash(password.as_ref().clone())
+        .await
+        .map_err(AuthAPIError::UnexpectedError)?;
+
+    let user = User::new(email, Password::from(password_hash), two_fa_method);
 
     let mut user_store = state.user_store.write().await;
 
@@ -37,14 +86,31 @@ pub async fn signup_handler(
         return Err(AuthAPIError::UserAlreadyExists);
     }
 
-    if let Err(e) = user_store.add_user(user).await {
+    if let Err(e) = user_store.add_user(user.clone()).await {
         return Err(AuthAPIError::UnexpectedError(e.into()));
     }
 
+    // The store lock is released first, as hashing the codes takes a while
+    drop(user_store);
+
+    // The account exists either way, and the user can ask for another link
+    if let Err(e) = send_verification_email(&user, &state).await {
+        tracing::error!("Failed to send verification email: {:?}", e);
+    }
+
+    let recovery_codes = if user.has_2fa() {
+        issue_recovery_codes(user.id(), &state)
+            .await
+            .map_err(AuthAPIError::UnexpectedError)?
+    } else {
+        Vec::new()
+    };
+
     Ok((
         StatusCode::CREATED,
         Json(SignupResponse {
             message: "User created successfully!".into(),
+            recovery_codes,
         }),
     ))
 }
@@ -0,0 +1,212 @@
+use crate::{
+    app_state::app_state::AppState,
+    domain::{error::AuthAPIError, TwoFAMethod, User, UserId},
+    routes::{
+        login::reauthenticate,
+        recovery_codes::{issue_recovery_codes, RecoveryCodesResponse},
+    },
+    utils::{
+        auth::authenticate,
+        totp::{generate_totp_secret, qr_code_svg, totp_uri, verify_totp_code},
+    },
+};
+
+use axum::{extract::State, response::IntoResponse, Json};
+use axum_extra::extract::CookieJar;
+use secrecy::{ExposeSecret, Secret};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Deserialize)]
+pub struct EnrollTotpRequest {
+    pub password: Secret<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct EnrollTotpResponse {
+    // Base32 secret, for typing into an authenticator app by hand
+    pub secret: String,
+    pub otpauth_uri: String,
+    // The otpauth URI as an SVG QR code
+    pub qr_code: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ConfirmTotpRequest {
+    pub code: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct DisableTotpRequest {
+    pub password: Secret<String>,
+    // What to use instead: `email`, or `none` to turn 2FA off
+    #[serde(rename = "twoFAMethod")]
+    pub two_fa_method: TwoFAMethod,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct DisableTotpResponse {
+    pub message: String,
+}
+
+// Stores a new TOTP secret for the user once they re-enter their password. Logins
+// keep using the current method until the secret is confirmed with a code from the
+// authenticator app.
+#[tracing::instrument(name = "Enroll_TOTP", skip_all)]
+pub async fn enroll_totp_handler(
+    State(state): State<AppState>,
+    cookie_jar: CookieJar,
+    Json(request): Json<EnrollTotpRequest>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let (_, user_id) = authenticate(&cookie_jar, &state).await?;
+
+    let user = get_user(&user_id, &state).await?;
+
+    // Replacing the secret would silently break the authenticator app in use
+    if user.two_fa_method() == TwoFAMethod::Totp {
+        return Err(AuthAPIError::TotpAlreadyEnabled);
+    }
+
+    reauthenticate(&user, request.password, &state).await?;
+
+    let secret = generate_totp_secret();
+    let otpauth_uri = totp_uri(&secret, user.email()).map_err(AuthAPIError::UnexpectedError)?;
+    let qr_code = qr_code_svg(&otpauth_uri).map_err(AuthAPIError::UnexpectedError)?;
+
+    state
+        .user_store
+        .write()
+        .await
+        .set_totp_secret(&user_id, secret.clone())
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    Ok(Json(EnrollTotpResponse {
+        secret: secret.expose_secret().to_owned(),
+        otpauth_uri,
+        qr_code,
+    }))
+}
+
+// Switches the user to TOTP once they prove their authenticator app has the secret,
+// handing out a fresh set of recovery codes
+#[tracing::instrument(name = "Confirm_TOTP", skip_all)]
+pub async fn confirm_totp_handler(
+    State(state): State<AppState>,
+    cookie_jar: CookieJar,
+    Json(request): Json<ConfirmTotpRequest>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let (_, user_id) = authenticate(&cookie_jar, &state).await?;
+
+    let user = get_user(&user_id, &state).await?;
+
+    if user.two_fa_method() == TwoFAMethod::Totp {
+        return Err(AuthAPIError::TotpAlreadyEnabled);
+    }
+
+    // Nothing to confirm before enrolling
+    let secret = user.totp_secret().ok_or(AuthAPIError::InvalidCredentials)?;
+
+    let step = verify_totp_code(secret, user.email(), &request.code)
+        .map_err(AuthAPIError::UnexpectedError)?
+        .ok_or(AuthAPIError::IncorrectCredentials)?;
+
+    let mut user_store = state.user_store.write().await;
+
+    // Also keeps the code used here from logging in afterwards
+    let accepted = user_store
+        .accept_totp_step(&user_id, step)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    if !accepted {
+        return Err(AuthAPIError::IncorrectCredentials);
+    }
+
+    user_store
+        .set_two_fa_method(&user_id, TwoFAMethod::Totp)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    drop(user_store);
+
+    let recovery_codes = issue_recovery_codes(&user_id, &state)
+        .await
+        .map_err(AuthAPIError::UnexpectedError)?;
+
+    Ok(Json(RecoveryCodesResponse { recovery_codes }))
+}
+
+// Turns TOTP off once the user re-enters their password, switching to emailed codes
+// or to no second factor. The secret is dropped, so enrolling again starts afresh.
+#[tracing::instrument(name = "Disable_TOTP", skip_all)]
+pub async fn disable_totp_handler(
+    State(state): State<AppState>,
+    cookie_jar: CookieJar,
+    Json(request): Json<DisableTotpRequest>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let (_, user_id) = authenticate(&cookie_jar, &state).await?;
+
+    if request.two_fa_method == TwoFAMethod::Totp {
+        return Err(AuthAPIError::InvalidCredentials);
+    }
+
+    let user = get_user(&user_id, &state).await?;
+
+    if user.two_fa_method() != TwoFAMethod::Totp {
+        return Err(AuthAPIError::TotpNotEnabled);
+    }
+
+    reauthenticate(&user, request.password, &state).await?;
+
+    let mut user_store = state.user_store.write().await;
+
+    user_store
+        .set_two_fa_method(&user_id, request.two_fa_method)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    user_store
+        .clear_totp_secret(&user_id)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    drop(user_store);
+
+    tracing::warn!(
+        user_id = %user_id,
+        two_fa_method = %request.two_fa_method,
+        "Security event: TOTP disabled"
+    );
+
+    // Lets the owner notice if someone else weakened their 2FA. TOTP is already off,
+    // so a failed email does not fail the request.
+    let sent = state
+        .email_client
+        .write()
+        .await
+        .send_email(
+            user.email(),
+            "Your authenticator app was removed",
+            "Your account no longer asks for codes from your authenticator app. If this wasn't you, reset your password right away.",
+        )
+        .await;
+
+    if let Err(e) = sent {
+        tracing::error!("Failed to send TOTP disabled email: {:?}", e);
+    }
+
+    Ok(Json(DisableTotpResponse {
+        message: "TOTP has been disabled".into(),
+    }))
+}
+
+async fn get_user(user_id: &UserId, state: &AppState) -> Result<User, AuthAPIError> {
+    state
+        .user_store
+        .read()
+        .await
+        .get_user_by_id(user_id)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))
+}
@@ -1,11 +1,19 @@
 use crate::{
     app_state::app_state::AppState,
     domain::{
-        data_stores::{LoginAttemptId, TwoFACode},
+        data_stores::{
+            LoginAttemptId, RecoveryCode, RecoveryCodeStoreError, TwoFACode, TwoFACodeHash,
+            TwoFACodeStoreError,
+        },
         error::AuthAPIError,
-        Email,
+        Email, TwoFAMethod, User,
+    },
+    utils::{
+        auth::start_session,
+        client_info::ClientInfo,
+        constants::{MAX_2FA_ATTEMPTS, TWO_FA_CODE_GENERATOR},
+        totp::verify_totp_code,
     },
-    utils::auth::generate_auth_cookie,
 };
 
 use ::serde::{Deserialize, Serialize};
@@ -18,7 +26,11 @@ use secrecy::Secret;
 pub struct Verify2FARequest {
     pub email: String,
     pub login_attempt_id: String,
+    #[serde(default)]
     pub two_fa_code: String,
+    // Stands in for the 2FA code when the user has lost their second factor
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub recovery_code: Option<String>,
 }
 
 #[derive(Debug, PartialEq, Serialize, Deserialize)]
@@ -30,37 +42,238 @@ pub struct Verify2FAResponse {
 pub async fn verify_2fa_handler(
     State(state): State<AppState>,
     cookie_jar: CookieJar,
+    client: ClientInfo,
     Json(request): Json<Verify2FARequest>,
 ) -> (CookieJar, Result<impl IntoResponse, AuthAPIError>) {
-    let (Ok(email), Ok(login_attempt_id_request), Ok(two_fa_code_request)) = (
+    let (Ok(email), Ok(login_attempt_id)) = (
         Email::parse(Secret::new(request.email)),
         LoginAttemptId::parse(Secret::new(request.login_attempt_id)),
-        TwoFACode::parse(request.two_fa_code),
     ) else {
         return (cookie_jar, Err(AuthAPIError::InvalidCredentials));
     };
 
-    let mut two_fa_store = state.two_fa_code_store.write().await;
+    // Only the shape is checked up front: six digits for TOTP, or whatever the
+    // emailed code generator is configured to produce
+    let recovery_code = match request.recovery_code {
+        Some(code) => match RecoveryCode::parse(Secret::new(code)) {
+            Ok(code) => Some(code),
+            Err(_) => return (cookie_jar, Err(AuthAPIError::InvalidCredentials)),
+        },
+        None if is_six_digits(&request.two_fa_code)
+            || TWO_FA_CODE_GENERATOR.is_well_formed(&request.two_fa_code) =>
+        {
+            None
+        }
+        None => return (cookie_jar, Err(AuthAPIError::InvalidCredentials)),
+    };
+
+    // The guess counts before it is checked, so concurrent guesses cannot get
+    // past the limit while earlier ones are still being compared
+    let reserved = state
+        .two_fa_code_store
+        .write()
+        .await
+        .reserve_attempt(&login_attempt_id)
+        .await;
+
+    let attempt = match reserved {
+        Ok(attempt) => attempt,
+        Err(TwoFACodeStoreError::TooManyAttempts) => {
+            return (cookie_jar, Err(AuthAPIError::TooManyTwoFAAttempts))
+        }
+        Err(TwoFACodeStoreError::LoginAttemptIdNotFound) => {
+            return (cookie_jar, Err(AuthAPIError::IncorrectCredentials))
+        }
+        Err(e) => return (cookie_jar, Err(AuthAPIError::UnexpectedError(e.into()))),
+    };
 
-    let (login_attempt_id, two_fa_code) = match two_fa_store.get_code(&email).await {
+    // Taken rather than read, so of several requests with the right code only
+    // one gets a session
+    let taken = state
+        .two_fa_code_store
+        .write()
+        .await
+        .take_code(&login_attempt_id)
+        .await;
+
+    let (login_email, two_fa_code_hash) = match taken {
         Ok(tfa_tuple) => tfa_tuple,
-        _ => return (cookie_jar, Err(AuthAPIError::IncorrectCredentials)),
+        Err(TwoFACodeStoreError::LoginAttemptIdNotFound) => {
+            return (cookie_jar, Err(AuthAPIError::IncorrectCredentials))
+        }
+        Err(e) => return (cookie_jar, Err(AuthAPIError::UnexpectedError(e.into()))),
+    };
+
+    let user = if email == login_email {
+        state.user_store.read().await.get_user(&email).await.ok()
+    } else {
+        None
+    };
+
+    let code_matches = match &user {
+        Some(user) => {
+            check_code(
+                user,
+                recovery_code,
+                request.two_fa_code,
+                &two_fa_code_hash,
+                &state,
+            )
+            .await
+        }
+        None => Ok(false),
     };
 
-    if login_attempt_id_request.ne(&login_attempt_id) || two_fa_code_request.ne(&two_fa_code) {
-        return (cookie_jar, Err(AuthAPIError::IncorrectCredentials));
+    let user = match (code_matches, user) {
+        (Ok(true), Some(user)) => user,
+        (Ok(_), user) => {
+            let error = reject_guess(&login_attempt_id, attempt, user.as_ref(), &state).await;
+            return (cookie_jar, Err(error));
+        }
+        (Err(e), _) => {
+            // Nothing was wrong with the guess, so it can be tried again
+            if let Err(restore_error) = restore_code(&login_attempt_id, &state).await {
+                tracing::error!("Failed to restore 2FA code: {:?}", restore_error);
+            }
+
+            return (cookie_jar, Err(e));
+        }
+    };
+
+    // Clears the attempt's counters along with the taken code
+    let removed = state
+        .two_fa_code_store
+        .write()
+        .await
+        .remove_code(&login_attempt_id)
+        .await;
+
+    if let Err(e) = removed {
+        return (cookie_jar, Err(AuthAPIError::UnexpectedError(e.into())));
     }
 
-    let auth_cookie = match generate_auth_cookie(&email) {
-        Ok(cookie) => cookie,
+    let (auth_cookie, refresh_cookie) = match start_session(&user, &client, &state).await {
+        Ok(cookies) => cookies,
         Err(e) => return (cookie_jar, Err(AuthAPIError::UnexpectedError(e))),
     };
 
-    let updated_jar = cookie_jar.add(auth_cookie);
+    let updated_jar = cookie_jar.add(auth_cookie).add(refresh_cookie);
+
+    (updated_jar, Ok(StatusCode::OK.into_response()))
+}
+
+// Each store call takes its own lock, so checking a recovery code (several
+// password-strength hashes) does not hold up other logins
+async fn check_code(
+    user: &User,
+    recovery_code: Option<RecoveryCode>,
+    two_fa_code: String,
+    two_fa_code_hash: &TwoFACodeHash,
+    state: &AppState,
+) -> Result<bool, AuthAPIError> {
+    match (recovery_code, user.two_fa_method(), user.totp_secret()) {
+        (Some(recovery_code), _, _) => {
+            let used = state
+                .recovery_code_store
+                .write()
+                .await
+                .use_code(user.id(), &recovery_code)
+                .await;
 
-    if let Err(e) = two_fa_store.remove_code(&email).await {
-        return (updated_jar, Err(AuthAPIError::UnexpectedError(e.into())));
+            match used {
+                Ok(()) => Ok(true),
+                Err(RecoveryCodeStoreError::InvalidCode) => Ok(false),
+                Err(e) => Err(AuthAPIError::UnexpectedError(e.into())),
+            }
+        }
+        (None, TwoFAMethod::Totp, Some(secret)) => {
+            let step = verify_totp_code(secret, user.email(), &two_fa_code)
+                .map_err(AuthAPIError::UnexpectedError)?;
+
+            match step {
+                // A right code is still refused if it was already used
+                Some(step) => state
+                    .user_store
+                    .write()
+                    .await
+                    .accept_totp_step(user.id(), step)
+                    .await
+                    .map_err(|e| AuthAPIError::UnexpectedError(e.into())),
+                None => Ok(false),
+            }
+        }
+        _ => match TwoFACode::parse(two_fa_code) {
+            Ok(code) => {
+                Ok(state.two_fa_code_store.read().await.hash_code(&code) == *two_fa_code_hash)
+            }
+            Err(_) => Ok(false),
+        },
     }
+}
 
-    (updated_jar, Ok(StatusCode::OK.into_response()))
+// Puts a wrongly guessed code back for another try, or drops it for good once
+// the last allowed attempt has failed
+async fn reject_guess(
+    login_attempt_id: &LoginAttemptId,
+    attempt: u32,
+    user: Option<&User>,
+    state: &AppState,
+) -> AuthAPIError {
+    if let Some(user) = user {
+        tracing::warn!(
+            user_id = %user.id(),
+            attempt,
+            max_attempts = MAX_2FA_ATTEMPTS,
+            "Security event: failed 2FA attempt"
+        );
+    }
+
+    if attempt < MAX_2FA_ATTEMPTS {
+        return match restore_code(login_attempt_id, state).await {
+            Ok(()) => AuthAPIError::IncorrectCredentials,
+            Err(e) => e,
+        };
+    }
+
+    let removed = state
+        .two_fa_code_store
+        .write()
+        .await
+        .remove_code(login_attempt_id)
+        .await;
+
+    if let Err(e) = removed {
+        return AuthAPIError::UnexpectedError(e.into());
+    }
+
+    if let Some(user) = user {
+        tracing::warn!(
+            user_id = %user.id(),
+            "Security event: 2FA code invalidated after too many failed attempts"
+        );
+    }
+
+    AuthAPIError::TooManyTwoFAAttempts
+}
+
+async fn restore_code(
+    login_attempt_id: &LoginAttemptId,
+    state: &AppState,
+) -> Result<(), AuthAPIError> {
+    let restored = state
+        .two_fa_code_store
+        .write()
+        .await
+        .restore_code(login_attempt_id)
+        .await;
+
+    match restored {
+        // Expired in the meantime
+        Ok(()) | Err(TwoFACodeStoreError::LoginAttemptIdNotFound) => Ok(()),
+        Err(e) => Err(AuthAPIError::UnexpectedError(e.into())),
+    }
+}
+
+fn is_six_digits(code: &str) -> bool {
+    code.len() == 6 && code.bytes().all(|b| b.is_ascii_digit())
 }
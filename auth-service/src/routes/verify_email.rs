@@ -0,0 +1,127 @@
+use crate::{
+    app_state::app_state::AppState,
+    domain::{
+        data_stores::{EmailVerificationToken, EmailVerificationTokenStoreError, UserStoreError},
+        error::AuthAPIError,
+        Email, User,
+    },
+    utils::constants::{EMAIL_VERIFICATION_TOKEN_TTL_SECONDS, EMAIL_VERIFICATION_URL},
+};
+
+use axum::{extract::State, http::StatusCode, response::IntoResponse, Json};
+use color_eyre::eyre;
+use secrecy::{ExposeSecret, Secret};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Deserialize)]
+pub struct VerifyEmailRequest {
+    pub token: Secret<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ResendVerificationRequest {
+    pub email: Secret<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct VerifyEmailResponse {
+    pub message: String,
+}
+
+// Issues a new verification token and emails its link, on signup and on resend
+pub async fn send_verification_email(user: &User, state: &AppState) -> eyre::Result<()> {
+    let token = EmailVerificationToken::default();
+
+    state
+        .email_verification_token_store
+        .write()
+        .await
+        .add_token(&token, *user.id())
+        .await?;
+
+    let content = format!(
+        "Confirm your email address here: {}?token={}\nThe link expires in {} hours.",
+        *EMAIL_VERIFICATION_URL,
+        token.as_ref().expose_secret(),
+        EMAIL_VERIFICATION_TOKEN_TTL_SECONDS / 3600
+    );
+
+    state
+        .email_client
+        .write()
+        .await
+        .send_email(user.email(), "Verify your email", &content)
+        .await?;
+
+    Ok(())
+}
+
+#[tracing::instrument(name = "Verify_Email", skip_all)]
+pub async fn verify_email_handler(
+    State(state): State<AppState>,
+    Json(request): Json<VerifyEmailRequest>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let token = EmailVerificationToken::parse(request.token)
+        .map_err(|_| AuthAPIError::InvalidEmailVerificationToken)?;
+
+    let user_id = state
+        .email_verification_token_store
+        .write()
+        .await
+        .take_token(&token)
+        .await
+        .map_err(|e| match e {
+            EmailVerificationTokenStoreError::TokenNotFound => {
+                AuthAPIError::InvalidEmailVerificationToken
+            }
+            e => AuthAPIError::UnexpectedError(e.into()),
+        })?;
+
+    state
+        .user_store
+        .write()
+        .await
+        .mark_email_verified(&user_id)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    Ok((
+        StatusCode::OK,
+        Json(VerifyEmailResponse {
+            message: "Email address verified".into(),
+        }),
+    ))
+}
+
+// Answers the same for unknown and already verified addresses, so the endpoint
+// cannot be used to find out who has signed up
+#[tracing::instrument(name = "Resend_Verification_Email", skip_all)]
+pub async fn resend_verification_email_handler(
+    State(state): State<AppState>,
+    Json(request): Json<ResendVerificationRequest>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let email = Email::parse(request.email).map_err(|_| AuthAPIError::InvalidCredentials)?;
+
+    let response = (
+        StatusCode::ACCEPTED,
+        Json(VerifyEmailResponse {
+            message: "If the address needs verifying, a new link has been sent".into(),
+        }),
+    );
+
+    let user = match state.user_store.read().await.get_user(&email).await {
+        Ok(user) => user,
+        Err(UserStoreError::UserNotFound) => return Ok(response),
+        Err(e) => return Err(AuthAPIError::UnexpectedError(e.into())),
+    };
+
+    if user.email_verified() {
+        return Ok(response);
+    }
+
+    send_verification_email(&user, &state)
+        .await
+        .map_err(AuthAPIError::UnexpectedError)?;
+
+    Ok(response)
+}
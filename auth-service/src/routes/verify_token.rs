@@ -20,10 +20,7 @@ pub async fn verify_token_handler(
     State(state): State<AppState>,
     Json(request): Json<VerifyTokenRequest>,
 ) -> impl IntoResponse {
-    if validate_token(&request.token, state.banned_token_store)
-        .await
-        .is_err()
-    {
+    if validate_token(&request.token, &state).await.is_err() {
         return Err(AuthAPIError::InvalidToken);
     }
 
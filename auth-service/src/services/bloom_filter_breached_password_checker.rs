@@ -0,0 +1,32 @@
+use crate::{
+    domain::BreachedPasswordChecker,
+    utils::breached_passwords::{sha1_digest, BloomFilter},
+};
+
+use color_eyre::eyre;
+use secrecy::Secret;
+use std::path::Path;
+
+// A filter built from a HIBP dump by the `build_breached_password_filter` binary.
+// Small enough to hold the full corpus in memory, but now and then rejects a
+// password that was never breached.
+#[derive(Debug, Clone)]
+pub struct BloomFilterBreachedPasswordChecker {
+    filter: BloomFilter,
+}
+
+impl BloomFilterBreachedPasswordChecker {
+    pub fn new(filter: BloomFilter) -> Self {
+        Self { filter }
+    }
+
+    pub fn load(path: &Path) -> eyre::Result<Self> {
+        Ok(Self::new(BloomFilter::load(path)?))
+    }
+}
+
+impl BreachedPasswordChecker for BloomFilterBreachedPasswordChecker {
+    fn is_breached(&self, password: &Secret<String>) -> bool {
+        self.filter.contains(&sha1_digest(password))
+    }
+}
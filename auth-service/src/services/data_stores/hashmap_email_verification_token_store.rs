@@ -0,0 +1,100 @@
+use chrono::{DateTime, Duration, Utc};
+
+use crate::{
+    domain::{
+        data_stores::{
+            EmailVerificationToken, EmailVerificationTokenStore, EmailVerificationTokenStoreError,
+        },
+        UserId,
+    },
+    utils::constants::EMAIL_VERIFICATION_TOKEN_TTL_SECONDS,
+};
+
+use std::collections::HashMap;
+
+#[derive(Default, Clone)]
+pub struct HashmapEmailVerificationTokenStore {
+    // Token fingerprints with the user and the time they were issued
+    tokens: HashMap<String, (UserId, DateTime<Utc>)>,
+}
+
+#[async_trait::async_trait]
+impl EmailVerificationTokenStore for HashmapEmailVerificationTokenStore {
+    async fn add_token(
+        &mut self,
+        token: &EmailVerificationToken,
+        user_id: UserId,
+    ) -> Result<(), EmailVerificationTokenStoreError> {
+        self.tokens
+            .retain(|_, (_, issued_at)| !is_expired(*issued_at));
+        self.tokens
+            .insert(token.fingerprint(), (user_id, Utc::now()));
+        Ok(())
+    }
+
+    async fn take_token(
+        &mut self,
+        token: &EmailVerificationToken,
+    ) -> Result<UserId, EmailVerificationTokenStoreError> {
+        self.tokens
+            .remove(&token.fingerprint())
+            .filter(|(_, issued_at)| !is_expired(*issued_at))
+            .map(|(user_id, _)| user_id)
+            .ok_or(EmailVerificationTokenStoreError::TokenNotFound)
+    }
+}
+
+fn is_expired(issued_at: DateTime<Utc>) -> bool {
+    issued_at + Duration::seconds(EMAIL_VERIFICATION_TOKEN_TTL_SECONDS) <= Utc::now()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use secrecy::{ExposeSecret, Secret};
+
+    #[tokio::test]
+    async fn test_take_token_only_once() {
+        let mut store = HashmapEmailVerificationTokenStore::default();
+        let token = EmailVerificationToken::default();
+        let user_id = UserId::default();
+
+        store.add_token(&token, user_id).await.unwrap();
+
+        assert_eq!(store.take_token(&token).await, Ok(user_id));
+        assert_eq!(
+            store.take_token(&token).await,
+            Err(EmailVerificationTokenStoreError::TokenNotFound)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_expired_token_not_found() {
+        let mut store = HashmapEmailVerificationTokenStore::default();
+        let token = EmailVerificationToken::default();
+        let issued_at = Utc::now() - Duration::seconds(EMAIL_VERIFICATION_TOKEN_TTL_SECONDS);
+
+        store
+            .tokens
+            .insert(token.fingerprint(), (UserId::default(), issued_at));
+
+        assert_eq!(
+            store.take_token(&token).await,
+            Err(EmailVerificationTokenStoreError::TokenNotFound)
+        );
+    }
+
+    #[test]
+    fn test_token_round_trips_parse() {
+        let token = EmailVerificationToken::default();
+        let parsed = EmailVerificationToken::parse(token.as_ref().clone()).unwrap();
+
+        assert_eq!(parsed.fingerprint(), token.fingerprint());
+        assert_ne!(
+            token.fingerprint(),
+            token.as_ref().expose_secret().to_owned()
+        );
+        assert!(EmailVerificationToken::parse(Secret::new("short".to_owned())).is_err());
+    }
+}
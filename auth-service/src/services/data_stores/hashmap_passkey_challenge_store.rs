@@ -0,0 +1,83 @@
+use chrono::{DateTime, Duration, Utc};
+
+use crate::{
+    domain::data_stores::{PasskeyChallenge, PasskeyChallengeStore, PasskeyChallengeStoreError},
+    utils::constants::PASSKEY_CHALLENGE_TTL_SECONDS,
+};
+
+use std::collections::HashMap;
+
+#[derive(Default, Clone)]
+pub struct HashmapPasskeyChallengeStore {
+    // Challenges with the time they were issued
+    challenges: HashMap<String, (PasskeyChallenge, DateTime<Utc>)>,
+}
+
+#[async_trait::async_trait]
+impl PasskeyChallengeStore for HashmapPasskeyChallengeStore {
+    async fn add_challenge(
+        &mut self,
+        id: String,
+        challenge: PasskeyChallenge,
+    ) -> Result<(), PasskeyChallengeStoreError> {
+        self.challenges
+            .retain(|_, (_, issued_at)| !is_expired(*issued_at));
+        self.challenges.insert(id, (challenge, Utc::now()));
+        Ok(())
+    }
+
+    async fn take_challenge(
+        &mut self,
+        id: &str,
+    ) -> Result<PasskeyChallenge, PasskeyChallengeStoreError> {
+        self.challenges
+            .remove(id)
+            .filter(|(_, issued_at)| !is_expired(*issued_at))
+            .map(|(challenge, _)| challenge)
+            .ok_or(PasskeyChallengeStoreError::ChallengeNotFound)
+    }
+}
+
+fn is_expired(issued_at: DateTime<Utc>) -> bool {
+    issued_at + Duration::seconds(PASSKEY_CHALLENGE_TTL_SECONDS) <= Utc::now()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::domain::data_stores::PasskeyCeremony;
+
+    #[tokio::test]
+    async fn test_take_challenge_only_once() {
+        let mut store = HashmapPasskeyChallengeStore::default();
+        let challenge = PasskeyChallenge::new(PasskeyCeremony::Authentication, None);
+
+        store
+            .add_challenge("id".to_owned(), challenge.clone())
+            .await
+            .unwrap();
+
+        assert_eq!(store.take_challenge("id").await, Ok(challenge));
+        assert_eq!(
+            store.take_challenge("id").await,
+            Err(PasskeyChallengeStoreError::ChallengeNotFound)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_expired_challenge_not_found() {
+        let mut store = HashmapPasskeyChallengeStore::default();
+        let challenge = PasskeyChallenge::new(PasskeyCeremony::Registration, None);
+        let issued_at = Utc::now() - Duration::seconds(PASSKEY_CHALLENGE_TTL_SECONDS);
+
+        store
+            .challenges
+            .insert("id".to_owned(), (challenge, issued_at));
+
+        assert_eq!(
+            store.take_challenge("id").await,
+            Err(PasskeyChallengeStoreError::ChallengeNotFound)
+        );
+    }
+}
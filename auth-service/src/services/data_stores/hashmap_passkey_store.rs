@@ -0,0 +1,159 @@
+use crate::domain::{
+    data_stores::{PasskeyCredential, PasskeyStore, PasskeyStoreError},
+    UserId,
+};
+
+use std::collections::HashMap;
+
+#[derive(Default, Clone)]
+pub struct HashmapPasskeyStore {
+    credentials: HashMap<String, PasskeyCredential>,
+}
+
+#[async_trait::async_trait]
+impl PasskeyStore for HashmapPasskeyStore {
+    async fn add_credential(
+        &mut self,
+        credential: PasskeyCredential,
+    ) -> Result<(), PasskeyStoreError> {
+        if self.credentials.contains_key(&credential.id) {
+            return Err(PasskeyStoreError::CredentialAlreadyExists);
+        }
+
+        self.credentials.insert(credential.id.clone(), credential);
+        Ok(())
+    }
+
+    async fn get_credential(&self, id: &str) -> Result<PasskeyCredential, PasskeyStoreError> {
+        self.credentials
+            .get(id)
+            .cloned()
+            .ok_or(PasskeyStoreError::CredentialNotFound)
+    }
+
+    async fn list_credentials(
+        &self,
+        user_id: &UserId,
+    ) -> Result<Vec<PasskeyCredential>, PasskeyStoreError> {
+        let mut credentials: Vec<PasskeyCredential> = self
+            .credentials
+            .values()
+            .filter(|credential| credential.user_id == *user_id)
+            .cloned()
+            .collect();
+
+        credentials.sort_by_key(|credential| credential.created_at);
+
+        Ok(credentials)
+    }
+
+    async fn update_sign_count(
+        &mut self,
+        id: &str,
+        sign_count: u32,
+    ) -> Result<(), PasskeyStoreError> {
+        let credential = self
+            .credentials
+            .get_mut(id)
+            .ok_or(PasskeyStoreError::CredentialNotFound)?;
+
+        credential.sign_count = sign_count;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use chrono::{Duration, Utc};
+
+    fn credential(id: &str, user_id: UserId) -> PasskeyCredential {
+        PasskeyCredential {
+            id: id.to_owned(),
+            user_id,
+            public_key: vec![4; 65],
+            sign_count: 0,
+            created_at: Utc::now(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_add_and_get_credential() {
+        let mut store = HashmapPasskeyStore::default();
+        let credential = credential("credential", UserId::default());
+
+        store.add_credential(credential.clone()).await.unwrap();
+
+        assert_eq!(store.get_credential("credential").await, Ok(credential));
+        assert_eq!(
+            store.get_credential("other").await,
+            Err(PasskeyStoreError::CredentialNotFound)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_add_existing_credential() {
+        let mut store = HashmapPasskeyStore::default();
+
+        store
+            .add_credential(credential("credential", UserId::default()))
+            .await
+            .unwrap();
+
+        assert_eq!(
+            store
+                .add_credential(credential("credential", UserId::default()))
+                .await,
+            Err(PasskeyStoreError::CredentialAlreadyExists)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_list_credentials_oldest_first() {
+        let mut store = HashmapPasskeyStore::default();
+        let user_id = UserId::default();
+        let mut older = credential("older", user_id);
+        older.created_at -= Duration::minutes(5);
+
+        store
+            .add_credential(credential("newer", user_id))
+            .await
+            .unwrap();
+        store.add_credential(older).await.unwrap();
+        store
+            .add_credential(credential("other", UserId::default()))
+            .await
+            .unwrap();
+
+        let ids: Vec<String> = store
+            .list_credentials(&user_id)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|credential| credential.id)
+            .collect();
+
+        assert_eq!(ids, vec!["older", "newer"]);
+    }
+
+    #[tokio::test]
+    async fn test_update_sign_count() {
+        let mut store = HashmapPasskeyStore::default();
+
+        store
+            .add_credential(credential("credential", UserId::default()))
+            .await
+            .unwrap();
+        store.update_sign_count("credential", 7).await.unwrap();
+
+        assert_eq!(
+            store.get_credential("credential").await.unwrap().sign_count,
+            7
+        );
+        assert_eq!(
+            store.update_sign_count("other", 1).await,
+            Err(PasskeyStoreError::CredentialNotFound)
+        );
+    }
+}
@@ -0,0 +1,80 @@
+use chrono::Utc;
+
+use crate::domain::data_stores::{RateLimitStore, RateLimitStoreError};
+
+use std::collections::{HashMap, VecDeque};
+
+// Only limits the replica it runs in; use the Redis store behind a load balancer
+#[derive(Default, Clone)]
+pub struct HashmapRateLimitStore {
+    // Times of the hits let through, in milliseconds, oldest first
+    hits: HashMap<String, VecDeque<i64>>,
+}
+
+#[async_trait::async_trait]
+impl RateLimitStore for HashmapRateLimitStore {
+    async fn hit(
+        &mut self,
+        key: &str,
+        limit: u32,
+        window_seconds: i64,
+    ) -> Result<(), RateLimitStoreError> {
+        let now = Utc::now().timestamp_millis();
+        let window_start = now - window_seconds * 1000;
+
+        // Keys nobody hit within the window would otherwise pile up
+        self.hits.retain(|_, hits| {
+            while hits.front().is_some_and(|hit| *hit <= window_start) {
+                hits.pop_front();
+            }
+            !hits.is_empty()
+        });
+
+        let hits = self.hits.entry(key.to_owned()).or_default();
+
+        if hits.len() >= limit as usize {
+            let oldest = hits.front().copied().unwrap_or(now);
+            let retry_after_millis = oldest + window_seconds * 1000 - now;
+
+            return Err(RateLimitStoreError::LimitExceeded(
+                (retry_after_millis as u64).div_ceil(1000).max(1),
+            ));
+        }
+
+        hits.push_back(now);
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_hit() {
+        let mut store = HashmapRateLimitStore::default();
+
+        for _ in 0..3 {
+            assert!(store.hit("login:ip:127.0.0.1", 3, 60).await.is_ok());
+        }
+
+        let result = store.hit("login:ip:127.0.0.1", 3, 60).await;
+
+        assert!(matches!(
+            result,
+            Err(RateLimitStoreError::LimitExceeded(seconds)) if (1..=60).contains(&seconds)
+        ));
+
+        // Keys are limited independently
+        assert!(store.hit("login:ip:10.0.0.1", 3, 60).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_hit_after_window() {
+        let mut store = HashmapRateLimitStore::default();
+
+        assert!(store.hit("signup:ip:127.0.0.1", 1, 0).await.is_ok());
+        assert!(store.hit("signup:ip:127.0.0.1", 1, 0).await.is_ok());
+    }
+}
@@ -0,0 +1,107 @@
+use crate::domain::{
+    data_stores::{RecoveryCode, RecoveryCodeStore, RecoveryCodeStoreError},
+    UserId,
+};
+
+use std::collections::HashMap;
+
+#[derive(Default, Clone)]
+pub struct HashmapRecoveryCodeStore {
+    codes: HashMap<UserId, Vec<RecoveryCode>>,
+}
+
+#[async_trait::async_trait]
+impl RecoveryCodeStore for HashmapRecoveryCodeStore {
+    async fn replace_codes(
+        &mut self,
+        user_id: &UserId,
+        codes: Vec<RecoveryCode>,
+    ) -> Result<(), RecoveryCodeStoreError> {
+        self.codes.insert(*user_id, codes);
+        Ok(())
+    }
+
+    async fn use_code(
+        &mut self,
+        user_id: &UserId,
+        code: &RecoveryCode,
+    ) -> Result<(), RecoveryCodeStoreError> {
+        let codes = self
+            .codes
+            .get_mut(user_id)
+            .ok_or(RecoveryCodeStoreError::InvalidCode)?;
+
+        let position = codes
+            .iter()
+            .position(|stored| stored == code)
+            .ok_or(RecoveryCodeStoreError::InvalidCode)?;
+
+        codes.remove(position);
+        Ok(())
+    }
+
+    async fn count_codes(&self, user_id: &UserId) -> Result<usize, RecoveryCodeStoreError> {
+        Ok(self.codes.get(user_id).map_or(0, Vec::len))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_use_code_only_once() {
+        let mut store = HashmapRecoveryCodeStore::default();
+        let user_id = UserId::default();
+        let codes: Vec<RecoveryCode> = (0..3).map(|_| RecoveryCode::default()).collect();
+
+        store.replace_codes(&user_id, codes.clone()).await.unwrap();
+
+        assert_eq!(store.use_code(&user_id, &codes[1]).await, Ok(()));
+        assert_eq!(store.count_codes(&user_id).await.unwrap(), 2);
+        assert_eq!(
+            store.use_code(&user_id, &codes[1]).await,
+            Err(RecoveryCodeStoreError::InvalidCode)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_use_code_of_another_user() {
+        let mut store = HashmapRecoveryCodeStore::default();
+        let code = RecoveryCode::default();
+
+        store
+            .replace_codes(&UserId::default(), vec![code.clone()])
+            .await
+            .unwrap();
+
+        assert_eq!(
+            store.use_code(&UserId::default(), &code).await,
+            Err(RecoveryCodeStoreError::InvalidCode)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_replace_codes_invalidates_old_codes() {
+        let mut store = HashmapRecoveryCodeStore::default();
+        let user_id = UserId::default();
+        let old_code = RecoveryCode::default();
+        let new_code = RecoveryCode::default();
+
+        store
+            .replace_codes(&user_id, vec![old_code.clone()])
+            .await
+            .unwrap();
+        store
+            .replace_codes(&user_id, vec![new_code.clone()])
+            .await
+            .unwrap();
+
+        assert_eq!(
+            store.use_code(&user_id, &old_code).await,
+            Err(RecoveryCodeStoreError::InvalidCode)
+        );
+        assert_eq!(store.use_code(&user_id, &new_code).await, Ok(()));
+        assert_eq!(store.count_codes(&user_id).await.unwrap(), 0);
+    }
+}
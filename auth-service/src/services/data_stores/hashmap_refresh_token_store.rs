@@ -0,0 +1,173 @@
+use chrono::Utc;
+
+use crate::{
+    domain::data_stores::{
+        RefreshToken, RefreshTokenRecord, RefreshTokenStore, RefreshTokenStoreError,
+    },
+    utils::constants::REFRESH_TOKEN_TTL_SECONDS,
+};
+
+use std::collections::HashMap;
+
+// Keyed by token fingerprint, each entry with the timestamp it expires at, like
+// the Redis store's TTLs
+#[derive(Default, Clone)]
+pub struct HashmapRefreshTokenStore {
+    tokens: HashMap<String, (RefreshTokenRecord, i64)>,
+    used_tokens: HashMap<String, i64>,
+    revoked_families: HashMap<String, i64>,
+}
+
+impl HashmapRefreshTokenStore {
+    fn purge_expired(&mut self) {
+        let now = Utc::now().timestamp();
+
+        self.tokens.retain(|_, (_, expires_at)| *expires_at > now);
+        self.used_tokens.retain(|_, expires_at| *expires_at > now);
+        self.revoked_families
+            .retain(|_, expires_at| *expires_at > now);
+    }
+}
+
+#[async_trait::async_trait]
+impl RefreshTokenStore for HashmapRefreshTokenStore {
+    async fn add_token(
+        &mut self,
+        token: RefreshToken,
+        record: RefreshTokenRecord,
+    ) -> Result<(), RefreshTokenStoreError> {
+        self.purge_expired();
+
+        self.tokens
+            .insert(token.fingerprint(), (record, expires_at()));
+        Ok(())
+    }
+
+    async fn consume_token(
+        &mut self,
+        token: &RefreshToken,
+    ) -> Result<RefreshTokenRecord, RefreshTokenStoreError> {
+        self.purge_expired();
+
+        let token = token.fingerprint();
+
+        let record = self
+            .tokens
+            .get(&token)
+            .map(|(record, _)| record.clone())
+            .ok_or(RefreshTokenStoreError::TokenNotFound)?;
+
+        if self.used_tokens.insert(token, expires_at()).is_some() {
+            return Err(RefreshTokenStoreError::TokenReused(record));
+        }
+
+        Ok(record)
+    }
+
+    async fn revoke_family(&mut self, family_id: &str) -> Result<(), RefreshTokenStoreError> {
+        self.purge_expired();
+
+        self.revoked_families
+            .insert(family_id.to_owned(), expires_at());
+        Ok(())
+    }
+
+    async fn is_family_revoked(&self, family_id: &str) -> Result<bool, RefreshTokenStoreError> {
+        Ok(self
+            .revoked_families
+            .get(family_id)
+            .is_some_and(|expires_at| *expires_at > Utc::now().timestamp()))
+    }
+}
+
+fn expires_at() -> i64 {
+    Utc::now().timestamp() + REFRESH_TOKEN_TTL_SECONDS
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::UserId;
+    use uuid::Uuid;
+
+    fn record() -> RefreshTokenRecord {
+        RefreshTokenRecord::new(UserId::default(), 0, Uuid::new_v4().to_string())
+    }
+
+    #[tokio::test]
+    async fn test_add_token() {
+        let mut store = HashmapRefreshTokenStore::default();
+        let token = RefreshToken::default();
+
+        let result = store.add_token(token.clone(), record()).await;
+
+        assert!(result.is_ok());
+        assert!(store.tokens.contains_key(&token.fingerprint()));
+    }
+
+    #[tokio::test]
+    async fn test_consume_token() {
+        let mut store = HashmapRefreshTokenStore::default();
+        let token = RefreshToken::default();
+        let record = record();
+
+        store
+            .add_token(token.clone(), record.clone())
+            .await
+            .unwrap();
+
+        let result = store.consume_token(&token).await;
+        assert_eq!(result.unwrap(), record);
+
+        // Presenting the same token again is a reuse
+        let result = store.consume_token(&token).await;
+        assert_eq!(
+            result,
+            Err(RefreshTokenStoreError::TokenReused(record.clone()))
+        );
+
+        // Unknown tokens are not found
+        let result = store.consume_token(&RefreshToken::default()).await;
+        assert_eq!(result, Err(RefreshTokenStoreError::TokenNotFound));
+    }
+
+    #[tokio::test]
+    async fn test_revoke_family() {
+        let mut store = HashmapRefreshTokenStore::default();
+        let record = record();
+
+        assert!(!store.is_family_revoked(&record.family_id).await.unwrap());
+
+        store.revoke_family(&record.family_id).await.unwrap();
+
+        assert!(store.is_family_revoked(&record.family_id).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_expired_entries_are_purged() {
+        let mut store = HashmapRefreshTokenStore::default();
+        let expired = RefreshToken::default();
+        let past = Utc::now().timestamp() - 1;
+
+        store.tokens.insert(expired.fingerprint(), (record(), past));
+        store.used_tokens.insert("expired_used".to_owned(), past);
+        store
+            .revoked_families
+            .insert("expired_family".to_owned(), past);
+
+        assert!(!store.is_family_revoked("expired_family").await.unwrap());
+        assert_eq!(
+            store.consume_token(&expired).await,
+            Err(RefreshTokenStoreError::TokenNotFound)
+        );
+
+        store
+            .add_token(RefreshToken::default(), record())
+            .await
+            .unwrap();
+
+        assert_eq!(store.tokens.len(), 1);
+        assert!(store.used_tokens.is_empty());
+        assert!(store.revoked_families.is_empty());
+    }
+}
@@ -0,0 +1,199 @@
+use chrono::{DateTime, Duration, Utc};
+
+use crate::{
+    domain::{
+        data_stores::{Session, SessionStore, SessionStoreError},
+        UserId,
+    },
+    utils::constants::SESSION_TTL_SECONDS,
+};
+
+use std::collections::HashMap;
+
+#[derive(Default, Clone)]
+pub struct HashmapSessionStore {
+    sessions: HashMap<String, Session>,
+}
+
+#[async_trait::async_trait]
+impl SessionStore for HashmapSessionStore {
+    async fn add_session(&mut self, session: Session) -> Result<(), SessionStoreError> {
+        self.sessions.retain(|_, session| is_active(session));
+        self.sessions.insert(session.id.clone(), session);
+        Ok(())
+    }
+
+    async fn get_session(&self, id: &str) -> Result<Session, SessionStoreError> {
+        self.sessions
+            .get(id)
+            .filter(|session| is_active(session))
+            .cloned()
+            .ok_or(SessionStoreError::SessionNotFound)
+    }
+
+    async fn list_sessions(&self, user_id: &UserId) -> Result<Vec<Session>, SessionStoreError> {
+        let mut sessions: Vec<Session> = self
+            .sessions
+            .values()
+            .filter(|session| session.user_id == *user_id && is_active(session))
+            .cloned()
+            .collect();
+
+        sessions.sort_by_key(|session| std::cmp::Reverse(session.last_seen_at));
+
+        Ok(sessions)
+    }
+
+    async fn touch_session(
+        &mut self,
+        id: &str,
+        last_seen_at: DateTime<Utc>,
+    ) -> Result<(), SessionStoreError> {
+        let session = self
+            .sessions
+            .get_mut(id)
+            .filter(|session| is_active(session))
+            .ok_or(SessionStoreError::SessionNotFound)?;
+
+        session.last_seen_at = last_seen_at;
+        Ok(())
+    }
+
+    async fn revoke_session(&mut self, id: &str) -> Result<(), SessionStoreError> {
+        self.sessions
+            .remove(id)
+            .map(|_| ())
+            .ok_or(SessionStoreError::SessionNotFound)
+    }
+
+    async fn revoke_user_sessions(&mut self, user_id: &UserId) -> Result<(), SessionStoreError> {
+        self.sessions
+            .retain(|_, session| session.user_id != *user_id);
+        Ok(())
+    }
+}
+
+fn is_active(session: &Session) -> bool {
+    session.last_seen_at + Duration::seconds(SESSION_TTL_SECONDS) > Utc::now()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn session(user_id: UserId) -> Session {
+        Session::new(
+            user_id,
+            "Firefox on Linux".to_owned(),
+            "127.0.0.1".to_owned(),
+            "Mozilla/5.0 (X11; Linux x86_64; rv:131.0) Gecko/20100101 Firefox/131.0".to_owned(),
+        )
+    }
+
+    #[tokio::test]
+    async fn test_add_and_get_session() {
+        let mut store = HashmapSessionStore::default();
+        let session = session(UserId::default());
+
+        store.add_session(session.clone()).await.unwrap();
+
+        assert_eq!(store.get_session(&session.id).await.unwrap(), session);
+        assert_eq!(
+            store.get_session("unknown").await,
+            Err(SessionStoreError::SessionNotFound)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_list_sessions_only_returns_the_users_sessions() {
+        let mut store = HashmapSessionStore::default();
+        let user_id = UserId::default();
+
+        let older = session(user_id);
+        let mut newer = session(user_id);
+        newer.last_seen_at += Duration::seconds(10);
+
+        store.add_session(older.clone()).await.unwrap();
+        store.add_session(newer.clone()).await.unwrap();
+        store.add_session(session(UserId::default())).await.unwrap();
+
+        let sessions = store.list_sessions(&user_id).await.unwrap();
+
+        assert_eq!(sessions, vec![newer, older]);
+    }
+
+    #[tokio::test]
+    async fn test_expired_sessions_are_not_found() {
+        let mut store = HashmapSessionStore::default();
+        let mut session = session(UserId::default());
+        session.last_seen_at -= Duration::seconds(SESSION_TTL_SECONDS + 1);
+
+        store.add_session(session.clone()).await.unwrap();
+
+        assert_eq!(
+            store.get_session(&session.id).await,
+            Err(SessionStoreError::SessionNotFound)
+        );
+        assert!(store
+            .list_sessions(&session.user_id)
+            .await
+            .unwrap()
+            .is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_touch_session() {
+        let mut store = HashmapSessionStore::default();
+        let session = session(UserId::default());
+        let last_seen_at = session.last_seen_at + Duration::seconds(30);
+
+        store.add_session(session.clone()).await.unwrap();
+        store
+            .touch_session(&session.id, last_seen_at)
+            .await
+            .unwrap();
+
+        let touched = store.get_session(&session.id).await.unwrap();
+        assert_eq!(touched.last_seen_at, last_seen_at);
+        assert_eq!(touched.created_at, session.created_at);
+    }
+
+    #[tokio::test]
+    async fn test_revoke_session() {
+        let mut store = HashmapSessionStore::default();
+        let user_id = UserId::default();
+        let revoked = session(user_id);
+        let kept = session(user_id);
+
+        store.add_session(revoked.clone()).await.unwrap();
+        store.add_session(kept.clone()).await.unwrap();
+
+        store.revoke_session(&revoked.id).await.unwrap();
+
+        assert_eq!(
+            store.get_session(&revoked.id).await,
+            Err(SessionStoreError::SessionNotFound)
+        );
+        assert!(store.get_session(&kept.id).await.is_ok());
+        assert_eq!(
+            store.revoke_session(&revoked.id).await,
+            Err(SessionStoreError::SessionNotFound)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_revoke_user_sessions() {
+        let mut store = HashmapSessionStore::default();
+        let user_id = UserId::default();
+        let other = session(UserId::default());
+
+        store.add_session(session(user_id)).await.unwrap();
+        store.add_session(session(user_id)).await.unwrap();
+        store.add_session(other.clone()).await.unwrap();
+
+        store.revoke_user_sessions(&user_id).await.unwrap();
+
+        assert!(store.list_sessions(&user_id).await.unwrap().is_empty());
+        assert!(store.get_session(&other.id).await.is_ok());
+    }
+}
@@ -0,0 +1,85 @@
+use crate::domain::data_stores::{SigningKeyStore, SigningKeyStoreError, StoredSigningKey};
+
+use std::collections::HashMap;
+
+// Only shares keys within one process; use the Redis store with several replicas
+#[derive(Default, Clone)]
+pub struct HashmapSigningKeyStore {
+    keys: HashMap<String, Vec<StoredSigningKey>>,
+}
+
+#[async_trait::async_trait]
+impl SigningKeyStore for HashmapSigningKeyStore {
+    async fn load_keys(
+        &self,
+        base_kid: &str,
+    ) -> Result<Vec<StoredSigningKey>, SigningKeyStoreError> {
+        Ok(self.keys.get(base_kid).cloned().unwrap_or_default())
+    }
+
+    async fn replace_keys(
+        &mut self,
+        base_kid: &str,
+        expected_active_kid: Option<&str>,
+        keys: Vec<StoredSigningKey>,
+    ) -> Result<(), SigningKeyStoreError> {
+        let active_kid = self
+            .keys
+            .get(base_kid)
+            .and_then(|keys| keys.iter().find(|key| key.retired_at.is_none()))
+            .map(|key| key.kid.as_str());
+
+        if active_kid != expected_active_kid {
+            return Err(SigningKeyStoreError::Conflict);
+        }
+
+        self.keys.insert(base_kid.to_owned(), keys);
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use jsonwebtoken::Algorithm;
+
+    fn key(kid: &str) -> StoredSigningKey {
+        StoredSigningKey {
+            kid: kid.to_owned(),
+            algorithm: Algorithm::HS256,
+            material: None,
+            retired_at: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_replace_keys_only_from_expected_active_key() {
+        let mut store = HashmapSigningKeyStore::default();
+
+        assert!(store.load_keys("base").await.unwrap().is_empty());
+
+        store
+            .replace_keys("base", None, vec![key("first")])
+            .await
+            .unwrap();
+
+        assert_eq!(
+            store.replace_keys("base", None, vec![key("second")]).await,
+            Err(SigningKeyStoreError::Conflict)
+        );
+
+        store
+            .replace_keys("base", Some("first"), vec![key("second")])
+            .await
+            .unwrap();
+
+        let keys = store.load_keys("base").await.unwrap();
+        assert_eq!(keys.len(), 1);
+        assert_eq!(keys[0].kid, "second");
+
+        // Keys rotated from another configured key are kept apart
+        assert!(store.load_keys("other").await.unwrap().is_empty());
+    }
+}
@@ -1,42 +1,160 @@
+use chrono::{DateTime, Duration, Utc};
+use secrecy::Secret;
 use std::collections::HashMap;
 
-use crate::domain::{
-    data_stores::{LoginAttemptId, TwoFACode, TwoFACodeStore, TwoFACodeStoreError},
-    email::Email,
+use crate::{
+    domain::{
+        data_stores::{
+            LoginAttemptId, TwoFACode, TwoFACodeHash, TwoFACodeStore, TwoFACodeStoreError,
+        },
+        email::Email,
+    },
+    utils::constants::{MAX_2FA_ATTEMPTS, MAX_2FA_RESENDS, TWO_FA_RESEND_COOLDOWN_SECONDS},
 };
 
-#[derive(Default, Debug, Clone, PartialEq)]
+#[derive(Debug, Clone)]
 pub struct HashmapTwoFACodeStore {
-    codes: HashMap<Email, (LoginAttemptId, TwoFACode)>,
+    hash_key: Secret<String>,
+    codes: HashMap<LoginAttemptId, (Email, TwoFACodeHash)>,
+    // Codes taken by a request that is still checking them
+    taken: HashMap<LoginAttemptId, (Email, TwoFACodeHash)>,
+    attempts: HashMap<LoginAttemptId, u32>,
+    // Resends so far and when the last code went out
+    sent: HashMap<LoginAttemptId, (u32, DateTime<Utc>)>,
+}
+
+impl HashmapTwoFACodeStore {
+    pub fn new(hash_key: Secret<String>) -> Self {
+        Self {
+            hash_key,
+            codes: HashMap::new(),
+            taken: HashMap::new(),
+            attempts: HashMap::new(),
+            sent: HashMap::new(),
+        }
+    }
 }
 
 #[async_trait::async_trait]
 impl TwoFACodeStore for HashmapTwoFACodeStore {
+    fn hash_code(&self, code: &TwoFACode) -> TwoFACodeHash {
+        code.hash(&self.hash_key)
+    }
+
     async fn add_code(
         &mut self,
-        email: Email,
         login_attempt_id: LoginAttemptId,
-        code: TwoFACode,
+        email: Email,
+        code_hash: TwoFACodeHash,
     ) -> Result<(), TwoFACodeStoreError> {
-        self.codes.insert(email, (login_attempt_id, code));
+        self.attempts.remove(&login_attempt_id);
+        self.sent.insert(login_attempt_id.clone(), (0, Utc::now()));
+        self.codes.insert(login_attempt_id, (email, code_hash));
 
         Ok(())
     }
 
-    async fn remove_code(&mut self, email: &Email) -> Result<(), TwoFACodeStoreError> {
-        self.codes.remove(email);
+    async fn remove_code(
+        &mut self,
+        login_attempt_id: &LoginAttemptId,
+    ) -> Result<(), TwoFACodeStoreError> {
+        self.codes.remove(login_attempt_id);
+        self.taken.remove(login_attempt_id);
+        self.attempts.remove(login_attempt_id);
+        self.sent.remove(login_attempt_id);
         Ok(())
     }
 
     async fn get_code(
         &self,
-        email: &Email,
-    ) -> Result<(LoginAttemptId, TwoFACode), TwoFACodeStoreError> {
-        match self.codes.get(email) {
+        login_attempt_id: &LoginAttemptId,
+    ) -> Result<(Email, TwoFACodeHash), TwoFACodeStoreError> {
+        match self.codes.get(login_attempt_id) {
             Some(data) => Ok((data.0.clone(), data.1.clone())),
             None => Err(TwoFACodeStoreError::LoginAttemptIdNotFound),
         }
     }
+
+    async fn take_code(
+        &mut self,
+        login_attempt_id: &LoginAttemptId,
+    ) -> Result<(Email, TwoFACodeHash), TwoFACodeStoreError> {
+        let code = self
+            .codes
+            .remove(login_attempt_id)
+            .ok_or(TwoFACodeStoreError::LoginAttemptIdNotFound)?;
+
+        self.taken.insert(login_attempt_id.clone(), code.clone());
+
+        Ok(code)
+    }
+
+    async fn restore_code(
+        &mut self,
+        login_attempt_id: &LoginAttemptId,
+    ) -> Result<(), TwoFACodeStoreError> {
+        let code = self
+            .taken
+            .remove(login_attempt_id)
+            .ok_or(TwoFACodeStoreError::LoginAttemptIdNotFound)?;
+
+        self.codes.insert(login_attempt_id.clone(), code);
+
+        Ok(())
+    }
+
+    async fn reserve_attempt(
+        &mut self,
+        login_attempt_id: &LoginAttemptId,
+    ) -> Result<u32, TwoFACodeStoreError> {
+        if !self.codes.contains_key(login_attempt_id) {
+            return Err(TwoFACodeStoreError::LoginAttemptIdNotFound);
+        }
+
+        let attempts = self.attempts.entry(login_attempt_id.clone()).or_default();
+        *attempts += 1;
+        let attempts = *attempts;
+
+        if attempts > MAX_2FA_ATTEMPTS {
+            self.remove_code(login_attempt_id).await?;
+            return Err(TwoFACodeStoreError::TooManyAttempts);
+        }
+
+        Ok(attempts)
+    }
+
+    async fn replace_code(
+        &mut self,
+        login_attempt_id: &LoginAttemptId,
+        code_hash: TwoFACodeHash,
+    ) -> Result<(), TwoFACodeStoreError> {
+        let Some((_, pending_code)) = self.codes.get_mut(login_attempt_id) else {
+            return Err(TwoFACodeStoreError::LoginAttemptIdNotFound);
+        };
+
+        let (resends, last_sent_at) = self
+            .sent
+            .entry(login_attempt_id.clone())
+            .or_insert((0, DateTime::<Utc>::MIN_UTC));
+
+        let next_allowed_at = *last_sent_at + Duration::seconds(TWO_FA_RESEND_COOLDOWN_SECONDS);
+        let now = Utc::now();
+
+        if now < next_allowed_at {
+            let wait = (next_allowed_at - now).num_seconds() + 1;
+            return Err(TwoFACodeStoreError::ResendTooSoon(wait as u64));
+        }
+
+        if *resends >= MAX_2FA_RESENDS {
+            return Err(TwoFACodeStoreError::TooManyResends);
+        }
+
+        *resends += 1;
+        *last_sent_at = now;
+        *pending_code = code_hash;
+
+        Ok(())
+    }
 }
 
 #[cfg(test)]
@@ -45,16 +163,20 @@ mod tests {
 
     use crate::api::helpers::get_random_email;
 
+    fn store() -> HashmapTwoFACodeStore {
+        HashmapTwoFACodeStore::new(Secret::new("two-fa-code-hash-key".to_owned()))
+    }
+
     #[tokio::test]
     async fn test_add_code() {
-        let mut two_fa_store = HashmapTwoFACodeStore::default();
+        let mut two_fa_store = store();
 
         let email = Email::parse(get_random_email()).unwrap();
-        let two_fa_code = TwoFACode::default();
+        let two_fa_code = two_fa_store.hash_code(&TwoFACode::default());
         let login_attempt_id = LoginAttemptId::default();
 
         let response = two_fa_store
-            .add_code(email, login_attempt_id, two_fa_code)
+            .add_code(login_attempt_id, email, two_fa_code)
             .await;
 
         assert!(response.is_ok())
@@ -62,47 +184,253 @@ mod tests {
 
     #[tokio::test]
     async fn test_remove_code() {
-        let mut two_fa_store = HashmapTwoFACodeStore::default();
+        let mut two_fa_store = store();
 
         let email = Email::parse(get_random_email()).unwrap();
-        let two_fa_code = TwoFACode::default();
+        let two_fa_code = two_fa_store.hash_code(&TwoFACode::default());
         let login_attempt_id = LoginAttemptId::default();
 
         let response = two_fa_store
-            .add_code(email.clone(), login_attempt_id, two_fa_code)
+            .add_code(login_attempt_id.clone(), email, two_fa_code)
             .await;
 
         assert!(response.is_ok());
 
-        let response = two_fa_store.remove_code(&email).await;
+        let response = two_fa_store.remove_code(&login_attempt_id).await;
 
         assert!(response.is_ok())
     }
 
     #[tokio::test]
     async fn test_get_code() {
-        let mut two_fa_store = HashmapTwoFACodeStore::default();
+        let mut two_fa_store = store();
 
         let email = Email::parse(get_random_email()).unwrap();
-        let two_fa_code = TwoFACode::default();
+        let two_fa_code = two_fa_store.hash_code(&TwoFACode::default());
         let login_attempt_id = LoginAttemptId::default();
 
         let add_code_response = two_fa_store
-            .add_code(email.clone(), login_attempt_id, two_fa_code)
+            .add_code(login_attempt_id.clone(), email.clone(), two_fa_code.clone())
             .await;
 
         assert!(add_code_response.is_ok());
 
-        let get_code_response = two_fa_store.get_code(&email).await;
+        let get_code_response = two_fa_store.get_code(&login_attempt_id).await;
 
-        assert!(get_code_response.is_ok());
+        assert_eq!(get_code_response, Ok((email, two_fa_code)));
 
-        let email = Email::parse(get_random_email()).unwrap();
         // in the case code doesnt exist
-        let empty_code_response = two_fa_store.get_code(&email).await;
+        let empty_code_response = two_fa_store.get_code(&LoginAttemptId::default()).await;
         assert_eq!(
             empty_code_response,
             Err(TwoFACodeStoreError::LoginAttemptIdNotFound)
         )
     }
+
+    #[tokio::test]
+    async fn test_concurrent_logins_keep_their_own_codes() {
+        let mut two_fa_store = store();
+
+        let email = Email::parse(get_random_email()).unwrap();
+        let first_attempt = LoginAttemptId::default();
+        let second_attempt = LoginAttemptId::default();
+        let first_code = two_fa_store.hash_code(&TwoFACode::default());
+        let second_code = two_fa_store.hash_code(&TwoFACode::default());
+
+        two_fa_store
+            .add_code(first_attempt.clone(), email.clone(), first_code.clone())
+            .await
+            .unwrap();
+        two_fa_store
+            .add_code(second_attempt.clone(), email.clone(), second_code.clone())
+            .await
+            .unwrap();
+
+        assert_eq!(
+            two_fa_store.get_code(&first_attempt).await,
+            Ok((email.clone(), first_code))
+        );
+
+        two_fa_store.remove_code(&second_attempt).await.unwrap();
+
+        assert!(two_fa_store.get_code(&first_attempt).await.is_ok());
+        assert_eq!(
+            two_fa_store.get_code(&second_attempt).await,
+            Err(TwoFACodeStoreError::LoginAttemptIdNotFound)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_take_code_only_once() {
+        let mut two_fa_store = store();
+
+        let email = Email::parse(get_random_email()).unwrap();
+        let two_fa_code = two_fa_store.hash_code(&TwoFACode::default());
+        let login_attempt_id = LoginAttemptId::default();
+
+        two_fa_store
+            .add_code(login_attempt_id.clone(), email.clone(), two_fa_code.clone())
+            .await
+            .unwrap();
+
+        assert_eq!(
+            two_fa_store.take_code(&login_attempt_id).await,
+            Ok((email.clone(), two_fa_code.clone()))
+        );
+        assert_eq!(
+            two_fa_store.take_code(&login_attempt_id).await,
+            Err(TwoFACodeStoreError::LoginAttemptIdNotFound)
+        );
+
+        // A wrong guess gives it back
+        two_fa_store.restore_code(&login_attempt_id).await.unwrap();
+
+        assert_eq!(
+            two_fa_store.take_code(&login_attempt_id).await,
+            Ok((email, two_fa_code))
+        );
+
+        two_fa_store.remove_code(&login_attempt_id).await.unwrap();
+
+        assert_eq!(
+            two_fa_store.restore_code(&login_attempt_id).await,
+            Err(TwoFACodeStoreError::LoginAttemptIdNotFound)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_reserve_attempt_refuses_past_the_limit() {
+        let mut two_fa_store = store();
+
+        let email = Email::parse(get_random_email()).unwrap();
+        let login_attempt_id = LoginAttemptId::default();
+
+        two_fa_store
+            .add_code(
+                login_attempt_id.clone(),
+                email,
+                two_fa_store.hash_code(&TwoFACode::default()),
+            )
+            .await
+            .unwrap();
+
+        // As a burst of guesses would, none of them checked yet
+        for attempt in 1..=MAX_2FA_ATTEMPTS {
+            assert_eq!(
+                two_fa_store.reserve_attempt(&login_attempt_id).await,
+                Ok(attempt)
+            );
+            assert!(two_fa_store.get_code(&login_attempt_id).await.is_ok());
+        }
+
+        assert_eq!(
+            two_fa_store.reserve_attempt(&login_attempt_id).await,
+            Err(TwoFACodeStoreError::TooManyAttempts)
+        );
+        assert_eq!(
+            two_fa_store.get_code(&login_attempt_id).await,
+            Err(TwoFACodeStoreError::LoginAttemptIdNotFound)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_replace_code_enforces_cooldown_and_limit() {
+        let mut two_fa_store = store();
+
+        let email = Email::parse(get_random_email()).unwrap();
+        let login_attempt_id = LoginAttemptId::default();
+
+        two_fa_store
+            .add_code(
+                login_attempt_id.clone(),
+                email,
+                two_fa_store.hash_code(&TwoFACode::default()),
+            )
+            .await
+            .unwrap();
+
+        // The error equality ignores the payload, so the wait is checked separately
+        let result = two_fa_store
+            .replace_code(
+                &login_attempt_id,
+                two_fa_store.hash_code(&TwoFACode::default()),
+            )
+            .await;
+        assert!(matches!(
+            result,
+            Err(TwoFACodeStoreError::ResendTooSoon(wait))
+                if wait > 0 && wait <= TWO_FA_RESEND_COOLDOWN_SECONDS as u64 + 1
+        ));
+
+        for _ in 0..MAX_2FA_RESENDS {
+            // Pretend the cooldown has passed
+            two_fa_store.sent.get_mut(&login_attempt_id).unwrap().1 -=
+                Duration::seconds(TWO_FA_RESEND_COOLDOWN_SECONDS);
+
+            let code = two_fa_store.hash_code(&TwoFACode::default());
+
+            two_fa_store
+                .replace_code(&login_attempt_id, code.clone())
+                .await
+                .unwrap();
+
+            assert_eq!(
+                two_fa_store.get_code(&login_attempt_id).await.unwrap().1,
+                code
+            );
+        }
+
+        two_fa_store.sent.get_mut(&login_attempt_id).unwrap().1 -=
+            Duration::seconds(TWO_FA_RESEND_COOLDOWN_SECONDS);
+
+        assert_eq!(
+            two_fa_store
+                .replace_code(
+                    &login_attempt_id,
+                    two_fa_store.hash_code(&TwoFACode::default())
+                )
+                .await,
+            Err(TwoFACodeStoreError::TooManyResends)
+        );
+        assert_eq!(
+            two_fa_store
+                .replace_code(
+                    &LoginAttemptId::default(),
+                    two_fa_store.hash_code(&TwoFACode::default())
+                )
+                .await,
+            Err(TwoFACodeStoreError::LoginAttemptIdNotFound)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_attempts_are_counted_per_login_attempt() {
+        let mut two_fa_store = store();
+
+        let email = Email::parse(get_random_email()).unwrap();
+        let first_attempt = LoginAttemptId::default();
+        let second_attempt = LoginAttemptId::default();
+
+        two_fa_store
+            .add_code(
+                first_attempt.clone(),
+                email.clone(),
+                two_fa_store.hash_code(&TwoFACode::default()),
+            )
+            .await
+            .unwrap();
+        two_fa_store.reserve_attempt(&first_attempt).await.unwrap();
+
+        two_fa_store
+            .add_code(
+                second_attempt.clone(),
+                email,
+                two_fa_store.hash_code(&TwoFACode::default()),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(two_fa_store.reserve_attempt(&second_attempt).await, Ok(1));
+        assert_eq!(two_fa_store.reserve_attempt(&first_attempt).await, Ok(2));
+    }
 }
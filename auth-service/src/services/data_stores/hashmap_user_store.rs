@@ -1,13 +1,21 @@
-use crate::domain::{
-    data_stores::{UserStore, UserStoreError},
-    Email, Password, User,
+use crate::{
+    domain::{
+        data_stores::{UserStore, UserStoreError},
+        Email, Password, TwoFAMethod, User, UserId,
+    },
+    utils::password_hashing::verify_password_hash,
 };
 
+use chrono::{DateTime, Utc};
+use secrecy::Secret;
+
 use std::collections::{hash_map::Entry, HashMap};
 
 #[derive(Default, Clone)]
 pub struct HashmapUserStore {
     users: HashMap<Email, User>,
+    // Last accepted TOTP time step per user
+    totp_steps: HashMap<UserId, u64>,
 }
 
 #[async_trait::async_trait]
@@ -29,26 +37,131 @@ impl UserStore for HashmapUserStore {
             .ok_or(UserStoreError::UserNotFound)
     }
 
+    async fn get_user_by_id(&self, id: &UserId) -> Result<User, UserStoreError> {
+        self.users
+            .values()
+            .find(|user| user.id() == id)
+            .cloned()
+            .ok_or(UserStoreError::UserNotFound)
+    }
+
     async fn validate_user(
         &self,
         email: &Email,
         password: &Password,
     ) -> Result<(), UserStoreError> {
-        let user = self.get_user(&email).await?;
+        let user = self.get_user(email).await?;
+
+        let matches =
+            verify_password_hash(user.password.as_ref().clone(), password.as_ref().clone())
+                .await
+                .map_err(UserStoreError::UnexpectedError)?;
 
-        if user.password != password.to_owned() {
+        if !matches {
             return Err(UserStoreError::InvalidCredentials);
         }
 
         Ok(())
     }
+
+    async fn update_password(
+        &mut self,
+        id: &UserId,
+        password_hash: Secret<String>,
+    ) -> Result<(), UserStoreError> {
+        self.get_user_mut(id)?.password = Password::from(password_hash);
+
+        Ok(())
+    }
+
+    async fn mark_email_verified(&mut self, id: &UserId) -> Result<(), UserStoreError> {
+        self.get_user_mut(id)?.email_verified = true;
+
+        Ok(())
+    }
+
+    async fn increment_token_generation(&mut self, id: &UserId) -> Result<(), UserStoreError> {
+        self.get_user_mut(id)?.token_generation += 1;
+
+        Ok(())
+    }
+
+    async fn set_totp_secret(
+        &mut self,
+        id: &UserId,
+        secret: Secret<String>,
+    ) -> Result<(), UserStoreError> {
+        self.get_user_mut(id)?.totp_secret = Some(secret);
+
+        Ok(())
+    }
+
+    async fn clear_totp_secret(&mut self, id: &UserId) -> Result<(), UserStoreError> {
+        self.get_user_mut(id)?.totp_secret = None;
+
+        Ok(())
+    }
+
+    async fn set_two_fa_method(
+        &mut self,
+        id: &UserId,
+        method: TwoFAMethod,
+    ) -> Result<(), UserStoreError> {
+        self.get_user_mut(id)?.two_fa_method = method;
+
+        Ok(())
+    }
+
+    async fn accept_totp_step(&mut self, id: &UserId, step: u64) -> Result<bool, UserStoreError> {
+        self.get_user_mut(id)?;
+
+        match self.totp_steps.get(id) {
+            Some(last_step) if *last_step >= step => Ok(false),
+            _ => {
+                self.totp_steps.insert(*id, step);
+                Ok(true)
+            }
+        }
+    }
+
+    async fn record_failed_login(&mut self, id: &UserId) -> Result<u32, UserStoreError> {
+        let user = self.get_user_mut(id)?;
+        user.failed_login_count += 1;
+
+        Ok(user.failed_login_count)
+    }
+
+    async fn lock_user(&mut self, id: &UserId, until: DateTime<Utc>) -> Result<(), UserStoreError> {
+        self.get_user_mut(id)?.locked_until = Some(until);
+
+        Ok(())
+    }
+
+    async fn unlock_user(&mut self, id: &UserId) -> Result<(), UserStoreError> {
+        let user = self.get_user_mut(id)?;
+        user.failed_login_count = 0;
+        user.locked_until = None;
+
+        Ok(())
+    }
+}
+
+impl HashmapUserStore {
+    fn get_user_mut(&mut self, id: &UserId) -> Result<&mut User, UserStoreError> {
+        self.users
+            .values_mut()
+            .find(|user| user.id() == id)
+            .ok_or(UserStoreError::UserNotFound)
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::api::helpers::get_random_password;
-    
+    use crate::{
+        api::helpers::get_random_password, utils::password_hashing::compute_password_hash,
+    };
+
     use secrecy::Secret;
 
     #[tokio::test]
@@ -58,7 +171,7 @@ mod tests {
         let email = Email::parse(Secret::new("test@example.com".to_owned())).unwrap();
         let password = Password::parse(get_random_password()).unwrap();
 
-        let user = User::new(email.clone(), password.clone(), false);
+        let user = User::new(email.clone(), password.clone(), TwoFAMethod::None);
 
         // Test adding a new user
         let result = user_store.add_user(user.clone()).await;
@@ -76,7 +189,7 @@ mod tests {
         let email = Email::parse(Secret::new("test@example.com".to_owned())).unwrap();
         let password = Password::parse(get_random_password()).unwrap();
 
-        let user = User::new(email.clone(), password.clone(), false);
+        let user = User::new(email.clone(), password.clone(), TwoFAMethod::None);
 
         // Test getting a user that exists
         user_store.users.insert(email.clone(), user.clone());
@@ -97,8 +210,15 @@ mod tests {
 
         let email = Email::parse(Secret::new("test@example.com".to_owned())).unwrap();
         let password = Password::parse(get_random_password()).unwrap();
+        let password_hash = compute_password_hash(password.as_ref().clone())
+            .await
+            .unwrap();
 
-        let user = User::new(email.clone(), password.clone(), false);
+        let user = User::new(
+            email.clone(),
+            Password::from(password_hash),
+            TwoFAMethod::None,
+        );
 
         // Test validating a user that exists with correct password
         user_store.users.insert(email.clone(), user.clone());
@@ -120,4 +240,145 @@ mod tests {
 
         // assert_eq!(result, Err(UserStoreError::UserNotFound));
     }
+
+    #[tokio::test]
+    async fn test_get_user_by_id() {
+        let mut user_store = HashmapUserStore::default();
+
+        let email = Email::parse(Secret::new("test@example.com".to_owned())).unwrap();
+        let password = Password::parse(get_random_password()).unwrap();
+
+        let user = User::new(email.clone(), password, TwoFAMethod::None);
+        user_store.add_user(user.clone()).await.unwrap();
+
+        let result = user_store.get_user_by_id(user.id()).await;
+        assert_eq!(result, Ok(user));
+
+        let result = user_store.get_user_by_id(&UserId::default()).await;
+        assert_eq!(result, Err(UserStoreError::UserNotFound));
+    }
+
+    #[tokio::test]
+    async fn test_increment_token_generation() {
+        let mut user_store = HashmapUserStore::default();
+
+        let email = Email::parse(Secret::new("test@example.com".to_owned())).unwrap();
+        let password = Password::parse(get_random_password()).unwrap();
+
+        let user = User::new(email.clone(), password, TwoFAMethod::None);
+        user_store.add_user(user.clone()).await.unwrap();
+
+        user_store
+            .increment_token_generation(user.id())
+            .await
+            .unwrap();
+
+        let result = user_store.get_user(&email).await.unwrap();
+        assert_eq!(result.token_generation(), user.token_generation() + 1);
+
+        let result = user_store
+            .increment_token_generation(&UserId::default())
+            .await;
+        assert_eq!(result, Err(UserStoreError::UserNotFound));
+    }
+
+    #[tokio::test]
+    async fn test_mark_email_verified() {
+        let mut user_store = HashmapUserStore::default();
+
+        let email = Email::parse(Secret::new("test@example.com".to_owned())).unwrap();
+        let password = Password::parse(get_random_password()).unwrap();
+
+        let user = User::new(email.clone(), password, TwoFAMethod::None);
+        assert!(!user.email_verified());
+        user_store.add_user(user.clone()).await.unwrap();
+
+        user_store.mark_email_verified(user.id()).await.unwrap();
+
+        let result = user_store.get_user(&email).await.unwrap();
+        assert!(result.email_verified());
+    }
+
+    #[tokio::test]
+    async fn test_enable_totp() {
+        let mut user_store = HashmapUserStore::default();
+
+        let email = Email::parse(Secret::new("test@example.com".to_owned())).unwrap();
+        let password = Password::parse(get_random_password()).unwrap();
+
+        let user = User::new(email.clone(), password, TwoFAMethod::Email);
+        user_store.add_user(user.clone()).await.unwrap();
+
+        user_store
+            .set_totp_secret(user.id(), Secret::new("JBSWY3DPEHPK3PXP".to_owned()))
+            .await
+            .unwrap();
+
+        // Storing a secret alone does not switch methods
+        let stored = user_store.get_user(&email).await.unwrap();
+        assert_eq!(stored.two_fa_method(), TwoFAMethod::Email);
+        assert!(stored.totp_secret().is_some());
+
+        user_store
+            .set_two_fa_method(user.id(), TwoFAMethod::Totp)
+            .await
+            .unwrap();
+
+        let stored = user_store.get_user(&email).await.unwrap();
+        assert_eq!(stored.two_fa_method(), TwoFAMethod::Totp);
+
+        let result = user_store
+            .set_two_fa_method(&UserId::default(), TwoFAMethod::Totp)
+            .await;
+        assert_eq!(result, Err(UserStoreError::UserNotFound));
+    }
+
+    #[tokio::test]
+    async fn test_lock_and_unlock_user() {
+        let mut user_store = HashmapUserStore::default();
+
+        let email = Email::parse(Secret::new("test@example.com".to_owned())).unwrap();
+        let password = Password::parse(get_random_password()).unwrap();
+
+        let user = User::new(email.clone(), password, TwoFAMethod::None);
+        user_store.add_user(user.clone()).await.unwrap();
+
+        assert_eq!(user_store.record_failed_login(user.id()).await, Ok(1));
+        assert_eq!(user_store.record_failed_login(user.id()).await, Ok(2));
+
+        let until = Utc::now() + chrono::Duration::minutes(15);
+        user_store.lock_user(user.id(), until).await.unwrap();
+
+        let stored = user_store.get_user(&email).await.unwrap();
+        assert_eq!(stored.locked_until(), Some(until));
+        assert_eq!(stored.failed_login_count(), 2);
+
+        user_store.unlock_user(user.id()).await.unwrap();
+
+        let stored = user_store.get_user(&email).await.unwrap();
+        assert_eq!(stored.locked_until(), None);
+        assert_eq!(stored.failed_login_count(), 0);
+
+        let result = user_store.record_failed_login(&UserId::default()).await;
+        assert_eq!(result, Err(UserStoreError::UserNotFound));
+    }
+
+    #[tokio::test]
+    async fn test_accept_totp_step_only_once() {
+        let mut user_store = HashmapUserStore::default();
+
+        let email = Email::parse(Secret::new("test@example.com".to_owned())).unwrap();
+        let password = Password::parse(get_random_password()).unwrap();
+
+        let user = User::new(email, password, TwoFAMethod::Totp);
+        user_store.add_user(user.clone()).await.unwrap();
+
+        assert_eq!(user_store.accept_totp_step(user.id(), 100).await, Ok(true));
+        assert_eq!(user_store.accept_totp_step(user.id(), 100).await, Ok(false));
+        assert_eq!(user_store.accept_totp_step(user.id(), 99).await, Ok(false));
+        assert_eq!(user_store.accept_totp_step(user.id(), 101).await, Ok(true));
+
+        let result = user_store.accept_totp_step(&UserId::default(), 102).await;
+        assert_eq!(result, Err(UserStoreError::UserNotFound));
+    }
 }
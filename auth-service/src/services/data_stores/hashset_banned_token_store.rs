@@ -1,48 +1,87 @@
-use secrecy::{ExposeSecret, Secret};
+use chrono::Utc;
 
 use crate::domain::data_stores::{BannedTokenStore, BannedTokenStoreError};
 
-use std::collections::HashSet;
+use std::collections::HashMap;
 
+// Banned jtis mapped to the `exp` of their token
 #[derive(Default, PartialEq, Clone)]
 pub struct HashsetBannedTokenStore {
-    tokens: HashSet<String>,
+    tokens: HashMap<String, usize>,
+}
+
+impl HashsetBannedTokenStore {
+    // Expired tokens are rejected anyway, so their bans can be forgotten
+    fn purge_expired(&mut self) {
+        let now = now();
+
+        self.tokens.retain(|_, exp| *exp > now);
+    }
 }
 
 #[async_trait::async_trait]
 impl BannedTokenStore for HashsetBannedTokenStore {
-    async fn add_token(&mut self, token: Secret<String>) -> Result<(), BannedTokenStoreError> {
-        self.tokens.insert(token.expose_secret().to_owned());
+    async fn add_token(&mut self, jti: &str, exp: usize) -> Result<(), BannedTokenStoreError> {
+        self.purge_expired();
+
+        if exp > now() {
+            self.tokens.insert(jti.to_owned(), exp);
+        }
+
         Ok(())
     }
 
-    async fn contains_token(&self, token: &Secret<String>) -> Result<bool, BannedTokenStoreError> {
-        Ok(self.tokens.contains(token.expose_secret()))
+    async fn contains_token(&self, jti: &str) -> Result<bool, BannedTokenStoreError> {
+        Ok(self.tokens.get(jti).is_some_and(|exp| *exp > now()))
     }
 }
 
+fn now() -> usize {
+    Utc::now().timestamp().try_into().unwrap_or_default()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+
+    fn in_seconds(seconds: i64) -> usize {
+        (Utc::now().timestamp() + seconds) as usize
+    }
+
     #[tokio::test]
     async fn test_add_token() {
         let mut store = HashsetBannedTokenStore::default();
-        let token = Secret::new("test_token".to_owned());
+        let exp = in_seconds(600);
 
-        let result = store.add_token(token.clone()).await;
+        let result = store.add_token("test_jti", exp).await;
 
         assert!(result.is_ok());
-        assert!(store.tokens.contains(&token.expose_secret().to_owned()));
+        assert_eq!(store.tokens.get("test_jti"), Some(&exp));
     }
 
     #[tokio::test]
     async fn test_contains_token() {
         let mut store = HashsetBannedTokenStore::default();
-        let token = "test_token".to_owned();
-        store.tokens.insert(token.clone());
+        store.tokens.insert("test_jti".to_owned(), in_seconds(600));
 
-        let result = store.contains_token(&Secret::new(token)).await;
+        let result = store.contains_token("test_jti").await;
 
         assert!(result.unwrap());
+        assert!(!store.contains_token("other_jti").await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_expired_tokens_are_purged() {
+        let mut store = HashsetBannedTokenStore::default();
+        store
+            .tokens
+            .insert("expired_jti".to_owned(), in_seconds(-1));
+
+        assert!(!store.contains_token("expired_jti").await.unwrap());
+
+        store.add_token("test_jti", in_seconds(600)).await.unwrap();
+
+        assert!(!store.tokens.contains_key("expired_jti"));
+        assert!(store.tokens.contains_key("test_jti"));
     }
 }
@@ -1,13 +1,51 @@
+pub mod hashmap_email_verification_token_store;
+pub mod hashmap_passkey_challenge_store;
+pub mod hashmap_passkey_store;
+pub mod hashmap_password_reset_token_store;
+pub mod hashmap_rate_limit_store;
+pub mod hashmap_recovery_code_store;
+pub mod hashmap_refresh_token_store;
+pub mod hashmap_session_store;
+pub mod hashmap_signing_key_store;
 pub mod hashmap_two_fa_code_store;
 pub mod hashmap_user_store;
 pub mod hashset_banned_token_store;
+pub mod mysql_passkey_store;
+pub mod mysql_recovery_code_store;
+pub mod mysql_session_store;
 pub mod mysql_user_store;
 pub mod redis_banned_token_store;
+pub mod redis_email_verification_token_store;
+pub mod redis_passkey_challenge_store;
+pub mod redis_password_reset_token_store;
+pub mod redis_rate_limit_store;
+pub mod redis_refresh_token_store;
+pub mod redis_session_store;
+pub mod redis_signing_key_store;
 pub mod redis_two_fa_code_store;
 
+pub use hashmap_email_verification_token_store::*;
+pub use hashmap_passkey_challenge_store::*;
+pub use hashmap_passkey_store::*;
+pub use hashmap_password_reset_token_store::*;
+pub use hashmap_rate_limit_store::*;
+pub use hashmap_recovery_code_store::*;
+pub use hashmap_refresh_token_store::*;
+pub use hashmap_session_store::*;
+pub use hashmap_signing_key_store::*;
 pub use hashmap_two_fa_code_store::*;
 pub use hashmap_user_store::*;
 pub use hashset_banned_token_store::*;
+pub use mysql_passkey_store::*;
+pub use mysql_recovery_code_store::*;
+pub use mysql_session_store::*;
 pub use mysql_user_store::*;
 pub use redis_banned_token_store::*;
+pub use redis_email_verification_token_store::*;
+pub use redis_passkey_challenge_store::*;
+pub use redis_password_reset_token_store::*;
+pub use redis_rate_limit_store::*;
+pub use redis_refresh_token_store::*;
+pub use redis_session_store::*;
+pub use redis_signing_key_store::*;
 pub use redis_two_fa_code_store::*;
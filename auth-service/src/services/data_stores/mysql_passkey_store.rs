@@ -0,0 +1,176 @@
+use crate::domain::{
+    data_stores::{PasskeyCredential, PasskeyStore, PasskeyStoreError},
+    UserId,
+};
+
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use chrono::DateTime;
+use color_eyre::eyre::{eyre, Context};
+use sqlx::MySqlPool;
+
+// Public keys are stored base64url encoded, timestamps as unix seconds
+#[derive(Debug, Clone)]
+pub struct MySqlPasskeyStore {
+    pub pool: MySqlPool,
+}
+
+impl MySqlPasskeyStore {
+    pub fn new(pool: MySqlPool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait::async_trait]
+impl PasskeyStore for MySqlPasskeyStore {
+    #[tracing::instrument(name = "Adding passkey credential to MySql", skip_all)]
+    async fn add_credential(
+        &mut self,
+        credential: PasskeyCredential,
+    ) -> Result<(), PasskeyStoreError> {
+        if self.get_credential(&credential.id).await.is_ok() {
+            return Err(PasskeyStoreError::CredentialAlreadyExists);
+        }
+
+        sqlx::query!(
+            "
+            INSERT INTO passkey_credentials (id, user_id, public_key, sign_count, created_at)
+            VALUES (?, ?, ?, ?, ?)
+            ",
+            credential.id,
+            credential.user_id.to_string(),
+            URL_SAFE_NO_PAD.encode(&credential.public_key),
+            credential.sign_count,
+            credential.created_at.timestamp()
+        )
+        .execute(&self.pool)
+        .await
+        .wrap_err("Failed to insert passkey credential to mysql database.")
+        .map_err(PasskeyStoreError::UnexpectedError)?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Retrieving passkey credential from MySql", skip_all)]
+    async fn get_credential(&self, id: &str) -> Result<PasskeyCredential, PasskeyStoreError> {
+        let record = sqlx::query!(
+            "
+            SELECT
+                id,
+                user_id,
+                public_key,
+                sign_count,
+                created_at
+            FROM
+                passkey_credentials
+            WHERE
+                id = ?
+            ",
+            id
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .wrap_err("Failed to retrieve passkey credential from mysql database.")
+        .map_err(PasskeyStoreError::UnexpectedError)?
+        .ok_or(PasskeyStoreError::CredentialNotFound)?;
+
+        to_credential(
+            record.id,
+            record.user_id,
+            record.public_key,
+            record.sign_count,
+            record.created_at,
+        )
+    }
+
+    #[tracing::instrument(name = "Listing passkey credentials from MySql", skip_all)]
+    async fn list_credentials(
+        &self,
+        user_id: &UserId,
+    ) -> Result<Vec<PasskeyCredential>, PasskeyStoreError> {
+        let records = sqlx::query!(
+            "
+            SELECT
+                id,
+                user_id,
+                public_key,
+                sign_count,
+                created_at
+            FROM
+                passkey_credentials
+            WHERE
+                user_id = ?
+            ORDER BY
+                created_at
+            ",
+            user_id.to_string()
+        )
+        .fetch_all(&self.pool)
+        .await
+        .wrap_err("Failed to list passkey credentials from mysql database.")
+        .map_err(PasskeyStoreError::UnexpectedError)?;
+
+        records
+            .into_iter()
+            .map(|record| {
+                to_credential(
+                    record.id,
+                    record.user_id,
+                    record.public_key,
+                    record.sign_count,
+                    record.created_at,
+                )
+            })
+            .collect()
+    }
+
+    #[tracing::instrument(name = "Updating passkey sign count in MySql", skip_all)]
+    async fn update_sign_count(
+        &mut self,
+        id: &str,
+        sign_count: u32,
+    ) -> Result<(), PasskeyStoreError> {
+        let result = sqlx::query!(
+            "
+            UPDATE passkey_credentials
+            SET sign_count = ?
+            WHERE id = ?
+            ",
+            sign_count,
+            id
+        )
+        .execute(&self.pool)
+        .await
+        .wrap_err("Failed to update passkey sign count in mysql database.")
+        .map_err(PasskeyStoreError::UnexpectedError)?;
+
+        if result.rows_affected() == 0 {
+            return Err(PasskeyStoreError::CredentialNotFound);
+        }
+
+        Ok(())
+    }
+}
+
+fn to_credential(
+    id: String,
+    user_id: String,
+    public_key: String,
+    sign_count: u32,
+    created_at: i64,
+) -> Result<PasskeyCredential, PasskeyStoreError> {
+    Ok(PasskeyCredential {
+        id,
+        user_id: UserId::parse(&user_id).map_err(PasskeyStoreError::UnexpectedError)?,
+        public_key: URL_SAFE_NO_PAD
+            .decode(public_key)
+            .wrap_err("Invalid passkey public key encoding")
+            .map_err(PasskeyStoreError::UnexpectedError)?,
+        sign_count,
+        created_at: DateTime::from_timestamp(created_at, 0).ok_or(
+            PasskeyStoreError::UnexpectedError(eyre!(
+                "Invalid passkey credential timestamp {}",
+                created_at
+            )),
+        )?,
+    })
+}
@@ -0,0 +1,164 @@
+use crate::{
+    domain::{
+        data_stores::{RecoveryCode, RecoveryCodeStore, RecoveryCodeStoreError},
+        UserId,
+    },
+    utils::password_hashing::{compute_password_hash, verify_password_hash},
+};
+
+use color_eyre::eyre::Context;
+use secrecy::{ExposeSecret, Secret};
+use sqlx::MySqlPool;
+use uuid::Uuid;
+
+// Codes are hashed like passwords, so a leaked table does not reveal usable codes
+#[derive(Debug, Clone)]
+pub struct MySqlRecoveryCodeStore {
+    pub pool: MySqlPool,
+}
+
+impl MySqlRecoveryCodeStore {
+    pub fn new(pool: MySqlPool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait::async_trait]
+impl RecoveryCodeStore for MySqlRecoveryCodeStore {
+    #[tracing::instrument(name = "Replacing recovery codes in MySql", skip_all)]
+    async fn replace_codes(
+        &mut self,
+        user_id: &UserId,
+        codes: Vec<RecoveryCode>,
+    ) -> Result<(), RecoveryCodeStoreError> {
+        let mut code_hashes = Vec::with_capacity(codes.len());
+
+        for code in codes {
+            let code_hash = compute_password_hash(code.as_ref().clone())
+                .await
+                .map_err(RecoveryCodeStoreError::UnexpectedError)?;
+
+            code_hashes.push(code_hash);
+        }
+
+        let mut transaction = self
+            .pool
+            .begin()
+            .await
+            .wrap_err("Failed to start transaction.")
+            .map_err(RecoveryCodeStoreError::UnexpectedError)?;
+
+        sqlx::query!(
+            "
+            DELETE FROM recovery_codes
+            WHERE user_id = ?
+            ",
+            user_id.to_string()
+        )
+        .execute(&mut *transaction)
+        .await
+        .wrap_err("Failed to delete recovery codes from mysql database.")
+        .map_err(RecoveryCodeStoreError::UnexpectedError)?;
+
+        for code_hash in code_hashes {
+            sqlx::query!(
+                "
+                INSERT INTO recovery_codes (id, user_id, code_hash)
+                VALUES (?, ?, ?)
+                ",
+                Uuid::new_v4().to_string(),
+                user_id.to_string(),
+                code_hash.expose_secret()
+            )
+            .execute(&mut *transaction)
+            .await
+            .wrap_err("Failed to insert recovery code to mysql database.")
+            .map_err(RecoveryCodeStoreError::UnexpectedError)?;
+        }
+
+        transaction
+            .commit()
+            .await
+            .wrap_err("Failed to commit recovery codes.")
+            .map_err(RecoveryCodeStoreError::UnexpectedError)?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Using recovery code in MySql", skip_all)]
+    async fn use_code(
+        &mut self,
+        user_id: &UserId,
+        code: &RecoveryCode,
+    ) -> Result<(), RecoveryCodeStoreError> {
+        let records = sqlx::query!(
+            "
+            SELECT
+                id,
+                code_hash
+            FROM
+                recovery_codes
+            WHERE
+                user_id = ?
+            ",
+            user_id.to_string()
+        )
+        .fetch_all(&self.pool)
+        .await
+        .wrap_err("Failed to retrieve recovery codes from mysql database.")
+        .map_err(RecoveryCodeStoreError::UnexpectedError)?;
+
+        for record in records {
+            let matches =
+                verify_password_hash(Secret::new(record.code_hash), code.as_ref().clone())
+                    .await
+                    .map_err(RecoveryCodeStoreError::UnexpectedError)?;
+
+            if !matches {
+                continue;
+            }
+
+            let result = sqlx::query!(
+                "
+                DELETE FROM recovery_codes
+                WHERE id = ?
+                ",
+                record.id
+            )
+            .execute(&self.pool)
+            .await
+            .wrap_err("Failed to delete recovery code from mysql database.")
+            .map_err(RecoveryCodeStoreError::UnexpectedError)?;
+
+            // A concurrent request used the same code first
+            if result.rows_affected() == 0 {
+                return Err(RecoveryCodeStoreError::InvalidCode);
+            }
+
+            return Ok(());
+        }
+
+        Err(RecoveryCodeStoreError::InvalidCode)
+    }
+
+    #[tracing::instrument(name = "Counting recovery codes in MySql", skip_all)]
+    async fn count_codes(&self, user_id: &UserId) -> Result<usize, RecoveryCodeStoreError> {
+        let count = sqlx::query_scalar!(
+            "
+            SELECT
+                COUNT(*)
+            FROM
+                recovery_codes
+            WHERE
+                user_id = ?
+            ",
+            user_id.to_string()
+        )
+        .fetch_one(&self.pool)
+        .await
+        .wrap_err("Failed to count recovery codes in mysql database.")
+        .map_err(RecoveryCodeStoreError::UnexpectedError)?;
+
+        Ok(count as usize)
+    }
+}
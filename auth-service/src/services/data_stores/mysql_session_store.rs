@@ -0,0 +1,227 @@
+use crate::{
+    domain::{
+        data_stores::{Session, SessionStore, SessionStoreError},
+        UserId,
+    },
+    utils::constants::SESSION_TTL_SECONDS,
+};
+
+use chrono::{DateTime, Utc};
+use color_eyre::eyre::{eyre, Context};
+use sqlx::MySqlPool;
+
+// Revoking deletes the row. Idle sessions are filtered out by `last_seen_at`.
+#[derive(Debug, Clone)]
+pub struct MySqlSessionStore {
+    pub pool: MySqlPool,
+}
+
+impl MySqlSessionStore {
+    pub fn new(pool: MySqlPool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait::async_trait]
+impl SessionStore for MySqlSessionStore {
+    #[tracing::instrument(name = "Adding session to MySql", skip_all)]
+    async fn add_session(&mut self, session: Session) -> Result<(), SessionStoreError> {
+        sqlx::query!(
+            "
+            INSERT INTO sessions (id, user_id, device, ip_address, user_agent, created_at, last_seen_at)
+            VALUES (?, ?, ?, ?, ?, ?, ?)
+            ",
+            session.id,
+            session.user_id.to_string(),
+            session.device,
+            session.ip_address,
+            session.user_agent,
+            session.created_at.timestamp(),
+            session.last_seen_at.timestamp()
+        )
+        .execute(&self.pool)
+        .await
+        .wrap_err("Failed to insert session to mysql database.")
+        .map_err(SessionStoreError::UnexpectedError)?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Retrieving session from MySql", skip_all)]
+    async fn get_session(&self, id: &str) -> Result<Session, SessionStoreError> {
+        let record = sqlx::query!(
+            "
+            SELECT
+                id,
+                user_id,
+                device,
+                ip_address,
+                user_agent,
+                created_at,
+                last_seen_at
+            FROM
+                sessions
+            WHERE
+                id = ? AND last_seen_at > ?
+            ",
+            id,
+            idle_cutoff()
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .wrap_err("Failed to retrieve session from mysql database.")
+        .map_err(SessionStoreError::UnexpectedError)?
+        .ok_or(SessionStoreError::SessionNotFound)?;
+
+        to_session(
+            record.id,
+            record.user_id,
+            record.device,
+            record.ip_address,
+            record.user_agent,
+            record.created_at,
+            record.last_seen_at,
+        )
+    }
+
+    #[tracing::instrument(name = "Listing sessions from MySql", skip_all)]
+    async fn list_sessions(&self, user_id: &UserId) -> Result<Vec<Session>, SessionStoreError> {
+        let records = sqlx::query!(
+            "
+            SELECT
+                id,
+                user_id,
+                device,
+                ip_address,
+                user_agent,
+                created_at,
+                last_seen_at
+            FROM
+                sessions
+            WHERE
+                user_id = ? AND last_seen_at > ?
+            ORDER BY
+                last_seen_at DESC
+            ",
+            user_id.to_string(),
+            idle_cutoff()
+        )
+        .fetch_all(&self.pool)
+        .await
+        .wrap_err("Failed to list sessions from mysql database.")
+        .map_err(SessionStoreError::UnexpectedError)?;
+
+        records
+            .into_iter()
+            .map(|record| {
+                to_session(
+                    record.id,
+                    record.user_id,
+                    record.device,
+                    record.ip_address,
+                    record.user_agent,
+                    record.created_at,
+                    record.last_seen_at,
+                )
+            })
+            .collect()
+    }
+
+    #[tracing::instrument(name = "Touching session in MySql", skip_all)]
+    async fn touch_session(
+        &mut self,
+        id: &str,
+        last_seen_at: DateTime<Utc>,
+    ) -> Result<(), SessionStoreError> {
+        let result = sqlx::query!(
+            "
+            UPDATE sessions
+            SET last_seen_at = ?
+            WHERE id = ? AND last_seen_at > ?
+            ",
+            last_seen_at.timestamp(),
+            id,
+            idle_cutoff()
+        )
+        .execute(&self.pool)
+        .await
+        .wrap_err("Failed to touch session in mysql database.")
+        .map_err(SessionStoreError::UnexpectedError)?;
+
+        if result.rows_affected() == 0 {
+            return Err(SessionStoreError::SessionNotFound);
+        }
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Revoking session in MySql", skip_all)]
+    async fn revoke_session(&mut self, id: &str) -> Result<(), SessionStoreError> {
+        let result = sqlx::query!(
+            "
+            DELETE FROM sessions
+            WHERE id = ?
+            ",
+            id
+        )
+        .execute(&self.pool)
+        .await
+        .wrap_err("Failed to delete session from mysql database.")
+        .map_err(SessionStoreError::UnexpectedError)?;
+
+        if result.rows_affected() == 0 {
+            return Err(SessionStoreError::SessionNotFound);
+        }
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Revoking user sessions in MySql", skip_all)]
+    async fn revoke_user_sessions(&mut self, user_id: &UserId) -> Result<(), SessionStoreError> {
+        sqlx::query!(
+            "
+            DELETE FROM sessions
+            WHERE user_id = ?
+            ",
+            user_id.to_string()
+        )
+        .execute(&self.pool)
+        .await
+        .wrap_err("Failed to delete user sessions from mysql database.")
+        .map_err(SessionStoreError::UnexpectedError)?;
+
+        Ok(())
+    }
+}
+
+// Sessions last seen at or before this unix time have expired
+fn idle_cutoff() -> i64 {
+    Utc::now().timestamp() - SESSION_TTL_SECONDS
+}
+
+fn to_session(
+    id: String,
+    user_id: String,
+    device: String,
+    ip_address: String,
+    user_agent: String,
+    created_at: i64,
+    last_seen_at: i64,
+) -> Result<Session, SessionStoreError> {
+    let to_datetime = |seconds: i64| {
+        DateTime::from_timestamp(seconds, 0).ok_or(SessionStoreError::UnexpectedError(eyre!(
+            "Invalid session timestamp {}",
+            seconds
+        )))
+    };
+
+    Ok(Session {
+        id,
+        user_id: UserId::parse(&user_id).map_err(SessionStoreError::UnexpectedError)?,
+        device,
+        ip_address,
+        user_agent,
+        created_at: to_datetime(created_at)?,
+        last_seen_at: to_datetime(last_seen_at)?,
+    })
+}
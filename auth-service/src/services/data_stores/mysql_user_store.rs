@@ -1,15 +1,16 @@
-use crate::domain::{
-    data_stores::{UserStore, UserStoreError},
-    Email, Password, User,
+use crate::{
+    domain::{
+        data_stores::{UserStore, UserStoreError},
+        Email, Password, TwoFAMethod, User, UserId,
+    },
+    utils::password_hashing::{compute_password_hash, needs_rehash, verify_password_hash},
 };
 
-use argon2::{
-    password_hash::{rand_core::OsRng, SaltString},
-    Algorithm, Argon2, Params, PasswordHash, PasswordHasher, PasswordVerifier, Version,
-};
-use color_eyre::eyre::{self, Context};
+use chrono::{DateTime, Utc};
+use color_eyre::eyre::{self, eyre, Context};
 use secrecy::{ExposeSecret, Secret};
 use sqlx::MySqlPool;
+use tracing::Instrument;
 
 #[derive(Debug, Clone)]
 pub struct MySqlUserStore {
@@ -32,18 +33,15 @@ impl UserStore for MySqlUserStore {
             return Err(UserStoreError::UserAlreadyExists);
         }
 
-        let password_hash = compute_password_hash(user.password.as_ref().to_owned())
-            .await
-            .map_err(UserStoreError::UnexpectedError)?;
-
         sqlx::query!(
             "
-            INSERT INTO users (email, password_hash, requires_2fa) 
-            VALUES (?, ?, ?)
+            INSERT INTO users (id, email, password_hash, two_fa_method) 
+            VALUES (?, ?, ?, ?)
             ",
+            user.id().to_string(),
             user.email().as_ref().expose_secret(),
-            &password_hash.expose_secret(),
-            user.has_2fa()
+            user.password.as_ref().expose_secret(),
+            user.two_fa_method().as_str()
         )
         .execute(&self.pool)
         .await
@@ -55,12 +53,19 @@ impl UserStore for MySqlUserStore {
 
     #[tracing::instrument(name = "Retrieving user from MySql", skip_all)]
     async fn get_user(&self, email: &Email) -> eyre::Result<User, UserStoreError> {
-        let record = sqlx::query!(
+        let record = sqlx::query_as!(
+            UserRecord,
             r#"
             SELECT 
+                id,
                 email, 
                 password_hash, 
-                requires_2fa as "requires_2fa: bool"
+                two_fa_method,
+                totp_secret,
+                token_generation,
+                email_verified as "email_verified: bool",
+                failed_login_count,
+                locked_until
             FROM 
                 users
             WHERE
@@ -72,14 +77,36 @@ impl UserStore for MySqlUserStore {
         .await
         .map_err(|_| UserStoreError::UserNotFound)?;
 
-        let email = match Email::parse(Secret::new(record.email)) {
-            Ok(email) => email,
-            Err(e) => return Err(UserStoreError::UnexpectedError(e)),
-        };
+        to_user(record)
+    }
 
-        let password = Password::from(Secret::new(record.password_hash));
+    #[tracing::instrument(name = "Retrieving user by id from MySql", skip_all)]
+    async fn get_user_by_id(&self, id: &UserId) -> eyre::Result<User, UserStoreError> {
+        let record = sqlx::query_as!(
+            UserRecord,
+            r#"
+            SELECT 
+                id,
+                email, 
+                password_hash, 
+                two_fa_method,
+                totp_secret,
+                token_generation,
+                email_verified as "email_verified: bool",
+                failed_login_count,
+                locked_until
+            FROM 
+                users
+            WHERE
+                id = ?
+            "#,
+            id.to_string()
+        )
+        .fetch_one(&self.pool)
+        .await
+        .map_err(|_| UserStoreError::UserNotFound)?;
 
-        Ok(User::new(email, password, record.requires_2fa))
+        to_user(record)
     }
 
     #[tracing::instrument(name = "Validating user credentials in MySql", skip_all)]
@@ -103,58 +130,347 @@ impl UserStore for MySqlUserStore {
         .await
         .map_err(|_| UserStoreError::UserNotFound)?;
 
-        verify_password_hash(Secret::new(record.password_hash), password.as_ref().clone())
-            .await
-            .map_err(|_| UserStoreError::IncorrectCredentials)
-    }
-}
+        let matches = verify_password_hash(
+            Secret::new(record.password_hash.clone()),
+            password.as_ref().clone(),
+        )
+        .await
+        .map_err(UserStoreError::UnexpectedError)?;
 
-#[tracing::instrument(name = "Verify password hash", skip_all)]
-async fn verify_password_hash(
-    expected_password_hash: Secret<String>,
-    password_candidate: Secret<String>,
-) -> eyre::Result<()> {
-    let current_span = tracing::Span::current();
-
-    let result = tokio::task::spawn_blocking(move || {
-        current_span.in_scope(|| {
-            let expected_password_hash: PasswordHash<'_> =
-                PasswordHash::new(expected_password_hash.expose_secret())?;
-
-            Argon2::default()
-                .verify_password(
-                    password_candidate.expose_secret().as_bytes(),
-                    &expected_password_hash,
+        if !matches {
+            return Err(UserStoreError::IncorrectCredentials);
+        }
+
+        // Only possible now that the plaintext is known to be right. Done in the
+        // background, so the login does not wait for a second hash.
+        if needs_rehash(&record.password_hash) {
+            tokio::spawn(
+                rehash_password(
+                    self.pool.clone(),
+                    email.clone(),
+                    Secret::new(record.password_hash),
+                    password.as_ref().clone(),
                 )
-                .map_err(|e| e.into())
-        })
-    })
-    .await;
+                .in_current_span(),
+            );
+        }
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Updating password in MySql", skip_all)]
+    async fn update_password(
+        &mut self,
+        id: &UserId,
+        password_hash: Secret<String>,
+    ) -> Result<(), UserStoreError> {
+        let result = sqlx::query!(
+            "
+            UPDATE users
+            SET password_hash = ?
+            WHERE id = ?
+            ",
+            password_hash.expose_secret(),
+            id.to_string()
+        )
+        .execute(&self.pool)
+        .await
+        .wrap_err("Failed to update password in mysql database.")
+        .map_err(UserStoreError::UnexpectedError)?;
 
-    result?
+        if result.rows_affected() == 0 {
+            return Err(UserStoreError::UserNotFound);
+        }
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Marking email verified in MySql", skip_all)]
+    async fn mark_email_verified(&mut self, id: &UserId) -> Result<(), UserStoreError> {
+        let result = sqlx::query!(
+            "
+            UPDATE users
+            SET email_verified = TRUE
+            WHERE id = ?
+            ",
+            id.to_string()
+        )
+        .execute(&self.pool)
+        .await
+        .wrap_err("Failed to mark email verified in mysql database.")
+        .map_err(UserStoreError::UnexpectedError)?;
+
+        if result.rows_affected() == 0 {
+            return Err(UserStoreError::UserNotFound);
+        }
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Incrementing token generation in MySql", skip_all)]
+    async fn increment_token_generation(&mut self, id: &UserId) -> Result<(), UserStoreError> {
+        let result = sqlx::query!(
+            "
+            UPDATE users
+            SET token_generation = token_generation + 1
+            WHERE id = ?
+            ",
+            id.to_string()
+        )
+        .execute(&self.pool)
+        .await
+        .wrap_err("Failed to increment token generation in mysql database.")
+        .map_err(UserStoreError::UnexpectedError)?;
+
+        if result.rows_affected() == 0 {
+            return Err(UserStoreError::UserNotFound);
+        }
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Setting TOTP secret in MySql", skip_all)]
+    async fn set_totp_secret(
+        &mut self,
+        id: &UserId,
+        secret: Secret<String>,
+    ) -> Result<(), UserStoreError> {
+        let result = sqlx::query!(
+            "
+            UPDATE users
+            SET totp_secret = ?
+            WHERE id = ?
+            ",
+            secret.expose_secret(),
+            id.to_string()
+        )
+        .execute(&self.pool)
+        .await
+        .wrap_err("Failed to set TOTP secret in mysql database.")
+        .map_err(UserStoreError::UnexpectedError)?;
+
+        if result.rows_affected() == 0 {
+            return Err(UserStoreError::UserNotFound);
+        }
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Clearing TOTP secret in MySql", skip_all)]
+    async fn clear_totp_secret(&mut self, id: &UserId) -> Result<(), UserStoreError> {
+        let result = sqlx::query!(
+            "
+            UPDATE users
+            SET totp_secret = ''
+            WHERE id = ?
+            ",
+            id.to_string()
+        )
+        .execute(&self.pool)
+        .await
+        .wrap_err("Failed to clear TOTP secret in mysql database.")
+        .map_err(UserStoreError::UnexpectedError)?;
+
+        if result.rows_affected() == 0 {
+            return Err(UserStoreError::UserNotFound);
+        }
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Setting 2FA method in MySql", skip_all)]
+    async fn set_two_fa_method(
+        &mut self,
+        id: &UserId,
+        method: TwoFAMethod,
+    ) -> Result<(), UserStoreError> {
+        let result = sqlx::query!(
+            "
+            UPDATE users
+            SET two_fa_method = ?
+            WHERE id = ?
+            ",
+            method.as_str(),
+            id.to_string()
+        )
+        .execute(&self.pool)
+        .await
+        .wrap_err("Failed to set 2FA method in mysql database.")
+        .map_err(UserStoreError::UnexpectedError)?;
+
+        if result.rows_affected() == 0 {
+            return Err(UserStoreError::UserNotFound);
+        }
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Accepting TOTP step in MySql", skip_all)]
+    async fn accept_totp_step(&mut self, id: &UserId, step: u64) -> Result<bool, UserStoreError> {
+        // Conditional, so two requests racing with the same code cannot both pass
+        let result = sqlx::query!(
+            "
+            UPDATE users
+            SET totp_last_step = ?
+            WHERE id = ? AND (totp_last_step IS NULL OR totp_last_step < ?)
+            ",
+            step,
+            id.to_string(),
+            step
+        )
+        .execute(&self.pool)
+        .await
+        .wrap_err("Failed to record TOTP step in mysql database.")
+        .map_err(UserStoreError::UnexpectedError)?;
+
+        Ok(result.rows_affected() == 1)
+    }
+
+    #[tracing::instrument(name = "Recording failed login in MySql", skip_all)]
+    async fn record_failed_login(&mut self, id: &UserId) -> Result<u32, UserStoreError> {
+        // LAST_INSERT_ID(expr) hands the new count back on this connection, so
+        // concurrent failures each see their own count without a second query
+        let result = sqlx::query!(
+            "
+            UPDATE users
+            SET failed_login_count = LAST_INSERT_ID(failed_login_count + 1)
+            WHERE id = ?
+            ",
+            id.to_string()
+        )
+        .execute(&self.pool)
+        .await
+        .wrap_err("Failed to record failed login in mysql database.")
+        .map_err(UserStoreError::UnexpectedError)?;
+
+        if result.rows_affected() == 0 {
+            return Err(UserStoreError::UserNotFound);
+        }
+
+        Ok(result.last_insert_id() as u32)
+    }
+
+    #[tracing::instrument(name = "Locking user in MySql", skip_all)]
+    async fn lock_user(&mut self, id: &UserId, until: DateTime<Utc>) -> Result<(), UserStoreError> {
+        let result = sqlx::query!(
+            "
+            UPDATE users
+            SET locked_until = ?
+            WHERE id = ?
+            ",
+            until.timestamp(),
+            id.to_string()
+        )
+        .execute(&self.pool)
+        .await
+        .wrap_err("Failed to lock user in mysql database.")
+        .map_err(UserStoreError::UnexpectedError)?;
+
+        if result.rows_affected() == 0 {
+            return Err(UserStoreError::UserNotFound);
+        }
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Unlocking user in MySql", skip_all)]
+    async fn unlock_user(&mut self, id: &UserId) -> Result<(), UserStoreError> {
+        let result = sqlx::query!(
+            "
+            UPDATE users
+            SET failed_login_count = 0, locked_until = NULL
+            WHERE id = ?
+            ",
+            id.to_string()
+        )
+        .execute(&self.pool)
+        .await
+        .wrap_err("Failed to unlock user in mysql database.")
+        .map_err(UserStoreError::UnexpectedError)?;
+
+        if result.rows_affected() == 0 {
+            return Err(UserStoreError::UserNotFound);
+        }
+
+        Ok(())
+    }
 }
 
-#[tracing::instrument(name = "Computing password hash", skip_all)]
-async fn compute_password_hash(password: Secret<String>) -> eyre::Result<Secret<String>> {
-    let current_span: tracing::Span = tracing::Span::current();
-
-    let result = tokio::task::spawn_blocking(move || {
-        // This code block ensures that the operations within the closure are executed within the context of the current span.
-        // This is especially useful for tracing operations that are performed in a different thread or task, such as within tokio::task::spawn_blocking.
-        current_span.in_scope(|| {
-            let salt: SaltString = SaltString::generate(&mut OsRng);
-            let password_hash = Argon2::new(
-                Algorithm::Argon2id,
-                Version::V0x13,
-                Params::new(15000, 2, 1, None)?,
-            )
-            .hash_password(password.expose_secret().as_bytes(), &salt)?
-            .to_string();
-
-            Ok(Secret::new(password_hash))
+struct UserRecord {
+    id: String,
+    email: String,
+    password_hash: String,
+    two_fa_method: String,
+    totp_secret: String,
+    token_generation: u32,
+    email_verified: bool,
+    failed_login_count: u32,
+    locked_until: Option<i64>,
+}
+
+fn to_user(record: UserRecord) -> Result<User, UserStoreError> {
+    let id = UserId::parse(&record.id).map_err(UserStoreError::UnexpectedError)?;
+    let email = Email::parse(Secret::new(record.email)).map_err(UserStoreError::UnexpectedError)?;
+    let password = Password::from(Secret::new(record.password_hash));
+    let two_fa_method =
+        TwoFAMethod::parse(&record.two_fa_method).map_err(UserStoreError::UnexpectedError)?;
+    let totp_secret = Some(record.totp_secret)
+        .filter(|secret| !secret.is_empty())
+        .map(Secret::new);
+    let locked_until = record
+        .locked_until
+        .map(|seconds| {
+            DateTime::from_timestamp(seconds, 0).ok_or(UserStoreError::UnexpectedError(eyre!(
+                "Invalid lock timestamp {}",
+                seconds
+            )))
         })
+        .transpose()?;
+
+    Ok(User {
+        id,
+        email,
+        password,
+        two_fa_method,
+        totp_secret,
+        token_generation: record.token_generation,
+        email_verified: record.email_verified,
+        failed_login_count: record.failed_login_count,
+        locked_until,
     })
+}
+
+// Replaces a legacy or outdated hash with an Argon2id one. Skipped if the password was
+// changed in the meantime.
+#[tracing::instrument(name = "Rehashing password in MySql", skip_all)]
+async fn rehash_password(
+    pool: MySqlPool,
+    email: Email,
+    old_password_hash: Secret<String>,
+    password: Secret<String>,
+) {
+    let result = async {
+        let password_hash = compute_password_hash(password).await?;
+
+        sqlx::query!(
+            "
+            UPDATE users
+            SET password_hash = ?
+            WHERE email = ? AND password_hash = ?
+            ",
+            password_hash.expose_secret(),
+            email.as_ref().expose_secret(),
+            old_password_hash.expose_secret()
+        )
+        .execute(&pool)
+        .await?;
+
+        eyre::Ok(())
+    }
     .await;
 
-    result?
+    match result {
+        Ok(()) => tracing::info!("Upgraded password hash to the current Argon2id parameters"),
+        Err(e) => tracing::error!("Failed to upgrade password hash: {:?}", e),
+    }
 }
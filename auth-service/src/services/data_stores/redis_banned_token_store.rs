@@ -1,14 +1,11 @@
 use std::sync::Arc;
 
+use chrono::Utc;
 use color_eyre::eyre::Context;
 use redis::{Commands, Connection};
-use secrecy::{ExposeSecret, Secret};
 use tokio::sync::RwLock;
 
-use crate::{
-    domain::data_stores::{BannedTokenStore, BannedTokenStoreError},
-    utils::constants::TOKEN_TTL_SECONDS,
-};
+use crate::domain::data_stores::{BannedTokenStore, BannedTokenStoreError};
 
 const BANNED_TOKEN_KEY_PREFIX: &str = "banned_token:";
 
@@ -24,33 +21,30 @@ impl RedisBannedTokenStore {
 #[async_trait::async_trait]
 impl BannedTokenStore for RedisBannedTokenStore {
     #[tracing::instrument(name = "Add_Token", skip_all)]
-    async fn add_token(&mut self, token: Secret<String>) -> Result<(), BannedTokenStoreError> {
-        let expired_in: u64 = TOKEN_TTL_SECONDS
-            .try_into()
-            .wrap_err("Failed to create expiration token.")
-            .map_err(BannedTokenStoreError::UnexpectedError)?;
-
-        let token_key = get_key(token.expose_secret());
+    async fn add_token(&mut self, jti: &str, exp: usize) -> Result<(), BannedTokenStoreError> {
+        // Keep the ban exactly as long as the token itself would have been valid
+        let expires_in = match remaining_lifetime(exp) {
+            Some(expires_in) => expires_in,
+            None => return Ok(()),
+        };
 
         let _: () = self
             .conn
             .write()
             .await
-            .set_ex(token_key, true, expired_in)
+            .set_ex(get_key(jti), true, expires_in)
             .wrap_err("Failed to set expiration token.")
             .map_err(BannedTokenStoreError::UnexpectedError)?;
 
         Ok(())
     }
     #[tracing::instrument(name = "Contains_Token", skip_all)]
-    async fn contains_token(&self, token: &Secret<String>) -> Result<bool, BannedTokenStoreError> {
-        let token_key = get_key(token.expose_secret());
-
+    async fn contains_token(&self, jti: &str) -> Result<bool, BannedTokenStoreError> {
         let is_banned = self
             .conn
             .write()
             .await
-            .exists(&token_key)
+            .exists(get_key(jti))
             .wrap_err("Failed to check if token is banned.")
             .map_err(BannedTokenStoreError::UnexpectedError)?;
 
@@ -58,7 +52,15 @@ impl BannedTokenStore for RedisBannedTokenStore {
     }
 }
 
+// Seconds until `exp`, or None if the token has already expired
+fn remaining_lifetime(exp: usize) -> Option<u64> {
+    let now = Utc::now().timestamp();
+    let exp = i64::try_from(exp).ok()?;
+
+    u64::try_from(exp - now).ok().filter(|seconds| *seconds > 0)
+}
+
 // prefix to prevent collisions and organize data!
-fn get_key(token: &str) -> String {
-    format!("{}{}", BANNED_TOKEN_KEY_PREFIX, token)
+fn get_key(jti: &str) -> String {
+    format!("{}{}", BANNED_TOKEN_KEY_PREFIX, jti)
 }
@@ -0,0 +1,76 @@
+use color_eyre::eyre::Context;
+use redis::{Commands, Connection};
+use std::sync::Arc;
+use tokio::sync::RwLock;
+
+use crate::{
+    domain::{
+        data_stores::{
+            EmailVerificationToken, EmailVerificationTokenStore, EmailVerificationTokenStoreError,
+        },
+        UserId,
+    },
+    utils::constants::EMAIL_VERIFICATION_TOKEN_TTL_SECONDS,
+};
+
+const EMAIL_VERIFICATION_TOKEN_KEY_PREFIX: &str = "email_verification_token:";
+
+pub struct RedisEmailVerificationTokenStore {
+    conn: Arc<RwLock<Connection>>,
+}
+
+impl RedisEmailVerificationTokenStore {
+    pub fn new(conn: Arc<RwLock<Connection>>) -> Self {
+        Self { conn }
+    }
+}
+
+#[async_trait::async_trait]
+impl EmailVerificationTokenStore for RedisEmailVerificationTokenStore {
+    #[tracing::instrument(name = "Add_Email_Verification_Token", skip_all)]
+    async fn add_token(
+        &mut self,
+        token: &EmailVerificationToken,
+        user_id: UserId,
+    ) -> Result<(), EmailVerificationTokenStoreError> {
+        let _: () = self
+            .conn
+            .write()
+            .await
+            .set_ex(
+                get_key(token),
+                user_id.to_string(),
+                EMAIL_VERIFICATION_TOKEN_TTL_SECONDS as u64,
+            )
+            .wrap_err("Failed to set email verification token in Redis.")
+            .map_err(EmailVerificationTokenStoreError::UnexpectedError)?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Take_Email_Verification_Token", skip_all)]
+    async fn take_token(
+        &mut self,
+        token: &EmailVerificationToken,
+    ) -> Result<UserId, EmailVerificationTokenStoreError> {
+        let user_id: Option<String> = self
+            .conn
+            .write()
+            .await
+            .get_del(get_key(token))
+            .wrap_err("Failed to take email verification token from Redis.")
+            .map_err(EmailVerificationTokenStoreError::UnexpectedError)?;
+
+        let user_id = user_id.ok_or(EmailVerificationTokenStoreError::TokenNotFound)?;
+
+        UserId::parse(&user_id).map_err(EmailVerificationTokenStoreError::UnexpectedError)
+    }
+}
+
+fn get_key(token: &EmailVerificationToken) -> String {
+    format!(
+        "{}{}",
+        EMAIL_VERIFICATION_TOKEN_KEY_PREFIX,
+        token.fingerprint()
+    )
+}
@@ -0,0 +1,75 @@
+use color_eyre::eyre::Context;
+use redis::{Commands, Connection};
+use std::sync::Arc;
+use tokio::sync::RwLock;
+
+use crate::{
+    domain::data_stores::{PasskeyChallenge, PasskeyChallengeStore, PasskeyChallengeStoreError},
+    utils::constants::PASSKEY_CHALLENGE_TTL_SECONDS,
+};
+
+const PASSKEY_CHALLENGE_KEY_PREFIX: &str = "passkey_challenge:";
+
+pub struct RedisPasskeyChallengeStore {
+    conn: Arc<RwLock<Connection>>,
+}
+
+impl RedisPasskeyChallengeStore {
+    pub fn new(conn: Arc<RwLock<Connection>>) -> Self {
+        Self { conn }
+    }
+}
+
+#[async_trait::async_trait]
+impl PasskeyChallengeStore for RedisPasskeyChallengeStore {
+    #[tracing::instrument(name = "Add_Passkey_Challenge", skip_all)]
+    async fn add_challenge(
+        &mut self,
+        id: String,
+        challenge: PasskeyChallenge,
+    ) -> Result<(), PasskeyChallengeStoreError> {
+        let serialized_challenge = serde_json::to_string(&challenge)
+            .wrap_err("Failed to serialize passkey challenge.")
+            .map_err(PasskeyChallengeStoreError::UnexpectedError)?;
+
+        let _: () = self
+            .conn
+            .write()
+            .await
+            .set_ex(
+                get_key(&id),
+                serialized_challenge,
+                PASSKEY_CHALLENGE_TTL_SECONDS as u64,
+            )
+            .wrap_err("Failed to set passkey challenge in Redis.")
+            .map_err(PasskeyChallengeStoreError::UnexpectedError)?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Take_Passkey_Challenge", skip_all)]
+    async fn take_challenge(
+        &mut self,
+        id: &str,
+    ) -> Result<PasskeyChallenge, PasskeyChallengeStoreError> {
+        // GETDEL, so concurrent requests cannot both answer the same challenge
+        let serialized_challenge: Option<String> = self
+            .conn
+            .write()
+            .await
+            .get_del(get_key(id))
+            .wrap_err("Failed to take passkey challenge from Redis.")
+            .map_err(PasskeyChallengeStoreError::UnexpectedError)?;
+
+        let serialized_challenge =
+            serialized_challenge.ok_or(PasskeyChallengeStoreError::ChallengeNotFound)?;
+
+        serde_json::from_str(&serialized_challenge)
+            .wrap_err("Failed to deserialize passkey challenge.")
+            .map_err(PasskeyChallengeStoreError::UnexpectedError)
+    }
+}
+
+fn get_key(id: &str) -> String {
+    format!("{}{}", PASSKEY_CHALLENGE_KEY_PREFIX, id)
+}
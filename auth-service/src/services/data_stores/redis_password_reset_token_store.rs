@@ -0,0 +1,89 @@
+use color_eyre::eyre::Context;
+use redis::{Commands, Connection};
+use std::sync::Arc;
+use tokio::sync::RwLock;
+
+use crate::{
+    domain::{
+        data_stores::{PasswordResetToken, PasswordResetTokenStore, PasswordResetTokenStoreError},
+        UserId,
+    },
+    utils::constants::PASSWORD_RESET_TOKEN_TTL_SECONDS,
+};
+
+const PASSWORD_RESET_TOKEN_KEY_PREFIX: &str = "password_reset_token:";
+
+pub struct RedisPasswordResetTokenStore {
+    conn: Arc<RwLock<Connection>>,
+}
+
+impl RedisPasswordResetTokenStore {
+    pub fn new(conn: Arc<RwLock<Connection>>) -> Self {
+        Self { conn }
+    }
+}
+
+#[async_trait::async_trait]
+impl PasswordResetTokenStore for RedisPasswordResetTokenStore {
+    #[tracing::instrument(name = "Add_Password_Reset_Token", skip_all)]
+    async fn add_token(
+        &mut self,
+        token: &PasswordResetToken,
+        user_id: UserId,
+    ) -> Result<(), PasswordResetTokenStoreError> {
+        let _: () = self
+            .conn
+            .write()
+            .await
+            .set_ex(
+                get_key(token),
+                user_id.to_string(),
+                PASSWORD_RESET_TOKEN_TTL_SECONDS as u64,
+            )
+            .wrap_err("Failed to set password reset token in Redis.")
+            .map_err(PasswordResetTokenStoreError::UnexpectedError)?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Peek_Password_Reset_Token", skip_all)]
+    async fn peek_token(
+        &self,
+        token: &PasswordResetToken,
+    ) -> Result<UserId, PasswordResetTokenStoreError> {
+        let user_id: Option<String> = self
+            .conn
+            .write()
+            .await
+            .get(get_key(token))
+            .wrap_err("Failed to get password reset token from Redis.")
+            .map_err(PasswordResetTokenStoreError::UnexpectedError)?;
+
+        let user_id = user_id.ok_or(PasswordResetTokenStoreError::TokenNotFound)?;
+
+        UserId::parse(&user_id).map_err(PasswordResetTokenStoreError::UnexpectedError)
+    }
+
+    #[tracing::instrument(name = "Take_Password_Reset_Token", skip_all)]
+    async fn take_token(
+        &mut self,
+        token: &PasswordResetToken,
+    ) -> Result<UserId, PasswordResetTokenStoreError> {
+        // GETDEL, so concurrent requests cannot both use the same link
+        let user_id: Option<String> = self
+            .conn
+            .write()
+            .await
+            .get_del(get_key(token))
+            .wrap_err("Failed to take password reset token from Redis.")
+            .map_err(PasswordResetTokenStoreError::UnexpectedError)?;
+
+        let user_id = user_id.ok_or(PasswordResetTokenStoreError::TokenNotFound)?;
+
+        UserId::parse(&user_id).map_err(PasswordResetTokenStoreError::UnexpectedError)
+    }
+}
+
+fn get_key(token: &PasswordResetToken) -> String {
+    format!("{}{}", PASSWORD_RESET_TOKEN_KEY_PREFIX, token.fingerprint())
+}
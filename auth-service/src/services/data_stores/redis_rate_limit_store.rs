@@ -0,0 +1,79 @@
+use color_eyre::eyre::Context;
+use redis::{Connection, Script};
+use std::sync::Arc;
+use tokio::sync::RwLock;
+use uuid::Uuid;
+
+use crate::domain::data_stores::{RateLimitStore, RateLimitStoreError};
+
+const RATE_LIMIT_KEY_PREFIX: &str = "rate_limit:";
+
+// Sorted set of the hits let through, scored by time. Runs as one script, so
+// replicas sharing the Redis instance cannot both take the last free slot, and
+// uses the Redis clock, so their clocks need not agree.
+// Returns 0 when the hit is allowed, otherwise milliseconds until it would be.
+const HIT_SCRIPT: &str = r"
+local time = redis.call('TIME')
+local now = tonumber(time[1]) * 1000 + math.floor(tonumber(time[2]) / 1000)
+local window = tonumber(ARGV[1])
+local limit = tonumber(ARGV[2])
+
+redis.call('ZREMRANGEBYSCORE', KEYS[1], '-inf', now - window)
+
+if redis.call('ZCARD', KEYS[1]) < limit then
+    redis.call('ZADD', KEYS[1], now, ARGV[3])
+    redis.call('PEXPIRE', KEYS[1], window)
+    return 0
+end
+
+local oldest = redis.call('ZRANGE', KEYS[1], 0, 0, 'WITHSCORES')
+return math.max(tonumber(oldest[2]) + window - now, 1)
+";
+
+pub struct RedisRateLimitStore {
+    conn: Arc<RwLock<Connection>>,
+    script: Script,
+}
+
+impl RedisRateLimitStore {
+    pub fn new(conn: Arc<RwLock<Connection>>) -> Self {
+        Self {
+            conn,
+            script: Script::new(HIT_SCRIPT),
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl RateLimitStore for RedisRateLimitStore {
+    #[tracing::instrument(name = "Rate_Limit_Hit", skip_all)]
+    async fn hit(
+        &mut self,
+        key: &str,
+        limit: u32,
+        window_seconds: i64,
+    ) -> Result<(), RateLimitStoreError> {
+        let retry_after_millis: u64 = self
+            .script
+            .key(get_key(key))
+            .arg(window_seconds * 1000)
+            .arg(limit)
+            // Members must be unique, or hits in the same millisecond would merge
+            .arg(Uuid::new_v4().to_string())
+            .invoke(&mut *self.conn.write().await)
+            .wrap_err("Failed to record rate limit hit in Redis.")
+            .map_err(RateLimitStoreError::UnexpectedError)?;
+
+        if retry_after_millis > 0 {
+            return Err(RateLimitStoreError::LimitExceeded(
+                retry_after_millis.div_ceil(1000),
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+fn get_key(key: &str) -> String {
+    format!("{}{}", RATE_LIMIT_KEY_PREFIX, key)
+}
@@ -0,0 +1,150 @@
+use color_eyre::eyre::Context;
+use redis::{Commands, Connection, ExistenceCheck, SetExpiry, SetOptions};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use tokio::sync::RwLock;
+
+use crate::{
+    domain::{
+        data_stores::{
+            RefreshToken, RefreshTokenRecord, RefreshTokenStore, RefreshTokenStoreError,
+        },
+        UserId,
+    },
+    utils::constants::REFRESH_TOKEN_TTL_SECONDS,
+};
+
+const REFRESH_TOKEN_KEY_PREFIX: &str = "refresh_token:";
+const USED_REFRESH_TOKEN_KEY_PREFIX: &str = "refresh_token_used:";
+const REVOKED_FAMILY_KEY_PREFIX: &str = "refresh_token_family_revoked:";
+
+#[derive(Serialize, Deserialize)]
+struct RefreshTokenTuple(pub String, pub String, pub u32, pub String);
+
+pub struct RedisRefreshTokenStore {
+    conn: Arc<RwLock<Connection>>,
+}
+
+impl RedisRefreshTokenStore {
+    pub fn new(conn: Arc<RwLock<Connection>>) -> Self {
+        Self { conn }
+    }
+}
+
+#[async_trait::async_trait]
+impl RefreshTokenStore for RedisRefreshTokenStore {
+    #[tracing::instrument(name = "Add_Refresh_Token", skip_all)]
+    async fn add_token(
+        &mut self,
+        token: RefreshToken,
+        record: RefreshTokenRecord,
+    ) -> Result<(), RefreshTokenStoreError> {
+        let key = get_key(REFRESH_TOKEN_KEY_PREFIX, &token.fingerprint());
+
+        let data = RefreshTokenTuple(
+            record.user_id.to_string(),
+            record.family_id,
+            record.token_generation,
+            record.session_id,
+        );
+
+        let serialized_data = serde_json::to_string(&data)
+            .wrap_err("Failed to serialize refresh token tuple.")
+            .map_err(RefreshTokenStoreError::UnexpectedError)?;
+
+        let _: () = self
+            .conn
+            .write()
+            .await
+            .set_ex(&key, serialized_data, ttl())
+            .wrap_err("Failed to set refresh token in Redis.")
+            .map_err(RefreshTokenStoreError::UnexpectedError)?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Consume_Refresh_Token", skip_all)]
+    async fn consume_token(
+        &mut self,
+        token: &RefreshToken,
+    ) -> Result<RefreshTokenRecord, RefreshTokenStoreError> {
+        let token = token.fingerprint();
+        let mut conn = self.conn.write().await;
+
+        let data: Option<String> = conn
+            .get(get_key(REFRESH_TOKEN_KEY_PREFIX, &token))
+            .wrap_err("Failed to get refresh token.")
+            .map_err(RefreshTokenStoreError::UnexpectedError)?;
+
+        let data: RefreshTokenTuple = match data {
+            Some(data) => serde_json::from_str(&data)
+                .wrap_err("Failed to deserialize refresh token tuple.")
+                .map_err(RefreshTokenStoreError::UnexpectedError)?,
+            None => return Err(RefreshTokenStoreError::TokenNotFound),
+        };
+
+        let user_id = UserId::parse(&data.0).map_err(RefreshTokenStoreError::UnexpectedError)?;
+
+        let record = RefreshTokenRecord {
+            user_id,
+            family_id: data.1,
+            token_generation: data.2,
+            session_id: data.3,
+        };
+
+        // SET NX makes marking the token as used atomic, so two replicas racing
+        // on the same token cannot both succeed.
+        let options = SetOptions::default()
+            .conditional_set(ExistenceCheck::NX)
+            .with_expiration(SetExpiry::EX(ttl() as usize));
+
+        let first_use: Option<String> = conn
+            .set_options(
+                get_key(USED_REFRESH_TOKEN_KEY_PREFIX, &token),
+                true,
+                options,
+            )
+            .wrap_err("Failed to mark refresh token as used.")
+            .map_err(RefreshTokenStoreError::UnexpectedError)?;
+
+        if first_use.is_none() {
+            return Err(RefreshTokenStoreError::TokenReused(record));
+        }
+
+        Ok(record)
+    }
+
+    #[tracing::instrument(name = "Revoke_Refresh_Token_Family", skip_all)]
+    async fn revoke_family(&mut self, family_id: &str) -> Result<(), RefreshTokenStoreError> {
+        let _: () = self
+            .conn
+            .write()
+            .await
+            .set_ex(get_key(REVOKED_FAMILY_KEY_PREFIX, family_id), true, ttl())
+            .wrap_err("Failed to revoke refresh token family.")
+            .map_err(RefreshTokenStoreError::UnexpectedError)?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Is_Refresh_Token_Family_Revoked", skip_all)]
+    async fn is_family_revoked(&self, family_id: &str) -> Result<bool, RefreshTokenStoreError> {
+        let is_revoked = self
+            .conn
+            .write()
+            .await
+            .exists(get_key(REVOKED_FAMILY_KEY_PREFIX, family_id))
+            .wrap_err("Failed to check if refresh token family is revoked.")
+            .map_err(RefreshTokenStoreError::UnexpectedError)?;
+
+        Ok(is_revoked)
+    }
+}
+
+fn ttl() -> u64 {
+    REFRESH_TOKEN_TTL_SECONDS as u64
+}
+
+fn get_key(prefix: &str, value: &str) -> String {
+    format!("{}{}", prefix, value)
+}
@@ -0,0 +1,242 @@
+use chrono::{DateTime, Utc};
+use color_eyre::eyre::Context;
+use redis::{Commands, Connection};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use tokio::sync::RwLock;
+
+use crate::{
+    domain::{
+        data_stores::{Session, SessionStore, SessionStoreError},
+        UserId,
+    },
+    utils::constants::SESSION_TTL_SECONDS,
+};
+
+const SESSION_KEY_PREFIX: &str = "session:";
+const USER_SESSIONS_KEY_PREFIX: &str = "user_sessions:";
+
+// Timestamps are stored as unix seconds
+#[derive(Serialize, Deserialize)]
+struct SessionTuple(
+    pub String,
+    pub String,
+    pub String,
+    pub String,
+    pub i64,
+    pub i64,
+);
+
+// Each session lives under its own key and expires when idle for SESSION_TTL_SECONDS.
+// A per-user set indexes the ids; ids whose session has expired are pruned lazily.
+pub struct RedisSessionStore {
+    conn: Arc<RwLock<Connection>>,
+}
+
+impl RedisSessionStore {
+    pub fn new(conn: Arc<RwLock<Connection>>) -> Self {
+        Self { conn }
+    }
+}
+
+#[async_trait::async_trait]
+impl SessionStore for RedisSessionStore {
+    #[tracing::instrument(name = "Add_Session", skip_all)]
+    async fn add_session(&mut self, session: Session) -> Result<(), SessionStoreError> {
+        let user_key = get_key(USER_SESSIONS_KEY_PREFIX, &session.user_id.to_string());
+        let mut conn = self.conn.write().await;
+
+        set_session(&mut conn, &session)?;
+
+        let _: () = conn
+            .sadd(&user_key, &session.id)
+            .wrap_err("Failed to index session in Redis.")
+            .map_err(SessionStoreError::UnexpectedError)?;
+
+        let _: () = conn
+            .expire(&user_key, SESSION_TTL_SECONDS)
+            .wrap_err("Failed to set expiration on user sessions.")
+            .map_err(SessionStoreError::UnexpectedError)?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Get_Session", skip_all)]
+    async fn get_session(&self, id: &str) -> Result<Session, SessionStoreError> {
+        let mut conn = self.conn.write().await;
+
+        get_session(&mut conn, id)?.ok_or(SessionStoreError::SessionNotFound)
+    }
+
+    #[tracing::instrument(name = "List_Sessions", skip_all)]
+    async fn list_sessions(&self, user_id: &UserId) -> Result<Vec<Session>, SessionStoreError> {
+        let user_key = get_key(USER_SESSIONS_KEY_PREFIX, &user_id.to_string());
+        let mut conn = self.conn.write().await;
+
+        let ids: Vec<String> = conn
+            .smembers(&user_key)
+            .wrap_err("Failed to list user sessions.")
+            .map_err(SessionStoreError::UnexpectedError)?;
+
+        let mut sessions = Vec::with_capacity(ids.len());
+
+        for id in ids {
+            match get_session(&mut conn, &id)? {
+                Some(session) => sessions.push(session),
+                None => {
+                    let _: () = conn
+                        .srem(&user_key, &id)
+                        .wrap_err("Failed to prune expired session.")
+                        .map_err(SessionStoreError::UnexpectedError)?;
+                }
+            }
+        }
+
+        sessions.sort_by_key(|session| std::cmp::Reverse(session.last_seen_at));
+
+        Ok(sessions)
+    }
+
+    #[tracing::instrument(name = "Touch_Session", skip_all)]
+    async fn touch_session(
+        &mut self,
+        id: &str,
+        last_seen_at: DateTime<Utc>,
+    ) -> Result<(), SessionStoreError> {
+        let mut conn = self.conn.write().await;
+
+        let mut session = get_session(&mut conn, id)?.ok_or(SessionStoreError::SessionNotFound)?;
+        session.last_seen_at = last_seen_at;
+
+        // XX only overwrites a key that still exists, so a session revoked on another
+        // replica since the GET above is not written back
+        let updated: Option<String> = redis::cmd("SET")
+            .arg(get_key(SESSION_KEY_PREFIX, id))
+            .arg(serialize_session(&session)?)
+            .arg("XX")
+            .arg("EX")
+            .arg(SESSION_TTL_SECONDS)
+            .query(&mut *conn)
+            .wrap_err("Failed to update session in Redis.")
+            .map_err(SessionStoreError::UnexpectedError)?;
+
+        if updated.is_none() {
+            return Err(SessionStoreError::SessionNotFound);
+        }
+
+        let _: () = conn
+            .expire(
+                get_key(USER_SESSIONS_KEY_PREFIX, &session.user_id.to_string()),
+                SESSION_TTL_SECONDS,
+            )
+            .wrap_err("Failed to set expiration on user sessions.")
+            .map_err(SessionStoreError::UnexpectedError)?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Revoke_Session", skip_all)]
+    async fn revoke_session(&mut self, id: &str) -> Result<(), SessionStoreError> {
+        let mut conn = self.conn.write().await;
+
+        let session = get_session(&mut conn, id)?.ok_or(SessionStoreError::SessionNotFound)?;
+
+        let _: () = conn
+            .del(get_key(SESSION_KEY_PREFIX, id))
+            .wrap_err("Failed to delete session from Redis.")
+            .map_err(SessionStoreError::UnexpectedError)?;
+
+        let _: () = conn
+            .srem(
+                get_key(USER_SESSIONS_KEY_PREFIX, &session.user_id.to_string()),
+                id,
+            )
+            .wrap_err("Failed to remove session from user index.")
+            .map_err(SessionStoreError::UnexpectedError)?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Revoke_User_Sessions", skip_all)]
+    async fn revoke_user_sessions(&mut self, user_id: &UserId) -> Result<(), SessionStoreError> {
+        let user_key = get_key(USER_SESSIONS_KEY_PREFIX, &user_id.to_string());
+        let mut conn = self.conn.write().await;
+
+        let ids: Vec<String> = conn
+            .smembers(&user_key)
+            .wrap_err("Failed to list user sessions.")
+            .map_err(SessionStoreError::UnexpectedError)?;
+
+        let mut keys: Vec<String> = ids
+            .iter()
+            .map(|id| get_key(SESSION_KEY_PREFIX, id))
+            .collect();
+        keys.push(user_key);
+
+        let _: () = conn
+            .del(keys)
+            .wrap_err("Failed to delete user sessions from Redis.")
+            .map_err(SessionStoreError::UnexpectedError)?;
+
+        Ok(())
+    }
+}
+
+fn set_session(conn: &mut Connection, session: &Session) -> Result<(), SessionStoreError> {
+    conn.set_ex(
+        get_key(SESSION_KEY_PREFIX, &session.id),
+        serialize_session(session)?,
+        SESSION_TTL_SECONDS as u64,
+    )
+    .wrap_err("Failed to set session in Redis.")
+    .map_err(SessionStoreError::UnexpectedError)
+}
+
+fn serialize_session(session: &Session) -> Result<String, SessionStoreError> {
+    let data = SessionTuple(
+        session.user_id.to_string(),
+        session.device.clone(),
+        session.ip_address.clone(),
+        session.user_agent.clone(),
+        session.created_at.timestamp(),
+        session.last_seen_at.timestamp(),
+    );
+
+    serde_json::to_string(&data)
+        .wrap_err("Failed to serialize session tuple.")
+        .map_err(SessionStoreError::UnexpectedError)
+}
+
+fn get_session(conn: &mut Connection, id: &str) -> Result<Option<Session>, SessionStoreError> {
+    let data: Option<String> = conn
+        .get(get_key(SESSION_KEY_PREFIX, id))
+        .wrap_err("Failed to get session from Redis.")
+        .map_err(SessionStoreError::UnexpectedError)?;
+
+    let data: SessionTuple = match data {
+        Some(data) => serde_json::from_str(&data)
+            .wrap_err("Failed to deserialize session tuple.")
+            .map_err(SessionStoreError::UnexpectedError)?,
+        None => return Ok(None),
+    };
+
+    Ok(Some(Session {
+        id: id.to_owned(),
+        user_id: UserId::parse(&data.0).map_err(SessionStoreError::UnexpectedError)?,
+        device: data.1,
+        ip_address: data.2,
+        user_agent: data.3,
+        created_at: from_timestamp(data.4)?,
+        last_seen_at: from_timestamp(data.5)?,
+    }))
+}
+
+fn from_timestamp(seconds: i64) -> Result<DateTime<Utc>, SessionStoreError> {
+    DateTime::from_timestamp(seconds, 0).ok_or(SessionStoreError::UnexpectedError(
+        color_eyre::eyre::eyre!("Invalid session timestamp {}", seconds),
+    ))
+}
+
+fn get_key(prefix: &str, value: &str) -> String {
+    format!("{}{}", prefix, value)
+}
@@ -0,0 +1,141 @@
+use chrono::DateTime;
+use color_eyre::eyre::{eyre, Context};
+use jsonwebtoken::Algorithm;
+use redis::{Commands, Connection, Script};
+use secrecy::{ExposeSecret, Secret};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use tokio::sync::RwLock;
+
+use crate::domain::data_stores::{SigningKeyStore, SigningKeyStoreError, StoredSigningKey};
+
+const SIGNING_KEYS_KEY_PREFIX: &str = "signing_keys:";
+
+// Hash with the active kid next to the JSON list of keys, so the check and the
+// write happen at once however many replicas rotate at the same time
+const REPLACE_SCRIPT: &str = r"
+local active = redis.call('HGET', KEYS[1], 'active')
+if (active or '') ~= ARGV[1] then
+    return 0
+end
+redis.call('HSET', KEYS[1], 'active', ARGV[2], 'keys', ARGV[3])
+return 1
+";
+
+#[derive(Serialize, Deserialize)]
+struct KeyRecord {
+    kid: String,
+    algorithm: Algorithm,
+    material: Option<String>,
+    retired_at: Option<i64>,
+}
+
+pub struct RedisSigningKeyStore {
+    conn: Arc<RwLock<Connection>>,
+    script: Script,
+}
+
+impl RedisSigningKeyStore {
+    pub fn new(conn: Arc<RwLock<Connection>>) -> Self {
+        Self {
+            conn,
+            script: Script::new(REPLACE_SCRIPT),
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl SigningKeyStore for RedisSigningKeyStore {
+    #[tracing::instrument(name = "Load_Signing_Keys", skip_all)]
+    async fn load_keys(
+        &self,
+        base_kid: &str,
+    ) -> Result<Vec<StoredSigningKey>, SigningKeyStoreError> {
+        let keys: Option<String> = self
+            .conn
+            .write()
+            .await
+            .hget(get_key(base_kid), "keys")
+            .wrap_err("Failed to get signing keys from Redis.")
+            .map_err(SigningKeyStoreError::UnexpectedError)?;
+
+        let Some(keys) = keys else {
+            return Ok(Vec::new());
+        };
+
+        serde_json::from_str::<Vec<KeyRecord>>(&keys)
+            .wrap_err("Failed to deserialize signing keys.")
+            .map_err(SigningKeyStoreError::UnexpectedError)?
+            .into_iter()
+            .map(|record| {
+                let retired_at = record
+                    .retired_at
+                    .map(|seconds| {
+                        DateTime::from_timestamp(seconds, 0)
+                            .ok_or(eyre!("Invalid retirement timestamp {}", seconds))
+                    })
+                    .transpose()
+                    .map_err(SigningKeyStoreError::UnexpectedError)?;
+
+                Ok(StoredSigningKey {
+                    kid: record.kid,
+                    algorithm: record.algorithm,
+                    material: record.material.map(Secret::new),
+                    retired_at,
+                })
+            })
+            .collect()
+    }
+
+    #[tracing::instrument(name = "Replace_Signing_Keys", skip_all)]
+    async fn replace_keys(
+        &mut self,
+        base_kid: &str,
+        expected_active_kid: Option<&str>,
+        keys: Vec<StoredSigningKey>,
+    ) -> Result<(), SigningKeyStoreError> {
+        let active_kid = keys
+            .iter()
+            .find(|key| key.retired_at.is_none())
+            .map(|key| key.kid.clone())
+            .ok_or(SigningKeyStoreError::UnexpectedError(eyre!(
+                "Signing keys have no active key"
+            )))?;
+
+        let records: Vec<KeyRecord> = keys
+            .into_iter()
+            .map(|key| KeyRecord {
+                kid: key.kid,
+                algorithm: key.algorithm,
+                material: key
+                    .material
+                    .map(|material| material.expose_secret().to_owned()),
+                retired_at: key.retired_at.map(|retired_at| retired_at.timestamp()),
+            })
+            .collect();
+
+        let records = serde_json::to_string(&records)
+            .wrap_err("Failed to serialize signing keys.")
+            .map_err(SigningKeyStoreError::UnexpectedError)?;
+
+        let replaced: bool = self
+            .script
+            .key(get_key(base_kid))
+            .arg(expected_active_kid.unwrap_or_default())
+            .arg(active_kid)
+            .arg(records)
+            .invoke(&mut *self.conn.write().await)
+            .wrap_err("Failed to replace signing keys in Redis.")
+            .map_err(SigningKeyStoreError::UnexpectedError)?;
+
+        if !replaced {
+            return Err(SigningKeyStoreError::Conflict);
+        }
+
+        Ok(())
+    }
+}
+
+fn get_key(base_kid: &str) -> String {
+    format!("{}{}", SIGNING_KEYS_KEY_PREFIX, base_kid)
+}
@@ -1,71 +1,161 @@
 use color_eyre::eyre::Context;
-use redis::{Commands, Connection};
+use redis::{Commands, Connection, Script};
 use secrecy::{ExposeSecret, Secret};
 use serde::{Deserialize, Serialize};
 use std::sync::Arc;
 use tokio::sync::RwLock;
 
-use crate::domain::{
-    data_stores::{LoginAttemptId, TwoFACode, TwoFACodeStore, TwoFACodeStoreError},
-    Email,
+use crate::{
+    domain::{
+        data_stores::{
+            LoginAttemptId, TwoFACode, TwoFACodeHash, TwoFACodeStore, TwoFACodeStoreError,
+        },
+        Email,
+    },
+    utils::constants::{MAX_2FA_ATTEMPTS, MAX_2FA_RESENDS, TWO_FA_RESEND_COOLDOWN_SECONDS},
 };
 
 const TEN_MINUTES_IN_SECONDS: u64 = 600;
 const TWO_FA_CODE_PREFIX: &str = "two_fa_code:";
+// A code taken by a request that is still checking it. Renamed rather than
+// deleted, so a code put back keeps its expiry.
+const TWO_FA_TAKEN_CODE_PREFIX: &str = "two_fa_taken_code:";
+// Guesses are counted under their own key, so INCR keeps the count exact
+// under concurrent requests
+const TWO_FA_ATTEMPTS_PREFIX: &str = "two_fa_attempts:";
+const TWO_FA_RESENDS_PREFIX: &str = "two_fa_resends:";
+// Exists while the last emailed code is inside its resend cooldown
+const TWO_FA_COOLDOWN_PREFIX: &str = "two_fa_cooldown:";
 
+// Counts a guess against a pending code in one step, dropping the code once
+// there have been too many. Returns -1 when there is no pending code.
+const RESERVE_ATTEMPT_SCRIPT: &str = r"
+if redis.call('EXISTS', KEYS[1]) == 0 then
+    return -1
+end
+
+local attempts = redis.call('INCR', KEYS[2])
+redis.call('EXPIRE', KEYS[2], ARGV[1])
+
+if attempts > tonumber(ARGV[2]) then
+    redis.call('DEL', KEYS[1])
+end
+
+return attempts
+";
+
+const TAKE_CODE_SCRIPT: &str = r"
+local code = redis.call('GET', KEYS[1])
+
+if code then
+    redis.call('RENAME', KEYS[1], KEYS[2])
+end
+
+return code
+";
+
+// Returns 0 when the taken code has expired
+const RESTORE_CODE_SCRIPT: &str = r"
+if redis.call('EXISTS', KEYS[1]) == 0 then
+    return 0
+end
+
+return redis.call('RENAMENX', KEYS[1], KEYS[2])
+";
+
+// (email, code hash) of one pending login attempt
 #[derive(Serialize, Deserialize)]
 struct TwoFATuple(pub String, pub String);
 
 pub struct RedisTwoFACodeStore {
     conn: Arc<RwLock<Connection>>,
+    hash_key: Secret<String>,
+    reserve_attempt_script: Script,
+    take_code_script: Script,
+    restore_code_script: Script,
 }
 
 impl RedisTwoFACodeStore {
-    pub fn new(conn: Arc<RwLock<Connection>>) -> Self {
-        Self { conn }
+    pub fn new(conn: Arc<RwLock<Connection>>, hash_key: Secret<String>) -> Self {
+        Self {
+            conn,
+            hash_key,
+            reserve_attempt_script: Script::new(RESERVE_ATTEMPT_SCRIPT),
+            take_code_script: Script::new(TAKE_CODE_SCRIPT),
+            restore_code_script: Script::new(RESTORE_CODE_SCRIPT),
+        }
     }
 }
 
 #[async_trait::async_trait]
 impl TwoFACodeStore for RedisTwoFACodeStore {
+    fn hash_code(&self, code: &TwoFACode) -> TwoFACodeHash {
+        code.hash(&self.hash_key)
+    }
+
     #[tracing::instrument(name = "Add_Code", skip_all)]
     async fn add_code(
         &mut self,
-        email: Email,
         login_attempt_id: LoginAttemptId,
-        code: TwoFACode,
+        email: Email,
+        code_hash: TwoFACodeHash,
     ) -> Result<(), TwoFACodeStoreError> {
-        let token_key = get_key(&email);
+        let token_key = get_key(TWO_FA_CODE_PREFIX, &login_attempt_id);
 
         let data = TwoFATuple(
-            login_attempt_id.as_ref().expose_secret().to_owned(),
-            code.as_ref().expose_secret().to_owned(),
+            email.as_ref().expose_secret().to_owned(),
+            code_hash.as_ref().expose_secret().to_owned(),
         );
 
         let serialized_data = serde_json::to_string(&data)
             .wrap_err("Failed to serialize 2FA tuple.")
             .map_err(TwoFACodeStoreError::UnexpectedError)?;
 
-        let _: () = self
-            .conn
-            .write()
-            .await
+        let mut conn = self.conn.write().await;
+
+        let _: () = conn
             .set_ex(&token_key, serialized_data, TEN_MINUTES_IN_SECONDS)
             .wrap_err("Failed to set 2FA code in Redis.")
             .map_err(TwoFACodeStoreError::UnexpectedError)?;
 
+        let _: () = conn
+            .del(&[
+                get_key(TWO_FA_ATTEMPTS_PREFIX, &login_attempt_id),
+                get_key(TWO_FA_RESENDS_PREFIX, &login_attempt_id),
+            ])
+            .wrap_err("Failed to reset 2FA attempts in Redis.")
+            .map_err(TwoFACodeStoreError::UnexpectedError)?;
+
+        let _: () = conn
+            .set_ex(
+                get_key(TWO_FA_COOLDOWN_PREFIX, &login_attempt_id),
+                1,
+                TWO_FA_RESEND_COOLDOWN_SECONDS as u64,
+            )
+            .wrap_err("Failed to set 2FA resend cooldown in Redis.")
+            .map_err(TwoFACodeStoreError::UnexpectedError)?;
+
         Ok(())
     }
 
     #[tracing::instrument(name = "Remove_Code", skip_all)]
-    async fn remove_code(&mut self, email: &Email) -> Result<(), TwoFACodeStoreError> {
-        let key = get_key(email);
+    async fn remove_code(
+        &mut self,
+        login_attempt_id: &LoginAttemptId,
+    ) -> Result<(), TwoFACodeStoreError> {
+        let keys = [
+            get_key(TWO_FA_CODE_PREFIX, login_attempt_id),
+            get_key(TWO_FA_TAKEN_CODE_PREFIX, login_attempt_id),
+            get_key(TWO_FA_ATTEMPTS_PREFIX, login_attempt_id),
+            get_key(TWO_FA_RESENDS_PREFIX, login_attempt_id),
+            get_key(TWO_FA_COOLDOWN_PREFIX, login_attempt_id),
+        ];
 
         let _: () = self
             .conn
             .write()
             .await
-            .del(&key)
+            .del(&keys)
             .wrap_err("Failed to delete 2FA code from Redis")
             .map_err(TwoFACodeStoreError::UnexpectedError)?;
 
@@ -75,31 +165,172 @@ impl TwoFACodeStore for RedisTwoFACodeStore {
     #[tracing::instrument(name = "Get_Code", skip_all)]
     async fn get_code(
         &self,
-        email: &Email,
-    ) -> Result<(LoginAttemptId, TwoFACode), TwoFACodeStoreError> {
-        let token_key = get_key(email);
+        login_attempt_id: &LoginAttemptId,
+    ) -> Result<(Email, TwoFACodeHash), TwoFACodeStoreError> {
+        let token_key = get_key(TWO_FA_CODE_PREFIX, login_attempt_id);
 
         let token = self
             .conn
             .write()
             .await
-            .get::<_, String>(&token_key)
+            .get::<_, Option<String>>(&token_key)
             .wrap_err("Failed to get token.")
+            .map_err(TwoFACodeStoreError::UnexpectedError)?
+            .ok_or(TwoFACodeStoreError::LoginAttemptIdNotFound)?;
+
+        parse_code(&token)
+    }
+
+    #[tracing::instrument(name = "Take_Code", skip_all)]
+    async fn take_code(
+        &mut self,
+        login_attempt_id: &LoginAttemptId,
+    ) -> Result<(Email, TwoFACodeHash), TwoFACodeStoreError> {
+        let token: String = self
+            .take_code_script
+            .key(get_key(TWO_FA_CODE_PREFIX, login_attempt_id))
+            .key(get_key(TWO_FA_TAKEN_CODE_PREFIX, login_attempt_id))
+            .invoke::<Option<String>>(&mut *self.conn.write().await)
+            .wrap_err("Failed to take 2FA code in Redis.")
+            .map_err(TwoFACodeStoreError::UnexpectedError)?
+            .ok_or(TwoFACodeStoreError::LoginAttemptIdNotFound)?;
+
+        parse_code(&token)
+    }
+
+    #[tracing::instrument(name = "Restore_Code", skip_all)]
+    async fn restore_code(
+        &mut self,
+        login_attempt_id: &LoginAttemptId,
+    ) -> Result<(), TwoFACodeStoreError> {
+        let restored: bool = self
+            .restore_code_script
+            .key(get_key(TWO_FA_TAKEN_CODE_PREFIX, login_attempt_id))
+            .key(get_key(TWO_FA_CODE_PREFIX, login_attempt_id))
+            .invoke(&mut *self.conn.write().await)
+            .wrap_err("Failed to restore 2FA code in Redis.")
             .map_err(TwoFACodeStoreError::UnexpectedError)?;
 
+        if !restored {
+            return Err(TwoFACodeStoreError::LoginAttemptIdNotFound);
+        }
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Reserve_Attempt", skip_all)]
+    async fn reserve_attempt(
+        &mut self,
+        login_attempt_id: &LoginAttemptId,
+    ) -> Result<u32, TwoFACodeStoreError> {
+        let attempts: i64 = self
+            .reserve_attempt_script
+            .key(get_key(TWO_FA_CODE_PREFIX, login_attempt_id))
+            .key(get_key(TWO_FA_ATTEMPTS_PREFIX, login_attempt_id))
+            .arg(TEN_MINUTES_IN_SECONDS)
+            .arg(MAX_2FA_ATTEMPTS)
+            .invoke(&mut *self.conn.write().await)
+            .wrap_err("Failed to count 2FA attempt in Redis.")
+            .map_err(TwoFACodeStoreError::UnexpectedError)?;
+
+        if attempts < 0 {
+            return Err(TwoFACodeStoreError::LoginAttemptIdNotFound);
+        }
+
+        if attempts > MAX_2FA_ATTEMPTS as i64 {
+            self.remove_code(login_attempt_id).await?;
+            return Err(TwoFACodeStoreError::TooManyAttempts);
+        }
+
+        Ok(attempts as u32)
+    }
+
+    #[tracing::instrument(name = "Replace_Code", skip_all)]
+    async fn replace_code(
+        &mut self,
+        login_attempt_id: &LoginAttemptId,
+        code_hash: TwoFACodeHash,
+    ) -> Result<(), TwoFACodeStoreError> {
+        let token_key = get_key(TWO_FA_CODE_PREFIX, login_attempt_id);
+        let resends_key = get_key(TWO_FA_RESENDS_PREFIX, login_attempt_id);
+        let cooldown_key = get_key(TWO_FA_COOLDOWN_PREFIX, login_attempt_id);
+        let mut conn = self.conn.write().await;
+
+        let token = conn
+            .get::<_, Option<String>>(&token_key)
+            .wrap_err("Failed to get token.")
+            .map_err(TwoFACodeStoreError::UnexpectedError)?
+            .ok_or(TwoFACodeStoreError::LoginAttemptIdNotFound)?;
+
+        let cooldown_left: i64 = conn
+            .ttl(&cooldown_key)
+            .wrap_err("Failed to check 2FA resend cooldown in Redis.")
+            .map_err(TwoFACodeStoreError::UnexpectedError)?;
+
+        // TTL is negative when the key is gone
+        if cooldown_left > 0 {
+            return Err(TwoFACodeStoreError::ResendTooSoon(cooldown_left as u64));
+        }
+
+        let resends: u32 = conn
+            .get::<_, Option<u32>>(&resends_key)
+            .wrap_err("Failed to get 2FA resends from Redis.")
+            .map_err(TwoFACodeStoreError::UnexpectedError)?
+            .unwrap_or(0);
+
+        if resends >= MAX_2FA_RESENDS {
+            return Err(TwoFACodeStoreError::TooManyResends);
+        }
+
         let data: TwoFATuple = serde_json::from_str(&token)
             .wrap_err("Failed to deserialize 2FA tuple")
             .map_err(TwoFACodeStoreError::UnexpectedError)?;
 
-        let login_attempt_id = LoginAttemptId::parse(Secret::new(data.0))
+        let serialized_data = serde_json::to_string(&TwoFATuple(
+            data.0,
+            code_hash.as_ref().expose_secret().to_owned(),
+        ))
+        .wrap_err("Failed to serialize 2FA tuple.")
+        .map_err(TwoFACodeStoreError::UnexpectedError)?;
+
+        let _: () = conn
+            .set_ex(&token_key, serialized_data, TEN_MINUTES_IN_SECONDS)
+            .wrap_err("Failed to set 2FA code in Redis.")
+            .map_err(TwoFACodeStoreError::UnexpectedError)?;
+
+        let _: () = conn
+            .set_ex(&resends_key, resends + 1, TEN_MINUTES_IN_SECONDS)
+            .wrap_err("Failed to count 2FA resend in Redis.")
             .map_err(TwoFACodeStoreError::UnexpectedError)?;
 
-        let email_code = TwoFACode::parse(data.1).map_err(TwoFACodeStoreError::UnexpectedError)?;
+        // Keep the failed guesses alive as long as the new code
+        let _: () = conn
+            .expire(
+                get_key(TWO_FA_ATTEMPTS_PREFIX, login_attempt_id),
+                TEN_MINUTES_IN_SECONDS as i64,
+            )
+            .wrap_err("Failed to set expiration on 2FA attempts.")
+            .map_err(TwoFACodeStoreError::UnexpectedError)?;
+
+        let _: () = conn
+            .set_ex(&cooldown_key, 1, TWO_FA_RESEND_COOLDOWN_SECONDS as u64)
+            .wrap_err("Failed to set 2FA resend cooldown in Redis.")
+            .map_err(TwoFACodeStoreError::UnexpectedError)?;
 
-        Ok((login_attempt_id, email_code))
+        Ok(())
     }
 }
 
-fn get_key(email: &Email) -> String {
-    format!("{}{}", TWO_FA_CODE_PREFIX, email.as_ref().expose_secret())
+fn parse_code(token: &str) -> Result<(Email, TwoFACodeHash), TwoFACodeStoreError> {
+    let data: TwoFATuple = serde_json::from_str(token)
+        .wrap_err("Failed to deserialize 2FA tuple")
+        .map_err(TwoFACodeStoreError::UnexpectedError)?;
+
+    let email = Email::parse(Secret::new(data.0)).map_err(TwoFACodeStoreError::UnexpectedError)?;
+
+    Ok((email, TwoFACodeHash::from(Secret::new(data.1))))
+}
+
+fn get_key(prefix: &str, login_attempt_id: &LoginAttemptId) -> String {
+    format!("{}{}", prefix, login_attempt_id.as_ref().expose_secret())
 }
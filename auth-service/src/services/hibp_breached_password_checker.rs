@@ -0,0 +1,100 @@
+use crate::{
+    domain::BreachedPasswordChecker,
+    utils::breached_passwords::{read_hashes, read_hashes_from_path, sha1_digest, PasswordHash},
+};
+
+use color_eyre::eyre;
+use secrecy::Secret;
+use std::{io::BufRead, path::Path};
+
+// Every hash of a HIBP dump, sorted for binary search. Exact, but a full dump
+// takes 20 bytes per password in memory; build a bloom filter for those.
+// The default checker knows no breached passwords.
+#[derive(Debug, Default, Clone)]
+pub struct HibpBreachedPasswordChecker {
+    hashes: Vec<PasswordHash>,
+}
+
+impl HibpBreachedPasswordChecker {
+    pub fn new(mut hashes: Vec<PasswordHash>) -> Self {
+        hashes.sort_unstable();
+        hashes.dedup();
+
+        Self { hashes }
+    }
+
+    // A dump file of full `HASH:COUNT` lines, or a directory of range files
+    pub fn load(path: &Path) -> eyre::Result<Self> {
+        let mut hashes = Vec::new();
+        read_hashes_from_path(path, 1, |hash| hashes.push(hash))?;
+
+        Ok(Self::new(hashes))
+    }
+
+    pub fn from_reader(reader: impl BufRead) -> eyre::Result<Self> {
+        let mut hashes = Vec::new();
+        read_hashes(reader, "", 1, |hash| hashes.push(hash))?;
+
+        Ok(Self::new(hashes))
+    }
+
+    pub fn len(&self) -> usize {
+        self.hashes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.hashes.is_empty()
+    }
+}
+
+impl BreachedPasswordChecker for HibpBreachedPasswordChecker {
+    fn is_breached(&self, password: &Secret<String>) -> bool {
+        self.hashes.binary_search(&sha1_digest(password)).is_ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::fs;
+
+    fn secret(password: &str) -> Secret<String> {
+        Secret::new(password.to_owned())
+    }
+
+    #[test]
+    fn should_report_passwords_in_dump() {
+        let checker = HibpBreachedPasswordChecker::from_reader(
+            "7C4A8D09CA3762AF61E59520943DC26494F8941B:37359195\n\
+             5BAA61E4C9B93F3F0682250B6CF8331B7EE68FD8:9545824\n"
+                .as_bytes(),
+        )
+        .unwrap();
+
+        assert_eq!(checker.len(), 2);
+        assert!(checker.is_breached(&secret("password")));
+        assert!(checker.is_breached(&secret("123456")));
+        assert!(!checker.is_breached(&secret("Tr0ub4dor&3-horse")));
+        assert!(!HibpBreachedPasswordChecker::default().is_breached(&secret("password")));
+    }
+
+    #[test]
+    fn should_load_directory_of_range_files() {
+        let dir = std::env::temp_dir().join(format!("hibp-{}", uuid::Uuid::new_v4()));
+        fs::create_dir(&dir).unwrap();
+        fs::write(
+            dir.join("5BAA6.txt"),
+            "1E4C9B93F3F0682250B6CF8331B7EE68FD8:9545824\r\n",
+        )
+        .unwrap();
+        fs::write(dir.join("README"), "Not a range file").unwrap();
+
+        let checker = HibpBreachedPasswordChecker::load(&dir).unwrap();
+
+        assert_eq!(checker.len(), 1);
+        assert!(checker.is_breached(&secret("password")));
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -1,6 +1,11 @@
+pub mod bloom_filter_breached_password_checker;
 pub mod data_stores;
+pub mod hibp_breached_password_checker;
 pub mod mock_email_client;
+pub mod password_hashers;
 pub mod postmark_email_client;
 
+pub use bloom_filter_breached_password_checker::*;
+pub use hibp_breached_password_checker::*;
 pub use mock_email_client::*;
 pub use postmark_email_client::*;
@@ -0,0 +1,124 @@
+use crate::domain::{phc_algorithm, PasswordHasher};
+
+use argon2::{
+    password_hash::{self, rand_core::OsRng, SaltString},
+    Algorithm, Argon2, Params, PasswordHash, PasswordHasher as _, PasswordVerifier, Version,
+};
+use color_eyre::eyre;
+use secrecy::{ExposeSecret, Secret};
+
+// Argon2id, the algorithm every new hash is made with
+#[derive(Debug, Clone)]
+pub struct Argon2PasswordHasher {
+    params: Params,
+}
+
+impl Argon2PasswordHasher {
+    pub fn new(params: Params) -> Self {
+        Self { params }
+    }
+}
+
+impl PasswordHasher for Argon2PasswordHasher {
+    fn can_verify(&self, password_hash: &str) -> bool {
+        matches!(
+            phc_algorithm(password_hash),
+            Some("argon2id" | "argon2i" | "argon2d")
+        )
+    }
+
+    fn hash(&self, password: &Secret<String>) -> eyre::Result<Secret<String>> {
+        let salt = SaltString::generate(&mut OsRng);
+        let password_hash = Argon2::new(Algorithm::Argon2id, Version::V0x13, self.params.clone())
+            .hash_password(password.expose_secret().as_bytes(), &salt)?
+            .to_string();
+
+        Ok(Secret::new(password_hash))
+    }
+
+    // The variant and parameters are read from the hash itself, so hashes made
+    // with older parameters keep verifying
+    fn verify(
+        &self,
+        password_hash: &Secret<String>,
+        password: &Secret<String>,
+    ) -> eyre::Result<bool> {
+        let password_hash = PasswordHash::new(password_hash.expose_secret())?;
+
+        match Argon2::default().verify_password(password.expose_secret().as_bytes(), &password_hash)
+        {
+            Ok(()) => Ok(true),
+            Err(password_hash::Error::Password) => Ok(false),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    fn needs_rehash(&self, password_hash: &str) -> bool {
+        let Ok(hash) = PasswordHash::new(password_hash) else {
+            return true;
+        };
+
+        let Ok(params) = Params::try_from(&hash) else {
+            return true;
+        };
+
+        hash.algorithm != Algorithm::Argon2id.ident()
+            || hash.version != Some(Version::V0x13.into())
+            || params.m_cost() != self.params.m_cost()
+            || params.t_cost() != self.params.t_cost()
+            || params.p_cost() != self.params.p_cost()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hasher(m_cost: u32, t_cost: u32) -> Argon2PasswordHasher {
+        Argon2PasswordHasher::new(Params::new(m_cost, t_cost, 1, None).unwrap())
+    }
+
+    #[test]
+    fn should_verify_own_hashes() {
+        let password = Secret::new("Tr0ub4dor&3-horse".to_owned());
+        let hasher = hasher(4096, 1);
+        let password_hash = hasher.hash(&password).unwrap();
+
+        assert!(hasher.can_verify(password_hash.expose_secret()));
+        assert!(hasher.verify(&password_hash, &password).unwrap());
+        assert!(!hasher
+            .verify(&password_hash, &Secret::new("wrong-password".to_owned()))
+            .unwrap());
+    }
+
+    #[test]
+    fn should_fail_on_malformed_hashes() {
+        let password = Secret::new("Tr0ub4dor&3-horse".to_owned());
+        let hasher = hasher(4096, 1);
+        let password_hash = hasher.hash(&password).unwrap();
+        let malformed = Secret::new(password_hash.expose_secret().replace("m=4096", "m=lots"));
+
+        assert!(hasher.verify(&malformed, &password).is_err());
+    }
+
+    #[test]
+    fn should_only_rehash_outdated_hashes() {
+        let password = Secret::new("Tr0ub4dor&3-horse".to_owned());
+        let current = hasher(4096, 2);
+        let weaker = hasher(2048, 1).hash(&password).unwrap();
+        let salt = SaltString::generate(&mut OsRng);
+        let argon2i = Argon2::new(
+            Algorithm::Argon2i,
+            Version::V0x13,
+            Params::new(4096, 2, 1, None).unwrap(),
+        )
+        .hash_password(password.expose_secret().as_bytes(), &salt)
+        .unwrap()
+        .to_string();
+
+        assert!(!current.needs_rehash(current.hash(&password).unwrap().expose_secret()));
+        assert!(current.needs_rehash(weaker.expose_secret()));
+        assert!(current.needs_rehash(&argon2i));
+        assert!(current.verify(&weaker, &password).unwrap());
+    }
+}
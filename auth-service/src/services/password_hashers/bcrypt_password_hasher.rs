@@ -0,0 +1,77 @@
+use crate::domain::{phc_algorithm, PasswordHasher};
+
+use color_eyre::eyre;
+use secrecy::{ExposeSecret, Secret};
+
+// Verifies bcrypt hashes imported from legacy systems
+#[derive(Debug, Clone)]
+pub struct BcryptPasswordHasher {
+    cost: u32,
+}
+
+impl BcryptPasswordHasher {
+    pub fn new(cost: u32) -> Self {
+        Self { cost }
+    }
+}
+
+impl Default for BcryptPasswordHasher {
+    fn default() -> Self {
+        Self::new(bcrypt::DEFAULT_COST)
+    }
+}
+
+impl PasswordHasher for BcryptPasswordHasher {
+    fn can_verify(&self, password_hash: &str) -> bool {
+        matches!(
+            phc_algorithm(password_hash),
+            Some("2a" | "2b" | "2x" | "2y")
+        )
+    }
+
+    fn hash(&self, password: &Secret<String>) -> eyre::Result<Secret<String>> {
+        Ok(Secret::new(bcrypt::hash(
+            password.expose_secret(),
+            self.cost,
+        )?))
+    }
+
+    fn verify(
+        &self,
+        password_hash: &Secret<String>,
+        password: &Secret<String>,
+    ) -> eyre::Result<bool> {
+        Ok(bcrypt::verify(
+            password.expose_secret(),
+            password_hash.expose_secret(),
+        )?)
+    }
+
+    fn needs_rehash(&self, password_hash: &str) -> bool {
+        password_hash
+            .parse::<bcrypt::HashParts>()
+            .map(|parts| parts.get_cost() < self.cost)
+            .unwrap_or(true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_verify_legacy_hashes() {
+        let password = Secret::new("Tr0ub4dor&3-horse".to_owned());
+        let hasher = BcryptPasswordHasher::new(4);
+        // As written by PHP's `password_hash` and most bcrypt libraries
+        let password_hash = hasher.hash(&password).unwrap();
+
+        assert!(password_hash.expose_secret().starts_with("$2b$04$"));
+        assert!(hasher.can_verify(password_hash.expose_secret()));
+        assert!(hasher.verify(&password_hash, &password).unwrap());
+        assert!(!hasher
+            .verify(&password_hash, &Secret::new("wrong-password".to_owned()))
+            .unwrap());
+        assert!(BcryptPasswordHasher::new(10).needs_rehash(password_hash.expose_secret()));
+    }
+}
@@ -0,0 +1,9 @@
+pub mod argon2_password_hasher;
+pub mod bcrypt_password_hasher;
+pub mod phc_password_hasher;
+pub mod scrypt_password_hasher;
+
+pub use argon2_password_hasher::*;
+pub use bcrypt_password_hasher::*;
+pub use phc_password_hasher::*;
+pub use scrypt_password_hasher::*;
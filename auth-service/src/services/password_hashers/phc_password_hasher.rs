@@ -0,0 +1,87 @@
+use crate::domain::PasswordHasher;
+
+use color_eyre::eyre::{self, eyre};
+use secrecy::{ExposeSecret, Secret};
+
+use super::{BcryptPasswordHasher, ScryptPasswordHasher};
+
+type BoxedPasswordHasher = Box<dyn PasswordHasher + Send + Sync>;
+
+// Makes new hashes with the current hasher, and verifies each stored hash with
+// whichever hasher its prefix names. Hashes from any other hasher, like those
+// imported from a legacy system, need rehashing.
+pub struct PhcPasswordHasher {
+    current: BoxedPasswordHasher,
+    legacy: Vec<BoxedPasswordHasher>,
+}
+
+impl PhcPasswordHasher {
+    // Also verifies bcrypt and scrypt hashes
+    pub fn new(current: impl PasswordHasher + Send + Sync + 'static) -> Self {
+        Self {
+            current: Box::new(current),
+            legacy: vec![
+                Box::new(BcryptPasswordHasher::default()),
+                Box::new(ScryptPasswordHasher::default()),
+            ],
+        }
+    }
+
+    fn hasher_for(&self, password_hash: &str) -> Option<&BoxedPasswordHasher> {
+        std::iter::once(&self.current)
+            .chain(&self.legacy)
+            .find(|hasher| hasher.can_verify(password_hash))
+    }
+}
+
+impl PasswordHasher for PhcPasswordHasher {
+    fn can_verify(&self, password_hash: &str) -> bool {
+        self.hasher_for(password_hash).is_some()
+    }
+
+    fn hash(&self, password: &Secret<String>) -> eyre::Result<Secret<String>> {
+        self.current.hash(password)
+    }
+
+    fn verify(
+        &self,
+        password_hash: &Secret<String>,
+        password: &Secret<String>,
+    ) -> eyre::Result<bool> {
+        self.hasher_for(password_hash.expose_secret())
+            .ok_or_else(|| eyre!("Unsupported password hash format"))?
+            .verify(password_hash, password)
+    }
+
+    fn needs_rehash(&self, password_hash: &str) -> bool {
+        !self.current.can_verify(password_hash) || self.current.needs_rehash(password_hash)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::services::password_hashers::Argon2PasswordHasher;
+
+    #[test]
+    fn should_dispatch_on_hash_prefix() {
+        let password = Secret::new("Tr0ub4dor&3-horse".to_owned());
+        let hasher = PhcPasswordHasher::new(Argon2PasswordHasher::new(
+            argon2::Params::new(4096, 1, 1, None).unwrap(),
+        ));
+        let current = hasher.hash(&password).unwrap();
+        let legacy = BcryptPasswordHasher::new(4).hash(&password).unwrap();
+
+        assert!(current.expose_secret().starts_with("$argon2id$"));
+        assert!(!hasher.needs_rehash(current.expose_secret()));
+
+        assert!(hasher.verify(&legacy, &password).unwrap());
+        assert!(hasher.needs_rehash(legacy.expose_secret()));
+
+        let unknown = Secret::new("$md5$abc$def".to_owned());
+
+        assert!(!hasher.can_verify(unknown.expose_secret()));
+        assert!(hasher.verify(&unknown, &password).is_err());
+    }
+}
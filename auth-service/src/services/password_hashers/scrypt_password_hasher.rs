@@ -0,0 +1,98 @@
+use crate::domain::{phc_algorithm, PasswordHasher};
+
+use color_eyre::eyre;
+use scrypt::{
+    password_hash::{
+        self, rand_core::OsRng, PasswordHash, PasswordHasher as _, PasswordVerifier, SaltString,
+    },
+    Params, Scrypt,
+};
+use secrecy::{ExposeSecret, Secret};
+
+// Verifies scrypt hashes imported from legacy systems, in PHC format
+// (`$scrypt$ln=17,r=8,p=1$<salt>$<hash>`)
+#[derive(Debug, Clone)]
+pub struct ScryptPasswordHasher {
+    params: Params,
+}
+
+impl ScryptPasswordHasher {
+    pub fn new(params: Params) -> Self {
+        Self { params }
+    }
+}
+
+impl Default for ScryptPasswordHasher {
+    fn default() -> Self {
+        Self::new(Params::recommended())
+    }
+}
+
+impl PasswordHasher for ScryptPasswordHasher {
+    fn can_verify(&self, password_hash: &str) -> bool {
+        phc_algorithm(password_hash) == Some("scrypt")
+    }
+
+    fn hash(&self, password: &Secret<String>) -> eyre::Result<Secret<String>> {
+        let salt = SaltString::generate(&mut OsRng);
+        let password_hash = Scrypt
+            .hash_password_customized(
+                password.expose_secret().as_bytes(),
+                None,
+                None,
+                self.params,
+                &salt,
+            )?
+            .to_string();
+
+        Ok(Secret::new(password_hash))
+    }
+
+    fn verify(
+        &self,
+        password_hash: &Secret<String>,
+        password: &Secret<String>,
+    ) -> eyre::Result<bool> {
+        let password_hash = PasswordHash::new(password_hash.expose_secret())?;
+
+        match Scrypt.verify_password(password.expose_secret().as_bytes(), &password_hash) {
+            Ok(()) => Ok(true),
+            Err(password_hash::Error::Password) => Ok(false),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    fn needs_rehash(&self, password_hash: &str) -> bool {
+        PasswordHash::new(password_hash)
+            .ok()
+            .and_then(|hash| Params::try_from(&hash).ok())
+            .map(|params| {
+                params.log_n() < self.params.log_n()
+                    || params.r() < self.params.r()
+                    || params.p() < self.params.p()
+            })
+            .unwrap_or(true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_verify_legacy_hashes() {
+        let password = Secret::new("Tr0ub4dor&3-horse".to_owned());
+        let hasher = ScryptPasswordHasher::new(Params::new(10, 8, 1, 32).unwrap());
+        let password_hash = hasher.hash(&password).unwrap();
+
+        assert!(password_hash
+            .expose_secret()
+            .starts_with("$scrypt$ln=10,r=8,p=1$"));
+        assert!(hasher.can_verify(password_hash.expose_secret()));
+        assert!(hasher.verify(&password_hash, &password).unwrap());
+        assert!(!hasher
+            .verify(&password_hash, &Secret::new("wrong-password".to_owned()))
+            .unwrap());
+        assert!(ScryptPasswordHasher::default().needs_rehash(password_hash.expose_secret()));
+    }
+}
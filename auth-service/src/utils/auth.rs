@@ -1,16 +1,84 @@
-use super::constants::{JWT_COOKIE_NAME, JWT_SECRET, TOKEN_TTL_SECONDS};
-use crate::{app_state::app_state::BannedTokenStoreType, domain::email::Email};
-
-use axum_extra::extract::cookie::{Cookie, SameSite};
+use super::{
+    client_info::ClientInfo,
+    constants::{
+        JWT_AUDIENCE, JWT_COOKIE_NAME, JWT_ISSUER, JWT_SECRET, JWT_SIGNING_KEY_PATH,
+        REFRESH_COOKIE_NAME, REFRESH_TOKEN_TTL_SECONDS, SESSION_TOUCH_INTERVAL_SECONDS,
+        TOKEN_TTL_SECONDS,
+    },
+    keyring::reload_keyring_if_stale,
+    signing_key::SigningKey,
+};
+use crate::{
+    app_state::app_state::{AppState, KeyringType, RefreshTokenStoreType},
+    domain::{
+        data_stores::{RefreshToken, RefreshTokenRecord, Session},
+        error::AuthAPIError,
+        user::User,
+        user_id::UserId,
+    },
+};
+
+use axum_extra::extract::{
+    cookie::{Cookie, SameSite},
+    CookieJar,
+};
 use chrono::Utc;
 use color_eyre::eyre::{self, Context};
-use jsonwebtoken::{decode, encode, DecodingKey, EncodingKey, Validation};
+use jsonwebtoken::{decode, decode_header, encode, Validation};
 use secrecy::{ExposeSecret, Secret};
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use uuid::Uuid;
+
+// Initial signing key: the PEM file from JWT_SIGNING_KEY_PATH if set, otherwise JWT_SECRET
+pub fn load_signing_key() -> SigningKey {
+    match JWT_SIGNING_KEY_PATH.as_ref() {
+        Some(path) => SigningKey::from_pem_file(path).expect("Failed to load JWT signing key."),
+        None => SigningKey::from_secret(&JWT_SECRET),
+    }
+}
+
+// Record a new session for the user and create the JWT and refresh cookies bound to it
+pub async fn start_session(
+    user: &User,
+    client: &ClientInfo,
+    state: &AppState,
+) -> eyre::Result<(Cookie<'static>, Cookie<'static>)> {
+    let session = Session::new(
+        *user.id(),
+        client.device(),
+        client.ip_address.clone(),
+        client.user_agent.clone(),
+    );
+    let session_id = session.id.clone();
+
+    state
+        .session_store
+        .write()
+        .await
+        .add_session(session)
+        .await
+        .wrap_err("Failed to store session.")?;
+
+    let auth_cookie = generate_auth_cookie(user, &session_id, state.keyring.clone()).await?;
+
+    let refresh_cookie = generate_refresh_cookie(
+        RefreshTokenRecord::new(*user.id(), user.token_generation(), session_id),
+        state.refresh_token_store.clone(),
+    )
+    .await?;
+
+    Ok((auth_cookie, refresh_cookie))
+}
 
 // Create cookie with a new JWT auth token
-pub fn generate_auth_cookie(email: &Email) -> eyre::Result<Cookie<'static>> {
-    let token = generate_auth_token(email)?;
+pub async fn generate_auth_cookie(
+    user: &User,
+    session_id: &str,
+    keyring: KeyringType,
+) -> eyre::Result<Cookie<'static>> {
+    let token = generate_auth_token(user, session_id, keyring).await?;
 
     Ok(create_auth_cookie(token))
 }
@@ -26,12 +94,49 @@ fn create_auth_cookie(token: String) -> Cookie<'static> {
     cookie
 }
 
-fn generate_auth_token(email: &Email) -> eyre::Result<String> {
+// Create cookie with a new opaque refresh token, stored under the given record
+pub async fn generate_refresh_cookie(
+    record: RefreshTokenRecord,
+    refresh_token_store: RefreshTokenStoreType,
+) -> eyre::Result<Cookie<'static>> {
+    let token = RefreshToken::default();
+
+    refresh_token_store
+        .write()
+        .await
+        .add_token(token.clone(), record)
+        .await
+        .wrap_err("Failed to store refresh token.")?;
+
+    Ok(create_refresh_cookie(
+        token.as_ref().expose_secret().to_owned(),
+    ))
+}
+
+// Create refresh cookie. Unlike the JWT cookie it outlives the browser session.
+fn create_refresh_cookie(token: String) -> Cookie<'static> {
+    let cookie = Cookie::build((REFRESH_COOKIE_NAME, token))
+        .path("/")
+        .http_only(true)
+        .same_site(SameSite::Lax)
+        .max_age(time::Duration::seconds(REFRESH_TOKEN_TTL_SECONDS))
+        .build();
+
+    cookie
+}
+
+async fn generate_auth_token(
+    user: &User,
+    session_id: &str,
+    keyring: KeyringType,
+) -> eyre::Result<String> {
     let delta = chrono::Duration::try_seconds(TOKEN_TTL_SECONDS)
         .ok_or(eyre::eyre!("Failed to create 10 minute time delta."))?;
 
+    let now = Utc::now();
+
     // Create JWT expiration time
-    let exp = Utc::now()
+    let exp = now
         .checked_add_signed(delta)
         .ok_or(eyre::eyre!("failed to add 10 minutes to current time"))?
         .timestamp();
@@ -42,55 +147,169 @@ fn generate_auth_token(email: &Email) -> eyre::Result<String> {
         exp
     ))?;
 
-    let sub = email.as_ref().expose_secret().to_owned();
-
-    let claims = Claims { sub, exp };
+    let iat: usize = now
+        .timestamp()
+        .try_into()
+        .wrap_err("failed to cast iat to usize")?;
+
+    let claims = Claims {
+        sub: user.id().to_string(),
+        iss: JWT_ISSUER.to_owned(),
+        aud: JWT_AUDIENCE.to_owned(),
+        exp,
+        iat,
+        nbf: iat,
+        jti: Uuid::new_v4().to_string(),
+        generation: user.token_generation(),
+        sid: session_id.to_owned(),
+        scope: None,
+        custom: HashMap::new(),
+    };
 
-    create_token(&claims)
+    create_token(&claims, keyring.read().await.active())
 }
 
-// Check if JWT auth token is valid by decoding it using the JWT secret
-pub async fn validate_token(
-    token: &str,
-    banned_token_store: BannedTokenStoreType,
-) -> eyre::Result<Claims> {
-    match banned_token_store
+// Check if JWT auth token is valid by decoding it with the key named in its kid header,
+// then make sure it has not been banned, issued before the user's last logout-all,
+// or issued to a session that has since been revoked
+pub async fn validate_token(token: &str, state: &AppState) -> eyre::Result<Claims> {
+    let header = decode_header(token).wrap_err("Failed to decode token header.")?;
+
+    // The key may have been rotated in on another replica since the last reload
+    if state
+        .keyring
         .read()
         .await
-        .contains_token(&Secret::new(token.to_owned()))
+        .find(header.kid.as_deref())
+        .is_none()
+    {
+        reload_keyring_if_stale(&state.keyring, &state.signing_key_store).await?;
+    }
+
+    let claims = {
+        let keyring = state.keyring.read().await;
+        let key = keyring
+            .find(header.kid.as_deref())
+            .ok_or(eyre::eyre!("Token signed with an unknown key"))?;
+
+        decode::<Claims>(token, key.decoding_key(), &validation(key))
+            .map(|data| data.claims)
+            .wrap_err("Failed to decode token.")?
+    };
+
+    if state
+        .banned_token_store
+        .read()
         .await
+        .contains_token(&claims.jti)
+        .await?
     {
-        Ok(value) => {
-            if value {
-                return Err(eyre::eyre!("Token is banned"));
-            }
-        }
-        Err(e) => return Err(e.into()),
+        return Err(eyre::eyre!("Token is banned"));
     }
 
-    decode::<Claims>(
-        token,
-        &DecodingKey::from_secret(JWT_SECRET.expose_secret().as_bytes()),
-        &Validation::default(),
-    )
-    .map(|data| data.claims)
-    .wrap_err("Failed to decode token.")
+    let user_id = UserId::parse(&claims.sub)?;
+    let user = state
+        .user_store
+        .read()
+        .await
+        .get_user_by_id(&user_id)
+        .await
+        .wrap_err("Token subject not found.")?;
+
+    if claims.generation != user.token_generation() {
+        return Err(eyre::eyre!("Token generation has been revoked"));
+    }
+
+    let session = state
+        .session_store
+        .read()
+        .await
+        .get_session(&claims.sid)
+        .await
+        .wrap_err("Token session is not active.")?;
+
+    if session.user_id != user_id {
+        return Err(eyre::eyre!("Token session belongs to another user"));
+    }
+
+    // Only write last-seen times every so often rather than on every request
+    let now = Utc::now();
+    if now - session.last_seen_at >= chrono::Duration::seconds(SESSION_TOUCH_INTERVAL_SECONDS) {
+        state
+            .session_store
+            .write()
+            .await
+            .touch_session(&session.id, now)
+            .await
+            .wrap_err("Failed to update session last seen time.")?;
+    }
+
+    Ok(claims)
 }
 
-// Create JWT auth token by encoding claims using the JWT secret
-fn create_token(claims: &Claims) -> eyre::Result<String> {
-    encode(
-        &jsonwebtoken::Header::default(),
-        &claims,
-        &EncodingKey::from_secret(JWT_SECRET.expose_secret().as_bytes()),
-    )
-    .wrap_err("Failed to create token.")
+// Validates the JWT cookie of a request made on behalf of a signed in user
+pub async fn authenticate(
+    cookie_jar: &CookieJar,
+    state: &AppState,
+) -> Result<(Claims, UserId), AuthAPIError> {
+    let cookie = cookie_jar
+        .get(JWT_COOKIE_NAME)
+        .ok_or(AuthAPIError::MissingToken)?;
+
+    let claims = validate_token(cookie.value(), state)
+        .await
+        .map_err(|_| AuthAPIError::InvalidToken)?;
+
+    let user_id = UserId::parse(&claims.sub).map_err(AuthAPIError::UnexpectedError)?;
+
+    Ok((claims, user_id))
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+// Only accept tokens issued by this service for the configured audience
+fn validation(key: &SigningKey) -> Validation {
+    let mut validation = Validation::new(key.algorithm());
+
+    validation.set_issuer(&[JWT_ISSUER.as_str()]);
+    validation.set_audience(&[JWT_AUDIENCE.as_str()]);
+    validation.set_required_spec_claims(&["exp", "nbf", "iss", "aud", "sub"]);
+    validation.validate_nbf = true;
+
+    validation
+}
+
+// Create JWT auth token by encoding claims using the given signing key
+fn create_token(claims: &Claims, key: &SigningKey) -> eyre::Result<String> {
+    encode(&key.header(), &claims, key.encoding_key()).wrap_err("Failed to create token.")
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct Claims {
+    // User id, which stays the same when the user changes their email
     pub sub: String,
+    pub iss: String,
+    pub aud: String,
     pub exp: usize,
+    pub iat: usize,
+    pub nbf: usize,
+    // Unique token id
+    pub jti: String,
+    // User's token generation when the token was issued
+    #[serde(rename = "gen")]
+    pub generation: u32,
+    // Session the token was issued to
+    pub sid: String,
+    // Space-separated scope values, as in RFC 6749
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub scope: Option<String>,
+    // Any claims not listed above
+    #[serde(flatten)]
+    pub custom: HashMap<String, serde_json::Value>,
+}
+
+// Compares a presented credential with the expected secret. Comparing digests
+// keeps the comparison time independent of where the two values differ.
+pub fn credentials_match(presented: &str, expected: &Secret<String>) -> bool {
+    Sha256::digest(presented.as_bytes()) == Sha256::digest(expected.expose_secret().as_bytes())
 }
 
 #[cfg(test)]
@@ -100,15 +319,92 @@ mod tests {
     use tokio::sync::RwLock;
 
     use crate::{
-        domain::data_stores::BannedTokenStore, services::data_stores::HashsetBannedTokenStore,
+        api::helpers::get_random_password,
+        domain::{data_stores::RefreshTokenStore, Email, Password, TwoFAMethod},
+        services::{
+            data_stores::{
+                HashmapEmailVerificationTokenStore, HashmapPasskeyChallengeStore,
+                HashmapPasskeyStore, HashmapPasswordResetTokenStore, HashmapRateLimitStore,
+                HashmapRecoveryCodeStore, HashmapRefreshTokenStore, HashmapSessionStore,
+                HashmapSigningKeyStore, HashmapTwoFACodeStore, HashmapUserStore,
+                HashsetBannedTokenStore,
+            },
+            HibpBreachedPasswordChecker, MockEmailClient,
+        },
+        utils::keyring::Keyring,
     };
 
     use super::*;
+    use crate::utils::keyring::rotate_keyring;
+
+    fn keyring() -> KeyringType {
+        let key = SigningKey::from_secret(&Secret::new("secret".to_owned()));
+
+        Arc::new(RwLock::new(Keyring::new(key)))
+    }
+
+    fn app_state() -> AppState {
+        AppState::new(
+            Arc::new(RwLock::new(HashmapUserStore::default())),
+            Arc::new(RwLock::new(HashsetBannedTokenStore::default())),
+            Arc::new(RwLock::new(HashmapTwoFACodeStore::new(Secret::new(
+                "two-fa-code-hash-key".to_owned(),
+            )))),
+            Arc::new(RwLock::new(MockEmailClient)),
+            Arc::new(RwLock::new(HashmapRefreshTokenStore::default())),
+            keyring(),
+            Arc::new(RwLock::new(HashmapSessionStore::default())),
+            Arc::new(RwLock::new(HashmapRecoveryCodeStore::default())),
+            Arc::new(RwLock::new(HashmapPasskeyStore::default())),
+            Arc::new(RwLock::new(HashmapPasskeyChallengeStore::default())),
+            Arc::new(RwLock::new(HashmapPasswordResetTokenStore::default())),
+            Arc::new(RwLock::new(HashmapEmailVerificationTokenStore::default())),
+            Arc::new(RwLock::new(HibpBreachedPasswordChecker::default())),
+            Arc::new(RwLock::new(HashmapRateLimitStore::default())),
+            Arc::new(RwLock::new(HashmapSigningKeyStore::default())),
+        )
+    }
+
+    // Signed up user with an active session, whose tokens validate against the given state
+    async fn add_user(state: &AppState) -> (User, String) {
+        let email = Email::parse(Secret::new("test@example.com".to_owned())).unwrap();
+        let password = Password::parse(get_random_password()).unwrap();
+        let user = User::new(email, password, TwoFAMethod::None);
+
+        state
+            .user_store
+            .write()
+            .await
+            .add_user(user.clone())
+            .await
+            .unwrap();
+
+        let session = Session::new(
+            *user.id(),
+            "Unknown device".to_owned(),
+            "127.0.0.1".to_owned(),
+            "Unknown".to_owned(),
+        );
+        let sid = session.id.clone();
+
+        state
+            .session_store
+            .write()
+            .await
+            .add_session(session)
+            .await
+            .unwrap();
+
+        (user, sid)
+    }
 
     #[tokio::test]
     async fn test_generate_auth_cookie() {
-        let email = Email::parse(Secret::new("test@example.com".to_owned())).unwrap();
-        let cookie = generate_auth_cookie(&email).unwrap();
+        let state = app_state();
+        let (user, sid) = add_user(&state).await;
+        let cookie = generate_auth_cookie(&user, &sid, state.keyring)
+            .await
+            .unwrap();
 
         assert_eq!(cookie.name(), JWT_COOKIE_NAME);
         assert_eq!(cookie.value().split('.').count(), 3);
@@ -129,21 +425,54 @@ mod tests {
         assert_eq!(cookie.same_site(), Some(SameSite::Lax));
     }
 
+    #[tokio::test]
+    async fn test_generate_refresh_cookie() {
+        let record = RefreshTokenRecord::new(UserId::default(), 0, Uuid::new_v4().to_string());
+        let refresh_token_store = Arc::new(RwLock::new(HashmapRefreshTokenStore::default()));
+
+        let cookie = generate_refresh_cookie(record.clone(), refresh_token_store.clone())
+            .await
+            .unwrap();
+
+        assert_eq!(cookie.name(), REFRESH_COOKIE_NAME);
+        assert_eq!(cookie.path(), Some("/"));
+        assert_eq!(cookie.http_only(), Some(true));
+        assert_eq!(
+            cookie.max_age(),
+            Some(time::Duration::seconds(REFRESH_TOKEN_TTL_SECONDS))
+        );
+
+        let token = RefreshToken::from(Secret::new(cookie.value().to_owned()));
+        let stored = refresh_token_store
+            .write()
+            .await
+            .consume_token(&token)
+            .await
+            .unwrap();
+
+        assert_eq!(stored, record);
+    }
+
     #[tokio::test]
     async fn test_generate_auth_token() {
-        let email = Email::parse(Secret::new("test@example.com".to_owned())).unwrap();
-        let result = generate_auth_token(&email).unwrap();
+        let state = app_state();
+        let (user, sid) = add_user(&state).await;
+        let result = generate_auth_token(&user, &sid, state.keyring)
+            .await
+            .unwrap();
 
         assert_eq!(result.split('.').count(), 3);
     }
 
     #[tokio::test]
     async fn test_validate_token_with_valid_token() {
-        let email = Email::parse(Secret::new("test@example.com".to_owned())).unwrap();
-        let token = generate_auth_token(&email).unwrap();
-        let banned_token_store = Arc::new(RwLock::new(HashsetBannedTokenStore::default()));
-        let result = validate_token(&token, banned_token_store).await.unwrap();
-        assert_eq!(result.sub, "test@example.com");
+        let state = app_state();
+        let (user, sid) = add_user(&state).await;
+        let token = generate_auth_token(&user, &sid, state.keyring.clone())
+            .await
+            .unwrap();
+        let result = validate_token(&token, &state).await.unwrap();
+        assert_eq!(result.sub, user.id().to_string());
 
         let exp = Utc::now()
             .checked_add_signed(chrono::Duration::try_minutes(9).expect("valid duration"))
@@ -151,27 +480,246 @@ mod tests {
             .timestamp();
 
         assert!(result.exp > exp as usize);
+        assert!(result.iat <= Utc::now().timestamp() as usize);
+        assert_eq!(result.nbf, result.iat);
+        assert_eq!(result.iss, *JWT_ISSUER);
+        assert_eq!(result.aud, *JWT_AUDIENCE);
+        assert_eq!(result.generation, user.token_generation());
+    }
+
+    #[tokio::test]
+    async fn test_generate_auth_token_uses_unique_jti() {
+        let state = app_state();
+        let (user, sid) = add_user(&state).await;
+
+        let mut jtis = Vec::new();
+        for _ in 0..2 {
+            let token = generate_auth_token(&user, &sid, state.keyring.clone())
+                .await
+                .unwrap();
+            let claims = validate_token(&token, &state).await.unwrap();
+            jtis.push(claims.jti);
+        }
+
+        assert_ne!(jtis[0], jtis[1]);
+    }
+
+    #[tokio::test]
+    async fn test_validate_token_rejects_wrong_issuer_or_audience() {
+        let state = app_state();
+        let (user, sid) = add_user(&state).await;
+
+        let token = generate_auth_token(&user, &sid, state.keyring.clone())
+            .await
+            .unwrap();
+        let claims = validate_token(&token, &state).await.unwrap();
+
+        let wrong_issuer = Claims {
+            iss: "someone-else".to_owned(),
+            ..claims.clone()
+        };
+        let wrong_audience = Claims {
+            aud: "another-service".to_owned(),
+            ..claims
+        };
+
+        for claims in [wrong_issuer, wrong_audience] {
+            let token = create_token(&claims, state.keyring.read().await.active()).unwrap();
+            let result = validate_token(&token, &state).await;
+
+            assert!(result.is_err());
+        }
     }
 
     #[tokio::test]
     async fn test_validate_token_with_invalid_token() {
         let token = "invalid_token".to_owned();
-        let banned_token_store = Arc::new(RwLock::new(HashsetBannedTokenStore::default()));
-        let result = validate_token(&token, banned_token_store).await;
+        let result = validate_token(&token, &app_state()).await;
 
         assert!(result.is_err());
     }
 
     #[tokio::test]
     async fn test_validate_token_with_banned_token() {
+        let state = app_state();
+        let (user, sid) = add_user(&state).await;
+        let token = generate_auth_token(&user, &sid, state.keyring.clone())
+            .await
+            .unwrap();
+        let claims = validate_token(&token, &state).await.unwrap();
+
+        state
+            .banned_token_store
+            .write()
+            .await
+            .add_token(&claims.jti, claims.exp)
+            .await
+            .unwrap();
+        let result = validate_token(&token, &state).await;
+
+        assert!(result.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_validate_token_from_older_generation() {
+        let state = app_state();
+        let (user, sid) = add_user(&state).await;
+        let token = generate_auth_token(&user, &sid, state.keyring.clone())
+            .await
+            .unwrap();
+
+        state
+            .user_store
+            .write()
+            .await
+            .increment_token_generation(user.id())
+            .await
+            .unwrap();
+
+        let result = validate_token(&token, &state).await;
+        assert!(result.is_err());
+
+        // Tokens issued after the bump carry the new generation
+        let user = state
+            .user_store
+            .read()
+            .await
+            .get_user_by_id(user.id())
+            .await
+            .unwrap();
+        let token = generate_auth_token(&user, &sid, state.keyring.clone())
+            .await
+            .unwrap();
+
+        assert!(validate_token(&token, &state).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_validate_token_for_unknown_user() {
+        let state = app_state();
         let email = Email::parse(Secret::new("test@example.com".to_owned())).unwrap();
-        let token = generate_auth_token(&email).unwrap();
-        let mut hs = HashsetBannedTokenStore::default();
+        let password = Password::parse(get_random_password()).unwrap();
+        let user = User::new(email, password, TwoFAMethod::None);
+        let sid = Uuid::new_v4().to_string();
 
-        hs.add_token(Secret::new(token.clone())).await.unwrap();
-        let banned_token_store = Arc::new(RwLock::new(hs));
-        let result = validate_token(&token, banned_token_store).await;
+        let token = generate_auth_token(&user, &sid, state.keyring.clone())
+            .await
+            .unwrap();
+        let result = validate_token(&token, &state).await;
 
         assert!(result.is_err());
     }
+
+    #[tokio::test]
+    async fn test_validate_token_after_key_rotation() {
+        let state = app_state();
+        let (user, sid) = add_user(&state).await;
+
+        let old_token = generate_auth_token(&user, &sid, state.keyring.clone())
+            .await
+            .unwrap();
+        rotate_keyring(&state.keyring, &state.signing_key_store)
+            .await
+            .unwrap();
+        let new_token = generate_auth_token(&user, &sid, state.keyring.clone())
+            .await
+            .unwrap();
+
+        assert_ne!(
+            decode_header(&old_token).unwrap().kid,
+            decode_header(&new_token).unwrap().kid
+        );
+
+        // Tokens signed by the retired key stay valid until they expire
+        for token in [old_token, new_token] {
+            let result = validate_token(&token, &state).await;
+            assert_eq!(result.unwrap().sub, user.id().to_string());
+        }
+    }
+
+    #[test]
+    fn test_credentials_match() {
+        let expected = Secret::new("client_secret".to_owned());
+
+        assert!(credentials_match("client_secret", &expected));
+        assert!(!credentials_match("client_secre", &expected));
+        assert!(!credentials_match("", &expected));
+    }
+
+    #[tokio::test]
+    async fn test_validate_token_with_unknown_kid() {
+        let state = app_state();
+        let (user, sid) = add_user(&state).await;
+        let token = generate_auth_token(&user, &sid, keyring()).await.unwrap();
+
+        // A different keyring never issued this kid
+        let state = AppState {
+            keyring: Arc::new(RwLock::new(Keyring::new(
+                SigningKey::generate(jsonwebtoken::Algorithm::HS256).unwrap(),
+            ))),
+            ..state
+        };
+        let result = validate_token(&token, &state).await;
+
+        assert!(result.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_validate_token_for_revoked_session() {
+        let state = app_state();
+        let (user, sid) = add_user(&state).await;
+        let token = generate_auth_token(&user, &sid, state.keyring.clone())
+            .await
+            .unwrap();
+
+        assert_eq!(validate_token(&token, &state).await.unwrap().sid, sid);
+
+        state
+            .session_store
+            .write()
+            .await
+            .revoke_session(&sid)
+            .await
+            .unwrap();
+
+        assert!(validate_token(&token, &state).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_start_session() {
+        let state = app_state();
+        let (user, _) = add_user(&state).await;
+        let client = ClientInfo {
+            ip_address: "10.0.0.1".to_owned(),
+            user_agent: "Mozilla/5.0 (X11; Linux x86_64; rv:131.0) Gecko/20100101 Firefox/131.0"
+                .to_owned(),
+        };
+
+        let (auth_cookie, refresh_cookie) = start_session(&user, &client, &state).await.unwrap();
+
+        let claims = validate_token(auth_cookie.value(), &state).await.unwrap();
+        let session = state
+            .session_store
+            .read()
+            .await
+            .get_session(&claims.sid)
+            .await
+            .unwrap();
+
+        assert_eq!(session.user_id, *user.id());
+        assert_eq!(session.device, "Firefox on Linux");
+        assert_eq!(session.ip_address, "10.0.0.1");
+        assert_eq!(session.user_agent, client.user_agent);
+
+        let token = RefreshToken::from(Secret::new(refresh_cookie.value().to_owned()));
+        let record = state
+            .refresh_token_store
+            .write()
+            .await
+            .consume_token(&token)
+            .await
+            .unwrap();
+
+        assert_eq!(record.session_id, claims.sid);
+    }
 }
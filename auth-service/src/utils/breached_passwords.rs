@@ -0,0 +1,306 @@
+use color_eyre::eyre::{self, eyre, WrapErr};
+use secrecy::{ExposeSecret, Secret};
+use sha1::{Digest, Sha1};
+use std::{
+    fs::{self, File},
+    io::{BufRead, BufReader, BufWriter, Read, Write},
+    path::Path,
+};
+
+pub type PasswordHash = [u8; 20];
+
+// Range files from the HIBP API are named after the 5 hex characters every hash
+// in them starts with
+const RANGE_PREFIX_LENGTH: usize = 5;
+
+// Identifies filters written by `BloomFilter::save`, followed by a format version
+const BLOOM_FILTER_MAGIC: &[u8; 8] = b"BPWBLOOM";
+const BLOOM_FILTER_VERSION: u32 = 1;
+
+pub fn sha1_digest(password: &Secret<String>) -> PasswordHash {
+    Sha1::digest(password.expose_secret().as_bytes()).into()
+}
+
+// One line of a dump, `<HASH>:<COUNT>` with the full hash or, in a range file,
+// only the part after the prefix. The count is optional.
+fn parse_line(line: &str, prefix: &str) -> eyre::Result<Option<(PasswordHash, u64)>> {
+    let line = line.trim();
+
+    if line.is_empty() {
+        return Ok(None);
+    }
+
+    let (hash, count) = match line.split_once(':') {
+        Some((hash, count)) => (
+            hash,
+            count
+                .trim()
+                .parse()
+                .wrap_err_with(|| format!("Invalid count in line: {}", line))?,
+        ),
+        None => (line, 1),
+    };
+
+    let hex = format!("{}{}", prefix, hash.trim());
+
+    if hex.len() != 40 {
+        return Err(eyre!("Expected a SHA-1 hash in line: {}", line));
+    }
+
+    let mut digest = [0; 20];
+
+    for (i, byte) in digest.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(&hex[i * 2..i * 2 + 2], 16)
+            .wrap_err_with(|| format!("Invalid hex in line: {}", line))?;
+    }
+
+    Ok(Some((digest, count)))
+}
+
+// Reads `HASH:COUNT` lines, passing on the hashes seen at least `min_count` times
+pub fn read_hashes(
+    reader: impl BufRead,
+    prefix: &str,
+    min_count: u64,
+    mut f: impl FnMut(PasswordHash),
+) -> eyre::Result<()> {
+    for line in reader.lines() {
+        if let Some((hash, count)) = parse_line(&line?, prefix)? {
+            if count >= min_count {
+                f(hash);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+// Reads either a single dump file of full hashes, or a directory of range files
+// as written by the official HIBP downloader
+pub fn read_hashes_from_path(
+    path: &Path,
+    min_count: u64,
+    mut f: impl FnMut(PasswordHash),
+) -> eyre::Result<()> {
+    if !path.is_dir() {
+        let file = File::open(path).wrap_err_with(|| format!("Failed to open {:?}", path))?;
+
+        return read_hashes(BufReader::new(file), "", min_count, f);
+    }
+
+    let mut entries = fs::read_dir(path)?.collect::<Result<Vec<_>, _>>()?;
+    entries.sort_by_key(|entry| entry.file_name());
+
+    for entry in entries {
+        let entry_path = entry.path();
+        let prefix = entry_path
+            .file_stem()
+            .and_then(|stem| stem.to_str())
+            .filter(|stem| {
+                stem.len() == RANGE_PREFIX_LENGTH && stem.chars().all(|c| c.is_ascii_hexdigit())
+            });
+
+        // Anything else in the directory, like a README, is not a range file
+        let Some(prefix) = prefix else {
+            continue;
+        };
+
+        let file =
+            File::open(&entry_path).wrap_err_with(|| format!("Failed to open {:?}", entry_path))?;
+
+        read_hashes(BufReader::new(file), prefix, min_count, &mut f)?;
+    }
+
+    Ok(())
+}
+
+// Set membership in a fraction of the space the hashes take, at the cost of
+// false positives. A breached password is always reported.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BloomFilter {
+    bits: Vec<u64>,
+    num_bits: u64,
+    num_hashes: u32,
+}
+
+impl BloomFilter {
+    // Sized for `capacity` entries at the given false positive rate
+    pub fn new(capacity: u64, false_positive_rate: f64) -> eyre::Result<Self> {
+        if !(false_positive_rate > 0.0 && false_positive_rate < 1.0) {
+            return Err(eyre!("False positive rate must be between 0 and 1"));
+        }
+
+        let ln2 = std::f64::consts::LN_2;
+        let capacity = capacity.max(1) as f64;
+        let num_bits = (-capacity * false_positive_rate.ln() / (ln2 * ln2)).ceil() as u64;
+        let num_hashes = ((num_bits as f64 / capacity) * ln2).round().max(1.0) as u32;
+
+        Ok(Self {
+            bits: vec![0; num_bits.div_ceil(64) as usize],
+            num_bits,
+            num_hashes,
+        })
+    }
+
+    // SHA-1 output is already uniform, so two halves of it are enough to derive
+    // every index (Kirsch-Mitzenmacher double hashing)
+    fn indexes(&self, hash: &PasswordHash) -> impl Iterator<Item = u64> + '_ {
+        let h1 = u64::from_le_bytes(hash[0..8].try_into().unwrap());
+        let h2 = u64::from_le_bytes(hash[8..16].try_into().unwrap()) | 1;
+
+        (0..self.num_hashes as u64)
+            .map(move |i| h1.wrapping_add(i.wrapping_mul(h2)) % self.num_bits)
+    }
+
+    pub fn insert(&mut self, hash: &PasswordHash) {
+        let indexes: Vec<u64> = self.indexes(hash).collect();
+
+        for index in indexes {
+            self.bits[(index / 64) as usize] |= 1 << (index % 64);
+        }
+    }
+
+    pub fn contains(&self, hash: &PasswordHash) -> bool {
+        self.indexes(hash)
+            .all(|index| self.bits[(index / 64) as usize] & (1 << (index % 64)) != 0)
+    }
+
+    pub fn save(&self, path: &Path) -> eyre::Result<()> {
+        let mut writer = BufWriter::new(File::create(path)?);
+
+        writer.write_all(BLOOM_FILTER_MAGIC)?;
+        writer.write_all(&BLOOM_FILTER_VERSION.to_le_bytes())?;
+        writer.write_all(&self.num_hashes.to_le_bytes())?;
+        writer.write_all(&self.num_bits.to_le_bytes())?;
+
+        for word in &self.bits {
+            writer.write_all(&word.to_le_bytes())?;
+        }
+
+        writer.flush()?;
+
+        Ok(())
+    }
+
+    pub fn load(path: &Path) -> eyre::Result<Self> {
+        let mut reader = BufReader::new(File::open(path)?);
+
+        let mut magic = [0; 8];
+        reader.read_exact(&mut magic)?;
+
+        if &magic != BLOOM_FILTER_MAGIC {
+            return Err(eyre!("{:?} is not a breached password filter", path));
+        }
+
+        let mut word = [0; 4];
+        reader.read_exact(&mut word)?;
+
+        if u32::from_le_bytes(word) != BLOOM_FILTER_VERSION {
+            return Err(eyre!("Unsupported breached password filter version"));
+        }
+
+        reader.read_exact(&mut word)?;
+        let num_hashes = u32::from_le_bytes(word);
+
+        let mut word = [0; 8];
+        reader.read_exact(&mut word)?;
+        let num_bits = u64::from_le_bytes(word);
+
+        if num_bits == 0 || num_hashes == 0 {
+            return Err(eyre!("Breached password filter is empty"));
+        }
+
+        let mut bits = Vec::with_capacity(num_bits.div_ceil(64) as usize);
+
+        for _ in 0..num_bits.div_ceil(64) {
+            reader.read_exact(&mut word)?;
+            bits.push(u64::from_le_bytes(word));
+        }
+
+        Ok(Self {
+            bits,
+            num_bits,
+            num_hashes,
+        })
+    }
+
+    // Whether the file was written by `save`, as opposed to a text dump
+    pub fn is_filter_file(path: &Path) -> bool {
+        let mut magic = [0; 8];
+
+        File::open(path)
+            .and_then(|mut file| file.read_exact(&mut magic))
+            .is_ok()
+            && &magic == BLOOM_FILTER_MAGIC
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn digest(password: &str) -> PasswordHash {
+        sha1_digest(&Secret::new(password.to_owned()))
+    }
+
+    #[test]
+    fn should_parse_full_and_range_lines() {
+        // SHA-1 of "password"
+        let full = "5BAA61E4C9B93F3F0682250B6CF8331B7EE68FD8:9545824";
+        let (prefix, suffix) = full.split_at(RANGE_PREFIX_LENGTH);
+
+        assert_eq!(
+            parse_line(full, "").unwrap(),
+            Some((digest("password"), 9545824))
+        );
+        assert_eq!(
+            parse_line(suffix, prefix).unwrap(),
+            Some((digest("password"), 9545824))
+        );
+        assert_eq!(parse_line("  \r", "").unwrap(), None);
+        assert!(parse_line("5BAA61E4:3", "").is_err());
+        assert!(parse_line("ZBAA61E4C9B93F3F0682250B6CF8331B7EE68FD8:3", "").is_err());
+    }
+
+    #[test]
+    fn should_skip_hashes_below_min_count() {
+        let dump = "5BAA61E4C9B93F3F0682250B6CF8331B7EE68FD8:10\r\n\
+                    7C4A8D09CA3762AF61E59520943DC26494F8941B:2\r\n";
+        let mut hashes = Vec::new();
+
+        read_hashes(dump.as_bytes(), "", 5, |hash| hashes.push(hash)).unwrap();
+
+        assert_eq!(hashes, [digest("password")]);
+    }
+
+    #[test]
+    fn should_find_inserted_hashes_in_bloom_filter() {
+        let mut filter = BloomFilter::new(1000, 0.001).unwrap();
+
+        for i in 0..1000 {
+            filter.insert(&digest(&format!("breached-{}", i)));
+        }
+
+        assert!((0..1000).all(|i| filter.contains(&digest(&format!("breached-{}", i)))));
+
+        let false_positives = (0..10_000)
+            .filter(|i| filter.contains(&digest(&format!("unseen-{}", i))))
+            .count();
+
+        assert!(false_positives < 50);
+    }
+
+    #[test]
+    fn should_save_and_load_bloom_filter() {
+        let path = std::env::temp_dir().join(format!("bloom-{}.bin", uuid::Uuid::new_v4()));
+        let mut filter = BloomFilter::new(10, 0.01).unwrap();
+        filter.insert(&digest("password"));
+
+        filter.save(&path).unwrap();
+
+        assert!(BloomFilter::is_filter_file(&path));
+        assert_eq!(BloomFilter::load(&path).unwrap(), filter);
+
+        fs::remove_file(path).unwrap();
+    }
+}
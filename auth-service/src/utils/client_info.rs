@@ -0,0 +1,132 @@
+use axum::{
+    async_trait,
+    extract::{ConnectInfo, FromRequestParts},
+    http::{header::USER_AGENT, request::Parts},
+};
+use std::{convert::Infallible, net::SocketAddr};
+
+const UNKNOWN: &str = "Unknown";
+
+// Where a request came from, recorded on the session it starts
+#[derive(Debug, Clone, PartialEq)]
+pub struct ClientInfo {
+    pub ip_address: String,
+    pub user_agent: String,
+}
+
+impl ClientInfo {
+    // Human readable device name derived from the user agent, e.g. "Firefox on Linux"
+    pub fn device(&self) -> String {
+        let browser = browser(&self.user_agent);
+        let os = operating_system(&self.user_agent);
+
+        match (browser, os) {
+            (Some(browser), Some(os)) => format!("{} on {}", browser, os),
+            (Some(name), None) | (None, Some(name)) => name.to_owned(),
+            (None, None) => format!("{} device", UNKNOWN),
+        }
+    }
+}
+
+#[async_trait]
+impl<S> FromRequestParts<S> for ClientInfo
+where
+    S: Send + Sync,
+{
+    type Rejection = Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        let ip_address = parts
+            .extensions
+            .get::<ConnectInfo<SocketAddr>>()
+            .map(|ConnectInfo(addr)| addr.ip().to_string())
+            .unwrap_or_else(|| UNKNOWN.to_owned());
+
+        let user_agent = parts
+            .headers
+            .get(USER_AGENT)
+            .and_then(|value| value.to_str().ok())
+            .unwrap_or(UNKNOWN)
+            .to_owned();
+
+        Ok(Self {
+            ip_address,
+            user_agent,
+        })
+    }
+}
+
+// Order matters: Edge and Opera also claim to be Chrome, and Chrome claims to be Safari
+fn browser(user_agent: &str) -> Option<&'static str> {
+    [
+        ("Edg/", "Edge"),
+        ("OPR/", "Opera"),
+        ("Firefox/", "Firefox"),
+        ("Chrome/", "Chrome"),
+        ("Safari/", "Safari"),
+        ("curl/", "curl"),
+    ]
+    .into_iter()
+    .find(|(token, _)| user_agent.contains(token))
+    .map(|(_, name)| name)
+}
+
+// Order matters: Android user agents also mention Linux
+fn operating_system(user_agent: &str) -> Option<&'static str> {
+    [
+        ("Android", "Android"),
+        ("iPhone", "iOS"),
+        ("iPad", "iPadOS"),
+        ("Windows", "Windows"),
+        ("Mac OS X", "macOS"),
+        ("CrOS", "ChromeOS"),
+        ("Linux", "Linux"),
+    ]
+    .into_iter()
+    .find(|(token, _)| user_agent.contains(token))
+    .map(|(_, name)| name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn client_info(user_agent: &str) -> ClientInfo {
+        ClientInfo {
+            ip_address: "127.0.0.1".to_owned(),
+            user_agent: user_agent.to_owned(),
+        }
+    }
+
+    #[test]
+    fn test_device_from_browser_user_agents() {
+        let cases = [
+            (
+                "Mozilla/5.0 (X11; Linux x86_64; rv:131.0) Gecko/20100101 Firefox/131.0",
+                "Firefox on Linux",
+            ),
+            (
+                "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/129.0.0.0 Safari/537.36 Edg/129.0.0.0",
+                "Edge on Windows",
+            ),
+            (
+                "Mozilla/5.0 (Linux; Android 14) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/129.0.0.0 Mobile Safari/537.36",
+                "Chrome on Android",
+            ),
+            (
+                "Mozilla/5.0 (iPhone; CPU iPhone OS 17_6 like Mac OS X) AppleWebKit/605.1.15 (KHTML, like Gecko) Version/17.6 Mobile/15E148 Safari/604.1",
+                "Safari on iOS",
+            ),
+        ];
+
+        for (user_agent, device) in cases {
+            assert_eq!(client_info(user_agent).device(), device);
+        }
+    }
+
+    #[test]
+    fn test_device_from_unrecognized_user_agent() {
+        assert_eq!(client_info("curl/8.9.1").device(), "curl");
+        assert_eq!(client_info("Unknown").device(), "Unknown device");
+    }
+}
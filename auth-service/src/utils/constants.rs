@@ -1,16 +1,128 @@
+use argon2::Params;
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
 use dotenvy::dotenv;
+use hkdf::Hkdf;
 use lazy_static::lazy_static;
-use secrecy::Secret;
-use std::env as std_env;
+use secrecy::{ExposeSecret, Secret};
+use sha2::Sha256;
+use std::{collections::HashMap, env as std_env};
+
+use crate::{
+    domain::{CharacterClass, LockoutPolicy, PasswordPolicy},
+    services::password_hashers::{Argon2PasswordHasher, PhcPasswordHasher},
+    utils::otp::OtpGenerator,
+};
 
 pub const TOKEN_TTL_SECONDS: i64 = 600; // Token valid for 10 minutes
 
+pub const REFRESH_TOKEN_TTL_SECONDS: i64 = 60 * 60 * 24 * 14; // Refresh token valid for 14 days
+
+pub const KEYRING_REFRESH_SECONDS: i64 = 30; // How often signing keys rotated elsewhere are picked up
+
+pub const SESSION_TTL_SECONDS: i64 = REFRESH_TOKEN_TTL_SECONDS; // Idle sessions end with their refresh token
+
+pub const SESSION_TOUCH_INTERVAL_SECONDS: i64 = 60; // How often last-seen times are written
+
+pub const MAX_2FA_ATTEMPTS: u32 = 5; // Wrong guesses before a 2FA code is invalidated
+
+pub const MAX_2FA_RESENDS: u32 = 3; // Fresh codes a single login attempt may ask for
+
+pub const TWO_FA_RESEND_COOLDOWN_SECONDS: i64 = 30; // Wait between two emailed codes
+
+pub const RECOVERY_CODE_COUNT: usize = 10; // Codes issued per set
+
+pub const PASSKEY_CHALLENGE_TTL_SECONDS: i64 = 300; // Time to complete a passkey ceremony
+
+pub const PASSWORD_RESET_TOKEN_TTL_SECONDS: i64 = 60 * 30; // Reset links work for 30 minutes
+
+pub const EMAIL_VERIFICATION_TOKEN_TTL_SECONDS: i64 = 60 * 60 * 24; // Verification links work for a day
+
 pub const JWT_COOKIE_NAME: &str = "jwt";
 
+pub const REFRESH_COOKIE_NAME: &str = "refresh_token";
+
 pub const DEFAULT_REDIS_HOSTNAME: &str = "127.0.0.1";
 
+pub const DEFAULT_JWT_ISSUER: &str = "auth-service";
+
+pub const DEFAULT_JWT_AUDIENCE: &str = "app-service";
+
+pub const DEFAULT_TOTP_ISSUER: &str = "auth-service";
+
+pub const DEFAULT_TWO_FA_CODE_LENGTH: usize = 6;
+
+pub const DEFAULT_TWO_FA_CODE_ALPHABET: &str = "0123456789";
+
+pub const TWO_FA_CODE_HASH_KEY_INFO: &[u8] = b"auth-service 2FA code hash key"; // HKDF context for the derived key
+
+pub const DEFAULT_PASSWORD_MIN_LENGTH: usize = 8;
+
+pub const DEFAULT_PASSWORD_MAX_LENGTH: usize = 128;
+
+pub const DEFAULT_PASSWORD_MIN_STRENGTH: u8 = 3; // zxcvbn score, "safely unguessable"
+
+pub const DEFAULT_ARGON2_MEMORY_KIB: u32 = 15000;
+
+pub const DEFAULT_ARGON2_ITERATIONS: u32 = 2;
+
+pub const DEFAULT_ARGON2_PARALLELISM: u32 = 1;
+
+pub const DEFAULT_HASHING_QUEUE_DEPTH: usize = 64;
+
+pub const HASHING_RETRY_AFTER_SECONDS: u64 = 1; // Suggested wait when hashing is at capacity
+
+pub const DEFAULT_RATE_LIMIT_WINDOW_SECONDS: i64 = 60;
+
+pub const DEFAULT_RATE_LIMIT_PER_IP: u32 = 30; // Requests per endpoint and window from one address
+
+pub const DEFAULT_RATE_LIMIT_PER_EMAIL: u32 = 10; // Requests per endpoint and window naming one email
+
+pub const DEFAULT_LOCKOUT_THRESHOLD: u32 = 5; // Failed logins in a row per lock
+
+pub const DEFAULT_LOCKOUT_SECONDS: i64 = 15 * 60; // First lock, doubled for each one after it
+
+pub const DEFAULT_LOCKOUT_MAX_SECONDS: i64 = 24 * 60 * 60;
+
+pub const WEBAUTHN_RP_NAME: &str = "auth-service";
+
+pub const DEFAULT_WEBAUTHN_RP_ID: &str = "localhost";
+
+pub const DEFAULT_WEBAUTHN_ORIGIN: &str = "http://localhost:3000";
+
+pub const DEFAULT_PASSWORD_RESET_URL: &str = "http://localhost:3000/password-reset";
+
+pub const DEFAULT_EMAIL_VERIFICATION_URL: &str = "http://localhost:3000/verify-email";
+
 lazy_static! {
     pub static ref JWT_SECRET: Secret<String> = set_token();
+    pub static ref JWT_SIGNING_KEY_PATH: Option<String> = set_signing_key_path();
+    pub static ref JWT_ISSUER: String = set_jwt_issuer();
+    pub static ref JWT_AUDIENCE: String = set_jwt_audience();
+    pub static ref TOTP_ISSUER: String = set_totp_issuer();
+    pub static ref TWO_FA_CODE_GENERATOR: OtpGenerator = set_two_fa_code_generator();
+    pub static ref PASSWORD_POLICY: PasswordPolicy = set_password_policy();
+    pub static ref ARGON2_PARAMS: Params = set_argon2_params();
+    pub static ref PASSWORD_HASHER: PhcPasswordHasher =
+        PhcPasswordHasher::new(Argon2PasswordHasher::new(ARGON2_PARAMS.clone()));
+    pub static ref HASHING_WORKERS: usize = set_hashing_workers();
+    pub static ref HASHING_QUEUE_DEPTH: usize = set_hashing_queue_depth();
+    pub static ref RATE_LIMIT_WINDOW_SECONDS: i64 = set_rate_limit_window_seconds();
+    pub static ref RATE_LIMIT_PER_IP: u32 =
+        set_rate_limit(env::RATE_LIMIT_PER_IP_ENV_VAR, DEFAULT_RATE_LIMIT_PER_IP);
+    pub static ref RATE_LIMIT_PER_EMAIL: u32 = set_rate_limit(
+        env::RATE_LIMIT_PER_EMAIL_ENV_VAR,
+        DEFAULT_RATE_LIMIT_PER_EMAIL
+    );
+    pub static ref LOCKOUT_POLICY: LockoutPolicy = set_lockout_policy();
+    pub static ref BREACHED_PASSWORDS_PATH: Option<String> = set_breached_passwords_path();
+    pub static ref TWO_FA_CODE_HASH_KEY: Secret<String> = set_two_fa_code_hash_key();
+    pub static ref WEBAUTHN_RP_ID: String = set_webauthn_rp_id();
+    pub static ref WEBAUTHN_ORIGIN: String = set_webauthn_origin();
+    pub static ref PASSWORD_RESET_URL: String = set_password_reset_url();
+    pub static ref EMAIL_VERIFICATION_URL: String = set_email_verification_url();
+    pub static ref ADMIN_API_TOKEN: Option<Secret<String>> = set_admin_api_token();
+    pub static ref INTROSPECTION_CLIENTS: HashMap<String, Secret<String>> =
+        set_introspection_clients();
     pub static ref DATABASE_URL: Secret<String> = set_database_url();
     pub static ref MYSQL_SERVER_URL: Secret<String> = set_mysql_server_url();
     pub static ref MYSQL_PASSWORD: Secret<String> = set_mysql_password();
@@ -21,6 +133,35 @@ lazy_static! {
 
 pub mod env {
     pub const JWT_SECRET_ENV_VAR: &str = "JWT_SECRET";
+    pub const JWT_SIGNING_KEY_PATH_ENV_VAR: &str = "JWT_SIGNING_KEY_PATH";
+    pub const JWT_ISSUER_ENV_VAR: &str = "JWT_ISSUER";
+    pub const JWT_AUDIENCE_ENV_VAR: &str = "JWT_AUDIENCE";
+    pub const TOTP_ISSUER_ENV_VAR: &str = "TOTP_ISSUER";
+    pub const PASSWORD_MIN_LENGTH_ENV_VAR: &str = "PASSWORD_MIN_LENGTH";
+    pub const PASSWORD_MAX_LENGTH_ENV_VAR: &str = "PASSWORD_MAX_LENGTH";
+    pub const PASSWORD_REQUIRED_CLASSES_ENV_VAR: &str = "PASSWORD_REQUIRED_CLASSES";
+    pub const PASSWORD_MIN_STRENGTH_ENV_VAR: &str = "PASSWORD_MIN_STRENGTH";
+    pub const ARGON2_MEMORY_KIB_ENV_VAR: &str = "ARGON2_MEMORY_KIB";
+    pub const ARGON2_ITERATIONS_ENV_VAR: &str = "ARGON2_ITERATIONS";
+    pub const ARGON2_PARALLELISM_ENV_VAR: &str = "ARGON2_PARALLELISM";
+    pub const HASHING_WORKERS_ENV_VAR: &str = "HASHING_WORKERS";
+    pub const HASHING_QUEUE_DEPTH_ENV_VAR: &str = "HASHING_QUEUE_DEPTH";
+    pub const RATE_LIMIT_WINDOW_SECONDS_ENV_VAR: &str = "RATE_LIMIT_WINDOW_SECONDS";
+    pub const RATE_LIMIT_PER_IP_ENV_VAR: &str = "RATE_LIMIT_PER_IP";
+    pub const RATE_LIMIT_PER_EMAIL_ENV_VAR: &str = "RATE_LIMIT_PER_EMAIL";
+    pub const LOCKOUT_THRESHOLD_ENV_VAR: &str = "LOCKOUT_THRESHOLD";
+    pub const LOCKOUT_SECONDS_ENV_VAR: &str = "LOCKOUT_SECONDS";
+    pub const LOCKOUT_MAX_SECONDS_ENV_VAR: &str = "LOCKOUT_MAX_SECONDS";
+    pub const BREACHED_PASSWORDS_PATH_ENV_VAR: &str = "BREACHED_PASSWORDS_PATH";
+    pub const TWO_FA_CODE_LENGTH_ENV_VAR: &str = "TWO_FA_CODE_LENGTH";
+    pub const TWO_FA_CODE_ALPHABET_ENV_VAR: &str = "TWO_FA_CODE_ALPHABET";
+    pub const TWO_FA_CODE_HASH_KEY_ENV_VAR: &str = "TWO_FA_CODE_HASH_KEY";
+    pub const WEBAUTHN_RP_ID_ENV_VAR: &str = "WEBAUTHN_RP_ID";
+    pub const WEBAUTHN_ORIGIN_ENV_VAR: &str = "WEBAUTHN_ORIGIN";
+    pub const PASSWORD_RESET_URL_ENV_VAR: &str = "PASSWORD_RESET_URL";
+    pub const EMAIL_VERIFICATION_URL_ENV_VAR: &str = "EMAIL_VERIFICATION_URL";
+    pub const ADMIN_API_TOKEN_ENV_VAR: &str = "ADMIN_API_TOKEN";
+    pub const INTROSPECTION_CLIENTS_ENV_VAR: &str = "INTROSPECTION_CLIENTS";
     pub const DATABASE_URL_ENV_VAR: &str = "DATABASE_URL";
     pub const MYSQL_SERVER_URL_ENV_VAR: &str = "MYSQL_SERVER_URL";
     pub const MYSQL_PASSWORD_ENV_VAR: &str = "MYSQL_PASSWORD";
@@ -63,6 +204,380 @@ fn set_token() -> Secret<String> {
     Secret::new(secret)
 }
 
+// Optional. When set, tokens are signed with the RSA or Ed25519 key in this PEM file instead of JWT_SECRET.
+fn set_signing_key_path() -> Option<String> {
+    dotenv().ok();
+
+    std_env::var(env::JWT_SIGNING_KEY_PATH_ENV_VAR)
+        .ok()
+        .filter(|path| !path.is_empty())
+}
+
+// `iss` claim written into tokens and required when validating them
+fn set_jwt_issuer() -> String {
+    dotenv().ok();
+
+    std_env::var(env::JWT_ISSUER_ENV_VAR)
+        .ok()
+        .filter(|issuer| !issuer.is_empty())
+        .unwrap_or(DEFAULT_JWT_ISSUER.to_owned())
+}
+
+// `aud` claim written into tokens and required when validating them
+fn set_jwt_audience() -> String {
+    dotenv().ok();
+
+    std_env::var(env::JWT_AUDIENCE_ENV_VAR)
+        .ok()
+        .filter(|audience| !audience.is_empty())
+        .unwrap_or(DEFAULT_JWT_AUDIENCE.to_owned())
+}
+
+// Name authenticator apps show next to TOTP codes
+fn set_totp_issuer() -> String {
+    dotenv().ok();
+
+    let issuer = std_env::var(env::TOTP_ISSUER_ENV_VAR)
+        .ok()
+        .filter(|issuer| !issuer.is_empty())
+        .unwrap_or(DEFAULT_TOTP_ISSUER.to_owned());
+
+    if issuer.contains(':') {
+        panic!("TOTP_ISSUER must not contain ':'.");
+    }
+
+    issuer
+}
+
+// Length and characters of emailed 2FA codes
+fn set_two_fa_code_generator() -> OtpGenerator {
+    dotenv().ok();
+
+    let length = std_env::var(env::TWO_FA_CODE_LENGTH_ENV_VAR)
+        .ok()
+        .filter(|length| !length.is_empty())
+        .map(|length| {
+            length
+                .parse()
+                .expect("TWO_FA_CODE_LENGTH must be a number.")
+        })
+        .unwrap_or(DEFAULT_TWO_FA_CODE_LENGTH);
+
+    let alphabet = std_env::var(env::TWO_FA_CODE_ALPHABET_ENV_VAR)
+        .ok()
+        .filter(|alphabet| !alphabet.is_empty())
+        .unwrap_or(DEFAULT_TWO_FA_CODE_ALPHABET.to_owned());
+
+    match OtpGenerator::new(length, &alphabet) {
+        Ok(generator) => generator,
+        Err(e) => panic!("Invalid TWO_FA_CODE_LENGTH or TWO_FA_CODE_ALPHABET: {}", e),
+    }
+}
+
+// Rules for new passwords. Character classes are a comma separated list of
+// lowercase, uppercase, digit and symbol; none are required by default.
+fn set_password_policy() -> PasswordPolicy {
+    dotenv().ok();
+
+    let min_length = std_env::var(env::PASSWORD_MIN_LENGTH_ENV_VAR)
+        .ok()
+        .filter(|length| !length.is_empty())
+        .map(|length| {
+            length
+                .parse()
+                .expect("PASSWORD_MIN_LENGTH must be a number.")
+        })
+        .unwrap_or(DEFAULT_PASSWORD_MIN_LENGTH);
+
+    let max_length = std_env::var(env::PASSWORD_MAX_LENGTH_ENV_VAR)
+        .ok()
+        .filter(|length| !length.is_empty())
+        .map(|length| {
+            length
+                .parse()
+                .expect("PASSWORD_MAX_LENGTH must be a number.")
+        })
+        .unwrap_or(DEFAULT_PASSWORD_MAX_LENGTH);
+
+    let required_classes = std_env::var(env::PASSWORD_REQUIRED_CLASSES_ENV_VAR)
+        .ok()
+        .map(|classes| {
+            classes
+                .split(',')
+                .filter(|class| !class.trim().is_empty())
+                .map(|class| match CharacterClass::parse(class) {
+                    Ok(class) => class,
+                    Err(e) => panic!("Invalid PASSWORD_REQUIRED_CLASSES: {}", e),
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+
+    let min_strength = std_env::var(env::PASSWORD_MIN_STRENGTH_ENV_VAR)
+        .ok()
+        .filter(|strength| !strength.is_empty())
+        .map(|strength| {
+            strength
+                .parse()
+                .expect("PASSWORD_MIN_STRENGTH must be a number.")
+        })
+        .unwrap_or(DEFAULT_PASSWORD_MIN_STRENGTH);
+
+    match PasswordPolicy::new(min_length, max_length, required_classes, min_strength) {
+        Ok(policy) => policy,
+        Err(e) => panic!("Invalid password policy: {}", e),
+    }
+}
+
+// Argon2id cost for new password hashes. Hashes made with other parameters are
+// upgraded the next time their user logs in.
+fn set_argon2_params() -> Params {
+    dotenv().ok();
+
+    let read = |name: &str, default: u32| {
+        std_env::var(name)
+            .ok()
+            .filter(|value| !value.is_empty())
+            .map(|value| {
+                value
+                    .parse()
+                    .unwrap_or_else(|_| panic!("{} must be a number.", name))
+            })
+            .unwrap_or(default)
+    };
+
+    let memory_kib = read(env::ARGON2_MEMORY_KIB_ENV_VAR, DEFAULT_ARGON2_MEMORY_KIB);
+    let iterations = read(env::ARGON2_ITERATIONS_ENV_VAR, DEFAULT_ARGON2_ITERATIONS);
+    let parallelism = read(env::ARGON2_PARALLELISM_ENV_VAR, DEFAULT_ARGON2_PARALLELISM);
+
+    match Params::new(memory_kib, iterations, parallelism, None) {
+        Ok(params) => params,
+        Err(e) => panic!("Invalid Argon2 parameters: {}", e),
+    }
+}
+
+// Threads hashing passwords at once. Defaults to one per CPU.
+fn set_hashing_workers() -> usize {
+    dotenv().ok();
+
+    let workers = std_env::var(env::HASHING_WORKERS_ENV_VAR)
+        .ok()
+        .filter(|workers| !workers.is_empty())
+        .map(|workers| workers.parse().expect("HASHING_WORKERS must be a number."))
+        .unwrap_or_else(|| {
+            std::thread::available_parallelism()
+                .map(usize::from)
+                .unwrap_or(1)
+        });
+
+    if workers == 0 {
+        panic!("HASHING_WORKERS must be at least 1.");
+    }
+
+    workers
+}
+
+// Hashing jobs waiting for a free thread before requests are refused with a 503
+fn set_hashing_queue_depth() -> usize {
+    dotenv().ok();
+
+    std_env::var(env::HASHING_QUEUE_DEPTH_ENV_VAR)
+        .ok()
+        .filter(|depth| !depth.is_empty())
+        .map(|depth| {
+            depth
+                .parse()
+                .expect("HASHING_QUEUE_DEPTH must be a number.")
+        })
+        .unwrap_or(DEFAULT_HASHING_QUEUE_DEPTH)
+}
+
+fn set_rate_limit_window_seconds() -> i64 {
+    dotenv().ok();
+
+    let window = std_env::var(env::RATE_LIMIT_WINDOW_SECONDS_ENV_VAR)
+        .ok()
+        .filter(|window| !window.is_empty())
+        .map(|window| {
+            window
+                .parse()
+                .expect("RATE_LIMIT_WINDOW_SECONDS must be a number.")
+        })
+        .unwrap_or(DEFAULT_RATE_LIMIT_WINDOW_SECONDS);
+
+    if window <= 0 {
+        panic!("RATE_LIMIT_WINDOW_SECONDS must be positive.");
+    }
+
+    window
+}
+
+// Requests allowed per window. 0 turns the limit off.
+fn set_rate_limit(env_var: &str, default: u32) -> u32 {
+    dotenv().ok();
+
+    std_env::var(env_var)
+        .ok()
+        .filter(|limit| !limit.is_empty())
+        .map(|limit| {
+            limit
+                .parse()
+                .unwrap_or_else(|_| panic!("{} must be a number.", env_var))
+        })
+        .unwrap_or(default)
+}
+
+// A threshold of 0 turns lockout off
+fn set_lockout_policy() -> LockoutPolicy {
+    dotenv().ok();
+
+    let threshold = std_env::var(env::LOCKOUT_THRESHOLD_ENV_VAR)
+        .ok()
+        .filter(|threshold| !threshold.is_empty())
+        .map(|threshold| {
+            threshold
+                .parse()
+                .expect("LOCKOUT_THRESHOLD must be a number.")
+        })
+        .unwrap_or(DEFAULT_LOCKOUT_THRESHOLD);
+    let seconds = set_lockout_seconds(env::LOCKOUT_SECONDS_ENV_VAR, DEFAULT_LOCKOUT_SECONDS);
+    let max_seconds = set_lockout_seconds(
+        env::LOCKOUT_MAX_SECONDS_ENV_VAR,
+        DEFAULT_LOCKOUT_MAX_SECONDS,
+    );
+
+    if max_seconds < seconds {
+        panic!("LOCKOUT_MAX_SECONDS must be at least LOCKOUT_SECONDS.");
+    }
+
+    LockoutPolicy::new(threshold, seconds, max_seconds)
+}
+
+fn set_lockout_seconds(env_var: &str, default: i64) -> i64 {
+    let seconds = std_env::var(env_var)
+        .ok()
+        .filter(|seconds| !seconds.is_empty())
+        .map(|seconds| {
+            seconds
+                .parse()
+                .unwrap_or_else(|_| panic!("{} must be a number.", env_var))
+        })
+        .unwrap_or(default);
+
+    if seconds <= 0 {
+        panic!("{} must be positive.", env_var);
+    }
+
+    seconds
+}
+
+// Optional. HIBP dump, directory of range files, or filter built from one; new
+// passwords found in it are rejected.
+fn set_breached_passwords_path() -> Option<String> {
+    dotenv().ok();
+
+    std_env::var(env::BREACHED_PASSWORDS_PATH_ENV_VAR)
+        .ok()
+        .filter(|path| !path.is_empty())
+}
+
+// Optional. HMAC key pending 2FA codes are hashed with. Without one, a key is derived
+// from JWT_SECRET with HKDF, so the JWT secret itself never doubles as the HMAC key.
+fn set_two_fa_code_hash_key() -> Secret<String> {
+    dotenv().ok();
+
+    std_env::var(env::TWO_FA_CODE_HASH_KEY_ENV_VAR)
+        .ok()
+        .filter(|key| !key.is_empty())
+        .map(Secret::new)
+        .unwrap_or_else(|| derive_key(&JWT_SECRET, TWO_FA_CODE_HASH_KEY_INFO))
+}
+
+// HKDF-SHA256 of `secret`, bound to what the key is for by `info`
+fn derive_key(secret: &Secret<String>, info: &[u8]) -> Secret<String> {
+    let mut key = [0u8; 32];
+
+    Hkdf::<Sha256>::new(None, secret.expose_secret().as_bytes())
+        .expand(info, &mut key)
+        .expect("32 bytes is a valid HKDF-SHA256 output length");
+
+    Secret::new(URL_SAFE_NO_PAD.encode(key))
+}
+
+// Domain passkeys are bound to. Must be the origin's host or a parent domain of it.
+fn set_webauthn_rp_id() -> String {
+    dotenv().ok();
+
+    std_env::var(env::WEBAUTHN_RP_ID_ENV_VAR)
+        .ok()
+        .filter(|rp_id| !rp_id.is_empty())
+        .unwrap_or(DEFAULT_WEBAUTHN_RP_ID.to_owned())
+}
+
+// Origin browsers report for pages running passkey ceremonies
+fn set_webauthn_origin() -> String {
+    dotenv().ok();
+
+    std_env::var(env::WEBAUTHN_ORIGIN_ENV_VAR)
+        .ok()
+        .filter(|origin| !origin.is_empty())
+        .unwrap_or(DEFAULT_WEBAUTHN_ORIGIN.to_owned())
+}
+
+// Page that reset emails link to, with the token appended as `?token=`
+fn set_password_reset_url() -> String {
+    dotenv().ok();
+
+    std_env::var(env::PASSWORD_RESET_URL_ENV_VAR)
+        .ok()
+        .filter(|url| !url.is_empty())
+        .unwrap_or(DEFAULT_PASSWORD_RESET_URL.to_owned())
+}
+
+// Page that verification emails link to, with the token appended as `?token=`
+fn set_email_verification_url() -> String {
+    dotenv().ok();
+
+    std_env::var(env::EMAIL_VERIFICATION_URL_ENV_VAR)
+        .ok()
+        .filter(|url| !url.is_empty())
+        .unwrap_or(DEFAULT_EMAIL_VERIFICATION_URL.to_owned())
+}
+
+// Optional. Bearer token for the /admin endpoints, which are disabled when it is not set.
+fn set_admin_api_token() -> Option<Secret<String>> {
+    dotenv().ok();
+
+    std_env::var(env::ADMIN_API_TOKEN_ENV_VAR)
+        .ok()
+        .filter(|token| !token.is_empty())
+        .map(Secret::new)
+}
+
+// Optional. Comma-separated `client_id:client_secret` pairs for resource servers allowed to call /introspect.
+fn set_introspection_clients() -> HashMap<String, Secret<String>> {
+    dotenv().ok();
+
+    let clients = std_env::var(env::INTROSPECTION_CLIENTS_ENV_VAR).unwrap_or_default();
+
+    clients
+        .split(',')
+        .filter(|client| !client.trim().is_empty())
+        .map(|client| {
+            let (id, secret) = client
+                .trim()
+                .split_once(':')
+                .expect("INTROSPECTION_CLIENTS entries must be client_id:client_secret.");
+
+            if id.is_empty() || secret.is_empty() {
+                panic!("INTROSPECTION_CLIENTS entries must not be empty.");
+            }
+
+            (id.to_owned(), Secret::new(secret.to_owned()))
+        })
+        .collect()
+}
+
 fn set_mysql_server_url() -> Secret<String> {
     dotenv().ok();
 
@@ -0,0 +1,276 @@
+use crate::utils::constants::{HASHING_QUEUE_DEPTH, HASHING_WORKERS};
+
+use lazy_static::lazy_static;
+use serde::{Deserialize, Serialize};
+use std::{
+    panic::{self, AssertUnwindSafe},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        mpsc::{self, Receiver, SyncSender, TrySendError},
+        Arc, Mutex,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+use tokio::sync::oneshot;
+
+// Upper bounds of the queue wait histogram, in milliseconds
+const QUEUE_WAIT_BUCKETS_MS: [u64; 8] = [1, 5, 10, 50, 100, 500, 1000, 5000];
+
+lazy_static! {
+    pub static ref HASHING_POOL: HashingPool =
+        HashingPool::new(*HASHING_WORKERS, *HASHING_QUEUE_DEPTH);
+}
+
+type Job = Box<dyn FnOnce() + Send>;
+
+#[derive(Debug, PartialEq, thiserror::Error)]
+pub enum HashingPoolError {
+    #[error("Password hashing is at capacity")]
+    Saturated,
+    #[error("Password hashing job failed")]
+    JobFailed,
+}
+
+// Runs password hashing on a fixed set of threads of its own, so a burst of
+// logins can neither pin every CPU nor starve Tokio's blocking pool. Jobs past
+// the queue depth are refused straight away instead of piling up.
+pub struct HashingPool {
+    sender: SyncSender<(Instant, Job)>,
+    workers: usize,
+    queue_depth: usize,
+    metrics: Arc<HashingPoolMetrics>,
+}
+
+impl HashingPool {
+    pub fn new(workers: usize, queue_depth: usize) -> Self {
+        let (sender, receiver) = mpsc::sync_channel(queue_depth);
+        let receiver = Arc::new(Mutex::new(receiver));
+        let metrics = Arc::new(HashingPoolMetrics::default());
+
+        for i in 0..workers {
+            let receiver = receiver.clone();
+            let metrics = metrics.clone();
+
+            thread::Builder::new()
+                .name(format!("password-hashing-{}", i))
+                .spawn(move || work(receiver, metrics))
+                .expect("Failed to spawn password hashing thread");
+        }
+
+        Self {
+            sender,
+            workers,
+            queue_depth,
+            metrics,
+        }
+    }
+
+    pub async fn run<T: Send + 'static>(
+        &self,
+        f: impl FnOnce() -> T + Send + 'static,
+    ) -> Result<T, HashingPoolError> {
+        let (result_sender, result_receiver) = oneshot::channel();
+        let job: Job = Box::new(move || {
+            let _ = result_sender.send(f());
+        });
+
+        self.metrics.queued.fetch_add(1, Ordering::Relaxed);
+
+        if let Err(e) = self.sender.try_send((Instant::now(), job)) {
+            self.metrics.queued.fetch_sub(1, Ordering::Relaxed);
+
+            return Err(match e {
+                TrySendError::Full(_) => {
+                    self.metrics.rejected.fetch_add(1, Ordering::Relaxed);
+                    tracing::warn!("Password hashing queue is full, refusing request");
+                    HashingPoolError::Saturated
+                }
+                TrySendError::Disconnected(_) => HashingPoolError::JobFailed,
+            });
+        }
+
+        // The sender is dropped without a result if the job panicked
+        result_receiver
+            .await
+            .map_err(|_| HashingPoolError::JobFailed)
+    }
+
+    pub fn stats(&self) -> HashingPoolStats {
+        let metrics = &self.metrics;
+
+        HashingPoolStats {
+            workers: self.workers,
+            queue_depth: self.queue_depth,
+            queued: metrics.queued.load(Ordering::Relaxed),
+            completed: metrics.completed.load(Ordering::Relaxed),
+            rejected: metrics.rejected.load(Ordering::Relaxed),
+            queue_wait_ms_total: metrics.wait_micros_total.load(Ordering::Relaxed) as f64 / 1000.0,
+            queue_wait_ms_max: metrics.wait_micros_max.load(Ordering::Relaxed) as f64 / 1000.0,
+            queue_wait_buckets: QUEUE_WAIT_BUCKETS_MS
+                .iter()
+                .zip(&metrics.wait_buckets)
+                .map(|(le, count)| QueueWaitBucket {
+                    le_ms: *le,
+                    count: count.load(Ordering::Relaxed),
+                })
+                .collect(),
+        }
+    }
+}
+
+fn work(receiver: Arc<Mutex<Receiver<(Instant, Job)>>>, metrics: Arc<HashingPoolMetrics>) {
+    loop {
+        // The lock is only held while waiting for the next job, not while running it
+        let next = receiver.lock().map(|receiver| receiver.recv());
+
+        let Ok(Ok((enqueued_at, job))) = next else {
+            return;
+        };
+
+        metrics.queued.fetch_sub(1, Ordering::Relaxed);
+        metrics.record_wait(enqueued_at.elapsed());
+
+        // A panicking job must not take the worker down with it
+        if panic::catch_unwind(AssertUnwindSafe(job)).is_err() {
+            tracing::error!("Password hashing job panicked");
+        }
+
+        metrics.completed.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+#[derive(Debug, Default)]
+struct HashingPoolMetrics {
+    queued: AtomicU64,
+    completed: AtomicU64,
+    rejected: AtomicU64,
+    wait_micros_total: AtomicU64,
+    wait_micros_max: AtomicU64,
+    // Cumulative, like Prometheus buckets: each counts the waits up to its bound
+    wait_buckets: [AtomicU64; QUEUE_WAIT_BUCKETS_MS.len()],
+}
+
+impl HashingPoolMetrics {
+    fn record_wait(&self, wait: Duration) {
+        let micros = wait.as_micros() as u64;
+
+        self.wait_micros_total.fetch_add(micros, Ordering::Relaxed);
+        self.wait_micros_max.fetch_max(micros, Ordering::Relaxed);
+
+        for (le, count) in QUEUE_WAIT_BUCKETS_MS.iter().zip(&self.wait_buckets) {
+            if micros <= le * 1000 {
+                count.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+
+        tracing::debug!(
+            queue_wait_ms = micros as f64 / 1000.0,
+            "Hashing job started"
+        );
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct HashingPoolStats {
+    pub workers: usize,
+    #[serde(rename = "queueDepth")]
+    pub queue_depth: usize,
+    pub queued: u64,
+    pub completed: u64,
+    pub rejected: u64,
+    #[serde(rename = "queueWaitMsTotal")]
+    pub queue_wait_ms_total: f64,
+    #[serde(rename = "queueWaitMsMax")]
+    pub queue_wait_ms_max: f64,
+    #[serde(rename = "queueWaitBuckets")]
+    pub queue_wait_buckets: Vec<QueueWaitBucket>,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct QueueWaitBucket {
+    #[serde(rename = "leMs")]
+    pub le_ms: u64,
+    pub count: u64,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::domain::{data_stores::UserStoreError, error::AuthAPIError};
+    use axum::{
+        http::{header::RETRY_AFTER, StatusCode},
+        response::IntoResponse,
+    };
+    use color_eyre::eyre;
+
+    #[tokio::test]
+    async fn should_run_jobs_and_record_waits() {
+        let pool = HashingPool::new(2, 4);
+
+        assert_eq!(pool.run(|| 2 + 2).await, Ok(4));
+        assert_eq!(
+            pool.run(|| panic!("boom")).await,
+            Err::<(), _>(HashingPoolError::JobFailed)
+        );
+        assert_eq!(pool.run(|| "still working").await, Ok("still working"));
+
+        // Waits are recorded before each job runs, so they are all in by now
+        let stats = pool.stats();
+
+        assert_eq!(stats.queued, 0);
+        assert_eq!(stats.queue_wait_buckets.last().unwrap().count, 3);
+    }
+
+    #[tokio::test]
+    async fn should_refuse_jobs_past_queue_depth() {
+        let pool = Arc::new(HashingPool::new(1, 1));
+        let (started_sender, started_receiver) = oneshot::channel();
+        let (release_sender, release_receiver) = mpsc::channel::<()>();
+
+        // Occupies the only worker until released
+        let running = tokio::spawn({
+            let pool = pool.clone();
+            async move {
+                pool.run(move || {
+                    started_sender.send(()).unwrap();
+                    release_receiver.recv().unwrap();
+                })
+                .await
+            }
+        });
+        started_receiver.await.unwrap();
+
+        // Polled once, which is when it takes the only queue slot
+        let queued = pool.run(|| ());
+        tokio::pin!(queued);
+
+        assert!(tokio::time::timeout(Duration::ZERO, &mut queued)
+            .await
+            .is_err());
+        assert_eq!(pool.stats().queued, 1);
+        assert_eq!(pool.run(|| ()).await, Err(HashingPoolError::Saturated));
+
+        release_sender.send(()).unwrap();
+
+        assert_eq!(running.await.unwrap(), Ok(()));
+        assert_eq!(queued.await, Ok(()));
+        assert_eq!(pool.stats().rejected, 1);
+    }
+
+    #[test]
+    fn should_answer_503_when_saturated() {
+        let store_error =
+            UserStoreError::UnexpectedError(eyre::Report::new(HashingPoolError::Saturated));
+        let response = AuthAPIError::UnexpectedError(store_error.into()).into_response();
+
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+        assert!(response.headers().contains_key(RETRY_AFTER));
+
+        let response =
+            AuthAPIError::UnexpectedError(HashingPoolError::JobFailed.into()).into_response();
+
+        assert_eq!(response.status(), StatusCode::INTERNAL_SERVER_ERROR);
+    }
+}
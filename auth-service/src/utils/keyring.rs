@@ -0,0 +1,470 @@
+use super::{
+    constants::{KEYRING_REFRESH_SECONDS, TOKEN_TTL_SECONDS},
+    signing_key::SigningKey,
+};
+use crate::{
+    app_state::app_state::{KeyringType, SigningKeyStoreType},
+    domain::data_stores::{SigningKeyStoreError, StoredSigningKey},
+};
+
+use aes_gcm::{
+    aead::{Aead, AeadCore, KeyInit, OsRng, Payload},
+    Aes256Gcm, Nonce,
+};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use chrono::{DateTime, Duration, Utc};
+use color_eyre::eyre::{self, eyre};
+use hkdf::Hkdf;
+use jsonwebtoken::jwk::JwkSet;
+use secrecy::{ExposeSecret, Secret};
+use sha2::Sha256;
+use std::time::Instant;
+
+// Matches the default clock skew leeway of jsonwebtoken::Validation
+const VALIDATION_LEEWAY_SECONDS: i64 = 60;
+
+// Unknown kids trigger a reload from the store at most this often
+const KEYRING_RELOAD_MIN_INTERVAL_SECONDS: u64 = 5;
+
+// Attempts at publishing a rotation when other replicas keep rotating first
+const MAX_ROTATION_ATTEMPTS: usize = 3;
+
+// HKDF info for the key sealing published key material
+const MATERIAL_SEALING_KEY_INFO: &[u8] = b"auth-service signing key material";
+
+const NONCE_LENGTH: usize = 12;
+
+#[derive(Clone)]
+struct RetiredKey {
+    key: SigningKey,
+    retired_at: DateTime<Utc>,
+}
+
+// One active key used to sign new tokens, plus the keys it replaced.
+// Retired keys only verify tokens until the last token they signed has expired.
+// Rotations are shared with other replicas through the signing key store.
+#[derive(Clone)]
+pub struct Keyring {
+    // The key from configuration, which every replica starts from
+    base: SigningKey,
+    active: SigningKey,
+    retired: Vec<RetiredKey>,
+    reloaded_at: Instant,
+}
+
+impl Keyring {
+    pub fn new(active: SigningKey) -> Self {
+        Self {
+            base: active.clone(),
+            active,
+            retired: Vec::new(),
+            reloaded_at: Instant::now(),
+        }
+    }
+
+    // Rebuilds the keyring other replicas published for the configured key
+    pub fn from_stored(base: SigningKey, stored: Vec<StoredSigningKey>) -> eyre::Result<Self> {
+        let mut keyring = Self::new(base);
+
+        if stored.is_empty() {
+            return Ok(keyring);
+        }
+
+        let mut active = None;
+
+        for stored_key in stored {
+            let key = match &stored_key.material {
+                Some(sealed) => {
+                    let material = open_material(&keyring.base, &stored_key.kid, sealed)?;
+
+                    SigningKey::from_material(&stored_key.kid, stored_key.algorithm, &material)?
+                }
+                None if stored_key.kid == keyring.base.kid() => keyring.base.clone(),
+                None => return Err(eyre!("Signing key {} has no material", stored_key.kid)),
+            };
+
+            match stored_key.retired_at {
+                Some(retired_at) => keyring.retired.push(RetiredKey { key, retired_at }),
+                None => active = Some(key),
+            }
+        }
+
+        keyring.active = active.ok_or(eyre!("Stored signing keys have no active key"))?;
+        keyring.prune();
+
+        Ok(keyring)
+    }
+
+    // The keys to publish for other replicas. The configured key goes without
+    // its material, as they have it already, and the material of the others is
+    // sealed with a key derived from it, so the store never sees a private key.
+    pub fn to_stored(&self) -> eyre::Result<Vec<StoredSigningKey>> {
+        let active = std::iter::once((&self.active, None));
+        let retired = self
+            .retired
+            .iter()
+            .map(|retired| (&retired.key, Some(retired.retired_at)));
+
+        active
+            .chain(retired)
+            .map(|(key, retired_at)| {
+                let material = (key.kid() != self.base.kid())
+                    .then(|| seal_material(&self.base, key))
+                    .transpose()?;
+
+                Ok(StoredSigningKey {
+                    kid: key.kid().to_owned(),
+                    algorithm: key.algorithm(),
+                    material,
+                    retired_at,
+                })
+            })
+            .collect()
+    }
+
+    pub fn base_kid(&self) -> &str {
+        self.base.kid()
+    }
+
+    // Active kid as the store knows it. Nothing is stored until the first rotation,
+    // and rotations never go back to the configured key.
+    fn stored_active_kid(&self) -> Option<&str> {
+        Some(self.active.kid()).filter(|kid| *kid != self.base.kid())
+    }
+
+    pub fn active(&self) -> &SigningKey {
+        &self.active
+    }
+
+    // Tokens issued before kid headers were introduced can only have been signed by the active key
+    pub fn find(&self, kid: Option<&str>) -> Option<&SigningKey> {
+        let kid = match kid {
+            Some(kid) => kid,
+            None => return Some(&self.active),
+        };
+
+        if self.active.kid() == kid {
+            return Some(&self.active);
+        }
+
+        let now = Utc::now();
+
+        self.retired
+            .iter()
+            .find(|retired| retired.key.kid() == kid && !is_expired(retired, now))
+            .map(|retired| &retired.key)
+    }
+
+    // Makes `next` the active key, keeping the previous one for verification
+    pub fn rotate_to(&mut self, next: SigningKey) {
+        let previous = std::mem::replace(&mut self.active, next);
+
+        self.retired.push(RetiredKey {
+            key: previous,
+            retired_at: Utc::now(),
+        });
+        self.prune();
+    }
+
+    // Drops retired keys whose tokens can no longer be valid
+    pub fn prune(&mut self) {
+        let now = Utc::now();
+
+        self.retired.retain(|retired| !is_expired(retired, now));
+    }
+
+    // Public keys for every key that may still have signed a valid token
+    pub fn jwks(&self) -> JwkSet {
+        let now = Utc::now();
+
+        let retired = self
+            .retired
+            .iter()
+            .filter(|retired| !is_expired(retired, now))
+            .map(|retired| &retired.key);
+
+        let keys = std::iter::once(&self.active)
+            .chain(retired)
+            .filter_map(SigningKey::jwk)
+            .cloned()
+            .collect();
+
+        JwkSet { keys }
+    }
+}
+
+// Another replica may sign with a key for up to a refresh interval after it was
+// retired elsewhere, so retired keys are kept that much longer
+fn is_expired(retired: &RetiredKey, now: DateTime<Utc>) -> bool {
+    let lifetime =
+        Duration::seconds(TOKEN_TTL_SECONDS + VALIDATION_LEEWAY_SECONDS + KEYRING_REFRESH_SECONDS);
+
+    retired.retired_at + lifetime < now
+}
+
+// Every replica derives the same key from the configured one, which is never stored
+fn material_cipher(base: &SigningKey) -> Aes256Gcm {
+    let mut key = [0u8; 32];
+
+    Hkdf::<Sha256>::new(None, base.material().expose_secret().as_bytes())
+        .expand(MATERIAL_SEALING_KEY_INFO, &mut key)
+        .expect("32 bytes is a valid HKDF-SHA256 output length");
+
+    Aes256Gcm::new(&key.into())
+}
+
+// Encrypts the key's material, bound to its kid, as base64 of the nonce and ciphertext
+fn seal_material(base: &SigningKey, key: &SigningKey) -> eyre::Result<Secret<String>> {
+    let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+    let payload = Payload {
+        msg: key.material().expose_secret().as_bytes(),
+        aad: key.kid().as_bytes(),
+    };
+
+    let ciphertext = material_cipher(base)
+        .encrypt(&nonce, payload)
+        .map_err(|_| eyre!("Failed to seal signing key {}", key.kid()))?;
+
+    let mut sealed = nonce.to_vec();
+    sealed.extend(ciphertext);
+
+    Ok(Secret::new(URL_SAFE_NO_PAD.encode(sealed)))
+}
+
+fn open_material(
+    base: &SigningKey,
+    kid: &str,
+    sealed: &Secret<String>,
+) -> eyre::Result<Secret<String>> {
+    let sealed = URL_SAFE_NO_PAD.decode(sealed.expose_secret())?;
+
+    let (nonce, ciphertext) = sealed
+        .split_first_chunk::<NONCE_LENGTH>()
+        .ok_or(eyre!("Sealed signing key {} is too short", kid))?;
+    let payload = Payload {
+        msg: ciphertext,
+        aad: kid.as_bytes(),
+    };
+
+    let material = material_cipher(base)
+        .decrypt(&Nonce::from(*nonce), payload)
+        .map_err(|_| eyre!("Signing key {} was not sealed with the configured key", kid))?;
+
+    Ok(Secret::new(String::from_utf8(material)?))
+}
+
+// Generates a new active key of the same algorithm and publishes it to the other
+// replicas. Generation (slow for RSA) runs off the async runtime, and the keyring
+// is only locked to swap the key in.
+pub async fn rotate_keyring(
+    keyring: &KeyringType,
+    store: &SigningKeyStoreType,
+) -> eyre::Result<String> {
+    let algorithm = keyring.read().await.active().algorithm();
+    let next = tokio::task::spawn_blocking(move || SigningKey::generate(algorithm)).await??;
+
+    for _ in 0..MAX_ROTATION_ATTEMPTS {
+        let mut rotated = keyring.read().await.clone();
+        let expected_active_kid = rotated.stored_active_kid().map(str::to_owned);
+        rotated.rotate_to(next.clone());
+
+        let result = store
+            .write()
+            .await
+            .replace_keys(
+                rotated.base_kid(),
+                expected_active_kid.as_deref(),
+                rotated.to_stored()?,
+            )
+            .await;
+
+        match result {
+            Ok(()) => {
+                *keyring.write().await = rotated;
+                return Ok(next.kid().to_owned());
+            }
+            // Another replica rotated first; start again from its keys
+            Err(SigningKeyStoreError::Conflict) => reload_keyring(keyring, store).await?,
+            Err(e) => return Err(e.into()),
+        }
+    }
+
+    Err(eyre!("Signing keys kept being rotated concurrently"))
+}
+
+// Picks up keys rotated by other replicas
+pub async fn reload_keyring(
+    keyring: &KeyringType,
+    store: &SigningKeyStoreType,
+) -> eyre::Result<()> {
+    let base = keyring.read().await.base.clone();
+    let stored = store.read().await.load_keys(base.kid()).await?;
+    let reloaded = Keyring::from_stored(base, stored)?;
+
+    *keyring.write().await = reloaded;
+
+    Ok(())
+}
+
+// For a token with an unknown kid, which may come from a key rotated elsewhere.
+// Throttled, so made-up kids cannot hammer the store.
+pub async fn reload_keyring_if_stale(
+    keyring: &KeyringType,
+    store: &SigningKeyStoreType,
+) -> eyre::Result<()> {
+    let reloaded_at = keyring.read().await.reloaded_at;
+
+    if reloaded_at.elapsed().as_secs() < KEYRING_RELOAD_MIN_INTERVAL_SECONDS {
+        return Ok(());
+    }
+
+    reload_keyring(keyring, store).await
+}
+
+// Runs for the lifetime of the app, so new tokens are signed with the key that
+// was rotated in last, whichever replica did it
+pub async fn refresh_keyring_periodically(keyring: KeyringType, store: SigningKeyStoreType) {
+    let mut interval = tokio::time::interval(std::time::Duration::from_secs(
+        KEYRING_REFRESH_SECONDS as u64,
+    ));
+
+    loop {
+        interval.tick().await;
+
+        if let Err(e) = reload_keyring(&keyring, &store).await {
+            tracing::error!("Failed to reload signing keys: {:?}", e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::services::data_stores::HashmapSigningKeyStore;
+
+    use jsonwebtoken::Algorithm;
+    use std::sync::Arc;
+    use tokio::sync::RwLock;
+
+    fn keyring() -> Keyring {
+        Keyring::new(SigningKey::generate(Algorithm::EdDSA).unwrap())
+    }
+
+    fn rotate(keyring: &mut Keyring) -> String {
+        let next = SigningKey::generate(keyring.active().algorithm()).unwrap();
+        let kid = next.kid().to_owned();
+        keyring.rotate_to(next);
+
+        kid
+    }
+
+    #[test]
+    fn test_find_active_key() {
+        let keyring = keyring();
+        let kid = keyring.active().kid().to_owned();
+
+        assert_eq!(keyring.find(Some(&kid)).unwrap().kid(), kid);
+        assert_eq!(keyring.find(None).unwrap().kid(), kid);
+        assert!(keyring.find(Some("unknown")).is_none());
+    }
+
+    #[test]
+    fn test_rotate_keeps_retired_key_for_validation() {
+        let mut keyring = keyring();
+        let old_kid = keyring.active().kid().to_owned();
+
+        let new_kid = rotate(&mut keyring);
+
+        assert_ne!(old_kid, new_kid);
+        assert_eq!(keyring.active().kid(), new_kid);
+        assert_eq!(keyring.find(Some(&old_kid)).unwrap().kid(), old_kid);
+        assert_eq!(keyring.jwks().keys.len(), 2);
+    }
+
+    #[test]
+    fn test_prune_drops_expired_retired_keys() {
+        let mut keyring = keyring();
+        let old_kid = keyring.active().kid().to_owned();
+
+        rotate(&mut keyring);
+        keyring.retired[0].retired_at -= Duration::seconds(TOKEN_TTL_SECONDS * 2);
+
+        assert!(keyring.find(Some(&old_kid)).is_none());
+
+        keyring.prune();
+
+        assert!(keyring.retired.is_empty());
+        assert_eq!(keyring.jwks().keys.len(), 1);
+    }
+
+    #[test]
+    fn test_stored_keys_round_trip() {
+        let mut keyring = keyring();
+        let base = keyring.active().clone();
+        let new_kid = rotate(&mut keyring);
+
+        let stored = keyring.to_stored().unwrap();
+
+        // The configured key is not published, and the new one only sealed
+        assert!(stored[1].material.is_none());
+        assert!(!stored[0]
+            .material
+            .as_ref()
+            .unwrap()
+            .expose_secret()
+            .contains(keyring.active().material().expose_secret()));
+
+        let reloaded = Keyring::from_stored(base.clone(), stored).unwrap();
+
+        assert_eq!(reloaded.active().kid(), new_kid);
+        assert_eq!(reloaded.find(Some(base.kid())).unwrap().kid(), base.kid());
+
+        let other = SigningKey::generate(Algorithm::EdDSA).unwrap();
+
+        assert!(Keyring::from_stored(other, keyring.to_stored().unwrap()).is_err());
+    }
+
+    #[test]
+    fn test_sealed_material_only_opens_with_the_configured_key() {
+        let base = SigningKey::generate(Algorithm::EdDSA).unwrap();
+        let key = SigningKey::generate(Algorithm::EdDSA).unwrap();
+        let sealed = seal_material(&base, &key).unwrap();
+
+        let opened = open_material(&base, key.kid(), &sealed).unwrap();
+
+        assert_eq!(opened.expose_secret(), key.material().expose_secret());
+
+        let other = SigningKey::generate(Algorithm::EdDSA).unwrap();
+
+        assert!(open_material(&other, key.kid(), &sealed).is_err());
+        // Nor can sealed material be passed off as another key's
+        assert!(open_material(&base, "other", &sealed).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_rotation_is_shared_through_the_store() {
+        let base = SigningKey::generate(Algorithm::HS256).unwrap();
+        let store: SigningKeyStoreType = Arc::new(RwLock::new(HashmapSigningKeyStore::default()));
+        let first: KeyringType = Arc::new(RwLock::new(Keyring::new(base.clone())));
+        let second: KeyringType = Arc::new(RwLock::new(Keyring::new(base)));
+
+        let kid = rotate_keyring(&first, &store).await.unwrap();
+
+        reload_keyring(&second, &store).await.unwrap();
+        assert_eq!(second.read().await.active().kid(), kid);
+
+        // The second replica has not seen the first one's latest rotation
+        let stale = second.read().await.clone();
+        let kid = rotate_keyring(&first, &store).await.unwrap();
+        *second.write().await = stale;
+
+        let other_kid = rotate_keyring(&second, &store).await.unwrap();
+
+        assert_ne!(other_kid, kid);
+        assert!(second.read().await.find(Some(&kid)).is_some());
+
+        reload_keyring(&first, &store).await.unwrap();
+        assert_eq!(first.read().await.active().kid(), other_kid);
+    }
+}
@@ -1,3 +1,13 @@
 pub mod auth;
+pub mod breached_passwords;
+pub mod client_info;
 pub mod constants;
+pub mod hashing_pool;
+pub mod keyring;
+pub mod otp;
+pub mod password_hashing;
+pub mod rate_limit;
+pub mod signing_key;
+pub mod totp;
 pub mod tracing;
+pub mod webauthn;
@@ -0,0 +1,97 @@
+use color_eyre::eyre::{self, eyre};
+use rand::{rng, Rng};
+use secrecy::Secret;
+
+// Emailed one-time codes of a fixed length, each character drawn uniformly from
+// the alphabet. `rand::rng()` is a ChaCha CSPRNG seeded from the OS.
+#[derive(Debug, Clone)]
+pub struct OtpGenerator {
+    length: usize,
+    alphabet: Vec<char>,
+}
+
+impl OtpGenerator {
+    pub fn new(length: usize, alphabet: &str) -> eyre::Result<Self> {
+        if !(4..=16).contains(&length) {
+            return Err(eyre!("OTP length must be between 4 and 16"));
+        }
+
+        let alphabet: Vec<char> = alphabet.chars().collect();
+
+        if alphabet.len() < 2 || !alphabet.iter().all(char::is_ascii_alphanumeric) {
+            return Err(eyre!(
+                "OTP alphabet must have at least two ASCII letters or digits"
+            ));
+        }
+
+        // A repeated character would be drawn more often than the others
+        if (1..alphabet.len()).any(|i| alphabet[..i].contains(&alphabet[i])) {
+            return Err(eyre!("OTP alphabet must not repeat characters"));
+        }
+
+        Ok(Self { length, alphabet })
+    }
+
+    pub fn generate(&self) -> Secret<String> {
+        let mut rng = rng();
+        let code = (0..self.length)
+            .map(|_| self.alphabet[rng.random_range(0..self.alphabet.len())])
+            .collect();
+
+        Secret::new(code)
+    }
+
+    // Whether the code could have come from this generator
+    pub fn is_well_formed(&self, code: &str) -> bool {
+        code.chars().count() == self.length && code.chars().all(|c| self.alphabet.contains(&c))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use secrecy::ExposeSecret;
+
+    #[test]
+    fn should_generate_codes_of_configured_shape() {
+        let generator = OtpGenerator::new(8, "ABC123").unwrap();
+
+        for _ in 0..100 {
+            let code = generator.generate();
+
+            assert_eq!(code.expose_secret().len(), 8);
+            assert!(code.expose_secret().chars().all(|c| "ABC123".contains(c)));
+            assert!(generator.is_well_formed(code.expose_secret()));
+        }
+    }
+
+    #[test]
+    fn should_use_every_character_of_alphabet() {
+        let generator = OtpGenerator::new(16, "0123456789").unwrap();
+        let codes: String = (0..20)
+            .map(|_| generator.generate().expose_secret().to_owned())
+            .collect();
+
+        assert!(('0'..='9').all(|digit| codes.contains(digit)));
+    }
+
+    #[test]
+    fn should_reject_malformed_codes() {
+        let generator = OtpGenerator::new(6, "0123456789").unwrap();
+
+        assert!(generator.is_well_formed("012345"));
+        assert!(!generator.is_well_formed("12345"));
+        assert!(!generator.is_well_formed("1234567"));
+        assert!(!generator.is_well_formed("12345a"));
+    }
+
+    #[test]
+    fn should_reject_invalid_settings() {
+        assert!(OtpGenerator::new(3, "0123456789").is_err());
+        assert!(OtpGenerator::new(17, "0123456789").is_err());
+        assert!(OtpGenerator::new(6, "0").is_err());
+        assert!(OtpGenerator::new(6, "0123 456").is_err());
+        assert!(OtpGenerator::new(6, "00123").is_err());
+    }
+}
@@ -0,0 +1,40 @@
+use crate::{
+    domain::PasswordHasher,
+    utils::{constants::PASSWORD_HASHER, hashing_pool::HASHING_POOL},
+};
+
+use color_eyre::eyre;
+use secrecy::Secret;
+
+// Whether the hash was made with a legacy algorithm, or other parameters than
+// `compute_password_hash` uses now
+pub fn needs_rehash(password_hash: &str) -> bool {
+    PASSWORD_HASHER.needs_rehash(password_hash)
+}
+
+// Dispatches on the hash's algorithm, so imported bcrypt and scrypt hashes verify
+// too. `Ok(false)` for a wrong password; errors mean it could not be checked.
+#[tracing::instrument(name = "Verify password hash", skip_all)]
+pub async fn verify_password_hash(
+    expected_password_hash: Secret<String>,
+    password_candidate: Secret<String>,
+) -> eyre::Result<bool> {
+    let current_span = tracing::Span::current();
+
+    HASHING_POOL
+        .run(move || {
+            current_span
+                .in_scope(|| PASSWORD_HASHER.verify(&expected_password_hash, &password_candidate))
+        })
+        .await?
+}
+
+#[tracing::instrument(name = "Computing password hash", skip_all)]
+pub async fn compute_password_hash(password: Secret<String>) -> eyre::Result<Secret<String>> {
+    let current_span: tracing::Span = tracing::Span::current();
+
+    // Runs on the hashing pool's own threads, within the current span
+    HASHING_POOL
+        .run(move || current_span.in_scope(|| PASSWORD_HASHER.hash(&password)))
+        .await?
+}
@@ -0,0 +1,91 @@
+use crate::{
+    app_state::app_state::AppState,
+    domain::{data_stores::RateLimitStoreError, error::AuthAPIError},
+    utils::{
+        client_info::ClientInfo,
+        constants::{RATE_LIMIT_PER_EMAIL, RATE_LIMIT_PER_IP, RATE_LIMIT_WINDOW_SECONDS},
+    },
+};
+
+use axum::{
+    body::{to_bytes, Body},
+    extract::{Request, State},
+    http::StatusCode,
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+
+// Credentials are small. Anything bigger is refused rather than buffered.
+const MAX_BODY_BYTES: usize = 64 * 1024;
+
+#[derive(Deserialize)]
+struct EmailField {
+    email: Option<String>,
+}
+
+// Throttles a route per client address, and per email address named in the
+// JSON body, so one account cannot be targeted from many addresses either.
+// Mounted with `axum::middleware::from_fn_with_state`.
+#[tracing::instrument(name = "Rate_Limit", skip_all)]
+pub async fn rate_limit(
+    State(state): State<AppState>,
+    client: ClientInfo,
+    request: Request,
+    next: Next,
+) -> Response {
+    let (parts, body) = request.into_parts();
+
+    let Ok(bytes) = to_bytes(body, MAX_BODY_BYTES).await else {
+        return StatusCode::PAYLOAD_TOO_LARGE.into_response();
+    };
+
+    let route = parts.uri.path().to_owned();
+    let mut limits = vec![(
+        format!("{}:ip:{}", route, client.ip_address),
+        *RATE_LIMIT_PER_IP,
+    )];
+
+    if let Some(email) = serde_json::from_slice::<EmailField>(&bytes)
+        .ok()
+        .and_then(|body| body.email)
+    {
+        limits.push((
+            format!("{}:email:{}", route, fingerprint(&email)),
+            *RATE_LIMIT_PER_EMAIL,
+        ));
+    }
+
+    for (key, limit) in limits.into_iter().filter(|(_, limit)| *limit > 0) {
+        let result = state
+            .rate_limit_store
+            .write()
+            .await
+            .hit(&key, limit, *RATE_LIMIT_WINDOW_SECONDS)
+            .await;
+
+        match result {
+            Ok(()) => {}
+            Err(RateLimitStoreError::LimitExceeded(retry_after)) => {
+                tracing::warn!(
+                    route = %route,
+                    ip_address = %client.ip_address,
+                    "Security event: rate limit exceeded"
+                );
+                return AuthAPIError::TooManyRequests(retry_after).into_response();
+            }
+            // Fails open: an outage of the limit store should not lock everyone out
+            Err(e) => tracing::error!("Failed to check rate limit: {:?}", e),
+        }
+    }
+
+    next.run(Request::from_parts(parts, Body::from(bytes)))
+        .await
+}
+
+// Keys name the account without storing its address
+fn fingerprint(email: &str) -> String {
+    URL_SAFE_NO_PAD.encode(Sha256::digest(email.trim().to_lowercase().as_bytes()))
+}
@@ -0,0 +1,376 @@
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use color_eyre::eyre::{self, Context};
+use ed25519_dalek::pkcs8::{spki::der::pem::LineEnding, DecodePrivateKey, EncodePrivateKey};
+use jsonwebtoken::{
+    jwk::{
+        AlgorithmParameters, CommonParameters, EllipticCurve, Jwk, KeyAlgorithm,
+        OctetKeyPairParameters, OctetKeyPairType, PublicKeyUse, RSAKeyParameters, RSAKeyType,
+    },
+    Algorithm, DecodingKey, EncodingKey, Header,
+};
+use rsa::{pkcs1::DecodeRsaPrivateKey, traits::PublicKeyParts, RsaPrivateKey};
+use secrecy::{ExposeSecret, Secret};
+use sha2::{Digest, Sha256};
+use std::path::Path;
+use uuid::Uuid;
+
+// Key id used for the HS256 key derived from JWT_SECRET
+const SECRET_KEY_ID: &str = "jwt-secret";
+
+// Key material used to sign and verify JWTs.
+// Asymmetric keys also carry a public JWK so they can be published at /.well-known/jwks.json.
+#[derive(Clone)]
+pub struct SigningKey {
+    kid: String,
+    algorithm: Algorithm,
+    encoding_key: EncodingKey,
+    decoding_key: DecodingKey,
+    jwk: Option<Jwk>,
+    // What the key can be rebuilt from: the PEM, or the base64 secret for HMAC keys
+    material: Secret<String>,
+}
+
+impl SigningKey {
+    // HS256 with a shared secret. Nothing is published for symmetric keys.
+    pub fn from_secret(secret: &Secret<String>) -> Self {
+        Self::hmac(SECRET_KEY_ID.to_owned(), secret.expose_secret().as_bytes())
+    }
+
+    fn hmac(kid: String, secret: &[u8]) -> Self {
+        Self {
+            kid,
+            algorithm: Algorithm::HS256,
+            encoding_key: EncodingKey::from_secret(secret),
+            decoding_key: DecodingKey::from_secret(secret),
+            jwk: None,
+            material: Secret::new(URL_SAFE_NO_PAD.encode(secret)),
+        }
+    }
+
+    // Rebuilds a key from its `material`, as published by another replica
+    pub fn from_material(
+        kid: &str,
+        algorithm: Algorithm,
+        material: &Secret<String>,
+    ) -> eyre::Result<Self> {
+        if algorithm == Algorithm::HS256 {
+            let secret = URL_SAFE_NO_PAD
+                .decode(material.expose_secret())
+                .wrap_err("Invalid HMAC signing key")?;
+
+            return Ok(Self::hmac(kid.to_owned(), &secret));
+        }
+
+        let key = Self::from_pem(material.expose_secret().as_bytes())?;
+
+        if key.kid != kid || key.algorithm != algorithm {
+            return Err(eyre::eyre!("Signing key does not match its kid {}", kid));
+        }
+
+        Ok(key)
+    }
+
+    pub fn from_pem_file(path: impl AsRef<Path>) -> eyre::Result<Self> {
+        let path = path.as_ref();
+        let pem = std::fs::read(path).wrap_err(format!(
+            "Failed to read signing key from {}",
+            path.display()
+        ))?;
+
+        Self::from_pem(&pem)
+    }
+
+    // Accepts an Ed25519 private key (PKCS#8) or an RSA private key (PKCS#8 or PKCS#1).
+    pub fn from_pem(pem: &[u8]) -> eyre::Result<Self> {
+        let pem_str = std::str::from_utf8(pem).wrap_err("Signing key is not valid PEM")?;
+
+        if let Ok(key) = ed25519_dalek::SigningKey::from_pkcs8_pem(pem_str) {
+            let x = URL_SAFE_NO_PAD.encode(key.verifying_key().as_bytes());
+
+            let jwk = Jwk {
+                common: common_parameters(KeyAlgorithm::EdDSA),
+                algorithm: AlgorithmParameters::OctetKeyPair(OctetKeyPairParameters {
+                    key_type: OctetKeyPairType::OctetKeyPair,
+                    curve: EllipticCurve::Ed25519,
+                    x,
+                }),
+            };
+
+            return Self::asymmetric(
+                Algorithm::EdDSA,
+                EncodingKey::from_ed_pem(pem)?,
+                jwk,
+                pem_str,
+            );
+        }
+
+        let key = RsaPrivateKey::from_pkcs8_pem(pem_str)
+            .or_else(|_| RsaPrivateKey::from_pkcs1_pem(pem_str))
+            .map_err(|_| eyre::eyre!("Signing key must be an RSA or Ed25519 private key"))?;
+
+        let jwk = Jwk {
+            common: common_parameters(KeyAlgorithm::RS256),
+            algorithm: AlgorithmParameters::RSA(RSAKeyParameters {
+                key_type: RSAKeyType::RSA,
+                n: URL_SAFE_NO_PAD.encode(key.n().to_bytes_be()),
+                e: URL_SAFE_NO_PAD.encode(key.e().to_bytes_be()),
+            }),
+        };
+
+        Self::asymmetric(
+            Algorithm::RS256,
+            EncodingKey::from_rsa_pem(pem)?,
+            jwk,
+            pem_str,
+        )
+    }
+
+    // Creates a brand new random key for the given algorithm, used when rotating
+    pub fn generate(algorithm: Algorithm) -> eyre::Result<Self> {
+        match algorithm {
+            Algorithm::HS256 => {
+                use rand::{rng, Rng};
+
+                let secret = rng().random::<[u8; 64]>();
+                Ok(Self::hmac(Uuid::new_v4().to_string(), &secret))
+            }
+            Algorithm::EdDSA => {
+                use rand::{rng, Rng};
+
+                let pem = ed25519_dalek::SigningKey::from_bytes(&rng().random::<[u8; 32]>())
+                    .to_pkcs8_pem(LineEnding::LF)
+                    .wrap_err("Failed to encode Ed25519 key")?;
+
+                Self::from_pem(pem.as_bytes())
+            }
+            Algorithm::RS256 => {
+                let pem = RsaPrivateKey::new(&mut rsa::rand_core::OsRng, 2048)
+                    .wrap_err("Failed to generate RSA key")?
+                    .to_pkcs8_pem(LineEnding::LF)
+                    .wrap_err("Failed to encode RSA key")?;
+
+                Self::from_pem(pem.as_bytes())
+            }
+            algorithm => Err(eyre::eyre!("Unsupported signing algorithm {:?}", algorithm)),
+        }
+    }
+
+    fn asymmetric(
+        algorithm: Algorithm,
+        encoding_key: EncodingKey,
+        mut jwk: Jwk,
+        pem: &str,
+    ) -> eyre::Result<Self> {
+        let decoding_key =
+            DecodingKey::from_jwk(&jwk).wrap_err("Failed to derive verification key")?;
+
+        // The thumbprint keeps the kid stable across restarts and replicas loading the same PEM
+        let kid = thumbprint(&jwk);
+        jwk.common.key_id = Some(kid.clone());
+
+        Ok(Self {
+            kid,
+            algorithm,
+            encoding_key,
+            decoding_key,
+            jwk: Some(jwk),
+            material: Secret::new(pem.to_owned()),
+        })
+    }
+
+    pub fn kid(&self) -> &str {
+        &self.kid
+    }
+
+    pub fn algorithm(&self) -> Algorithm {
+        self.algorithm
+    }
+
+    pub fn header(&self) -> Header {
+        Header {
+            kid: Some(self.kid.clone()),
+            ..Header::new(self.algorithm)
+        }
+    }
+
+    pub fn encoding_key(&self) -> &EncodingKey {
+        &self.encoding_key
+    }
+
+    pub fn decoding_key(&self) -> &DecodingKey {
+        &self.decoding_key
+    }
+
+    // Public key in JWK form, `None` for symmetric keys
+    pub fn jwk(&self) -> Option<&Jwk> {
+        self.jwk.as_ref()
+    }
+
+    pub fn material(&self) -> &Secret<String> {
+        &self.material
+    }
+}
+
+// RFC 7638 JWK thumbprint: SHA-256 over the required members in lexicographic order
+fn thumbprint(jwk: &Jwk) -> String {
+    let canonical = match &jwk.algorithm {
+        AlgorithmParameters::RSA(params) => {
+            format!(r#"{{"e":"{}","kty":"RSA","n":"{}"}}"#, params.e, params.n)
+        }
+        AlgorithmParameters::OctetKeyPair(params) => {
+            format!(r#"{{"crv":"Ed25519","kty":"OKP","x":"{}"}}"#, params.x)
+        }
+        _ => unreachable!("only RSA and Ed25519 keys are supported"),
+    };
+
+    URL_SAFE_NO_PAD.encode(Sha256::digest(canonical.as_bytes()))
+}
+
+fn common_parameters(key_algorithm: KeyAlgorithm) -> CommonParameters {
+    CommonParameters {
+        public_key_use: Some(PublicKeyUse::Signature),
+        key_algorithm: Some(key_algorithm),
+        ..Default::default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use jsonwebtoken::{decode, decode_header, encode, Validation};
+    use serde::{Deserialize, Serialize};
+
+    const RSA_PRIVATE_KEY_PEM: &str = include_str!("../../tests/fixtures/rsa_private_key.pem");
+
+    #[derive(Debug, Serialize, Deserialize, PartialEq)]
+    struct TestClaims {
+        sub: String,
+        exp: usize,
+    }
+
+    fn ed25519_pem() -> String {
+        use rand::{rng, Rng};
+
+        ed25519_dalek::SigningKey::from_bytes(&rng().random::<[u8; 32]>())
+            .to_pkcs8_pem(LineEnding::LF)
+            .unwrap()
+            .to_string()
+    }
+
+    fn round_trip(key: &SigningKey) -> TestClaims {
+        let claims = TestClaims {
+            sub: "test@example.com".to_owned(),
+            exp: 10_000_000_000,
+        };
+
+        let token = encode(&key.header(), &claims, key.encoding_key()).unwrap();
+
+        assert_eq!(
+            decode_header(&token).unwrap().kid.as_deref(),
+            Some(key.kid())
+        );
+
+        decode::<TestClaims>(
+            &token,
+            key.decoding_key(),
+            &Validation::new(key.algorithm()),
+        )
+        .unwrap()
+        .claims
+    }
+
+    #[test]
+    fn test_from_secret_signs_with_hs256() {
+        let key = SigningKey::from_secret(&Secret::new("secret".to_owned()));
+
+        assert_eq!(key.algorithm(), Algorithm::HS256);
+        assert!(key.jwk().is_none());
+        assert_eq!(round_trip(&key).sub, "test@example.com");
+    }
+
+    #[test]
+    fn test_from_pem_with_ed25519_key() {
+        let key = SigningKey::from_pem(ed25519_pem().as_bytes()).unwrap();
+
+        assert_eq!(key.algorithm(), Algorithm::EdDSA);
+        assert!(matches!(
+            key.jwk().unwrap().algorithm,
+            AlgorithmParameters::OctetKeyPair(_)
+        ));
+        assert_eq!(round_trip(&key).sub, "test@example.com");
+    }
+
+    #[test]
+    fn test_from_pem_with_rsa_key() {
+        let key = SigningKey::from_pem(RSA_PRIVATE_KEY_PEM.as_bytes()).unwrap();
+
+        assert_eq!(key.algorithm(), Algorithm::RS256);
+        assert!(matches!(
+            key.jwk().unwrap().algorithm,
+            AlgorithmParameters::RSA(_)
+        ));
+        assert_eq!(round_trip(&key).sub, "test@example.com");
+    }
+
+    #[test]
+    fn test_kid_is_stable_for_the_same_pem() {
+        let first = SigningKey::from_pem(RSA_PRIVATE_KEY_PEM.as_bytes()).unwrap();
+        let second = SigningKey::from_pem(RSA_PRIVATE_KEY_PEM.as_bytes()).unwrap();
+
+        assert_eq!(first.kid(), second.kid());
+        assert_eq!(
+            first.jwk().unwrap().common.key_id.as_deref(),
+            Some(first.kid())
+        );
+    }
+
+    #[test]
+    fn test_generate_creates_a_new_key() {
+        let active = SigningKey::from_pem(ed25519_pem().as_bytes()).unwrap();
+        let generated = SigningKey::generate(active.algorithm()).unwrap();
+
+        assert_eq!(generated.algorithm(), Algorithm::EdDSA);
+        assert_ne!(generated.kid(), active.kid());
+        assert_eq!(round_trip(&generated).sub, "test@example.com");
+
+        let generated = SigningKey::generate(Algorithm::HS256).unwrap();
+        assert_eq!(round_trip(&generated).sub, "test@example.com");
+    }
+
+    #[test]
+    fn test_from_material_rebuilds_the_same_key() {
+        for algorithm in [Algorithm::HS256, Algorithm::EdDSA] {
+            let key = SigningKey::generate(algorithm).unwrap();
+            let rebuilt = SigningKey::from_material(key.kid(), algorithm, key.material()).unwrap();
+
+            assert_eq!(rebuilt.kid(), key.kid());
+
+            let token = encode(
+                &key.header(),
+                &TestClaims {
+                    sub: "a".to_owned(),
+                    exp: 10_000_000_000,
+                },
+                key.encoding_key(),
+            )
+            .unwrap();
+
+            assert!(decode::<TestClaims>(
+                &token,
+                rebuilt.decoding_key(),
+                &Validation::new(algorithm)
+            )
+            .is_ok());
+        }
+
+        let key = SigningKey::generate(Algorithm::EdDSA).unwrap();
+
+        assert!(SigningKey::from_material("other", Algorithm::EdDSA, key.material()).is_err());
+    }
+
+    #[test]
+    fn test_from_pem_rejects_garbage() {
+        assert!(SigningKey::from_pem(b"not a key").is_err());
+    }
+}
@@ -0,0 +1,148 @@
+use super::constants::TOTP_ISSUER;
+use crate::domain::Email;
+
+use color_eyre::eyre::{self, Context};
+use qrcode::{render::svg, QrCode};
+use secrecy::{ExposeSecret, Secret};
+use std::time::{SystemTime, UNIX_EPOCH};
+use subtle::ConstantTimeEq;
+use totp_rs::{Algorithm, TOTP};
+
+// RFC 6238 defaults, the only parameters every authenticator app supports
+const DIGITS: usize = 6;
+const STEP_SECONDS: u64 = 30;
+// Also accept the previous and next code to allow for clock drift
+const SKEW_STEPS: u8 = 1;
+
+// New random 160-bit secret, base32 encoded
+pub fn generate_totp_secret() -> Secret<String> {
+    match totp_rs::Secret::generate_secret().to_encoded() {
+        totp_rs::Secret::Encoded(secret) => Secret::new(secret),
+        totp_rs::Secret::Raw(_) => unreachable!("to_encoded always returns an encoded secret"),
+    }
+}
+
+fn totp(secret: &Secret<String>, account: &Email) -> eyre::Result<TOTP> {
+    let secret = totp_rs::Secret::Encoded(secret.expose_secret().to_owned())
+        .to_bytes()
+        .map_err(|e| eyre::eyre!("Invalid TOTP secret: {:?}", e))?;
+
+    TOTP::new(
+        Algorithm::SHA1,
+        DIGITS,
+        SKEW_STEPS,
+        STEP_SECONDS,
+        secret,
+        Some(TOTP_ISSUER.to_owned()),
+        account.as_ref().expose_secret().to_owned(),
+    )
+    .wrap_err("Failed to create TOTP.")
+}
+
+// otpauth:// URI that authenticator apps import, usually by scanning it as a QR code
+pub fn totp_uri(secret: &Secret<String>, account: &Email) -> eyre::Result<String> {
+    Ok(totp(secret, account)?.get_url())
+}
+
+// The time step the code belongs to, if it is the current code or a neighbour.
+// Callers keep the last accepted step per user, so each code works only once.
+pub fn verify_totp_code(
+    secret: &Secret<String>,
+    account: &Email,
+    code: &str,
+) -> eyre::Result<Option<u64>> {
+    let totp = totp(secret, account)?;
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .wrap_err("System clock is before the unix epoch.")?
+        .as_secs();
+    let current_step = now / STEP_SECONDS;
+    let skew = SKEW_STEPS as u64;
+
+    Ok(
+        (current_step.saturating_sub(skew)..=current_step + skew).find(|step| {
+            totp.generate(step * STEP_SECONDS)
+                .as_bytes()
+                .ct_eq(code.as_bytes())
+                .into()
+        }),
+    )
+}
+
+// QR code of the given data as a standalone SVG document
+pub fn qr_code_svg(data: &str) -> eyre::Result<String> {
+    let code = QrCode::new(data.as_bytes()).wrap_err("Failed to encode QR code.")?;
+
+    Ok(code
+        .render::<svg::Color<'_>>()
+        .min_dimensions(200, 200)
+        .build())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn email() -> Email {
+        Email::parse(Secret::new("test@example.com".to_owned())).unwrap()
+    }
+
+    #[test]
+    fn test_verify_totp_code() {
+        let secret = generate_totp_secret();
+        let step = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs()
+            / STEP_SECONDS;
+        let code = totp(&secret, &email())
+            .unwrap()
+            .generate(step * STEP_SECONDS);
+
+        // Still accepted if the step ends in between, as the previous code
+        assert_eq!(
+            verify_totp_code(&secret, &email(), &code).unwrap(),
+            Some(step)
+        );
+
+        let wrong_code = format!("{:06}", (code.parse::<u32>().unwrap() + 1) % 1_000_000);
+        assert_eq!(
+            verify_totp_code(&secret, &email(), &wrong_code).unwrap(),
+            None
+        );
+    }
+
+    #[test]
+    fn test_verify_totp_code_with_rfc_6238_vector() {
+        // SHA1 test vector from RFC 6238 appendix B, truncated to 6 digits
+        let secret = Secret::new("GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ".to_owned());
+        let totp = totp(&secret, &email()).unwrap();
+
+        assert_eq!(totp.generate(59), "287082");
+        assert!(totp.check("287082", 59));
+    }
+
+    #[test]
+    fn test_totp_uri() {
+        let secret = generate_totp_secret();
+        let uri = totp_uri(&secret, &email()).unwrap();
+
+        assert!(uri.starts_with("otpauth://totp/"));
+        assert!(uri.contains(&format!("secret={}", secret.expose_secret())));
+        assert!(uri.contains(&format!("issuer={}", TOTP_ISSUER.as_str())));
+    }
+
+    #[test]
+    fn test_qr_code_svg() {
+        let svg = qr_code_svg("otpauth://totp/test").unwrap();
+
+        assert!(svg.contains("<svg"));
+    }
+
+    #[test]
+    fn test_invalid_secret() {
+        let secret = Secret::new("not base32!".to_owned());
+
+        assert!(verify_totp_code(&secret, &email(), "123456").is_err());
+    }
+}
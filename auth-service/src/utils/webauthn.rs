@@ -0,0 +1,463 @@
+use super::constants::{WEBAUTHN_ORIGIN, WEBAUTHN_RP_ID};
+
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use ciborium::Value;
+use color_eyre::eyre::{self, bail, eyre, Context};
+use p256::{
+    ecdsa::{signature::Verifier, DerSignature, VerifyingKey},
+    PublicKey,
+};
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+
+// COSE identifier of ES256 (ECDSA on P-256 with SHA-256), the algorithm every
+// platform authenticator supports and the only one accepted
+pub const ES256: i64 = -7;
+
+const COSE_KEY_TYPE_EC2: i128 = 2;
+const COSE_CURVE_P256: i128 = 1;
+
+const FLAG_USER_PRESENT: u8 = 0x01;
+const FLAG_USER_VERIFIED: u8 = 0x04;
+const FLAG_ATTESTED_CREDENTIAL_DATA: u8 = 0x40;
+
+// rpIdHash (32) + flags (1) + signCount (4)
+const AUTHENTICATOR_DATA_MIN_LENGTH: usize = 37;
+// aaguid (16) + credentialIdLength (2)
+const ATTESTED_CREDENTIAL_HEADER_LENGTH: usize = 18;
+
+// A credential created by an authenticator during registration
+#[derive(Debug, PartialEq)]
+pub struct NewCredential {
+    // Base64url credential id
+    pub id: String,
+    // Uncompressed SEC1 public key
+    pub public_key: Vec<u8>,
+    pub sign_count: u32,
+}
+
+#[derive(Deserialize)]
+struct ClientData {
+    #[serde(rename = "type")]
+    ceremony_type: String,
+    challenge: String,
+    origin: String,
+}
+
+struct AuthenticatorData {
+    rp_id_hash: Vec<u8>,
+    flags: u8,
+    sign_count: u32,
+    // Credential id and public key, only present when registering
+    attested_credential: Option<(Vec<u8>, Vec<u8>)>,
+}
+
+// Checks the response to a `navigator.credentials.create()` call. Attestation
+// statements are not verified, as the options ask for `"none"`.
+pub fn verify_registration(
+    client_data_json: &[u8],
+    attestation_object: &[u8],
+    expected_challenge: &str,
+) -> eyre::Result<NewCredential> {
+    verify_client_data(client_data_json, "webauthn.create", expected_challenge)?;
+
+    let authenticator_data = parse_authenticator_data(&attestation_auth_data(attestation_object)?)?;
+    verify_authenticator_data(&authenticator_data)?;
+
+    let (id, public_key) = authenticator_data
+        .attested_credential
+        .ok_or(eyre!("Authenticator data has no attested credential"))?;
+
+    Ok(NewCredential {
+        id: URL_SAFE_NO_PAD.encode(id),
+        public_key,
+        sign_count: authenticator_data.sign_count,
+    })
+}
+
+// Checks the response to a `navigator.credentials.get()` call against the stored
+// credential and returns the authenticator's new signature counter
+pub fn verify_assertion(
+    client_data_json: &[u8],
+    authenticator_data: &[u8],
+    signature: &[u8],
+    expected_challenge: &str,
+    public_key: &[u8],
+    stored_sign_count: u32,
+) -> eyre::Result<u32> {
+    verify_client_data(client_data_json, "webauthn.get", expected_challenge)?;
+
+    let parsed = parse_authenticator_data(authenticator_data)?;
+    verify_authenticator_data(&parsed)?;
+
+    let verifying_key =
+        VerifyingKey::from_sec1_bytes(public_key).wrap_err("Invalid stored public key")?;
+    let signature = DerSignature::from_bytes(signature).wrap_err("Malformed signature")?;
+
+    let mut signed_data = authenticator_data.to_vec();
+    signed_data.extend_from_slice(&Sha256::digest(client_data_json));
+
+    verifying_key
+        .verify(&signed_data, &signature)
+        .wrap_err("Invalid signature")?;
+
+    // Counters only go up. One that does not means the credential was cloned.
+    // Authenticators without a counter always report 0.
+    if (parsed.sign_count != 0 || stored_sign_count != 0) && parsed.sign_count <= stored_sign_count
+    {
+        bail!(
+            "Signature counter went from {} to {}",
+            stored_sign_count,
+            parsed.sign_count
+        );
+    }
+
+    Ok(parsed.sign_count)
+}
+
+fn verify_client_data(
+    client_data_json: &[u8],
+    ceremony_type: &str,
+    expected_challenge: &str,
+) -> eyre::Result<()> {
+    let client_data: ClientData =
+        serde_json::from_slice(client_data_json).wrap_err("Malformed client data")?;
+
+    if client_data.ceremony_type != ceremony_type {
+        bail!("Unexpected ceremony type {}", client_data.ceremony_type);
+    }
+
+    if client_data.challenge != expected_challenge {
+        bail!("Challenge does not match");
+    }
+
+    if client_data.origin != *WEBAUTHN_ORIGIN {
+        bail!("Unexpected origin {}", client_data.origin);
+    }
+
+    Ok(())
+}
+
+// A passkey login stands in for both factors, so the user must have been
+// verified (PIN or biometrics), not only present
+fn verify_authenticator_data(authenticator_data: &AuthenticatorData) -> eyre::Result<()> {
+    if authenticator_data.rp_id_hash[..] != Sha256::digest(WEBAUTHN_RP_ID.as_bytes())[..] {
+        bail!("Credential is scoped to another relying party");
+    }
+
+    if authenticator_data.flags & FLAG_USER_PRESENT == 0 {
+        bail!("User was not present");
+    }
+
+    if authenticator_data.flags & FLAG_USER_VERIFIED == 0 {
+        bail!("User was not verified");
+    }
+
+    Ok(())
+}
+
+fn attestation_auth_data(attestation_object: &[u8]) -> eyre::Result<Vec<u8>> {
+    let value: Value =
+        ciborium::from_reader(attestation_object).wrap_err("Malformed attestation object")?;
+
+    value
+        .into_map()
+        .map_err(|_| eyre!("Attestation object is not a map"))?
+        .into_iter()
+        .find(|(key, _)| key.as_text() == Some("authData"))
+        .and_then(|(_, value)| value.into_bytes().ok())
+        .ok_or(eyre!("Attestation object has no authData"))
+}
+
+fn parse_authenticator_data(data: &[u8]) -> eyre::Result<AuthenticatorData> {
+    if data.len() < AUTHENTICATOR_DATA_MIN_LENGTH {
+        bail!("Authenticator data is too short");
+    }
+
+    let flags = data[32];
+    let sign_count = u32::from_be_bytes([data[33], data[34], data[35], data[36]]);
+
+    let attested_credential = if flags & FLAG_ATTESTED_CREDENTIAL_DATA != 0 {
+        let rest = &data[AUTHENTICATOR_DATA_MIN_LENGTH..];
+
+        if rest.len() < ATTESTED_CREDENTIAL_HEADER_LENGTH {
+            bail!("Attested credential data is too short");
+        }
+
+        let id_length = u16::from_be_bytes([rest[16], rest[17]]) as usize;
+        let rest = &rest[ATTESTED_CREDENTIAL_HEADER_LENGTH..];
+
+        if rest.len() < id_length {
+            bail!("Credential id is truncated");
+        }
+
+        let (id, cose_key) = rest.split_at(id_length);
+
+        Some((id.to_vec(), parse_cose_key(cose_key)?))
+    } else {
+        None
+    };
+
+    Ok(AuthenticatorData {
+        rp_id_hash: data[..32].to_vec(),
+        flags,
+        sign_count,
+        attested_credential,
+    })
+}
+
+// COSE_Key (RFC 9053) of an ES256 credential, as an uncompressed SEC1 point
+fn parse_cose_key(cose_key: &[u8]) -> eyre::Result<Vec<u8>> {
+    let value: Value = ciborium::from_reader(cose_key).wrap_err("Malformed COSE key")?;
+    let entries = value
+        .into_map()
+        .map_err(|_| eyre!("COSE key is not a map"))?;
+
+    let field = |label: i128| {
+        entries
+            .iter()
+            .find(|(key, _)| key.as_integer().map(i128::from) == Some(label))
+            .map(|(_, value)| value)
+    };
+    let integer = |label: i128| field(label).and_then(Value::as_integer).map(i128::from);
+    let coordinate = |label: i128| {
+        field(label)
+            .and_then(Value::as_bytes)
+            .filter(|bytes| bytes.len() == 32)
+    };
+
+    if integer(1) != Some(COSE_KEY_TYPE_EC2)
+        || integer(3) != Some(ES256.into())
+        || integer(-1) != Some(COSE_CURVE_P256)
+    {
+        bail!("Only ES256 credentials are supported");
+    }
+
+    let (Some(x), Some(y)) = (coordinate(-2), coordinate(-3)) else {
+        bail!("COSE key is missing its coordinates");
+    };
+
+    let mut public_key = vec![0x04];
+    public_key.extend_from_slice(x);
+    public_key.extend_from_slice(y);
+
+    PublicKey::from_sec1_bytes(&public_key).wrap_err("Public key is not on the P-256 curve")?;
+
+    Ok(public_key)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use p256::ecdsa::{signature::Signer, Signature, SigningKey};
+
+    const CHALLENGE: &str = "challenge";
+    const CREDENTIAL_ID: &[u8] = b"credential-id";
+
+    fn signing_key() -> SigningKey {
+        SigningKey::from_slice(&[7; 32]).unwrap()
+    }
+
+    fn client_data(ceremony_type: &str, origin: &str) -> Vec<u8> {
+        serde_json::to_vec(&serde_json::json!({
+            "type": ceremony_type,
+            "challenge": CHALLENGE,
+            "origin": origin,
+        }))
+        .unwrap()
+    }
+
+    fn authenticator_data(rp_id: &str, flags: u8, sign_count: u32, attested: bool) -> Vec<u8> {
+        let mut data = Sha256::digest(rp_id.as_bytes()).to_vec();
+        data.push(
+            flags
+                | if attested {
+                    FLAG_ATTESTED_CREDENTIAL_DATA
+                } else {
+                    0
+                },
+        );
+        data.extend_from_slice(&sign_count.to_be_bytes());
+
+        if attested {
+            let point = signing_key().verifying_key().to_encoded_point(false);
+            let cose_key = Value::Map(vec![
+                (Value::from(1), Value::from(2)),
+                (Value::from(3), Value::from(ES256)),
+                (Value::from(-1), Value::from(1)),
+                (Value::from(-2), Value::from(point.x().unwrap().to_vec())),
+                (Value::from(-3), Value::from(point.y().unwrap().to_vec())),
+            ]);
+
+            data.extend_from_slice(&[0; 16]);
+            data.extend_from_slice(&(CREDENTIAL_ID.len() as u16).to_be_bytes());
+            data.extend_from_slice(CREDENTIAL_ID);
+            ciborium::into_writer(&cose_key, &mut data).unwrap();
+        }
+
+        data
+    }
+
+    fn attestation_object(authenticator_data: Vec<u8>) -> Vec<u8> {
+        let mut object = Vec::new();
+        let value = Value::Map(vec![
+            (Value::from("fmt"), Value::from("none")),
+            (Value::from("attStmt"), Value::Map(vec![])),
+            (Value::from("authData"), Value::from(authenticator_data)),
+        ]);
+
+        ciborium::into_writer(&value, &mut object).unwrap();
+
+        object
+    }
+
+    fn sign(authenticator_data: &[u8], client_data: &[u8]) -> Vec<u8> {
+        let mut signed_data = authenticator_data.to_vec();
+        signed_data.extend_from_slice(&Sha256::digest(client_data));
+
+        let signature: Signature = signing_key().sign(&signed_data);
+
+        signature.to_der().as_bytes().to_vec()
+    }
+
+    fn public_key() -> Vec<u8> {
+        signing_key()
+            .verifying_key()
+            .to_encoded_point(false)
+            .as_bytes()
+            .to_vec()
+    }
+
+    const VERIFIED: u8 = FLAG_USER_PRESENT | FLAG_USER_VERIFIED;
+
+    #[test]
+    fn test_verify_registration() {
+        let credential = verify_registration(
+            &client_data("webauthn.create", &WEBAUTHN_ORIGIN),
+            &attestation_object(authenticator_data(&WEBAUTHN_RP_ID, VERIFIED, 0, true)),
+            CHALLENGE,
+        )
+        .unwrap();
+
+        assert_eq!(
+            credential,
+            NewCredential {
+                id: URL_SAFE_NO_PAD.encode(CREDENTIAL_ID),
+                public_key: public_key(),
+                sign_count: 0,
+            }
+        );
+    }
+
+    #[test]
+    fn test_verify_registration_rejects_mismatched_client_data() {
+        let attestation =
+            attestation_object(authenticator_data(&WEBAUTHN_RP_ID, VERIFIED, 0, true));
+
+        for client_data in [
+            client_data("webauthn.get", &WEBAUTHN_ORIGIN),
+            client_data("webauthn.create", "https://evil.example"),
+        ] {
+            assert!(verify_registration(&client_data, &attestation, CHALLENGE).is_err());
+        }
+
+        assert!(verify_registration(
+            &client_data("webauthn.create", &WEBAUTHN_ORIGIN),
+            &attestation,
+            "other challenge"
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_verify_registration_rejects_other_rp_or_unverified_user() {
+        for authenticator_data in [
+            authenticator_data("evil.example", VERIFIED, 0, true),
+            authenticator_data(&WEBAUTHN_RP_ID, FLAG_USER_PRESENT, 0, true),
+            authenticator_data(&WEBAUTHN_RP_ID, VERIFIED, 0, false),
+        ] {
+            assert!(verify_registration(
+                &client_data("webauthn.create", &WEBAUTHN_ORIGIN),
+                &attestation_object(authenticator_data),
+                CHALLENGE
+            )
+            .is_err());
+        }
+    }
+
+    #[test]
+    fn test_verify_assertion() {
+        let client_data = client_data("webauthn.get", &WEBAUTHN_ORIGIN);
+        let authenticator_data = authenticator_data(&WEBAUTHN_RP_ID, VERIFIED, 5, false);
+        let signature = sign(&authenticator_data, &client_data);
+
+        let sign_count = verify_assertion(
+            &client_data,
+            &authenticator_data,
+            &signature,
+            CHALLENGE,
+            &public_key(),
+            4,
+        )
+        .unwrap();
+
+        assert_eq!(sign_count, 5);
+    }
+
+    #[test]
+    fn test_verify_assertion_rejects_bad_signature() {
+        let client_data = client_data("webauthn.get", &WEBAUTHN_ORIGIN);
+        let authenticator_data = authenticator_data(&WEBAUTHN_RP_ID, VERIFIED, 5, false);
+        let mut signature = sign(&authenticator_data, &client_data);
+        let last = signature.len() - 1;
+        signature[last] ^= 1;
+
+        assert!(verify_assertion(
+            &client_data,
+            &authenticator_data,
+            &signature,
+            CHALLENGE,
+            &public_key(),
+            0,
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_verify_assertion_rejects_counter_going_backwards() {
+        let client_data = client_data("webauthn.get", &WEBAUTHN_ORIGIN);
+        let authenticator_data = authenticator_data(&WEBAUTHN_RP_ID, VERIFIED, 5, false);
+        let signature = sign(&authenticator_data, &client_data);
+
+        assert!(verify_assertion(
+            &client_data,
+            &authenticator_data,
+            &signature,
+            CHALLENGE,
+            &public_key(),
+            5,
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_verify_assertion_accepts_authenticator_without_counter() {
+        let client_data = client_data("webauthn.get", &WEBAUTHN_ORIGIN);
+        let authenticator_data = authenticator_data(&WEBAUTHN_RP_ID, VERIFIED, 0, false);
+        let signature = sign(&authenticator_data, &client_data);
+
+        assert_eq!(
+            verify_assertion(
+                &client_data,
+                &authenticator_data,
+                &signature,
+                CHALLENGE,
+                &public_key(),
+                0,
+            )
+            .unwrap(),
+            0
+        );
+    }
+}
@@ -0,0 +1,119 @@
+use crate::helpers::{get_random_email, TestApp, ADMIN_API_TOKEN};
+
+use auth_service::{
+    domain::error::ErrorResponse, routes::admin::RotateSigningKeyResponse,
+    utils::hashing_pool::HashingPoolStats,
+};
+use auth_service_macros::api_test;
+use secrecy::ExposeSecret;
+
+#[api_test]
+async fn should_return_200_and_rotate_signing_key() {
+    let old_kid = app.keyring.read().await.active().kid().to_owned();
+
+    let response = app.post_rotate_signing_key(Some(ADMIN_API_TOKEN)).await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    let body = response
+        .json::<RotateSigningKeyResponse>()
+        .await
+        .expect("Could not deserialize response body to RotateSigningKeyResponse");
+
+    assert_ne!(body.kid, old_kid);
+    assert_eq!(app.keyring.read().await.active().kid(), body.kid);
+}
+
+#[api_test]
+async fn should_accept_tokens_signed_before_rotation() {
+    let old_token = app.signup_and_login().await.jwt;
+
+    let response = app.post_rotate_signing_key(Some(ADMIN_API_TOKEN)).await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    let new_token = app.signup_and_login().await.jwt;
+
+    for token in [old_token, new_token] {
+        let response = app
+            .post_verify_token(&serde_json::json!({ "token": token }))
+            .await;
+
+        assert_eq!(response.status().as_u16(), 200);
+    }
+}
+
+#[api_test]
+async fn should_return_400_if_admin_token_missing() {
+    let response = app.post_rotate_signing_key(None).await;
+
+    assert_eq!(response.status().as_u16(), 400);
+
+    assert_eq!(
+        response
+            .json::<ErrorResponse>()
+            .await
+            .expect("Could not deserialize response body to ErrorResponse")
+            .error,
+        "Missing auth token".to_owned()
+    );
+}
+
+#[api_test]
+async fn should_return_401_if_admin_token_invalid() {
+    let old_kid = app.keyring.read().await.active().kid().to_owned();
+
+    let response = app
+        .post_rotate_signing_key(Some("not_the_admin_token"))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 401);
+    assert_eq!(app.keyring.read().await.active().kid(), old_kid);
+}
+
+#[api_test]
+async fn should_report_hashing_metrics() {
+    // Signing up and logging in each hash a password
+    app.signup_and_login().await;
+
+    let response = app.get_hashing_metrics(Some(ADMIN_API_TOKEN)).await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    let stats = response
+        .json::<HashingPoolStats>()
+        .await
+        .expect("Could not deserialize response body to HashingPoolStats");
+
+    assert!(stats.workers > 0);
+    assert!(stats.completed >= 2);
+
+    let response = app.get_hashing_metrics(None).await;
+
+    assert_eq!(response.status().as_u16(), 400);
+
+    let response = app.get_hashing_metrics(Some("wrong_token")).await;
+
+    assert_eq!(response.status().as_u16(), 401);
+}
+
+#[api_test]
+async fn should_return_404_when_unlocking_unknown_account() {
+    let body = serde_json::json!({ "email": get_random_email().expose_secret() });
+
+    let response = app.post_unlock_account(&body, Some(ADMIN_API_TOKEN)).await;
+
+    assert_eq!(response.status().as_u16(), 404);
+    assert_eq!(
+        response
+            .json::<ErrorResponse>()
+            .await
+            .expect("Could not deserialize response body to ErrorResponse")
+            .error,
+        "User not found"
+    );
+
+    let response = app.post_unlock_account(&body, Some("wrong_token")).await;
+
+    assert_eq!(response.status().as_u16(), 401);
+}
@@ -0,0 +1,200 @@
+use crate::helpers::{TestApp, TEST_PASSWORD};
+
+use auth_service::{
+    domain::error::ErrorResponse, routes::change_password::ChangePasswordResponse,
+    utils::constants::DEFAULT_LOCKOUT_THRESHOLD,
+};
+use auth_service_macros::api_test;
+use wiremock::{
+    matchers::{method, path},
+    Mock, ResponseTemplate,
+};
+
+#[api_test]
+async fn should_change_password_and_notify_user() {
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    let email = app.signup_and_login().await.email;
+
+    let response = app
+        .post_change_password(&serde_json::json!({
+            "currentPassword": TEST_PASSWORD,
+            "newPassword": "new-password456",
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 200);
+    assert_eq!(
+        response
+            .json::<ChangePasswordResponse>()
+            .await
+            .expect("Could not deserialize response body to ChangePasswordResponse")
+            .message,
+        "Password has been changed".to_owned()
+    );
+
+    let requests = app
+        .email_server
+        .received_requests()
+        .await
+        .expect("Request recording is disabled");
+    let body: serde_json::Value = requests
+        .last()
+        .expect("No email sent")
+        .body_json()
+        .expect("Email body is not JSON");
+
+    assert_eq!(body["Subject"], "Your password was changed");
+
+    let response = app.login(&email, TEST_PASSWORD).await;
+
+    assert_eq!(response.status().as_u16(), 401);
+
+    let response = app.login(&email, "new-password456").await;
+
+    assert_eq!(response.status().as_u16(), 200);
+}
+
+#[api_test]
+async fn should_return_401_if_current_password_incorrect() {
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+        .mount(&app.email_server)
+        .await;
+
+    let email = app.signup_and_login().await.email;
+
+    let response = app
+        .post_change_password(&serde_json::json!({
+            "currentPassword": "wrong-password",
+            "newPassword": "new-password456",
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 401);
+    assert_eq!(
+        response
+            .json::<ErrorResponse>()
+            .await
+            .expect("Could not deserialize response body to ErrorResponse")
+            .error,
+        "Incorrect credentials".to_owned()
+    );
+
+    let response = app.login(&email, TEST_PASSWORD).await;
+
+    assert_eq!(response.status().as_u16(), 200);
+}
+
+#[api_test]
+async fn should_change_password_even_if_notification_email_fails() {
+    let email = app.signup_and_login().await.email;
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(500))
+        .mount(&app.email_server)
+        .await;
+
+    let response = app
+        .post_change_password(&serde_json::json!({
+            "currentPassword": TEST_PASSWORD,
+            "newPassword": "new-password456",
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    let response = app.login(&email, "new-password456").await;
+
+    assert_eq!(response.status().as_u16(), 200);
+}
+
+#[api_test]
+async fn should_lock_account_after_too_many_incorrect_current_passwords() {
+    let email = app.signup_and_login().await.email;
+
+    let wrong_password = serde_json::json!({
+        "currentPassword": "wrong-password",
+        "newPassword": "new-password456",
+    });
+
+    for _ in 1..DEFAULT_LOCKOUT_THRESHOLD {
+        let response = app.post_change_password(&wrong_password).await;
+
+        assert_eq!(response.status().as_u16(), 401);
+    }
+
+    let response = app.post_change_password(&wrong_password).await;
+
+    assert_eq!(response.status().as_u16(), 423);
+    assert!(app
+        .get_email_subjects(&email)
+        .await
+        .contains(&"Your account has been locked".to_owned()));
+
+    // The right password does not get past the lock either
+    let response = app
+        .post_change_password(&serde_json::json!({
+            "currentPassword": TEST_PASSWORD,
+            "newPassword": "new-password456",
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 423);
+}
+
+#[api_test]
+async fn should_return_400_if_new_password_invalid() {
+    app.signup_and_login().await;
+
+    let response = app
+        .post_change_password(&serde_json::json!({
+            "currentPassword": TEST_PASSWORD,
+            "newPassword": "password123",
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 400);
+
+    let violations = response
+        .json::<ErrorResponse>()
+        .await
+        .expect("Could not deserialize response body to ErrorResponse")
+        .violations;
+
+    assert_eq!(violations.len(), 1);
+    assert_eq!(violations[0].rule, "strength");
+}
+
+#[api_test]
+async fn should_return_400_if_jwt_cookie_missing() {
+    let response = app
+        .post_change_password(&serde_json::json!({
+            "currentPassword": TEST_PASSWORD,
+            "newPassword": "new-password456",
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 400);
+}
+
+#[api_test]
+async fn should_return_422_if_malformed_input() {
+    app.signup_and_login().await;
+
+    let response = app
+        .post_change_password(&serde_json::json!({
+            "newPassword": "new-password456",
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 422);
+}
@@ -1,13 +1,27 @@
 use auth_service::{
-    app_state::app_state::{AppState, BannedTokenStoreType, TwoFACodeStoreType},
-    configure_redis,
+    app_state::app_state::{
+        AppState, BannedTokenStoreType, KeyringType, PasskeyStoreType, RecoveryCodeStoreType,
+        RefreshTokenStoreType, SessionStoreType, TwoFACodeStoreType, UserStoreType,
+    },
+    configure_keyring, configure_redis,
     domain::Email,
     get_mysql_pool,
     services::{
-        data_stores::{MySqlUserStore, RedisBannedTokenStore, RedisTwoFACodeStore},
-        MockEmailClient, PostmarkEmailClient,
+        data_stores::{
+            HashmapRateLimitStore, HashmapSigningKeyStore, MySqlPasskeyStore,
+            MySqlRecoveryCodeStore, MySqlSessionStore, MySqlUserStore, RedisBannedTokenStore,
+            RedisEmailVerificationTokenStore, RedisPasskeyChallengeStore,
+            RedisPasswordResetTokenStore, RedisRefreshTokenStore, RedisTwoFACodeStore,
+        },
+        HibpBreachedPasswordChecker, PostmarkEmailClient,
+    },
+    utils::{
+        breached_passwords::sha1_digest,
+        constants::{
+            env::{ADMIN_API_TOKEN_ENV_VAR, INTROSPECTION_CLIENTS_ENV_VAR},
+            test, JWT_COOKIE_NAME, MYSQL_SERVER_URL, REFRESH_COOKIE_NAME, TWO_FA_CODE_HASH_KEY,
+        },
     },
-    utils::constants::{test, MYSQL_SERVER_URL},
     Application,
 };
 
@@ -21,41 +35,126 @@ use sqlx::MySqlPool;
 use std::{ops::Range, sync::Arc};
 use tokio::sync::RwLock;
 use uuid::Uuid;
-use wiremock::MockServer;
+use wiremock::{
+    matchers::{body_partial_json, method, path},
+    Mock, MockServer, ResponseTemplate,
+};
+
+pub const ADMIN_API_TOKEN: &str = "test_admin_token";
+pub const INTROSPECTION_CLIENT_ID: &str = "test_resource_server";
+pub const INTROSPECTION_CLIENT_SECRET: &str = "test_resource_server_secret";
+// Strong enough for the password policy, but listed as breached in every test app
+pub const BREACHED_PASSWORD: &str = "Purple-Monkey-Dishwasher-1987";
+// Password the users created by `TestApp::signup` log in with
+pub const TEST_PASSWORD: &str = "Tr0ub4dor&3-horse";
+
+// A user logged in through `TestApp::login_as`. The test client also keeps the cookies.
+pub struct TestUser {
+    pub email: Secret<String>,
+    pub jwt: String,
+    pub refresh_token: String,
+}
 
 pub struct TestApp {
     pub address: String,
     pub cookie_jar: Arc<Jar>,
     pub http_client: reqwest::Client,
+    pub user_store: UserStoreType,
     pub two_fa_code_store: TwoFACodeStoreType,
     pub banned_token_store: BannedTokenStoreType,
+    pub refresh_token_store: RefreshTokenStoreType,
+    pub keyring: KeyringType,
+    pub session_store: SessionStoreType,
+    pub recovery_code_store: RecoveryCodeStoreType,
+    pub passkey_store: PasskeyStoreType,
     pub email_server: MockServer,
+    pub mysql_pool: MySqlPool,
     pub db_name: String,
     pub cleaned_up_called: bool,
 }
 
 impl TestApp {
     pub async fn new() -> Self {
+        std::env::set_var(ADMIN_API_TOKEN_ENV_VAR, ADMIN_API_TOKEN);
+        std::env::set_var(
+            INTROSPECTION_CLIENTS_ENV_VAR,
+            format!(
+                "{}:{}",
+                INTROSPECTION_CLIENT_ID, INTROSPECTION_CLIENT_SECRET
+            ),
+        );
+
         let (mysql_pool, db_name) = configure_mysql().await;
         let redis_connection = Arc::new(RwLock::new(configure_redis()));
 
-        let user_store = Arc::new(RwLock::new(MySqlUserStore::new(mysql_pool)));
+        let user_store = Arc::new(RwLock::new(MySqlUserStore::new(mysql_pool.clone())));
+        let session_store = Arc::new(RwLock::new(MySqlSessionStore::new(mysql_pool.clone())));
+        let recovery_code_store =
+            Arc::new(RwLock::new(MySqlRecoveryCodeStore::new(mysql_pool.clone())));
+        let passkey_store = Arc::new(RwLock::new(MySqlPasskeyStore::new(mysql_pool.clone())));
 
         let banned_token_store = Arc::new(RwLock::new(RedisBannedTokenStore::new(
             redis_connection.clone(),
         )));
-        let two_fa_code_store = Arc::new(RwLock::new(RedisTwoFACodeStore::new(redis_connection)));
+        let two_fa_code_store = Arc::new(RwLock::new(RedisTwoFACodeStore::new(
+            redis_connection.clone(),
+            TWO_FA_CODE_HASH_KEY.clone(),
+        )));
+        let passkey_challenge_store = Arc::new(RwLock::new(RedisPasskeyChallengeStore::new(
+            redis_connection.clone(),
+        )));
+        let password_reset_token_store = Arc::new(RwLock::new(RedisPasswordResetTokenStore::new(
+            redis_connection.clone(),
+        )));
+        let email_verification_token_store = Arc::new(RwLock::new(
+            RedisEmailVerificationTokenStore::new(redis_connection.clone()),
+        ));
+        let refresh_token_store =
+            Arc::new(RwLock::new(RedisRefreshTokenStore::new(redis_connection)));
+        let keyring = Arc::new(RwLock::new(configure_keyring()));
+        let breached_password_checker =
+            Arc::new(RwLock::new(HibpBreachedPasswordChecker::new(vec![
+                sha1_digest(&Secret::new(BREACHED_PASSWORD.to_owned())),
+            ])));
+
+        // Tests run in parallel from the same address, so they cannot share limits in Redis
+        let rate_limit_store = Arc::new(RwLock::new(HashmapRateLimitStore::default()));
+        // Nor signing keys, or one test's rotation would change the keys of the others
+        let signing_key_store = Arc::new(RwLock::new(HashmapSigningKeyStore::default()));
 
         let email_server = MockServer::start().await;
         let base_url = email_server.uri();
-        
+
         let email_client = Arc::new(RwLock::new(configure_postmark_email_client(base_url)));
 
+        // Every signup sends a verification email. Answered ahead of the mocks
+        // tests mount, so it does not count towards their expectations.
+        Mock::given(path("/email"))
+            .and(method("POST"))
+            .and(body_partial_json(
+                serde_json::json!({ "Subject": "Verify your email" }),
+            ))
+            .respond_with(ResponseTemplate::new(200))
+            .with_priority(1)
+            .mount(&email_server)
+            .await;
+
         let app_state = AppState::new(
             user_store.clone(),
             banned_token_store.clone(),
             two_fa_code_store.clone(),
             email_client,
+            refresh_token_store.clone(),
+            keyring.clone(),
+            session_store.clone(),
+            recovery_code_store.clone(),
+            passkey_store.clone(),
+            passkey_challenge_store,
+            password_reset_token_store,
+            email_verification_token_store,
+            breached_password_checker,
+            rate_limit_store,
+            signing_key_store,
         );
 
         let app = Application::build(app_state, test::APP_ADDRESS)
@@ -79,9 +178,16 @@ impl TestApp {
             address,
             cookie_jar,
             http_client,
+            user_store,
             banned_token_store,
             two_fa_code_store,
+            refresh_token_store,
+            keyring,
+            session_store,
+            recovery_code_store,
+            passkey_store,
             email_server,
+            mysql_pool,
             db_name,
             cleaned_up_called: false,
         }
@@ -89,7 +195,15 @@ impl TestApp {
 
     pub async fn get_root(&self) -> reqwest::Response {
         self.http_client
-            .get(&format!("{}/", &self.address))
+            .get(format!("{}/", &self.address))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_jwks(&self) -> reqwest::Response {
+        self.http_client
+            .get(format!("{}/.well-known/jwks.json", &self.address))
             .send()
             .await
             .expect("Failed to execute request.")
@@ -100,7 +214,7 @@ impl TestApp {
         Body: serde::Serialize,
     {
         self.http_client
-            .post(&format!("{}/signup", &self.address))
+            .post(format!("{}/signup", &self.address))
             .json(body)
             .send()
             .await
@@ -112,7 +226,31 @@ impl TestApp {
         Body: serde::Serialize,
     {
         self.http_client
-            .post(&format!("{}/login", &self.address))
+            .post(format!("{}/login", &self.address))
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_start_passkey_login<Body>(&self, body: &Body) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.http_client
+            .post(format!("{}/login/passkey/start", &self.address))
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_finish_passkey_login<Body>(&self, body: &Body) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.http_client
+            .post(format!("{}/login/passkey/finish", &self.address))
             .json(body)
             .send()
             .await
@@ -121,7 +259,143 @@ impl TestApp {
 
     pub async fn post_logout(&self) -> reqwest::Response {
         self.http_client
-            .post(&format!("{}/logout", &self.address))
+            .post(format!("{}/logout", &self.address))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_logout_all(&self) -> reqwest::Response {
+        self.http_client
+            .post(format!("{}/logout-all", &self.address))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_sessions(&self) -> reqwest::Response {
+        self.http_client
+            .get(format!("{}/sessions", &self.address))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn delete_session(&self, id: &str) -> reqwest::Response {
+        self.http_client
+            .delete(format!("{}/sessions/{}", &self.address, id))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_enroll_totp<Body>(&self, body: &Body) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.http_client
+            .post(format!("{}/2fa/totp/enroll", &self.address))
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_disable_totp<Body>(&self, body: &Body) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.http_client
+            .post(format!("{}/2fa/totp/disable", &self.address))
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_confirm_totp<Body>(&self, body: &Body) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.http_client
+            .post(format!("{}/2fa/totp/confirm", &self.address))
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_start_passkey_registration(&self) -> reqwest::Response {
+        self.http_client
+            .post(format!("{}/passkeys/register/start", &self.address))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_finish_passkey_registration<Body>(&self, body: &Body) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.http_client
+            .post(format!("{}/passkeys/register/finish", &self.address))
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_recovery_codes<Body>(&self, body: &Body) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.http_client
+            .post(format!("{}/2fa/recovery-codes", &self.address))
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_recovery_codes(&self) -> reqwest::Response {
+        self.http_client
+            .get(format!("{}/2fa/recovery-codes", &self.address))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_password_reset_request<Body>(&self, body: &Body) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.http_client
+            .post(format!("{}/password-reset/request", &self.address))
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_password_reset_confirm<Body>(&self, body: &Body) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.http_client
+            .post(format!("{}/password-reset/confirm", &self.address))
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_change_password<Body>(&self, body: &Body) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.http_client
+            .post(format!("{}/change-password", &self.address))
+            .json(body)
             .send()
             .await
             .expect("Failed to execute request.")
@@ -132,7 +406,162 @@ impl TestApp {
         Body: serde::Serialize,
     {
         self.http_client
-            .post(&format!("{}/verify-2fa", &self.address))
+            .post(format!("{}/verify-2fa", &self.address))
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    // 2FA codes are only stored hashed, so tests read them back from the emails
+    // the mock server received, oldest first
+    pub async fn get_emailed_2fa_codes(&self) -> Vec<String> {
+        self.email_server
+            .received_requests()
+            .await
+            .expect("Request recording is disabled")
+            .iter()
+            .filter_map(|request| request.body_json::<serde_json::Value>().ok())
+            .filter(|body| body["Subject"] == "2FA Code")
+            .map(|body| {
+                body["TextBody"]
+                    .as_str()
+                    .expect("2FA email has no text body")
+                    .to_owned()
+            })
+            .collect()
+    }
+
+    // Token from the last verification link emailed to the address
+    pub async fn get_emailed_verification_token(&self, email: &Secret<String>) -> Option<String> {
+        self.email_server
+            .received_requests()
+            .await
+            .expect("Request recording is disabled")
+            .iter()
+            .filter_map(|request| request.body_json::<serde_json::Value>().ok())
+            .filter(|body| {
+                body["Subject"] == "Verify your email" && body["To"] == *email.expose_secret()
+            })
+            .filter_map(|body| {
+                body["TextBody"]
+                    .as_str()
+                    .and_then(|text| text.split("token=").nth(1))
+                    .and_then(|rest| rest.split_whitespace().next())
+                    .map(str::to_owned)
+            })
+            .next_back()
+    }
+
+    // Follows the emailed verification link, so the user can log in
+    pub async fn verify_email(&self, email: &Secret<String>) {
+        let token = self
+            .get_emailed_verification_token(email)
+            .await
+            .expect("No verification email sent");
+
+        let response = self
+            .post_verify_email(&serde_json::json!({ "token": token }))
+            .await;
+
+        assert_eq!(response.status().as_u16(), 200);
+    }
+
+    // Signs up with TEST_PASSWORD without verifying the email
+    pub async fn signup_unverified(
+        &self,
+        email: &Secret<String>,
+        requires_2fa: bool,
+    ) -> reqwest::Response {
+        let response = self
+            .post_signup(&serde_json::json!({
+                "email": email.expose_secret(),
+                "password": TEST_PASSWORD,
+                "requires2FA": requires_2fa
+            }))
+            .await;
+
+        assert_eq!(response.status().as_u16(), 201);
+
+        response
+    }
+
+    // Signs up a new user without 2FA, ready to log in
+    pub async fn signup(&self) -> Secret<String> {
+        let email = get_random_email();
+
+        self.signup_unverified(&email, false).await;
+        self.verify_email(&email).await;
+
+        email
+    }
+
+    pub async fn login(&self, email: &Secret<String>, password: &str) -> reqwest::Response {
+        self.post_login(&serde_json::json!({
+            "email": email.expose_secret(),
+            "password": password,
+        }))
+        .await
+    }
+
+    // Logs in with TEST_PASSWORD, starting a new session
+    pub async fn login_as(&self, email: &Secret<String>) -> TestUser {
+        let response = self.login(email, TEST_PASSWORD).await;
+
+        assert_eq!(response.status().as_u16(), 200);
+
+        let cookie = |name: &str| {
+            response
+                .cookies()
+                .find(|cookie| cookie.name() == name)
+                .unwrap_or_else(|| panic!("No {} cookie found", name))
+                .value()
+                .to_owned()
+        };
+
+        TestUser {
+            email: email.clone(),
+            jwt: cookie(JWT_COOKIE_NAME),
+            refresh_token: cookie(REFRESH_COOKIE_NAME),
+        }
+    }
+
+    pub async fn signup_and_login(&self) -> TestUser {
+        let email = self.signup().await;
+
+        self.login_as(&email).await
+    }
+
+    pub async fn post_verify_email<Body>(&self, body: &Body) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.http_client
+            .post(format!("{}/verify-email", &self.address))
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_resend_verification_email<Body>(&self, body: &Body) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.http_client
+            .post(format!("{}/verify-email/resend", &self.address))
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_resend_2fa<Body>(&self, body: &Body) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.http_client
+            .post(format!("{}/resend-2fa", &self.address))
             .json(body)
             .send()
             .await
@@ -144,13 +573,98 @@ impl TestApp {
         Body: serde::Serialize,
     {
         self.http_client
-            .post(&format!("{}/verify-token", &self.address))
+            .post(format!("{}/verify-token", &self.address))
             .json(body)
             .send()
             .await
             .expect("Failed to execute request.")
     }
 
+    pub async fn post_introspect<Body>(
+        &self,
+        body: &Body,
+        client_credentials: Option<(&str, &str)>,
+    ) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        let mut request = self
+            .http_client
+            .post(format!("{}/introspect", &self.address))
+            .form(body);
+
+        if let Some((client_id, client_secret)) = client_credentials {
+            request = request.basic_auth(client_id, Some(client_secret));
+        }
+
+        request.send().await.expect("Failed to execute request.")
+    }
+
+    pub async fn post_refresh(&self) -> reqwest::Response {
+        self.http_client
+            .post(format!("{}/refresh", &self.address))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_rotate_signing_key(&self, admin_token: Option<&str>) -> reqwest::Response {
+        let mut request = self
+            .http_client
+            .post(format!("{}/admin/rotate-signing-key", &self.address));
+
+        if let Some(admin_token) = admin_token {
+            request = request.bearer_auth(admin_token);
+        }
+
+        request.send().await.expect("Failed to execute request.")
+    }
+
+    pub async fn get_hashing_metrics(&self, admin_token: Option<&str>) -> reqwest::Response {
+        let mut request = self
+            .http_client
+            .get(format!("{}/admin/metrics/hashing", &self.address));
+
+        if let Some(admin_token) = admin_token {
+            request = request.bearer_auth(admin_token);
+        }
+
+        request.send().await.expect("Failed to execute request.")
+    }
+
+    pub async fn post_unlock_account<Body>(
+        &self,
+        body: &Body,
+        admin_token: Option<&str>,
+    ) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        let mut request = self
+            .http_client
+            .post(format!("{}/admin/unlock-account", &self.address))
+            .json(body);
+
+        if let Some(admin_token) = admin_token {
+            request = request.bearer_auth(admin_token);
+        }
+
+        request.send().await.expect("Failed to execute request.")
+    }
+
+    // Subjects of the emails sent to the address, oldest first
+    pub async fn get_email_subjects(&self, email: &Secret<String>) -> Vec<String> {
+        self.email_server
+            .received_requests()
+            .await
+            .expect("Request recording is disabled")
+            .iter()
+            .filter_map(|request| request.body_json::<serde_json::Value>().ok())
+            .filter(|body| body["To"] == *email.expose_secret())
+            .filter_map(|body| body["Subject"].as_str().map(str::to_owned))
+            .collect()
+    }
+
     pub async fn clean_up(&mut self) {
         if self.cleaned_up_called {
             return;
@@ -176,7 +690,7 @@ pub async fn configure_mysql() -> (MySqlPool, String) {
     let mysql_conn_url = MYSQL_SERVER_URL.to_owned();
     let db_name = Uuid::new_v4().to_string();
 
-    configure_database(&mysql_conn_url.expose_secret(), &db_name).await;
+    configure_database(mysql_conn_url.expose_secret(), &db_name).await;
 
     let mysql_conn_url_with_db =
         Secret::new(format!("{}/{}", mysql_conn_url.expose_secret(), db_name));
@@ -243,12 +757,27 @@ fn configure_postmark_email_client(base_url: String) -> PostmarkEmailClient {
     PostmarkEmailClient::new(base_url, sender, postmark_auth_token, http_client)
 }
 
+// Reads the jti claim without verifying the token
+pub fn get_jti(token: &str) -> String {
+    use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+
+    let payload = token.split('.').nth(1).expect("Token has no payload");
+    let payload = URL_SAFE_NO_PAD
+        .decode(payload)
+        .expect("Token payload is not base64");
+
+    serde_json::from_slice::<serde_json::Value>(&payload).expect("Token payload is not JSON")["jti"]
+        .as_str()
+        .expect("Token has no jti")
+        .to_owned()
+}
+
 pub fn get_random_email() -> Secret<String> {
     Secret::new(SafeEmail().fake())
 }
 
 pub fn get_random_password() -> Secret<String> {
-    Secret::new(en::Password(Range { start: 8, end: 15 }).fake())
+    Secret::new(en::Password(Range { start: 12, end: 20 }).fake())
 }
 
 pub fn get_invalid_password() -> String {
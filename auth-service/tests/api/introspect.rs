@@ -0,0 +1,116 @@
+use crate::helpers::{TestApp, INTROSPECTION_CLIENT_ID, INTROSPECTION_CLIENT_SECRET};
+
+use auth_service::{
+    domain::{error::ErrorResponse, Email},
+    routes::introspect::IntrospectionResponse,
+    utils::constants::{JWT_AUDIENCE, JWT_ISSUER},
+};
+use auth_service_macros::api_test;
+
+const CLIENT_CREDENTIALS: Option<(&str, &str)> =
+    Some((INTROSPECTION_CLIENT_ID, INTROSPECTION_CLIENT_SECRET));
+
+async fn introspect(app: &TestApp, token: &str) -> IntrospectionResponse {
+    let body = serde_json::json!({
+        "token": token,
+        "token_type_hint": "access_token",
+    });
+
+    let response = app.post_introspect(&body, CLIENT_CREDENTIALS).await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    response
+        .json::<IntrospectionResponse>()
+        .await
+        .expect("Could not deserialize response body to IntrospectionResponse")
+}
+
+#[api_test]
+async fn should_return_active_token_with_claims() {
+    let user = app.signup_and_login().await;
+    let token = user.jwt;
+
+    let response = introspect(&app, &token).await;
+
+    assert!(response.active);
+
+    let claims = response.claims.expect("Active token should have claims");
+
+    let user = app
+        .user_store
+        .read()
+        .await
+        .get_user(&Email::parse(user.email).unwrap())
+        .await
+        .expect("User should exist");
+
+    assert_eq!(claims.sub, user.id().to_string());
+    assert_eq!(claims.iss, *JWT_ISSUER);
+    assert_eq!(claims.aud, *JWT_AUDIENCE);
+    assert!(claims.exp > claims.iat);
+    assert!(!claims.jti.is_empty());
+}
+
+#[api_test]
+async fn should_return_inactive_for_invalid_token() {
+    let response = introspect(&app, "invalidToken").await;
+
+    assert_eq!(
+        response,
+        IntrospectionResponse {
+            active: false,
+            claims: None,
+        }
+    );
+}
+
+#[api_test]
+async fn should_return_inactive_for_banned_token() {
+    let token = app.signup_and_login().await.jwt;
+
+    let response = app.post_logout().await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    let response = introspect(&app, &token).await;
+
+    assert!(!response.active);
+    assert!(response.claims.is_none());
+}
+
+#[api_test]
+async fn should_return_401_without_client_credentials() {
+    let token = app.signup_and_login().await.jwt;
+    let body = serde_json::json!({ "token": token });
+
+    let test_cases = [
+        None,
+        Some((INTROSPECTION_CLIENT_ID, "wrong_secret")),
+        Some(("unknown_client", INTROSPECTION_CLIENT_SECRET)),
+    ];
+
+    for client_credentials in test_cases {
+        let response = app.post_introspect(&body, client_credentials).await;
+
+        assert_eq!(response.status().as_u16(), 401);
+
+        assert_eq!(
+            response
+                .json::<ErrorResponse>()
+                .await
+                .expect("Could not deserialize response body to ErrorResponse")
+                .error,
+            "Invalid client credentials".to_owned()
+        );
+    }
+}
+
+#[api_test]
+async fn should_return_422_if_token_missing() {
+    let body = serde_json::json!({ "token_type_hint": "access_token" });
+
+    let response = app.post_introspect(&body, CLIENT_CREDENTIALS).await;
+
+    assert_eq!(response.status().as_u16(), 422);
+}
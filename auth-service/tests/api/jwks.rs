@@ -0,0 +1,36 @@
+use crate::helpers::{TestApp, ADMIN_API_TOKEN};
+
+use auth_service_macros::api_test;
+use jsonwebtoken::jwk::JwkSet;
+
+async fn get_jwks(app: &TestApp) -> JwkSet {
+    let response = app.get_jwks().await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    response
+        .json::<JwkSet>()
+        .await
+        .expect("Could not deserialize response body to JwkSet")
+}
+
+#[api_test]
+async fn should_return_200_with_public_signing_keys() {
+    let jwks = get_jwks(&app).await;
+
+    // Only asymmetric keys are published, HS256 secrets never leave the service
+    assert_eq!(jwks.keys, app.keyring.read().await.jwks().keys);
+}
+
+#[api_test]
+async fn should_keep_publishing_retired_keys_after_rotation() {
+    let before = get_jwks(&app).await;
+
+    let response = app.post_rotate_signing_key(Some(ADMIN_API_TOKEN)).await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    let after = get_jwks(&app).await;
+
+    assert!(before.keys.iter().all(|key| after.keys.contains(key)));
+}
@@ -1,9 +1,10 @@
-use crate::helpers::{get_random_email, get_random_password, TestApp};
+use crate::helpers::{get_random_email, get_random_password, TestApp, ADMIN_API_TOKEN};
 
 use auth_service::{
-    domain::{email::Email, error::ErrorResponse},
+    domain::{data_stores::LoginAttemptId, email::Email, error::ErrorResponse, PasswordHasher},
     routes::login::TwoFactorAuthResponse,
-    utils::constants::JWT_COOKIE_NAME,
+    services::password_hashers::BcryptPasswordHasher,
+    utils::constants::{DEFAULT_LOCKOUT_THRESHOLD, JWT_COOKIE_NAME, RATE_LIMIT_PER_EMAIL},
 };
 use auth_service_macros::api_test;
 use secrecy::{ExposeSecret, Secret};
@@ -15,7 +16,7 @@ use wiremock::{
 #[api_test]
 async fn should_return_200_if_valid_credentials_and_2fa_disabled() {
     let email = get_random_email();
-    let password = Secret::new("password123".to_owned());
+    let password = Secret::new("Tr0ub4dor&3-horse".to_owned());
 
     let signup_body = serde_json::json!( {
         "email": email.expose_secret(),
@@ -27,6 +28,8 @@ async fn should_return_200_if_valid_credentials_and_2fa_disabled() {
 
     assert_eq!(response.status().as_u16(), 201);
 
+    app.verify_email(&email).await;
+
     Mock::given(path("/email"))
         .and(method("POST"))
         .respond_with(ResponseTemplate::new(200))
@@ -66,6 +69,8 @@ async fn should_return_206_if_valid_credentials_and_2fa_enabled() {
 
     assert_eq!(response.status().as_u16(), 201);
 
+    app.verify_email(&email).await;
+
     // Define an expectation for the mock server
     Mock::given(path("/email")) // Expect an HTTP request to the "/email" path
         .and(method("POST")) // Expect the HTTP method to be POST
@@ -90,18 +95,16 @@ async fn should_return_206_if_valid_credentials_and_2fa_enabled() {
     assert_eq!(json_body.message, "2FA required".to_owned());
 
     let two_fa_code_store = app.two_fa_code_store.clone();
+    let login_attempt_id = LoginAttemptId::parse(Secret::new(json_body.login_attempt_id)).unwrap();
 
-    let response = two_fa_code_store
+    let (login_email, _) = two_fa_code_store
         .read()
         .await
-        .get_code(&Email::parse(email).unwrap())
+        .get_code(&login_attempt_id)
         .await
         .unwrap();
 
-    assert_eq!(
-        json_body.login_attempt_id,
-        response.0.as_ref().expose_secret().to_owned()
-    );
+    assert_eq!(login_email, Email::parse(email).unwrap());
 }
 
 #[api_test]
@@ -183,7 +186,7 @@ async fn should_return_422_if_malformed_credentials() {
 
     let signup_body = serde_json::json!({
         "email": random_email.expose_secret(),
-        "password": "password123",
+        "password": "Tr0ub4dor&3-horse",
         "requires2FA": false
     });
 
@@ -193,7 +196,7 @@ async fn should_return_422_if_malformed_credentials() {
 
     let test_cases = [
         serde_json::json!({
-            "password": "password123",
+            "password": "Tr0ub4dor&3-horse",
         }),
         serde_json::json!({
             "email": random_email.expose_secret(),
@@ -212,3 +215,175 @@ async fn should_return_422_if_malformed_credentials() {
         );
     }
 }
+
+#[api_test]
+async fn should_accept_imported_bcrypt_hash_and_upgrade_it() {
+    let email = get_random_email();
+    let password = Secret::new("Tr0ub4dor&3-horse".to_owned());
+
+    let response = app
+        .post_signup(&serde_json::json!({
+            "email": email.expose_secret(),
+            "password": password.expose_secret(),
+            "requires2FA": false
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 201);
+
+    app.verify_email(&email).await;
+
+    // As a migration from a legacy system would leave it
+    let legacy_hash = BcryptPasswordHasher::new(4).hash(&password).unwrap();
+
+    sqlx::query("UPDATE users SET password_hash = ? WHERE email = ?")
+        .bind(legacy_hash.expose_secret())
+        .bind(email.expose_secret())
+        .execute(&app.mysql_pool)
+        .await
+        .expect("Failed to import legacy hash");
+
+    let response = app
+        .post_login(&serde_json::json!({
+            "email": email.expose_secret(),
+            "password": password.expose_secret(),
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    // The upgrade happens in the background after the login
+    let mut password_hash = String::new();
+
+    for _ in 0..50 {
+        password_hash = sqlx::query_scalar("SELECT password_hash FROM users WHERE email = ?")
+            .bind(email.expose_secret())
+            .fetch_one(&app.mysql_pool)
+            .await
+            .expect("Failed to read password hash");
+
+        if password_hash.starts_with("$argon2id$") {
+            break;
+        }
+
+        tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+    }
+
+    assert!(password_hash.starts_with("$argon2id$"));
+
+    let response = app
+        .post_login(&serde_json::json!({
+            "email": email.expose_secret(),
+            "password": password.expose_secret(),
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 200);
+}
+
+#[api_test]
+async fn should_return_429_if_too_many_attempts_for_one_email() {
+    let email = get_random_email();
+    let credentials = serde_json::json!({
+        "email": email.expose_secret(),
+        "password": get_random_password().expose_secret(),
+    });
+
+    for _ in 0..*RATE_LIMIT_PER_EMAIL {
+        let response = app.post_login(&credentials).await;
+
+        assert_eq!(response.status().as_u16(), 401);
+    }
+
+    let response = app.post_login(&credentials).await;
+
+    assert_eq!(response.status().as_u16(), 429);
+    assert!(response.headers().contains_key("retry-after"));
+    assert_eq!(
+        response
+            .json::<ErrorResponse>()
+            .await
+            .expect("Could not deserialize response body to ErrorResponse")
+            .error,
+        "Too many requests, please try again later"
+    );
+
+    // Other accounts are not affected
+    let response = app
+        .post_login(&serde_json::json!({
+            "email": get_random_email().expose_secret(),
+            "password": get_random_password().expose_secret(),
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 401);
+}
+
+#[api_test]
+async fn should_lock_account_after_too_many_failed_logins() {
+    let email = get_random_email();
+    let password = get_random_password();
+
+    let response = app
+        .post_signup(&serde_json::json!({
+            "email": email.expose_secret(),
+            "password": password.expose_secret(),
+            "requires2FA": false
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 201);
+
+    app.verify_email(&email).await;
+
+    let wrong_credentials = serde_json::json!({
+        "email": email.expose_secret(),
+        "password": get_random_password().expose_secret(),
+    });
+
+    for _ in 1..DEFAULT_LOCKOUT_THRESHOLD {
+        let response = app.post_login(&wrong_credentials).await;
+
+        assert_eq!(response.status().as_u16(), 401);
+    }
+
+    // Answered like any wrong password, so the lock does not reveal the account
+    let response = app.post_login(&wrong_credentials).await;
+
+    assert_eq!(response.status().as_u16(), 401);
+    assert!(app
+        .get_email_subjects(&email)
+        .await
+        .contains(&"Your account has been locked".to_owned()));
+
+    // Not even the right password gets in while locked, nor is it told apart
+    let credentials = serde_json::json!({
+        "email": email.expose_secret(),
+        "password": password.expose_secret(),
+    });
+
+    let response = app.post_login(&credentials).await;
+
+    assert_eq!(response.status().as_u16(), 401);
+    assert_eq!(
+        response
+            .json::<ErrorResponse>()
+            .await
+            .expect("Could not deserialize response body to ErrorResponse")
+            .error,
+        "Incorrect credentials".to_owned()
+    );
+
+    let response = app
+        .post_unlock_account(
+            &serde_json::json!({ "email": email.expose_secret() }),
+            Some(ADMIN_API_TOKEN),
+        )
+        .await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    let response = app.post_login(&credentials).await;
+
+    assert_eq!(response.status().as_u16(), 200);
+}
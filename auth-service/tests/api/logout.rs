@@ -1,9 +1,9 @@
-use crate::helpers::{get_random_email, TestApp};
+use crate::helpers::{get_jti, get_random_email, TestApp};
 
 use auth_service::{domain::error::ErrorResponse, utils::constants::JWT_COOKIE_NAME};
 use auth_service_macros::api_test;
 use reqwest::Url;
-use secrecy::{ExposeSecret, Secret};
+use secrecy::ExposeSecret;
 
 #[api_test]
 async fn should_return_200_if_valid_jwt_cookie() {
@@ -11,7 +11,7 @@ async fn should_return_200_if_valid_jwt_cookie() {
 
     let signup_body = serde_json::json!({
         "email": random_email.expose_secret(),
-        "password": "password123",
+        "password": "Tr0ub4dor&3-horse",
         "requires2FA": false
     });
 
@@ -19,9 +19,11 @@ async fn should_return_200_if_valid_jwt_cookie() {
 
     assert_eq!(response.status().as_u16(), 201);
 
+    app.verify_email(&random_email).await;
+
     let login_body = serde_json::json!({
         "email": random_email.expose_secret(),
-        "password": "password123",
+        "password": "Tr0ub4dor&3-horse",
     });
 
     let response = app.post_login(&login_body).await;
@@ -35,7 +37,7 @@ async fn should_return_200_if_valid_jwt_cookie() {
 
     assert!(!auth_cookie.value().is_empty());
 
-    let token = Secret::new(auth_cookie.value().to_owned());
+    let jti = get_jti(auth_cookie.value());
 
     let response = app.post_logout().await;
 
@@ -53,7 +55,7 @@ async fn should_return_200_if_valid_jwt_cookie() {
     let contains_token = banned_token_store
         .read()
         .await
-        .contains_token(&token)
+        .contains_token(&jti)
         .await
         .expect("Failed to check if token is banned");
 
@@ -92,7 +94,7 @@ async fn should_return_400_if_logout_called_twice_in_a_row() {
 
     let signup_body = serde_json::json!({
         "email": random_email.expose_secret(),
-        "password": "password123",
+        "password": "Tr0ub4dor&3-horse",
         "requires2FA": false
     });
 
@@ -100,9 +102,11 @@ async fn should_return_400_if_logout_called_twice_in_a_row() {
 
     assert_eq!(response.status().as_u16(), 201);
 
+    app.verify_email(&random_email).await;
+
     let login_body = serde_json::json!({
         "email": random_email.expose_secret(),
-        "password": "password123",
+        "password": "Tr0ub4dor&3-horse",
     });
 
     let response = app.post_login(&login_body).await;
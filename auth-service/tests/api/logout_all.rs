@@ -0,0 +1,89 @@
+use crate::helpers::TestApp;
+
+use auth_service::{
+    domain::error::ErrorResponse,
+    utils::constants::{JWT_COOKIE_NAME, REFRESH_COOKIE_NAME},
+};
+use auth_service_macros::api_test;
+use reqwest::Url;
+
+#[api_test]
+async fn should_invalidate_every_session_of_the_user() {
+    let email = app.signup().await;
+
+    // Two devices, the second login also leaves its cookies in the test client
+    let first = app.login_as(&email).await;
+    let second = app.login_as(&email).await;
+
+    let response = app.post_logout_all().await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    let auth_cookie = response
+        .cookies()
+        .find(|cookie| cookie.name() == JWT_COOKIE_NAME)
+        .expect("No auth cookie found");
+
+    assert!(auth_cookie.value().is_empty());
+
+    for token in [first.jwt, second.jwt] {
+        let response = app
+            .post_verify_token(&serde_json::json!({ "token": token }))
+            .await;
+
+        assert_eq!(response.status().as_u16(), 401);
+    }
+
+    // Refresh tokens from before the logout cannot mint new JWTs either
+    app.cookie_jar.add_cookie_str(
+        &format!(
+            "{}={}; HttpOnly; SameSite=Lax; Secure; Path=/",
+            REFRESH_COOKIE_NAME, first.refresh_token
+        ),
+        &Url::parse("http://127.0.0.1").expect("Failed to parse URL"),
+    );
+
+    let response = app.post_refresh().await;
+
+    assert_eq!(response.status().as_u16(), 401);
+
+    // Logging in again works and yields a valid token
+    let token = app.login_as(&email).await.jwt;
+
+    let response = app
+        .post_verify_token(&serde_json::json!({ "token": token }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 200);
+}
+
+#[api_test]
+async fn should_return_400_if_jwt_cookie_missing() {
+    let response = app.post_logout_all().await;
+
+    assert_eq!(response.status().as_u16(), 400);
+
+    assert_eq!(
+        response
+            .json::<ErrorResponse>()
+            .await
+            .expect("Could not deserialize response body to ErrorResponse")
+            .error,
+        "Missing auth token".to_owned()
+    );
+}
+
+#[api_test]
+async fn should_return_401_if_invalid_token() {
+    app.cookie_jar.add_cookie_str(
+        &format!(
+            "{}=invalid; HttpOnly; SameSite=Lax; Secure; Path=/",
+            JWT_COOKIE_NAME
+        ),
+        &Url::parse("http://127.0.0.1").expect("Failed to parse URL"),
+    );
+
+    let response = app.post_logout_all().await;
+
+    assert_eq!(response.status().as_u16(), 401);
+}
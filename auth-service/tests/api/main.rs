@@ -1,14 +1,40 @@
 #[cfg(test)]
+mod admin;
+#[cfg(test)]
+mod change_password;
+#[cfg(test)]
 mod helpers;
 #[cfg(test)]
+mod introspect;
+#[cfg(test)]
+mod jwks;
+#[cfg(test)]
 mod login;
 #[cfg(test)]
 mod logout;
 #[cfg(test)]
+mod logout_all;
+#[cfg(test)]
+mod passkey;
+#[cfg(test)]
+mod password_reset;
+#[cfg(test)]
+mod recovery_codes;
+#[cfg(test)]
+mod refresh;
+#[cfg(test)]
+mod resend_2fa;
+#[cfg(test)]
 mod root;
 #[cfg(test)]
+mod sessions;
+#[cfg(test)]
 mod signup;
 #[cfg(test)]
+mod totp;
+#[cfg(test)]
 mod verify_2fa;
 #[cfg(test)]
+mod verify_email;
+#[cfg(test)]
 mod verify_token;
@@ -0,0 +1,406 @@
+use crate::helpers::{get_random_email, TestApp, TEST_PASSWORD};
+
+use auth_service::{
+    domain::{data_stores::LoginAttemptId, error::ErrorResponse},
+    routes::{
+        login::TwoFactorAuthResponse,
+        passkey::{
+            AssertionResponse, AttestationResponse, FinishLoginRequest, FinishRegistrationRequest,
+            FinishRegistrationResponse, LoginCredential, RegistrationCredential,
+            StartLoginResponse, StartRegistrationResponse,
+        },
+    },
+    utils::{
+        constants::{JWT_COOKIE_NAME, WEBAUTHN_ORIGIN},
+        webauthn::ES256,
+    },
+};
+use auth_service_macros::api_test;
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use ciborium::Value;
+use p256::ecdsa::{signature::Signer, Signature, SigningKey};
+use secrecy::{ExposeSecret, Secret};
+use sha2::{Digest, Sha256};
+use wiremock::{
+    matchers::{method, path},
+    Mock, ResponseTemplate,
+};
+
+// User present and user verified
+const FLAGS: u8 = 0x01 | 0x04;
+const FLAG_ATTESTED_CREDENTIAL_DATA: u8 = 0x40;
+
+// Stands in for a platform authenticator and the browser talking to it
+#[derive(Clone)]
+struct SoftwareAuthenticator {
+    signing_key: SigningKey,
+    credential_id: Vec<u8>,
+    sign_count: u32,
+}
+
+impl SoftwareAuthenticator {
+    fn new() -> Self {
+        use rand::{rng, Rng};
+
+        let mut secret = [0u8; 32];
+        let mut credential_id = vec![0u8; 16];
+        rng().fill(&mut secret);
+        rng().fill(&mut credential_id[..]);
+
+        Self {
+            signing_key: SigningKey::from_slice(&secret).expect("Invalid signing key"),
+            credential_id,
+            sign_count: 0,
+        }
+    }
+
+    fn id(&self) -> String {
+        URL_SAFE_NO_PAD.encode(&self.credential_id)
+    }
+
+    fn client_data(ceremony_type: &str, challenge: &str) -> Vec<u8> {
+        serde_json::to_vec(&serde_json::json!({
+            "type": ceremony_type,
+            "challenge": challenge,
+            "origin": WEBAUTHN_ORIGIN.as_str(),
+        }))
+        .unwrap()
+    }
+
+    fn authenticator_data(&self, rp_id: &str, flags: u8) -> Vec<u8> {
+        let mut data = Sha256::digest(rp_id.as_bytes()).to_vec();
+        data.push(flags);
+        data.extend_from_slice(&self.sign_count.to_be_bytes());
+        data
+    }
+
+    // `navigator.credentials.create()`
+    fn register(&self, options: &StartRegistrationResponse) -> FinishRegistrationRequest {
+        let point = self.signing_key.verifying_key().to_encoded_point(false);
+        let cose_key = Value::Map(vec![
+            (Value::from(1), Value::from(2)),
+            (Value::from(3), Value::from(ES256)),
+            (Value::from(-1), Value::from(1)),
+            (Value::from(-2), Value::from(point.x().unwrap().to_vec())),
+            (Value::from(-3), Value::from(point.y().unwrap().to_vec())),
+        ]);
+
+        let mut authenticator_data = self.authenticator_data(
+            &options.public_key.rp.id,
+            FLAGS | FLAG_ATTESTED_CREDENTIAL_DATA,
+        );
+        authenticator_data.extend_from_slice(&[0; 16]);
+        authenticator_data.extend_from_slice(&(self.credential_id.len() as u16).to_be_bytes());
+        authenticator_data.extend_from_slice(&self.credential_id);
+        ciborium::into_writer(&cose_key, &mut authenticator_data).unwrap();
+
+        let mut attestation_object = Vec::new();
+        ciborium::into_writer(
+            &Value::Map(vec![
+                (Value::from("fmt"), Value::from("none")),
+                (Value::from("attStmt"), Value::Map(vec![])),
+                (Value::from("authData"), Value::from(authenticator_data)),
+            ]),
+            &mut attestation_object,
+        )
+        .unwrap();
+
+        FinishRegistrationRequest {
+            challenge_id: options.challenge_id.clone(),
+            credential: RegistrationCredential {
+                id: self.id(),
+                response: AttestationResponse {
+                    client_data_json: URL_SAFE_NO_PAD.encode(Self::client_data(
+                        "webauthn.create",
+                        &options.public_key.challenge,
+                    )),
+                    attestation_object: URL_SAFE_NO_PAD.encode(attestation_object),
+                },
+            },
+        }
+    }
+
+    // `navigator.credentials.get()`
+    fn login(&mut self, options: &StartLoginResponse) -> FinishLoginRequest {
+        self.sign_count += 1;
+
+        let client_data = Self::client_data("webauthn.get", &options.public_key.challenge);
+        let authenticator_data = self.authenticator_data(&options.public_key.rp_id, FLAGS);
+
+        let mut signed_data = authenticator_data.clone();
+        signed_data.extend_from_slice(&Sha256::digest(&client_data));
+        let signature: Signature = self.signing_key.sign(&signed_data);
+
+        FinishLoginRequest {
+            challenge_id: options.challenge_id.clone(),
+            credential: LoginCredential {
+                id: self.id(),
+                response: AssertionResponse {
+                    client_data_json: URL_SAFE_NO_PAD.encode(client_data),
+                    authenticator_data: URL_SAFE_NO_PAD.encode(authenticator_data),
+                    signature: URL_SAFE_NO_PAD.encode(signature.to_der().as_bytes()),
+                },
+            },
+        }
+    }
+}
+
+async fn register(app: &TestApp, authenticator: &SoftwareAuthenticator) -> reqwest::Response {
+    let response = app.post_start_passkey_registration().await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    let options = response
+        .json::<StartRegistrationResponse>()
+        .await
+        .expect("Could not deserialize response body to StartRegistrationResponse");
+
+    app.post_finish_passkey_registration(&authenticator.register(&options))
+        .await
+}
+
+async fn start_login(app: &TestApp, body: serde_json::Value) -> StartLoginResponse {
+    let response = app.post_start_passkey_login(&body).await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    response
+        .json::<StartLoginResponse>()
+        .await
+        .expect("Could not deserialize response body to StartLoginResponse")
+}
+
+#[api_test]
+async fn should_log_in_with_passkey_skipping_2fa_email() {
+    // Only the password login of the 2FA user sends a code
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    let email = get_random_email();
+
+    app.signup_unverified(&email, true).await;
+    app.verify_email(&email).await;
+
+    let response = app.login(&email, TEST_PASSWORD).await;
+
+    assert_eq!(response.status().as_u16(), 206);
+
+    let login_attempt_id = LoginAttemptId::parse(Secret::new(
+        response
+            .json::<TwoFactorAuthResponse>()
+            .await
+            .expect("Could not deserialize response body to TwoFactorAuthResponse")
+            .login_attempt_id,
+    ))
+    .unwrap();
+
+    let two_fa_code = app
+        .get_emailed_2fa_codes()
+        .await
+        .pop()
+        .expect("No 2FA email sent");
+
+    let response = app
+        .post_verify_2fa(&serde_json::json!({
+            "email": email.expose_secret(),
+            "login_attempt_id": login_attempt_id.as_ref().expose_secret(),
+            "two_fa_code": two_fa_code,
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    let mut authenticator = SoftwareAuthenticator::new();
+    let response = register(&app, &authenticator).await;
+
+    assert_eq!(response.status().as_u16(), 201);
+    assert_eq!(
+        response
+            .json::<FinishRegistrationResponse>()
+            .await
+            .expect("Could not deserialize response body to FinishRegistrationResponse"),
+        FinishRegistrationResponse {
+            id: authenticator.id()
+        }
+    );
+
+    let options = start_login(&app, serde_json::json!({ "email": email.expose_secret() })).await;
+
+    assert_eq!(options.public_key.allow_credentials.len(), 1);
+    assert_eq!(
+        options.public_key.allow_credentials[0].id,
+        authenticator.id()
+    );
+
+    let response = app
+        .post_finish_passkey_login(&authenticator.login(&options))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 200);
+    assert!(response
+        .cookies()
+        .any(|cookie| cookie.name() == JWT_COOKIE_NAME));
+
+    let credential = app
+        .passkey_store
+        .read()
+        .await
+        .get_credential(&authenticator.id())
+        .await
+        .unwrap();
+
+    assert_eq!(credential.sign_count, 1);
+}
+
+#[api_test]
+async fn should_log_in_with_discoverable_passkey() {
+    let mut authenticator = SoftwareAuthenticator::new();
+
+    app.signup_and_login().await;
+
+    assert_eq!(register(&app, &authenticator).await.status().as_u16(), 201);
+
+    let response = app.post_logout().await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    let options = start_login(&app, serde_json::json!({})).await;
+
+    assert!(options.public_key.allow_credentials.is_empty());
+
+    let response = app
+        .post_finish_passkey_login(&authenticator.login(&options))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 200);
+}
+
+#[api_test]
+async fn should_return_401_if_challenge_reused() {
+    let mut authenticator = SoftwareAuthenticator::new();
+
+    app.signup_and_login().await;
+    register(&app, &authenticator).await;
+
+    let options = start_login(&app, serde_json::json!({})).await;
+
+    let response = app
+        .post_finish_passkey_login(&authenticator.login(&options))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    let response = app
+        .post_finish_passkey_login(&authenticator.login(&options))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 401);
+}
+
+#[api_test]
+async fn should_return_401_if_sign_count_does_not_increase() {
+    let mut authenticator = SoftwareAuthenticator::new();
+
+    app.signup_and_login().await;
+    register(&app, &authenticator).await;
+
+    // A copy of the key that has not seen the latest login
+    let mut clone = authenticator.clone();
+
+    let options = start_login(&app, serde_json::json!({})).await;
+    let response = app
+        .post_finish_passkey_login(&authenticator.login(&options))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    let options = start_login(&app, serde_json::json!({})).await;
+    let response = app.post_finish_passkey_login(&clone.login(&options)).await;
+
+    assert_eq!(response.status().as_u16(), 401);
+}
+
+#[api_test]
+async fn should_return_401_if_signed_by_another_key() {
+    let authenticator = SoftwareAuthenticator::new();
+
+    app.signup_and_login().await;
+    register(&app, &authenticator).await;
+
+    let mut impostor = SoftwareAuthenticator {
+        credential_id: authenticator.credential_id.clone(),
+        ..SoftwareAuthenticator::new()
+    };
+
+    let options = start_login(&app, serde_json::json!({})).await;
+    let response = app
+        .post_finish_passkey_login(&impostor.login(&options))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 401);
+}
+
+#[api_test]
+async fn should_return_401_if_passkey_belongs_to_another_user() {
+    let mut authenticator = SoftwareAuthenticator::new();
+    let other_email = app.signup_and_login().await.email;
+
+    app.signup_and_login().await;
+    register(&app, &authenticator).await;
+
+    let options = start_login(
+        &app,
+        serde_json::json!({ "email": other_email.expose_secret() }),
+    )
+    .await;
+
+    assert!(options.public_key.allow_credentials.is_empty());
+
+    let response = app
+        .post_finish_passkey_login(&authenticator.login(&options))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 401);
+}
+
+#[api_test]
+async fn should_return_409_if_passkey_already_registered() {
+    let authenticator = SoftwareAuthenticator::new();
+
+    app.signup_and_login().await;
+
+    assert_eq!(register(&app, &authenticator).await.status().as_u16(), 201);
+
+    let response = app.post_start_passkey_registration().await;
+    let options = response
+        .json::<StartRegistrationResponse>()
+        .await
+        .expect("Could not deserialize response body to StartRegistrationResponse");
+
+    assert_eq!(options.public_key.exclude_credentials.len(), 1);
+
+    let response = app
+        .post_finish_passkey_registration(&authenticator.register(&options))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 409);
+    assert_eq!(
+        response
+            .json::<ErrorResponse>()
+            .await
+            .expect("Could not deserialize response body to ErrorResponse")
+            .error,
+        "Passkey is already registered".to_owned()
+    );
+}
+
+#[api_test]
+async fn should_return_400_if_jwt_cookie_missing() {
+    let response = app.post_start_passkey_registration().await;
+
+    assert_eq!(response.status().as_u16(), 400);
+}
@@ -0,0 +1,246 @@
+use crate::helpers::{get_random_email, TestApp, TEST_PASSWORD};
+
+use auth_service::{
+    domain::error::ErrorResponse,
+    routes::password_reset::PasswordResetResponse,
+    utils::constants::{JWT_COOKIE_NAME, RATE_LIMIT_PER_IP},
+};
+use auth_service_macros::api_test;
+use secrecy::ExposeSecret;
+use wiremock::{
+    matchers::{method, path},
+    Mock, ResponseTemplate,
+};
+
+// Token from the link in the last reset email the mock server received. The
+// email is sent in the background after the request is answered.
+async fn emailed_reset_token(app: &TestApp) -> String {
+    for _ in 0..50 {
+        let requests = app
+            .email_server
+            .received_requests()
+            .await
+            .expect("Request recording is disabled");
+
+        let body = requests
+            .iter()
+            .rev()
+            .filter_map(|request| request.body_json::<serde_json::Value>().ok())
+            .find(|body| body["Subject"] == "Reset your password");
+
+        if let Some(body) = body {
+            return body["TextBody"]
+                .as_str()
+                .and_then(|text| text.split("token=").nth(1))
+                .and_then(|rest| rest.split_whitespace().next())
+                .expect("No reset link in email")
+                .to_owned();
+        }
+
+        tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+    }
+
+    panic!("No reset email sent");
+}
+
+#[api_test]
+async fn should_reset_password_and_end_sessions() {
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    let email = app.signup().await;
+
+    let response = app.login(&email, TEST_PASSWORD).await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    let old_token = response
+        .cookies()
+        .find(|cookie| cookie.name() == JWT_COOKIE_NAME)
+        .expect("No auth cookie found")
+        .value()
+        .to_owned();
+
+    let response = app
+        .post_password_reset_request(&serde_json::json!({
+            "email": email.expose_secret(),
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 202);
+
+    let token = emailed_reset_token(&app).await;
+
+    let response = app
+        .post_password_reset_confirm(&serde_json::json!({
+            "token": token,
+            "newPassword": "new-password456",
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 200);
+    assert_eq!(
+        response
+            .json::<PasswordResetResponse>()
+            .await
+            .expect("Could not deserialize response body to PasswordResetResponse")
+            .message,
+        "Password has been reset".to_owned()
+    );
+
+    let response = app
+        .post_verify_token(&serde_json::json!({ "token": old_token }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 401);
+
+    assert_eq!(
+        app.login(&email, TEST_PASSWORD).await.status().as_u16(),
+        401
+    );
+    assert_eq!(
+        app.login(&email, "new-password456").await.status().as_u16(),
+        200
+    );
+
+    // The link only works once
+    let response = app
+        .post_password_reset_confirm(&serde_json::json!({
+            "token": token,
+            "newPassword": "another-password789",
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 400);
+    assert_eq!(
+        response
+            .json::<ErrorResponse>()
+            .await
+            .expect("Could not deserialize response body to ErrorResponse")
+            .error,
+        "Invalid or expired password reset link".to_owned()
+    );
+}
+
+#[api_test]
+async fn should_return_202_without_email_for_unknown_account() {
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+        .mount(&app.email_server)
+        .await;
+
+    let response = app
+        .post_password_reset_request(&serde_json::json!({
+            "email": get_random_email().expose_secret(),
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 202);
+}
+
+#[api_test]
+async fn should_return_202_if_email_fails() {
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(500))
+        .mount(&app.email_server)
+        .await;
+
+    let email = app.signup().await;
+
+    let response = app
+        .post_password_reset_request(&serde_json::json!({
+            "email": email.expose_secret(),
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 202);
+}
+
+#[api_test]
+async fn should_keep_token_if_new_password_invalid() {
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .mount(&app.email_server)
+        .await;
+
+    let email = app.signup().await;
+
+    app.post_password_reset_request(&serde_json::json!({
+        "email": email.expose_secret(),
+    }))
+    .await;
+
+    let token = emailed_reset_token(&app).await;
+
+    let response = app
+        .post_password_reset_confirm(&serde_json::json!({
+            "token": token,
+            "newPassword": "short",
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 400);
+
+    let response = app
+        .post_password_reset_confirm(&serde_json::json!({
+            "token": token,
+            "newPassword": "new-password456",
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 200);
+}
+
+#[api_test]
+async fn should_return_400_if_token_invalid() {
+    let response = app
+        .post_password_reset_confirm(&serde_json::json!({
+            "token": "not-a-token",
+            "newPassword": "new-password456",
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 400);
+}
+
+#[api_test]
+async fn should_return_429_if_too_many_token_guesses() {
+    let guess = serde_json::json!({
+        "token": "not-a-token",
+        "newPassword": "new-password456",
+    });
+
+    for _ in 0..*RATE_LIMIT_PER_IP {
+        let response = app.post_password_reset_confirm(&guess).await;
+
+        assert_eq!(response.status().as_u16(), 400);
+    }
+
+    let response = app.post_password_reset_confirm(&guess).await;
+
+    assert_eq!(response.status().as_u16(), 429);
+    assert!(response.headers().contains_key("retry-after"));
+}
+
+#[api_test]
+async fn should_return_422_if_malformed_input() {
+    let response = app
+        .post_password_reset_request(&serde_json::json!({ "mail": "x" }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 422);
+
+    let response = app
+        .post_password_reset_confirm(&serde_json::json!({ "token": "x" }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 422);
+}
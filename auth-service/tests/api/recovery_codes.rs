@@ -0,0 +1,242 @@
+use crate::helpers::{get_random_email, TestApp, TEST_PASSWORD};
+
+use auth_service::{
+    domain::{
+        data_stores::{RecoveryCode, RecoveryCodeStoreError},
+        error::ErrorResponse,
+        Email,
+    },
+    routes::{
+        login::TwoFactorAuthResponse,
+        recovery_codes::{RecoveryCodesResponse, RemainingRecoveryCodesResponse},
+        signup::SignupResponse,
+    },
+    utils::constants::{JWT_COOKIE_NAME, RECOVERY_CODE_COUNT},
+};
+use auth_service_macros::api_test;
+use secrecy::{ExposeSecret, Secret};
+use wiremock::{
+    matchers::{method, path},
+    Mock, ResponseTemplate,
+};
+
+// Signs up with emailed 2FA and returns the recovery codes handed out
+async fn signup_with_2fa(app: &TestApp, email: &Secret<String>) -> Vec<String> {
+    let response = app.signup_unverified(email, true).await;
+
+    app.verify_email(email).await;
+
+    let signup_response = response
+        .json::<SignupResponse>()
+        .await
+        .expect("Could not deserialize response body to SignupResponse");
+
+    assert_eq!(signup_response.recovery_codes.len(), RECOVERY_CODE_COUNT);
+
+    signup_response.recovery_codes
+}
+
+async fn login_attempt_id(app: &TestApp, email: &Secret<String>) -> String {
+    let response = app.login(email, TEST_PASSWORD).await;
+
+    assert_eq!(response.status().as_u16(), 206);
+
+    response
+        .json::<TwoFactorAuthResponse>()
+        .await
+        .expect("Could not deserialize response body to TwoFactorAuthResponse")
+        .login_attempt_id
+}
+
+async fn verify_with_recovery_code(
+    app: &TestApp,
+    email: &Secret<String>,
+    recovery_code: &str,
+) -> reqwest::Response {
+    let login_attempt_id = login_attempt_id(app, email).await;
+
+    app.post_verify_2fa(&serde_json::json!({
+        "email": email.expose_secret(),
+        "login_attempt_id": login_attempt_id,
+        "recovery_code": recovery_code,
+    }))
+    .await
+}
+
+fn password_body() -> serde_json::Value {
+    serde_json::json!({ "password": TEST_PASSWORD })
+}
+
+async fn mount_email_server(app: &TestApp) {
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .mount(&app.email_server)
+        .await;
+}
+
+async fn remaining_codes(app: &TestApp) -> usize {
+    let response = app.get_recovery_codes().await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    response
+        .json::<RemainingRecoveryCodesResponse>()
+        .await
+        .expect("Could not deserialize response body to RemainingRecoveryCodesResponse")
+        .remaining
+}
+
+#[api_test]
+async fn should_log_in_with_recovery_code_only_once() {
+    mount_email_server(&app).await;
+
+    let email = get_random_email();
+    let recovery_codes = signup_with_2fa(&app, &email).await;
+
+    let response = verify_with_recovery_code(&app, &email, &recovery_codes[0]).await;
+
+    assert_eq!(response.status().as_u16(), 200);
+    assert!(response
+        .cookies()
+        .any(|cookie| cookie.name() == JWT_COOKIE_NAME));
+
+    assert_eq!(remaining_codes(&app).await, RECOVERY_CODE_COUNT - 1);
+
+    let response = verify_with_recovery_code(&app, &email, &recovery_codes[0]).await;
+
+    assert_eq!(response.status().as_u16(), 401);
+}
+
+#[api_test]
+async fn should_accept_recovery_code_without_dash_in_any_case() {
+    mount_email_server(&app).await;
+
+    let email = get_random_email();
+    let recovery_codes = signup_with_2fa(&app, &email).await;
+    let typed_code = recovery_codes[0].replace('-', "").to_uppercase();
+
+    let response = verify_with_recovery_code(&app, &email, &typed_code).await;
+
+    assert_eq!(response.status().as_u16(), 200);
+}
+
+#[api_test]
+async fn should_regenerate_recovery_codes() {
+    mount_email_server(&app).await;
+
+    let email = get_random_email();
+    let recovery_codes = signup_with_2fa(&app, &email).await;
+
+    let response = verify_with_recovery_code(&app, &email, &recovery_codes[0]).await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    let response = app.post_recovery_codes(&password_body()).await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    let regenerated = response
+        .json::<RecoveryCodesResponse>()
+        .await
+        .expect("Could not deserialize response body to RecoveryCodesResponse");
+
+    assert_eq!(regenerated.recovery_codes.len(), RECOVERY_CODE_COUNT);
+    assert_eq!(remaining_codes(&app).await, RECOVERY_CODE_COUNT);
+
+    // The previous set no longer works
+    let user = app
+        .user_store
+        .read()
+        .await
+        .get_user(&Email::parse(email).unwrap())
+        .await
+        .unwrap();
+    let old_code = RecoveryCode::parse(Secret::new(recovery_codes[1].clone())).unwrap();
+
+    let result = app
+        .recovery_code_store
+        .write()
+        .await
+        .use_code(user.id(), &old_code)
+        .await;
+
+    assert_eq!(result, Err(RecoveryCodeStoreError::InvalidCode));
+}
+
+#[api_test]
+async fn should_return_401_if_password_incorrect() {
+    mount_email_server(&app).await;
+
+    let email = get_random_email();
+    let recovery_codes = signup_with_2fa(&app, &email).await;
+
+    let response = verify_with_recovery_code(&app, &email, &recovery_codes[0]).await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    // A session alone does not get new codes
+    let response = app
+        .post_recovery_codes(&serde_json::json!({ "password": "wrong-password123" }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 401);
+    assert_eq!(remaining_codes(&app).await, RECOVERY_CODE_COUNT - 1);
+}
+
+#[api_test]
+async fn should_return_400_if_2fa_not_enabled() {
+    let email = get_random_email();
+
+    let response = app.signup_unverified(&email, false).await;
+
+    assert_eq!(
+        response
+            .json::<SignupResponse>()
+            .await
+            .expect("Could not deserialize response body to SignupResponse")
+            .recovery_codes,
+        Vec::<String>::new()
+    );
+
+    app.verify_email(&email).await;
+
+    let response = app.login(&email, TEST_PASSWORD).await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    let response = app.post_recovery_codes(&password_body()).await;
+
+    assert_eq!(response.status().as_u16(), 400);
+    assert_eq!(
+        response
+            .json::<ErrorResponse>()
+            .await
+            .expect("Could not deserialize response body to ErrorResponse")
+            .error,
+        "2FA is not enabled".to_owned()
+    );
+}
+
+#[api_test]
+async fn should_return_400_if_recovery_code_malformed() {
+    mount_email_server(&app).await;
+
+    let email = get_random_email();
+    signup_with_2fa(&app, &email).await;
+
+    let response = verify_with_recovery_code(&app, &email, "not-a-recovery-code").await;
+
+    assert_eq!(response.status().as_u16(), 400);
+}
+
+#[api_test]
+async fn should_return_400_if_jwt_cookie_missing() {
+    let response = app.post_recovery_codes(&password_body()).await;
+
+    assert_eq!(response.status().as_u16(), 400);
+
+    let response = app.get_recovery_codes().await;
+
+    assert_eq!(response.status().as_u16(), 400);
+}
@@ -0,0 +1,138 @@
+use crate::helpers::TestApp;
+
+use auth_service::{
+    domain::{
+        data_stores::{RefreshToken, RefreshTokenStoreError},
+        error::ErrorResponse,
+    },
+    utils::constants::{JWT_COOKIE_NAME, REFRESH_COOKIE_NAME},
+};
+use auth_service_macros::api_test;
+use reqwest::Url;
+use secrecy::Secret;
+
+fn set_refresh_cookie(app: &TestApp, token: &str) {
+    app.cookie_jar.add_cookie_str(
+        &format!(
+            "{}={}; HttpOnly; SameSite=Lax; Secure; Path=/",
+            REFRESH_COOKIE_NAME, token
+        ),
+        &Url::parse("http://127.0.0.1").expect("Failed to parse URL"),
+    );
+}
+
+#[api_test]
+async fn should_return_200_and_rotate_refresh_token() {
+    let refresh_token = app.signup_and_login().await.refresh_token;
+
+    let response = app.post_refresh().await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    let auth_cookie = response
+        .cookies()
+        .find(|cookie| cookie.name() == JWT_COOKIE_NAME)
+        .expect("No auth cookie found");
+
+    assert!(!auth_cookie.value().is_empty());
+
+    let rotated_refresh_cookie = response
+        .cookies()
+        .find(|cookie| cookie.name() == REFRESH_COOKIE_NAME)
+        .expect("No refresh cookie found");
+
+    assert!(!rotated_refresh_cookie.value().is_empty());
+    assert_ne!(rotated_refresh_cookie.value(), refresh_token);
+
+    // The previous token has been used up by the rotation
+    let result = app
+        .refresh_token_store
+        .write()
+        .await
+        .consume_token(&RefreshToken::from(Secret::new(refresh_token)))
+        .await;
+
+    assert!(matches!(
+        result,
+        Err(RefreshTokenStoreError::TokenReused(_))
+    ));
+}
+
+#[api_test]
+async fn should_return_400_if_refresh_cookie_missing() {
+    let response = app.post_refresh().await;
+
+    assert_eq!(response.status().as_u16(), 400);
+
+    assert_eq!(
+        response
+            .json::<ErrorResponse>()
+            .await
+            .expect("Could not deserialize response body to ErrorResponse")
+            .error,
+        "Missing auth token".to_owned()
+    );
+}
+
+#[api_test]
+async fn should_return_401_if_invalid_refresh_token() {
+    set_refresh_cookie(&app, "invalid");
+
+    let response = app.post_refresh().await;
+
+    assert_eq!(response.status().as_u16(), 401);
+
+    assert_eq!(
+        response
+            .json::<ErrorResponse>()
+            .await
+            .expect("Could not deserialize response body to ErrorResponse")
+            .error,
+        "Invalid auth token".to_owned()
+    );
+}
+
+#[api_test]
+async fn should_revoke_token_family_if_refresh_token_reused() {
+    let first_refresh_token = app.signup_and_login().await.refresh_token;
+
+    let response = app.post_refresh().await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    let second_refresh_token = response
+        .cookies()
+        .find(|cookie| cookie.name() == REFRESH_COOKIE_NAME)
+        .expect("No refresh cookie found")
+        .value()
+        .to_owned();
+
+    // Replay the token that was already rotated
+    set_refresh_cookie(&app, &first_refresh_token);
+
+    let response = app.post_refresh().await;
+
+    assert_eq!(response.status().as_u16(), 401);
+
+    // The latest token belongs to the same family, so it is revoked as well
+    set_refresh_cookie(&app, &second_refresh_token);
+
+    let response = app.post_refresh().await;
+
+    assert_eq!(response.status().as_u16(), 401);
+}
+
+#[api_test]
+async fn should_return_401_if_refresh_after_logout() {
+    let refresh_token = app.signup_and_login().await.refresh_token;
+
+    let response = app.post_logout().await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    set_refresh_cookie(&app, &refresh_token);
+
+    let response = app.post_refresh().await;
+
+    assert_eq!(response.status().as_u16(), 401);
+}
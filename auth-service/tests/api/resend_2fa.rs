@@ -0,0 +1,117 @@
+use crate::helpers::{get_random_email, TestApp, TEST_PASSWORD};
+
+use auth_service::{
+    domain::{data_stores::LoginAttemptId, error::ErrorResponse},
+    routes::login::TwoFactorAuthResponse,
+};
+use auth_service_macros::api_test;
+use reqwest::header::RETRY_AFTER;
+use secrecy::{ExposeSecret, Secret};
+use wiremock::{
+    matchers::{method, path},
+    Mock, ResponseTemplate,
+};
+
+// Signs up with emailed 2FA, logs in and returns the pending login attempt
+async fn start_2fa_login(app: &TestApp, email: &Secret<String>) -> String {
+    app.signup_unverified(email, true).await;
+    app.verify_email(email).await;
+
+    let response = app.login(email, TEST_PASSWORD).await;
+
+    assert_eq!(response.status().as_u16(), 206);
+
+    response
+        .json::<TwoFactorAuthResponse>()
+        .await
+        .expect("Could not deserialize response body to TwoFactorAuthResponse")
+        .login_attempt_id
+}
+
+#[api_test]
+async fn should_return_429_if_resent_within_cooldown() {
+    // Only the login sends an email
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    let email = get_random_email();
+    let login_attempt_id = start_2fa_login(&app, &email).await;
+
+    let (_, code_before) = app
+        .two_fa_code_store
+        .read()
+        .await
+        .get_code(&LoginAttemptId::parse(Secret::new(login_attempt_id.clone())).unwrap())
+        .await
+        .expect("Failed to get 2FA code");
+
+    let response = app
+        .post_resend_2fa(&serde_json::json!({
+            "loginAttemptId": login_attempt_id,
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 429);
+    assert!(response
+        .headers()
+        .get(RETRY_AFTER)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.parse::<u64>().ok())
+        .is_some_and(|seconds| seconds > 0));
+    assert_eq!(
+        response
+            .json::<ErrorResponse>()
+            .await
+            .expect("Could not deserialize response body to ErrorResponse")
+            .error,
+        "Please wait before requesting another code".to_owned()
+    );
+
+    // The pending code is left untouched
+    let (_, code_after) = app
+        .two_fa_code_store
+        .read()
+        .await
+        .get_code(&LoginAttemptId::parse(Secret::new(login_attempt_id)).unwrap())
+        .await
+        .expect("Failed to get 2FA code");
+
+    assert_eq!(code_before, code_after);
+}
+
+#[api_test]
+async fn should_return_401_if_login_attempt_unknown() {
+    let response = app
+        .post_resend_2fa(&serde_json::json!({
+            "loginAttemptId": LoginAttemptId::default().as_ref().expose_secret(),
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 401);
+}
+
+#[api_test]
+async fn should_return_400_if_invalid_input() {
+    let response = app
+        .post_resend_2fa(&serde_json::json!({
+            "loginAttemptId": "not-a-login-attempt-id",
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 400);
+}
+
+#[api_test]
+async fn should_return_422_if_malformed_input() {
+    let response = app
+        .post_resend_2fa(&serde_json::json!({
+            "login_attempt": "123",
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 422);
+}
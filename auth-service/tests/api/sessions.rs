@@ -0,0 +1,213 @@
+use crate::helpers::TestApp;
+
+use auth_service::{
+    domain::{error::ErrorResponse, Email},
+    routes::sessions::{ListSessionsResponse, SessionResponse},
+    utils::constants::{JWT_COOKIE_NAME, REFRESH_COOKIE_NAME},
+};
+use auth_service_macros::api_test;
+use reqwest::Url;
+use secrecy::ExposeSecret;
+
+async fn list_sessions(app: &TestApp) -> Vec<SessionResponse> {
+    let response = app.get_sessions().await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    response
+        .json::<ListSessionsResponse>()
+        .await
+        .expect("Could not deserialize response body to ListSessionsResponse")
+        .sessions
+}
+
+async fn verify_token_status(app: &TestApp, token: &str) -> u16 {
+    app.post_verify_token(&serde_json::json!({ "token": token }))
+        .await
+        .status()
+        .as_u16()
+}
+
+#[api_test]
+async fn should_list_every_session_of_the_user() {
+    let email = app.signup().await;
+
+    app.login_as(&email).await;
+    app.login_as(&email).await;
+
+    let sessions = list_sessions(&app).await;
+
+    assert_eq!(sessions.len(), 2);
+    assert_eq!(sessions.iter().filter(|session| session.current).count(), 1);
+
+    for session in &sessions {
+        assert_eq!(session.ip_address, "127.0.0.1");
+        assert!(!session.device.is_empty());
+        assert!(!session.created_at.is_empty());
+        assert!(!session.last_seen_at.is_empty());
+    }
+
+    // Matches what was recorded in the store
+    let user = app
+        .user_store
+        .read()
+        .await
+        .get_user(&Email::parse(email).unwrap())
+        .await
+        .unwrap();
+
+    let stored = app
+        .session_store
+        .read()
+        .await
+        .list_sessions(user.id())
+        .await
+        .unwrap();
+
+    let mut stored_ids: Vec<_> = stored.into_iter().map(|session| session.id).collect();
+    let mut listed_ids: Vec<_> = sessions.into_iter().map(|session| session.id).collect();
+    stored_ids.sort();
+    listed_ids.sort();
+
+    assert_eq!(stored_ids, listed_ids);
+}
+
+#[api_test]
+async fn should_revoke_another_session() {
+    let email = app.signup().await;
+
+    let first_token = app.login_as(&email).await.jwt;
+    let second_token = app.login_as(&email).await.jwt;
+
+    let other = list_sessions(&app)
+        .await
+        .into_iter()
+        .find(|session| !session.current)
+        .expect("No other session found");
+
+    let response = app.delete_session(&other.id).await;
+
+    assert_eq!(response.status().as_u16(), 204);
+
+    // Only the revoked session's token stops working
+    assert_eq!(verify_token_status(&app, &first_token).await, 401);
+    assert_eq!(verify_token_status(&app, &second_token).await, 200);
+
+    let sessions = list_sessions(&app).await;
+
+    assert_eq!(sessions.len(), 1);
+    assert!(sessions[0].current);
+}
+
+#[api_test]
+async fn should_clear_cookies_when_revoking_the_current_session() {
+    let email = app.signup().await;
+    let token = app.login_as(&email).await.jwt;
+
+    let current = list_sessions(&app).await.remove(0);
+
+    let response = app.delete_session(&current.id).await;
+
+    assert_eq!(response.status().as_u16(), 204);
+
+    for name in [JWT_COOKIE_NAME, REFRESH_COOKIE_NAME] {
+        let cookie = response
+            .cookies()
+            .find(|cookie| cookie.name() == name)
+            .expect("Cookie was not removed");
+
+        assert!(cookie.value().is_empty());
+    }
+
+    assert_eq!(verify_token_status(&app, &token).await, 401);
+}
+
+#[api_test]
+async fn should_not_refresh_a_revoked_session() {
+    let email = app.signup().await;
+    let login_body = serde_json::json!({
+        "email": email.expose_secret(),
+        "password": "Tr0ub4dor&3-horse",
+    });
+
+    let response = app.post_login(&login_body).await;
+    let refresh_token = response
+        .cookies()
+        .find(|cookie| cookie.name() == REFRESH_COOKIE_NAME)
+        .expect("No refresh cookie found")
+        .value()
+        .to_owned();
+
+    // Revoke the first session from a second one
+    app.login_as(&email).await;
+
+    let other = list_sessions(&app)
+        .await
+        .into_iter()
+        .find(|session| !session.current)
+        .expect("No other session found");
+
+    assert_eq!(app.delete_session(&other.id).await.status().as_u16(), 204);
+
+    app.cookie_jar.add_cookie_str(
+        &format!(
+            "{}={}; HttpOnly; SameSite=Lax; Secure; Path=/",
+            REFRESH_COOKIE_NAME, refresh_token
+        ),
+        &Url::parse("http://127.0.0.1").expect("Failed to parse URL"),
+    );
+
+    let response = app.post_refresh().await;
+
+    assert_eq!(response.status().as_u16(), 401);
+}
+
+#[api_test]
+async fn should_return_404_for_another_users_session() {
+    let first_email = app.signup().await;
+    app.login_as(&first_email).await;
+
+    let first_session = list_sessions(&app).await.remove(0);
+
+    let second_email = app.signup().await;
+    app.login_as(&second_email).await;
+
+    let response = app.delete_session(&first_session.id).await;
+
+    assert_eq!(response.status().as_u16(), 404);
+
+    assert_eq!(
+        response
+            .json::<ErrorResponse>()
+            .await
+            .expect("Could not deserialize response body to ErrorResponse")
+            .error,
+        "Session not found".to_owned()
+    );
+}
+
+#[api_test]
+async fn should_return_400_if_jwt_cookie_missing() {
+    let response = app.get_sessions().await;
+
+    assert_eq!(response.status().as_u16(), 400);
+
+    let response = app.delete_session("some-session").await;
+
+    assert_eq!(response.status().as_u16(), 400);
+}
+
+#[api_test]
+async fn should_return_401_if_invalid_token() {
+    app.cookie_jar.add_cookie_str(
+        &format!(
+            "{}=invalid; HttpOnly; SameSite=Lax; Secure; Path=/",
+            JWT_COOKIE_NAME
+        ),
+        &Url::parse("http://127.0.0.1").expect("Failed to parse URL"),
+    );
+
+    let response = app.get_sessions().await;
+
+    assert_eq!(response.status().as_u16(), 401);
+}
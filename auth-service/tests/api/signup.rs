@@ -1,6 +1,11 @@
-use crate::helpers::{get_invalid_password, get_random_email, get_random_password, TestApp};
+use crate::helpers::{
+    get_invalid_password, get_random_email, get_random_password, TestApp, BREACHED_PASSWORD,
+};
 
-use auth_service::{domain::error::ErrorResponse, routes::signup::SignupResponse};
+use auth_service::{
+    domain::error::ErrorResponse, routes::signup::SignupResponse,
+    utils::constants::RECOVERY_CODE_COUNT,
+};
 use auth_service_macros::api_test;
 use secrecy::ExposeSecret;
 
@@ -8,7 +13,7 @@ use secrecy::ExposeSecret;
 pub async fn should_return_201_if_valid_input() {
     let test_case = serde_json::json!({
         "email": get_random_email().expose_secret(),
-        "password": "password123",
+        "password": "Tr0ub4dor&3-horse",
         "requires2FA": true
     });
 
@@ -16,16 +21,14 @@ pub async fn should_return_201_if_valid_input() {
 
     assert_eq!(response.status().as_u16(), 201);
 
-    let expected_response = SignupResponse {
-        message: "User created successfully!".into(),
-    };
-    assert_eq!(
-        response
-            .json::<SignupResponse>()
-            .await
-            .expect("Could not deserialize response body to UserBody"),
-        expected_response
-    );
+    let signup_response = response
+        .json::<SignupResponse>()
+        .await
+        .expect("Could not deserialize response body to UserBody");
+
+    assert_eq!(signup_response.message, "User created successfully!");
+    // Signing up with 2FA hands out the recovery codes
+    assert_eq!(signup_response.recovery_codes.len(), RECOVERY_CODE_COUNT);
 }
 
 #[api_test]
@@ -46,11 +49,6 @@ pub async fn should_return_400_if_invalid_input() {
             "password": "".to_owned(),
             "requires_2fa": false
         }),
-        serde_json::json!( {
-            "email": get_random_email().expose_secret(),
-            "password": "".to_owned(),
-            "requires_2fa": false
-        }),
     ];
 
     for test_case in test_cases {
@@ -93,3 +91,74 @@ pub async fn should_return_409_if_email_already_exists() {
         "User already exists"
     );
 }
+
+#[api_test]
+pub async fn should_return_400_with_violations_if_password_breaks_policy() {
+    let email = get_random_email();
+    let local_part = email.expose_secret().split('@').next().unwrap().to_owned();
+
+    let response = app
+        .post_signup(&serde_json::json!({
+            "email": email.expose_secret(),
+            "password": format!("{}1", local_part),
+            "requires2FA": false
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 400);
+
+    let error_response = response
+        .json::<ErrorResponse>()
+        .await
+        .expect("Could not deserialize response body to ErrorResponse");
+
+    assert_eq!(
+        error_response.error,
+        "Password does not meet the requirements"
+    );
+    assert!(error_response
+        .violations
+        .iter()
+        .any(|violation| violation.rule == "contains_email"));
+
+    let response = app
+        .post_signup(&serde_json::json!({
+            "email": email.expose_secret(),
+            "password": "",
+            "requires2FA": false
+        }))
+        .await;
+
+    let rules: Vec<_> = response
+        .json::<ErrorResponse>()
+        .await
+        .expect("Could not deserialize response body to ErrorResponse")
+        .violations
+        .into_iter()
+        .map(|violation| violation.rule)
+        .collect();
+
+    assert_eq!(rules, ["min_length", "strength"]);
+}
+
+#[api_test]
+pub async fn should_return_400_if_password_breached() {
+    let response = app
+        .post_signup(&serde_json::json!({
+            "email": get_random_email().expose_secret(),
+            "password": BREACHED_PASSWORD,
+            "requires2FA": false
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 400);
+
+    let violations = response
+        .json::<ErrorResponse>()
+        .await
+        .expect("Could not deserialize response body to ErrorResponse")
+        .violations;
+
+    assert_eq!(violations.len(), 1);
+    assert_eq!(violations[0].rule, "breached");
+}
@@ -0,0 +1,356 @@
+use crate::helpers::{TestApp, TEST_PASSWORD};
+
+use auth_service::{
+    domain::{error::ErrorResponse, Email, TwoFAMethod},
+    routes::{
+        login::TwoFactorAuthResponse, recovery_codes::RecoveryCodesResponse,
+        totp::EnrollTotpResponse,
+    },
+    utils::constants::{JWT_COOKIE_NAME, RATE_LIMIT_PER_IP, RECOVERY_CODE_COUNT},
+};
+use auth_service_macros::api_test;
+use secrecy::{ExposeSecret, Secret};
+use std::time::{SystemTime, UNIX_EPOCH};
+use totp_rs::TOTP;
+use wiremock::{
+    matchers::{method, path},
+    Mock, ResponseTemplate,
+};
+
+fn password_body() -> serde_json::Value {
+    serde_json::json!({ "password": TEST_PASSWORD })
+}
+
+async fn enroll(app: &TestApp) -> EnrollTotpResponse {
+    let response = app.post_enroll_totp(&password_body()).await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    response
+        .json::<EnrollTotpResponse>()
+        .await
+        .expect("Could not deserialize response body to EnrollTotpResponse")
+}
+
+// What the user's authenticator app would show right now
+fn current_code(enrollment: &EnrollTotpResponse) -> String {
+    TOTP::from_url(&enrollment.otpauth_uri)
+        .expect("Invalid otpauth URI")
+        .generate_current()
+        .expect("Failed to generate TOTP code")
+}
+
+// The code after the current one, still accepted for clock drift. Stands in for
+// waiting until the next code, as each code only works once.
+fn next_code(enrollment: &EnrollTotpResponse) -> String {
+    let totp = TOTP::from_url(&enrollment.otpauth_uri).expect("Invalid otpauth URI");
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .expect("System clock is before the unix epoch")
+        .as_secs();
+
+    totp.generate(now + totp.step)
+}
+
+// Enrolls and confirms, leaving the user on TOTP
+async fn enable_totp(app: &TestApp) -> EnrollTotpResponse {
+    let enrollment = enroll(app).await;
+
+    let response = app
+        .post_confirm_totp(&serde_json::json!({ "code": current_code(&enrollment) }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    enrollment
+}
+
+async fn start_login(app: &TestApp, email: &Secret<String>) -> TwoFactorAuthResponse {
+    let response = app.login(email, TEST_PASSWORD).await;
+
+    assert_eq!(response.status().as_u16(), 206);
+
+    response
+        .json::<TwoFactorAuthResponse>()
+        .await
+        .expect("Could not deserialize response body to TwoFactorAuthResponse")
+}
+
+#[api_test]
+async fn should_log_in_with_authenticator_app_after_enrolling() {
+    let email = app.signup_and_login().await.email;
+
+    let enrollment = enroll(&app).await;
+
+    assert!(enrollment.otpauth_uri.starts_with("otpauth://totp/"));
+    assert!(enrollment.otpauth_uri.contains(&enrollment.secret));
+    assert!(enrollment.qr_code.contains("<svg"));
+
+    let response = app
+        .post_confirm_totp(&serde_json::json!({ "code": current_code(&enrollment) }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    let confirm_response = response
+        .json::<RecoveryCodesResponse>()
+        .await
+        .expect("Could not deserialize response body to RecoveryCodesResponse");
+
+    assert_eq!(confirm_response.recovery_codes.len(), RECOVERY_CODE_COUNT);
+
+    let user = app
+        .user_store
+        .read()
+        .await
+        .get_user(&Email::parse(email.clone()).unwrap())
+        .await
+        .unwrap();
+
+    assert_eq!(user.two_fa_method(), TwoFAMethod::Totp);
+
+    // No code is emailed to TOTP users
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+        .mount(&app.email_server)
+        .await;
+
+    let response = app.login(&email, TEST_PASSWORD).await;
+
+    assert_eq!(response.status().as_u16(), 206);
+
+    let login_response = response
+        .json::<TwoFactorAuthResponse>()
+        .await
+        .expect("Could not deserialize response body to TwoFactorAuthResponse");
+
+    assert_eq!(login_response.two_fa_method, TwoFAMethod::Totp);
+
+    let response = app
+        .post_verify_2fa(&serde_json::json!({
+            "email": email.expose_secret(),
+            "login_attempt_id": login_response.login_attempt_id,
+            "two_fa_code": next_code(&enrollment),
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 200);
+    assert!(response
+        .cookies()
+        .any(|cookie| cookie.name() == JWT_COOKIE_NAME));
+}
+
+#[api_test]
+async fn should_refuse_a_totp_code_used_before() {
+    let email = app.signup_and_login().await.email;
+
+    let enrollment = enable_totp(&app).await;
+    let code = next_code(&enrollment);
+
+    let login_response = start_login(&app, &email).await;
+
+    let response = app
+        .post_verify_2fa(&serde_json::json!({
+            "email": email.expose_secret(),
+            "login_attempt_id": login_response.login_attempt_id,
+            "two_fa_code": code,
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    // Replaying the same code on another login fails, though it is still in its window
+    let login_response = start_login(&app, &email).await;
+
+    let response = app
+        .post_verify_2fa(&serde_json::json!({
+            "email": email.expose_secret(),
+            "login_attempt_id": login_response.login_attempt_id,
+            "two_fa_code": code,
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 401);
+}
+
+#[api_test]
+async fn should_return_401_if_enrolling_with_incorrect_password() {
+    app.signup_and_login().await;
+
+    let response = app
+        .post_enroll_totp(&serde_json::json!({ "password": "wrong-password" }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 401);
+}
+
+#[api_test]
+async fn should_switch_back_to_email_after_disabling() {
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .mount(&app.email_server)
+        .await;
+
+    let email = app.signup_and_login().await.email;
+
+    enable_totp(&app).await;
+
+    let response = app
+        .post_disable_totp(&serde_json::json!({
+            "password": TEST_PASSWORD,
+            "twoFAMethod": "email",
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 200);
+    assert!(app
+        .get_email_subjects(&email)
+        .await
+        .contains(&"Your authenticator app was removed".to_owned()));
+
+    let user = app
+        .user_store
+        .read()
+        .await
+        .get_user(&Email::parse(email.clone()).unwrap())
+        .await
+        .unwrap();
+
+    assert_eq!(user.two_fa_method(), TwoFAMethod::Email);
+    assert!(user.totp_secret().is_none());
+
+    let login_response = start_login(&app, &email).await;
+
+    assert_eq!(login_response.two_fa_method, TwoFAMethod::Email);
+}
+
+#[api_test]
+async fn should_keep_totp_if_disabling_with_incorrect_password() {
+    let email = app.signup_and_login().await.email;
+
+    enable_totp(&app).await;
+
+    let response = app
+        .post_disable_totp(&serde_json::json!({
+            "password": "wrong-password",
+            "twoFAMethod": "none",
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 401);
+
+    let login_response = start_login(&app, &email).await;
+
+    assert_eq!(login_response.two_fa_method, TwoFAMethod::Totp);
+}
+
+#[api_test]
+async fn should_return_409_if_disabling_without_totp() {
+    app.signup_and_login().await;
+
+    let response = app
+        .post_disable_totp(&serde_json::json!({
+            "password": TEST_PASSWORD,
+            "twoFAMethod": "none",
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 409);
+}
+
+#[api_test]
+async fn should_keep_current_method_until_confirmed() {
+    let email = app.signup_and_login().await.email;
+
+    let enrollment = enroll(&app).await;
+    let wrong_code = format!(
+        "{:06}",
+        (current_code(&enrollment).parse::<u32>().unwrap() + 500_000) % 1_000_000
+    );
+
+    let response = app
+        .post_confirm_totp(&serde_json::json!({ "code": wrong_code }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 401);
+
+    // Still logs in without a second factor
+    let response = app.login(&email, TEST_PASSWORD).await;
+
+    assert_eq!(response.status().as_u16(), 200);
+}
+
+#[api_test]
+async fn should_return_429_if_too_many_confirm_guesses() {
+    app.signup_and_login().await;
+
+    let enrollment = enroll(&app).await;
+    let wrong_code = format!(
+        "{:06}",
+        (current_code(&enrollment).parse::<u32>().unwrap() + 500_000) % 1_000_000
+    );
+    let guess = serde_json::json!({ "code": wrong_code });
+
+    for _ in 0..*RATE_LIMIT_PER_IP {
+        let response = app.post_confirm_totp(&guess).await;
+
+        assert_eq!(response.status().as_u16(), 401);
+    }
+
+    let response = app.post_confirm_totp(&guess).await;
+
+    assert_eq!(response.status().as_u16(), 429);
+    assert!(response.headers().contains_key("retry-after"));
+}
+
+#[api_test]
+async fn should_return_409_if_totp_already_enabled() {
+    app.signup_and_login().await;
+
+    let enrollment = enroll(&app).await;
+
+    let response = app
+        .post_confirm_totp(&serde_json::json!({ "code": current_code(&enrollment) }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    let response = app.post_enroll_totp(&password_body()).await;
+
+    assert_eq!(response.status().as_u16(), 409);
+    assert_eq!(
+        response
+            .json::<ErrorResponse>()
+            .await
+            .expect("Could not deserialize response body to ErrorResponse")
+            .error,
+        "TOTP is already enabled".to_owned()
+    );
+}
+
+#[api_test]
+async fn should_return_400_if_confirming_before_enrolling() {
+    app.signup_and_login().await;
+
+    let response = app
+        .post_confirm_totp(&serde_json::json!({ "code": "123456" }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 400);
+}
+
+#[api_test]
+async fn should_return_400_if_jwt_cookie_missing() {
+    let response = app.post_enroll_totp(&password_body()).await;
+
+    assert_eq!(response.status().as_u16(), 400);
+
+    let response = app
+        .post_confirm_totp(&serde_json::json!({ "code": "123456" }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 400);
+}
@@ -1,21 +1,32 @@
-use crate::helpers::{get_random_email, TestApp};
+use crate::helpers::{get_random_email, TestApp, TEST_PASSWORD};
 use auth_service::{
     api::helpers::get_random_password,
-    domain::{data_stores::LoginAttemptId, error::ErrorResponse, Email},
-    routes::{
-        login::{LoginResponse, TwoFactorAuthResponse},
-        signup::SignupResponse,
-        verify_2fa::Verify2FARequest,
-    },
-    utils::constants::JWT_COOKIE_NAME,
+    domain::{data_stores::LoginAttemptId, error::ErrorResponse, TwoFAMethod},
+    routes::{login::TwoFactorAuthResponse, signup::SignupResponse, verify_2fa::Verify2FARequest},
+    utils::constants::{JWT_COOKIE_NAME, MAX_2FA_ATTEMPTS},
 };
 use auth_service_macros::api_test;
-use secrecy::ExposeSecret;
+use secrecy::{ExposeSecret, Secret};
 use wiremock::{
     matchers::{method, path},
     Mock, ResponseTemplate,
 };
 
+// Checks the login asked for emailed 2FA and returns the attempt it started
+async fn pending_login_attempt(response: reqwest::Response) -> LoginAttemptId {
+    assert_eq!(response.status().as_u16(), 206);
+
+    let body = response
+        .json::<TwoFactorAuthResponse>()
+        .await
+        .expect("Could not deserialize response body to TwoFactorAuthResponse");
+
+    assert_eq!(body.message, "2FA required".to_owned());
+    assert_eq!(body.two_fa_method, TwoFAMethod::Email);
+
+    LoginAttemptId::parse(Secret::new(body.login_attempt_id)).unwrap()
+}
+
 #[api_test]
 async fn should_return_200_if_correct_code() {
     let email = get_random_email();
@@ -37,9 +48,19 @@ async fn should_return_200_if_correct_code() {
             .expect("Could not deserialize response body to UserBody"),
         SignupResponse {
             message: "User created successfully!".into(),
+            recovery_codes: vec![],
         }
     );
 
+    app.verify_email(&email).await;
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
     let response = app
         .post_login(&serde_json::json!( {
             "email": email.clone().expose_secret(),
@@ -47,32 +68,19 @@ async fn should_return_200_if_correct_code() {
         }))
         .await;
 
-    let (login_attempt_id, two_fa_code) = {
-        app.two_fa_code_store
-            .read()
-            .await
-            .get_code(&Email::parse(email.clone()).unwrap())
-            .await
-            .expect("Failed to get 2FA code")
-    };
+    let login_attempt_id = pending_login_attempt(response).await;
 
-    assert_eq!(response.status().as_u16(), 206);
-    assert_eq!(
-        response
-            .json::<LoginResponse>()
-            .await
-            .expect("Could not deserialize response body to UserBody"),
-        LoginResponse::TwoFactorAuth(TwoFactorAuthResponse {
-            message: "2FA required".into(),
-            login_attempt_id: login_attempt_id.clone().as_ref().expose_secret().to_owned(),
-        })
-    );
+    let two_fa_code = app
+        .get_emailed_2fa_codes()
+        .await
+        .pop()
+        .expect("No 2FA email sent");
 
     let response = app
         .post_verify_2fa(&serde_json::json!( {
             "email": email.expose_secret(),
             "login_attempt_id": login_attempt_id.as_ref().expose_secret(),
-            "two_fa_code": two_fa_code.as_ref().expose_secret(),
+            "two_fa_code": two_fa_code,
         }))
         .await;
 
@@ -89,6 +97,7 @@ async fn should_return_400_if_invalid_input() {
         email: "fakeemail".into(),
         login_attempt_id: "fakeID".into(),
         two_fa_code: "faketwofa".into(),
+        recovery_code: None,
     })];
 
     for test_case in test_cases.iter() {
@@ -127,9 +136,12 @@ async fn should_return_401_if_incorrect_credentials() {
             .expect("Could not deserialize response body to UserBody"),
         SignupResponse {
             message: "User created successfully!".into(),
+            recovery_codes: vec![],
         }
     );
 
+    app.verify_email(&email).await;
+
     let login_response = app
         .post_login(&serde_json::json!( {
             "email": email.clone().expose_secret(),
@@ -137,25 +149,7 @@ async fn should_return_401_if_incorrect_credentials() {
         }))
         .await;
 
-    let (login_attempt_id, _) = {
-        app.two_fa_code_store
-            .read()
-            .await
-            .get_code(&Email::parse(email.clone()).unwrap())
-            .await
-            .unwrap()
-    };
-    assert_eq!(login_response.status().as_u16(), 206);
-    assert_eq!(
-        login_response
-            .json::<LoginResponse>()
-            .await
-            .expect("Could not deserialize response body to UserBody"),
-        LoginResponse::TwoFactorAuth(TwoFactorAuthResponse {
-            message: "2FA required".into(),
-            login_attempt_id: login_attempt_id.as_ref().expose_secret().to_owned()
-        })
-    );
+    pending_login_attempt(login_response).await;
 
     let verify_2fa_response = app
         .post_verify_2fa(&serde_json::json!( {
@@ -177,7 +171,7 @@ async fn should_return_401_if_incorrect_credentials() {
 }
 
 #[api_test]
-async fn should_return_401_if_old_code() {
+async fn should_return_200_for_each_concurrent_login() {
     let email = get_random_email();
     let password = get_random_password();
 
@@ -196,10 +190,13 @@ async fn should_return_401_if_old_code() {
             .await
             .expect("Could not deserialize response body to UserBody"),
         SignupResponse {
-            message: "User created successfully!".into()
+            message: "User created successfully!".into(),
+            recovery_codes: vec![],
         }
     );
 
+    app.verify_email(&email).await;
+
     Mock::given(path("/email"))
         .and(method("POST"))
         .respond_with(ResponseTemplate::new(200))
@@ -214,59 +211,40 @@ async fn should_return_401_if_old_code() {
         }))
         .await;
 
-    let (login_attempt_id, _) = {
-        app.two_fa_code_store
-            .read()
-            .await
-            .get_code(&Email::parse(email.clone()).unwrap())
-            .await
-            .unwrap()
-    };
+    let first_attempt = pending_login_attempt(login_response).await;
 
-    assert_eq!(login_response.status().as_u16(), 206);
-    assert_eq!(
-        login_response
-            .json::<LoginResponse>()
-            .await
-            .expect("Could not deserialize response body to UserBody"),
-        LoginResponse::TwoFactorAuth(TwoFactorAuthResponse {
-            message: "2FA required".into(),
-            login_attempt_id: login_attempt_id.as_ref().expose_secret().to_owned(),
-        })
-    );
+    let login_response = app
+        .post_login(&serde_json::json!( {
+            "email": email.clone().expose_secret(),
+            "password": password.expose_secret()
+        }))
+        .await;
 
-    let (login_attempt_id, two_fa_code) = {
-        app.two_fa_code_store
-            .read()
-            .await
-            .get_code(&Email::parse(email.clone()).unwrap())
-            .await
-            .expect("Failed to get 2FA")
-    };
+    let second_attempt = pending_login_attempt(login_response).await;
 
-    app.post_login(&serde_json::json!( {
-        "email": email.clone().expose_secret(),
-        "password": password.expose_secret()
-    }))
-    .await;
+    let emailed_codes = app.get_emailed_2fa_codes().await;
+    let (first_code, second_code) = (&emailed_codes[0], &emailed_codes[1]);
 
+    let verify_2fa_response = app
+        .post_verify_2fa(&serde_json::json!( {
+            "email": email.clone().expose_secret(),
+            "login_attempt_id": second_attempt.as_ref().expose_secret(),
+            "two_fa_code": second_code,
+        }))
+        .await;
+
+    assert_eq!(verify_2fa_response.status().as_u16(), 200);
+
+    // Completing the second login leaves the first one pending
     let verify_2fa_response = app
         .post_verify_2fa(&serde_json::json!( {
             "email": email.expose_secret(),
-            "login_attempt_id": login_attempt_id.as_ref().expose_secret(),
-            "two_fa_code": two_fa_code.as_ref().expose_secret(),
+            "login_attempt_id": first_attempt.as_ref().expose_secret(),
+            "two_fa_code": first_code,
         }))
         .await;
 
-    assert_eq!(verify_2fa_response.status().as_u16(), 401);
-    assert_eq!(
-        verify_2fa_response
-            .json::<ErrorResponse>()
-            .await
-            .expect("Could not deserialize response body to ErrorResponse")
-            .error,
-        "Incorrect credentials".to_owned()
-    );
+    assert_eq!(verify_2fa_response.status().as_u16(), 200);
 }
 
 #[api_test]
@@ -289,10 +267,13 @@ async fn should_return_401_if_same_code_twice() {
             .await
             .expect("Could not deserialize response body to UserBody"),
         SignupResponse {
-            message: "User created successfully!".into()
+            message: "User created successfully!".into(),
+            recovery_codes: vec![],
         }
     );
 
+    app.verify_email(&email).await;
+
     Mock::given(path("/email"))
         .and(method("POST"))
         .respond_with(ResponseTemplate::new(200))
@@ -307,32 +288,19 @@ async fn should_return_401_if_same_code_twice() {
         }))
         .await;
 
-    let (login_attempt_id, two_fa_code) = {
-        app.two_fa_code_store
-            .read()
-            .await
-            .get_code(&Email::parse(email.clone()).unwrap())
-            .await
-            .unwrap()
-    };
+    let login_attempt_id = pending_login_attempt(login_response).await;
 
-    assert_eq!(login_response.status().as_u16(), 206);
-    assert_eq!(
-        login_response
-            .json::<LoginResponse>()
-            .await
-            .expect("Could not deserialize response body to UserBody"),
-        LoginResponse::TwoFactorAuth(TwoFactorAuthResponse {
-            message: "2FA required".into(),
-            login_attempt_id: login_attempt_id.clone().as_ref().expose_secret().to_owned(),
-        })
-    );
+    let two_fa_code = app
+        .get_emailed_2fa_codes()
+        .await
+        .pop()
+        .expect("No 2FA email sent");
 
     let verify_2fa_resposne = app
         .post_verify_2fa(&serde_json::json!( {
             "email": email.clone().expose_secret(),
             "login_attempt_id": login_attempt_id.clone().as_ref().expose_secret(),
-            "two_fa_code": two_fa_code.as_ref().expose_secret()
+            "two_fa_code": two_fa_code
         }))
         .await;
 
@@ -342,13 +310,95 @@ async fn should_return_401_if_same_code_twice() {
         .post_verify_2fa(&serde_json::json!( {
             "email": email.expose_secret(),
             "login_attempt_id": login_attempt_id.as_ref().expose_secret(),
-            "two_fa_code": two_fa_code.as_ref().expose_secret()
+            "two_fa_code": two_fa_code
         }))
         .await;
 
     assert_eq!(second_verify_2fa_resposne.status().as_u16(), 401);
 }
 
+#[api_test]
+async fn should_invalidate_code_after_too_many_failed_attempts() {
+    let email = get_random_email();
+    let password = get_random_password();
+
+    let signup_response = app
+        .post_signup(&serde_json::json!( {
+            "email": email.clone().expose_secret(),
+            "password": password.clone().expose_secret(),
+            "requires_2fa": true
+        }))
+        .await;
+
+    assert_eq!(signup_response.status().as_u16(), 201);
+
+    app.verify_email(&email).await;
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    let login_response = app
+        .post_login(&serde_json::json!( {
+            "email": email.clone().expose_secret(),
+            "password": password.clone().expose_secret(),
+        }))
+        .await;
+
+    let login_attempt_id = pending_login_attempt(login_response).await;
+
+    let two_fa_code = app
+        .get_emailed_2fa_codes()
+        .await
+        .pop()
+        .expect("No 2FA email sent");
+    let wrong_code = if two_fa_code == "000000" {
+        "111111"
+    } else {
+        "000000"
+    };
+
+    for attempt in 1..=MAX_2FA_ATTEMPTS {
+        let response = app
+            .post_verify_2fa(&serde_json::json!( {
+                "email": email.clone().expose_secret(),
+                "login_attempt_id": login_attempt_id.as_ref().expose_secret(),
+                "two_fa_code": wrong_code
+            }))
+            .await;
+
+        let expected_error = if attempt < MAX_2FA_ATTEMPTS {
+            "Incorrect credentials"
+        } else {
+            "Too many failed attempts, please log in again"
+        };
+
+        assert_eq!(response.status().as_u16(), 401);
+        assert_eq!(
+            response
+                .json::<ErrorResponse>()
+                .await
+                .expect("Could not deserialize response body to ErrorResponse")
+                .error,
+            expected_error.to_owned()
+        );
+    }
+
+    // The real code no longer works once the attempt budget is spent
+    let response = app
+        .post_verify_2fa(&serde_json::json!( {
+            "email": email.expose_secret(),
+            "login_attempt_id": login_attempt_id.as_ref().expose_secret(),
+            "two_fa_code": two_fa_code
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 401);
+}
+
 #[api_test]
 pub async fn should_return_422_if_malformed_input() {
     let body = serde_json::json!({});
@@ -357,3 +407,38 @@ pub async fn should_return_422_if_malformed_input() {
 
     assert_eq!(response.status().as_u16(), 422);
 }
+
+#[api_test]
+async fn should_accept_code_only_once_when_sent_concurrently() {
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .mount(&app.email_server)
+        .await;
+
+    let email = get_random_email();
+
+    app.signup_unverified(&email, true).await;
+    app.verify_email(&email).await;
+
+    let login_attempt_id = pending_login_attempt(app.login(&email, TEST_PASSWORD).await).await;
+
+    let two_fa_code = app
+        .get_emailed_2fa_codes()
+        .await
+        .pop()
+        .expect("No 2FA email sent");
+
+    let body = serde_json::json!({
+        "email": email.expose_secret(),
+        "login_attempt_id": login_attempt_id.as_ref().expose_secret(),
+        "two_fa_code": two_fa_code,
+    });
+
+    let (first, second) = tokio::join!(app.post_verify_2fa(&body), app.post_verify_2fa(&body));
+
+    let mut statuses = [first.status().as_u16(), second.status().as_u16()];
+    statuses.sort();
+
+    assert_eq!(statuses, [200, 401]);
+}
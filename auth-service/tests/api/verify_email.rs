@@ -0,0 +1,137 @@
+use crate::helpers::{get_random_email, TestApp, TEST_PASSWORD};
+
+use auth_service::{domain::error::ErrorResponse, routes::verify_email::VerifyEmailResponse};
+use auth_service_macros::api_test;
+use secrecy::ExposeSecret;
+
+#[api_test]
+async fn should_refuse_login_until_email_verified() {
+    let email = get_random_email();
+    app.signup_unverified(&email, false).await;
+
+    let response = app.login(&email, TEST_PASSWORD).await;
+
+    assert_eq!(response.status().as_u16(), 403);
+    assert_eq!(
+        response
+            .json::<ErrorResponse>()
+            .await
+            .expect("Could not deserialize response body to ErrorResponse")
+            .error,
+        "Email address has not been verified".to_owned()
+    );
+
+    let token = app
+        .get_emailed_verification_token(&email)
+        .await
+        .expect("No verification email sent");
+
+    let response = app
+        .post_verify_email(&serde_json::json!({ "token": token }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 200);
+    assert_eq!(
+        response
+            .json::<VerifyEmailResponse>()
+            .await
+            .expect("Could not deserialize response body to VerifyEmailResponse")
+            .message,
+        "Email address verified".to_owned()
+    );
+
+    let response = app.login(&email, TEST_PASSWORD).await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    // The link only works once
+    let response = app
+        .post_verify_email(&serde_json::json!({ "token": token }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 400);
+}
+
+#[api_test]
+async fn should_resend_verification_link() {
+    let email = get_random_email();
+    app.signup_unverified(&email, false).await;
+
+    let first_token = app.get_emailed_verification_token(&email).await;
+
+    let response = app
+        .post_resend_verification_email(&serde_json::json!({ "email": email.expose_secret() }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 202);
+
+    let second_token = app.get_emailed_verification_token(&email).await;
+
+    assert!(second_token.is_some());
+    assert_ne!(first_token, second_token);
+
+    app.verify_email(&email).await;
+
+    let response = app.login(&email, TEST_PASSWORD).await;
+
+    assert_eq!(response.status().as_u16(), 200);
+}
+
+#[api_test]
+async fn should_not_resend_to_unknown_or_verified_address() {
+    let email = app.signup().await;
+
+    let unknown_email = get_random_email();
+
+    for email in [&email, &unknown_email] {
+        let response = app
+            .post_resend_verification_email(&serde_json::json!({ "email": email.expose_secret() }))
+            .await;
+
+        assert_eq!(response.status().as_u16(), 202);
+    }
+
+    let sent = app
+        .email_server
+        .received_requests()
+        .await
+        .expect("Request recording is disabled")
+        .iter()
+        .filter_map(|request| request.body_json::<serde_json::Value>().ok())
+        .filter(|body| body["Subject"] == "Verify your email")
+        .count();
+
+    assert_eq!(sent, 1);
+}
+
+#[api_test]
+async fn should_return_400_if_token_invalid() {
+    for token in ["not-a-token", "AAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAA"] {
+        let response = app
+            .post_verify_email(&serde_json::json!({ "token": token }))
+            .await;
+
+        assert_eq!(response.status().as_u16(), 400);
+        assert_eq!(
+            response
+                .json::<ErrorResponse>()
+                .await
+                .expect("Could not deserialize response body to ErrorResponse")
+                .error,
+            "Invalid or expired verification link".to_owned()
+        );
+    }
+}
+
+#[api_test]
+async fn should_return_422_if_malformed_input() {
+    let response = app.post_verify_email(&serde_json::json!({})).await;
+
+    assert_eq!(response.status().as_u16(), 422);
+
+    let response = app
+        .post_resend_verification_email(&serde_json::json!({}))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 422);
+}
@@ -10,7 +10,7 @@ async fn should_return_200_valid_token() {
 
     let signup_body = serde_json::json!({
         "email": random_email.expose_secret(),
-        "password": "password123",
+        "password": "Tr0ub4dor&3-horse",
         "requires2FA": false
     });
 
@@ -18,9 +18,11 @@ async fn should_return_200_valid_token() {
 
     assert_eq!(response.status().as_u16(), 201);
 
+    app.verify_email(&random_email).await;
+
     let login_body = serde_json::json!({
         "email": random_email.expose_secret(),
-        "password": "password123",
+        "password": "Tr0ub4dor&3-horse",
     });
 
     let response = app.post_login(&login_body).await;
@@ -62,7 +64,7 @@ async fn should_return_401_if_banned_token() {
 
     let signup_body = serde_json::json!({
         "email": random_email.expose_secret(),
-        "password": "password123",
+        "password": "Tr0ub4dor&3-horse",
         "requires2FA": false
     });
 
@@ -70,9 +72,11 @@ async fn should_return_401_if_banned_token() {
 
     assert_eq!(response.status().as_u16(), 201);
 
+    app.verify_email(&random_email).await;
+
     let login_body = serde_json::json!({
         "email": random_email.expose_secret(),
-        "password": "password123",
+        "password": "Tr0ub4dor&3-horse",
     });
 
     let response = app.post_login(&login_body).await;